@@ -0,0 +1,369 @@
+use std::error::Error;
+use std::fmt::Display;
+use std::io::{Read, Write};
+
+use aes::cipher::{KeyIvInit, StreamCipher};
+use byteorder::{ByteOrder, LittleEndian};
+use hmac::{Hmac, Mac};
+use sha1::Sha1;
+
+const AES_VENDOR_ID: &[u8; 2] = b"AE";
+const AES_EXTRA_FIELD_DATA_SIZE: usize = 7;
+const PBKDF2_ITERATIONS: u32 = 1000;
+const PASSWORD_VERIFIER_LEN: usize = 2;
+pub const AUTHENTICATION_CODE_LEN: usize = 10;
+// WinZip AES uses CTR mode with a little endian counter starting from 1
+const INITIAL_COUNTER_BLOCK: [u8; 16] = [1, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0];
+
+type Aes128Ctr = ctr::Ctr128LE<aes::Aes128>;
+type Aes192Ctr = ctr::Ctr128LE<aes::Aes192>;
+type Aes256Ctr = ctr::Ctr128LE<aes::Aes256>;
+
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub enum AesError {
+    EmptyPassword,
+    IncorrectPassword,
+    AuthenticationFailed,
+    IOError(String),
+}
+
+impl Display for AesError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            AesError::EmptyPassword => write!(f, "Empty password given for AES encryption"),
+            AesError::IncorrectPassword => write!(f, "Incorrect password"),
+            AesError::AuthenticationFailed => write!(
+                f,
+                "The authentication code of the AES encrypted data doesn't match. The data is corrupted or was tampered with"
+            ),
+            AesError::IOError(err) => write!(
+                f,
+                "An I/O error occurred while setting up AES encryption.\n {}",
+                err
+            ),
+        }
+    }
+}
+
+impl Error for AesError {}
+
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub enum AesStrength {
+    Aes128,
+    Aes192,
+    Aes256,
+}
+
+impl AesStrength {
+    pub fn from_byte(byte: u8) -> Option<Self> {
+        match byte {
+            1 => Some(AesStrength::Aes128),
+            2 => Some(AesStrength::Aes192),
+            3 => Some(AesStrength::Aes256),
+            _ => None,
+        }
+    }
+
+    pub fn key_len(&self) -> usize {
+        match self {
+            AesStrength::Aes128 => 16,
+            AesStrength::Aes192 => 24,
+            AesStrength::Aes256 => 32,
+        }
+    }
+
+    /// The salt is half as long as the key.
+    pub fn salt_len(&self) -> usize {
+        self.key_len() / 2
+    }
+}
+
+impl Display for AesStrength {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "AES-{}", self.key_len() * 8)
+    }
+}
+
+/// AE-1 entries keep their CRC-32 while AE-2 entries set it to 0 and only rely on the
+/// authentication code.
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub enum AesVendorVersion {
+    Ae1,
+    Ae2,
+}
+
+/// The AES extra field (0x9901) of entries encrypted with WinZip AES. The compression method of
+/// these entries is set to 99 and the real one is kept in the extra field.
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub struct AesExtraField {
+    pub vendor_version: AesVendorVersion,
+    pub strength: AesStrength,
+    pub compression_method: u16,
+}
+
+impl AesExtraField {
+    pub fn from_bytes(data: &[u8]) -> Option<Self> {
+        if data.len() != AES_EXTRA_FIELD_DATA_SIZE || &data[2..4] != AES_VENDOR_ID {
+            return None;
+        }
+
+        let vendor_version = match LittleEndian::read_u16(&data[0..2]) {
+            1 => AesVendorVersion::Ae1,
+            2 => AesVendorVersion::Ae2,
+            _ => return None,
+        };
+
+        Some(Self {
+            vendor_version,
+            strength: AesStrength::from_byte(data[4])?,
+            compression_method: LittleEndian::read_u16(&data[5..7]),
+        })
+    }
+
+    /// Returns the data of the extra field without its header.
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let vendor_version: u16 = match self.vendor_version {
+            AesVendorVersion::Ae1 => 1,
+            AesVendorVersion::Ae2 => 2,
+        };
+        let strength: u8 = match self.strength {
+            AesStrength::Aes128 => 1,
+            AesStrength::Aes192 => 2,
+            AesStrength::Aes256 => 3,
+        };
+        let mut data = Vec::with_capacity(AES_EXTRA_FIELD_DATA_SIZE);
+
+        data.extend_from_slice(&vendor_version.to_le_bytes());
+        data.extend_from_slice(AES_VENDOR_ID);
+        data.push(strength);
+        data.extend_from_slice(&self.compression_method.to_le_bytes());
+
+        data
+    }
+}
+
+enum AesCtr {
+    Aes128(Aes128Ctr),
+    Aes192(Aes192Ctr),
+    Aes256(Aes256Ctr),
+}
+
+impl AesCtr {
+    fn new(strength: AesStrength, key: &[u8]) -> Self {
+        // The key length always matches the strength so creating the cipher cannot fail
+        match strength {
+            AesStrength::Aes128 => {
+                AesCtr::Aes128(Aes128Ctr::new_from_slices(key, &INITIAL_COUNTER_BLOCK).unwrap())
+            }
+            AesStrength::Aes192 => {
+                AesCtr::Aes192(Aes192Ctr::new_from_slices(key, &INITIAL_COUNTER_BLOCK).unwrap())
+            }
+            AesStrength::Aes256 => {
+                AesCtr::Aes256(Aes256Ctr::new_from_slices(key, &INITIAL_COUNTER_BLOCK).unwrap())
+            }
+        }
+    }
+
+    fn apply_keystream(&mut self, buf: &mut [u8]) {
+        match self {
+            AesCtr::Aes128(cipher) => cipher.apply_keystream(buf),
+            AesCtr::Aes192(cipher) => cipher.apply_keystream(buf),
+            AesCtr::Aes256(cipher) => cipher.apply_keystream(buf),
+        }
+    }
+}
+
+// Derives the encryption key, the authentication key and the password verifier from the password
+fn derive_keys(
+    password: &str,
+    salt: &[u8],
+    strength: AesStrength,
+) -> (AesCtr, Hmac<Sha1>, [u8; PASSWORD_VERIFIER_LEN]) {
+    let key_len = strength.key_len();
+    let mut derived_key = vec![0u8; 2 * key_len + PASSWORD_VERIFIER_LEN];
+
+    pbkdf2::pbkdf2_hmac::<Sha1>(
+        password.as_bytes(),
+        salt,
+        PBKDF2_ITERATIONS,
+        &mut derived_key,
+    );
+
+    // The HMAC-SHA1 key can be of any length so creating it cannot fail
+    let hmac = Hmac::<Sha1>::new_from_slice(&derived_key[key_len..2 * key_len]).unwrap();
+    let mut password_verifier = [0u8; PASSWORD_VERIFIER_LEN];
+
+    password_verifier.copy_from_slice(&derived_key[2 * key_len..]);
+
+    (
+        AesCtr::new(strength, &derived_key[..key_len]),
+        hmac,
+        password_verifier,
+    )
+}
+
+/// Decrypts the data of an entry encrypted with WinZip AES. The encrypted data is preceded by a
+/// salt and a password verifier and followed by an authentication code, which is checked as soon
+/// as the last byte of the data is read.
+pub struct AesReader<R: Read> {
+    reader: R,
+    cipher: AesCtr,
+    hmac: Hmac<Sha1>,
+    remaining_len: u64,
+}
+
+impl<R: Read> AesReader<R> {
+    /// The given length is the length of the whole entry including the salt, the password
+    /// verifier and the authentication code, which is the compressed size of the entry.
+    pub fn new(
+        password: &str,
+        strength: AesStrength,
+        mut reader: R,
+        encrypted_len: u64,
+    ) -> Result<Self, AesError> {
+        if password.is_empty() {
+            return Err(AesError::EmptyPassword);
+        }
+
+        let mut salt = vec![0u8; strength.salt_len()];
+        let mut password_verifier = [0u8; PASSWORD_VERIFIER_LEN];
+
+        reader
+            .read_exact(&mut salt)
+            .and_then(|_| reader.read_exact(&mut password_verifier))
+            .map_err(|err| AesError::IOError(err.to_string()))?;
+
+        let (cipher, hmac, expected_password_verifier) = derive_keys(password, &salt, strength);
+
+        if expected_password_verifier != password_verifier {
+            return Err(AesError::IncorrectPassword);
+        }
+
+        let overhead_len = (salt.len() + PASSWORD_VERIFIER_LEN + AUTHENTICATION_CODE_LEN) as u64;
+        let remaining_len = encrypted_len.checked_sub(overhead_len).ok_or_else(|| {
+            AesError::IOError(format!(
+                "The encrypted data is shorter than the {} bytes of the AES header",
+                overhead_len
+            ))
+        })?;
+
+        Ok(Self {
+            reader,
+            cipher,
+            hmac,
+            remaining_len,
+        })
+    }
+
+    fn verify_authentication_code(&mut self) -> std::io::Result<()> {
+        let mut authentication_code = [0u8; AUTHENTICATION_CODE_LEN];
+
+        self.reader.read_exact(&mut authentication_code)?;
+
+        // The authentication code is the first 10 bytes of the HMAC-SHA1 of the encrypted data
+        self.hmac
+            .clone()
+            .verify_truncated_left(&authentication_code)
+            .map_err(|_| {
+                std::io::Error::new(
+                    std::io::ErrorKind::InvalidData,
+                    AesError::AuthenticationFailed,
+                )
+            })
+    }
+}
+
+impl<R: Read> Read for AesReader<R> {
+    fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+        if self.remaining_len == 0 || buf.is_empty() {
+            return Ok(0);
+        }
+
+        let read_len = buf
+            .len()
+            .min(self.remaining_len.min(usize::MAX as u64) as usize);
+        let read_bytes = self.reader.read(&mut buf[..read_len])?;
+
+        if read_bytes == 0 {
+            return Err(std::io::Error::new(
+                std::io::ErrorKind::UnexpectedEof,
+                "The AES encrypted data ended before its authentication code",
+            ));
+        }
+
+        // The authentication code is computed over the encrypted bytes
+        self.hmac.update(&buf[..read_bytes]);
+        self.cipher.apply_keystream(&mut buf[..read_bytes]);
+        self.remaining_len -= read_bytes as u64;
+
+        if self.remaining_len == 0 {
+            self.verify_authentication_code()?;
+        }
+
+        Ok(read_bytes)
+    }
+}
+
+/// Encrypts the data of an entry with WinZip AES. A random salt and the password verifier are
+/// written as soon as the writer is created and the authentication code is written by `finish`.
+pub struct AesWriter<W: Write> {
+    writer: W,
+    cipher: AesCtr,
+    hmac: Hmac<Sha1>,
+    buf: Vec<u8>,
+}
+
+impl<W: Write> AesWriter<W> {
+    pub fn new(password: &str, strength: AesStrength, mut writer: W) -> Result<Self, AesError> {
+        if password.is_empty() {
+            return Err(AesError::EmptyPassword);
+        }
+
+        let mut salt = vec![0u8; strength.salt_len()];
+
+        getrandom::fill(&mut salt).map_err(|err| AesError::IOError(err.to_string()))?;
+
+        let (cipher, hmac, password_verifier) = derive_keys(password, &salt, strength);
+
+        writer
+            .write_all(&salt)
+            .and_then(|_| writer.write_all(&password_verifier))
+            .map_err(|err| AesError::IOError(err.to_string()))?;
+
+        Ok(Self {
+            writer,
+            cipher,
+            hmac,
+            buf: Vec::new(),
+        })
+    }
+
+    /// Writes the authentication code after the encrypted data and returns the underlying
+    /// writer.
+    pub fn finish(mut self) -> std::io::Result<W> {
+        let authentication_code = self.hmac.finalize().into_bytes();
+
+        self.writer
+            .write_all(&authentication_code[..AUTHENTICATION_CODE_LEN])?;
+
+        Ok(self.writer)
+    }
+}
+
+impl<W: Write> Write for AesWriter<W> {
+    fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+        // The keystream moves forward as the bytes are encrypted, so the whole buffer is written
+        // before returning
+        self.buf.clear();
+        self.buf.extend_from_slice(buf);
+        self.cipher.apply_keystream(&mut self.buf);
+        self.hmac.update(&self.buf);
+        self.writer.write_all(&self.buf)?;
+
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> std::io::Result<()> {
+        self.writer.flush()
+    }
+}
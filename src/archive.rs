@@ -1,34 +1,71 @@
 use std::error::Error;
 use std::fmt::Display;
-use std::fs::File;
 use std::io::{BufRead, BufReader, Read, Seek, SeekFrom, Write};
-use std::path::{Path, PathBuf};
+use std::ops::Deref;
+use std::path::{Component, Path, PathBuf};
+use std::sync::atomic::{AtomicUsize, Ordering};
 
 use byteorder::{ByteOrder, LittleEndian};
-use crc::{Crc, CRC_32_ISO_HDLC};
-use flate2::bufread::DeflateDecoder;
+use crc::{Algorithm, Crc, CRC_32_ISO_HDLC};
+use filetime::FileTime;
+use flate2::read::DeflateDecoder;
 
+use crate::aes::{AesError, AesReader};
 use crate::commands::ExtractOptions;
-use crate::headers::{CompressionMethod, EncryptionMethod, ZipFile};
+use crate::extra_field::{ExtraFields, UnixExtraField};
+use crate::file_type::{FileType, SNIFFED_PREFIX_SIZE};
+use crate::headers::{CompressionMethod, EncryptionMethod, EntryKind, ZipFile};
+#[cfg(feature = "lzma")]
+use crate::lzma::lzma_reader;
+use crate::sink::ExtractSink;
+use crate::util::long_path;
 use crate::zip_crypto::{ZipCryptoError, ZipCryptoReader, ZIP_CRYPTO_RANDOM_BYTES_LEN};
 use crate::Crc32;
 
 const MIN_LOCAL_FILE_HEADER_SIZE: usize = 30;
 const FILE_READ_WRITE_BUFFER_SIZE: usize = 4096;
+// setuid, setgid and sticky bits
+const SPECIAL_PERMISSIONS_MASK: u32 = 0o7000;
+// Longest symlink target read from an entry, PATH_MAX on Linux
+const SYMLINK_TARGET_MAX_LEN: usize = 4096;
+// Small entries are not checked against the compression ratio limit since a short run of repeated
+// bytes compresses well beyond any sensible limit without being harmful.
+const COMPRESSION_RATIO_MIN_SIZE: u64 = 1024 * 1024;
 
 pub trait ReadableArchive: BufRead + Seek {}
 
 impl<T: Read + Seek> ReadableArchive for BufReader<T> {}
 
-#[derive(Debug, PartialEq, Eq)]
+/// Name of an entry with the result of extracting or verifying it.
+pub type EntryResult = (String, Result<(), ExtractError>);
+
+#[derive(Debug, PartialEq, Eq, Clone)]
 pub enum ExtractError {
     IOError(String),
     InvalidZipFileParent(PathBuf),
     UnableToCreateExtractedFile(String, String),
     DeflateDecodingError(String),
+    DecodingError(CompressionMethod, String),
     InvalidExtractedFile(u32, u32),
     UnsupportedEncryption(EncryptionMethod),
     ZipCryptoError(ZipCryptoError),
+    AesError(AesError),
+    EntryNotFound(String),
+    MemoryBudgetExceeded(usize),
+    EntriesFailed(usize),
+    EntriesCorrupt(usize),
+    InvalidExtractedSize(u64, u64),
+    CompressionRatioExceeded(u64, u64),
+    DeclaredSizeExceeded(u64),
+    EntrySizeExceeded(u64),
+    TotalSizeExceeded(u64),
+    EntryCountExceeded(usize),
+    ExternalSymlink(String, String),
+    EntryThroughSymlink(String),
+    DuplicateEntry(String),
+    DestinationExists(PathBuf),
+    ArchivesFailed(usize),
+    InvalidInto(PathBuf),
 }
 
 impl Display for ExtractError {
@@ -37,187 +74,939 @@ impl Display for ExtractError {
             ExtractError::IOError(err_msg) => {
                 write!(f, "An I/O error occurred while extracting file {}", err_msg)
             }
-            ExtractError::InvalidZipFileParent(parent_path) => write!(f, "Invalid parent path for the zip file. Invalid parent path: {}", parent_path.as_path().display().to_string()),
+            ExtractError::InvalidZipFileParent(parent_path) => write!(f, "Invalid parent path for the zip file. Invalid parent path: {}", parent_path.as_path().display()),
             ExtractError::UnableToCreateExtractedFile(file_name, error_msg) => write!(f, "Unable to create the extracted file \"{}\".\nError: {}", file_name, error_msg),
             ExtractError::DeflateDecodingError(error_msg) => write!(f, "Unable to decode the deflated stream. {}", error_msg),
+            ExtractError::DecodingError(compression_method, error_msg) => write!(f, "Unable to decode the {} stream. {}", compression_method, error_msg),
             ExtractError::InvalidExtractedFile(crc32, extracted_file_crc32) => write!(f, "Extracted file corruption. CRC-32 checksums are not matching. File CRC-32: 0x{:X}, Extracted file CRC-32: 0x{:X}", crc32, extracted_file_crc32),
             ExtractError::UnsupportedEncryption(encryption_method) => write!(f, "Unsupported encryption method set for the zip file. Read Encryption method: {}", encryption_method),
             ExtractError::ZipCryptoError(err) => write!(f, "{}", err),
+            ExtractError::AesError(err) => write!(f, "{}", err),
+            ExtractError::EntryNotFound(file_name) => write!(f, "No entry named \"{}\" found in the archive", file_name),
+            ExtractError::MemoryBudgetExceeded(limit) => write!(f, "Decoding the entry in memory exceeds the memory budget of {} bytes", limit),
+            ExtractError::EntriesFailed(failed_count) => write!(f, "{} entries could not be extracted", failed_count),
+            ExtractError::EntriesCorrupt(corrupt_count) => write!(f, "{} entries are corrupt", corrupt_count),
+            ExtractError::CompressionRatioExceeded(ratio, max_ratio) => write!(f, "The entry expands {}:1 which exceeds the compression ratio limit of {}:1. It may be a zip bomb", ratio, max_ratio),
+            ExtractError::DeclaredSizeExceeded(size) => write!(f, "The decoded entry exceeds its declared uncompressed size of {} bytes. It may be a zip bomb", size),
+            ExtractError::EntrySizeExceeded(max_size) => write!(f, "The entry exceeds the size limit of {} bytes", max_size),
+            ExtractError::TotalSizeExceeded(max_size) => write!(f, "The extracted entries exceed the total size limit of {} bytes", max_size),
+            ExtractError::EntryCountExceeded(max_count) => write!(f, "The archive has more than {} entries", max_count),
+            ExtractError::ArchivesFailed(failed_count) => write!(f, "{} archives could not be extracted", failed_count),
+            ExtractError::DestinationExists(path) => write!(f, "The destination \"{}\" already exists", path.display()),
+            ExtractError::DuplicateEntry(file_name) => write!(f, "The archive has more than one entry named \"{}\"", file_name),
+            ExtractError::ExternalSymlink(file_name, target) => write!(f, "The symlink \"{}\" points to \"{}\" outside of the destination", file_name, target),
+            ExtractError::EntryThroughSymlink(file_name) => write!(f, "The entry \"{}\" would be written through a symlink extracted before it", file_name),
+            ExtractError::InvalidExtractedSize(size, extracted_size) => write!(f, "Extracted file corruption. Sizes are not matching. File size: {}, Extracted file size: {}", size, extracted_size),
+            ExtractError::InvalidInto(into) => write!(f, "Invalid directory to extract into \"{}\". It must be a relative path without \"..\" components", into.display()),
         }
     }
 }
 
 impl Error for ExtractError {}
 
+impl ExtractError {
+    /// Whether the error comes from a wrong password, in which case another password may still
+    /// decrypt the entry.
+    pub fn is_wrong_password(&self) -> bool {
+        matches!(
+            self,
+            Self::ZipCryptoError(ZipCryptoError::IncorrectPassword)
+                | Self::AesError(AesError::IncorrectPassword)
+        )
+    }
+
+    /// Whether the error shows that the password is wrong for the entry, either from its password
+    /// verifier or from the AES authentication code checked once the entry is read to its end.
+    /// Other errors come from a corrupt entry instead.
+    pub fn is_password_failure(&self) -> bool {
+        self.is_wrong_password() || matches!(self, Self::AesError(AesError::AuthenticationFailed))
+    }
+
+    // Whether the decoded data of the entry turned out wrong, either from a corrupt entry or from a
+    // wrong Zip Crypto password which passed its check byte
+    fn is_data_error(&self) -> bool {
+        matches!(
+            self,
+            Self::DeflateDecodingError(_)
+                | Self::DecodingError(..)
+                | Self::InvalidExtractedFile(..)
+                | Self::InvalidExtractedSize(..)
+        )
+    }
+}
+
+/// Caps the number of bytes decoded into memory by the in-memory extraction APIs. The budget is
+/// shared across decodes so a single budget bounds every buffer handed out with it. The bytes of a
+/// buffer are given back to the budget when the buffer is dropped.
+#[derive(Debug)]
+pub struct MemoryBudget {
+    limit: usize,
+    used: AtomicUsize,
+}
+
+impl MemoryBudget {
+    pub fn new(limit: usize) -> Self {
+        Self {
+            limit,
+            used: AtomicUsize::new(0),
+        }
+    }
+
+    pub fn limit(&self) -> usize {
+        self.limit
+    }
+
+    pub fn used(&self) -> usize {
+        self.used.load(Ordering::Acquire)
+    }
+
+    /// Charges the given number of bytes, failing without charging anything if the budget would
+    /// be exceeded.
+    fn reserve(&self, bytes: usize) -> Result<(), ExtractError> {
+        self.used
+            .fetch_update(Ordering::AcqRel, Ordering::Acquire, |used| {
+                used.checked_add(bytes).filter(|used| *used <= self.limit)
+            })
+            .map(|_| ())
+            .map_err(|_| ExtractError::MemoryBudgetExceeded(self.limit))
+    }
+
+    fn release(&self, bytes: usize) {
+        // The closure never fails so neither does the update
+        let _ = self
+            .used
+            .fetch_update(Ordering::AcqRel, Ordering::Acquire, |used| {
+                Some(used.saturating_sub(bytes))
+            });
+    }
+
+    /// Returns how many more bytes can be charged.
+    fn remaining(&self) -> usize {
+        self.limit.saturating_sub(self.used())
+    }
+}
+
+/// Bytes decoded into memory by the in-memory extraction APIs. The whole capacity of the buffer is
+/// charged to the budget it was decoded with and released when the buffer is dropped.
+#[derive(Debug)]
+pub struct MemoryBuffer<'a> {
+    data: Vec<u8>,
+    budget: &'a MemoryBudget,
+    charged: usize,
+}
+
+impl<'a> MemoryBuffer<'a> {
+    fn new(budget: &'a MemoryBudget) -> Self {
+        Self {
+            data: Vec::new(),
+            budget,
+            charged: 0,
+        }
+    }
+
+    /// Appends the bytes, growing the buffer in steps charged to the budget before they are
+    /// allocated. The capacity is doubled while the budget allows it, then grown to what is left
+    /// of the budget.
+    fn extend_from_slice(&mut self, bytes: &[u8]) -> Result<(), ExtractError> {
+        let needed_capacity = self
+            .data
+            .len()
+            .checked_add(bytes.len())
+            .ok_or(ExtractError::MemoryBudgetExceeded(self.budget.limit()))?;
+
+        if needed_capacity > self.charged {
+            let grown_capacity = self
+                .charged
+                .saturating_mul(2)
+                .min(self.charged.saturating_add(self.budget.remaining()))
+                .max(needed_capacity);
+
+            self.charge(grown_capacity - self.charged)?;
+            self.data.reserve_exact(grown_capacity - self.data.len());
+
+            // The allocator may hand out more than asked for
+            if self.data.capacity() > self.charged {
+                self.charge(self.data.capacity() - self.charged)?;
+            }
+        }
+
+        self.data.extend_from_slice(bytes);
+
+        Ok(())
+    }
+
+    // Gives the unused capacity back to the budget once nothing else is appended.
+    fn shrink_to_fit(&mut self) {
+        self.data.shrink_to_fit();
+
+        if self.data.capacity() < self.charged {
+            self.budget.release(self.charged - self.data.capacity());
+            self.charged = self.data.capacity();
+        }
+    }
+
+    fn charge(&mut self, bytes: usize) -> Result<(), ExtractError> {
+        self.budget.reserve(bytes)?;
+        self.charged += bytes;
+
+        Ok(())
+    }
+
+    /// Number of bytes charged to the budget for the buffer.
+    pub fn charged(&self) -> usize {
+        self.charged
+    }
+}
+
+impl Drop for MemoryBuffer<'_> {
+    fn drop(&mut self) {
+        self.budget.release(self.charged);
+    }
+}
+
+impl Deref for MemoryBuffer<'_> {
+    type Target = [u8];
+
+    fn deref(&self) -> &Self::Target {
+        &self.data
+    }
+}
+
+impl AsRef<[u8]> for MemoryBuffer<'_> {
+    fn as_ref(&self) -> &[u8] {
+        &self.data
+    }
+}
+
+impl PartialEq for MemoryBuffer<'_> {
+    fn eq(&self, other: &Self) -> bool {
+        self.data == other.data
+    }
+}
+
+impl Eq for MemoryBuffer<'_> {}
+
+impl PartialEq<[u8]> for MemoryBuffer<'_> {
+    fn eq(&self, other: &[u8]) -> bool {
+        self.data == other
+    }
+}
+
+impl PartialEq<&[u8]> for MemoryBuffer<'_> {
+    fn eq(&self, other: &&[u8]) -> bool {
+        self.data == *other
+    }
+}
+
+impl<const N: usize> PartialEq<&[u8; N]> for MemoryBuffer<'_> {
+    fn eq(&self, other: &&[u8; N]) -> bool {
+        self.data == *other
+    }
+}
+
+impl PartialEq<Vec<u8>> for MemoryBuffer<'_> {
+    fn eq(&self, other: &Vec<u8>) -> bool {
+        self.data == *other
+    }
+}
+
 pub trait Extract {
     //TODO: Consider making ExtractError as trait type
-    fn extract<P, R>(
+    /// Writes the entry to the given output path of the sink, which is already resolved from the
+    /// extract options.
+    fn extract<P, R, S>(
         &self,
-        extract_path: &P,
+        output_path: &P,
         extract_file: &mut R,
         password: &Option<String>,
-        verbose: bool,
+        extract_options: &ExtractOptions,
+        sink: &mut S,
     ) -> Result<(), ExtractError>
     where
         P: AsRef<Path>,
-        R: ReadableArchive;
+        R: ReadableArchive,
+        S: ExtractSink;
 }
 
 pub trait Archive {
+    /// Extracts the entries, trying each of the passwords on the encrypted ones.
     fn extract_items(
         &mut self,
         extract_path: ExtractOptions,
-        password: Option<String>,
+        passwords: Vec<String>,
     ) -> Result<usize, ExtractError>;
 }
 
 impl Extract for ZipFile {
-    fn extract<P, R>(
+    fn extract<P, R, S>(
         &self,
-        extract_path: &P,
+        output_path: &P,
         extract_file: &mut R,
         password: &Option<String>,
-        verbose: bool,
+        extract_options: &ExtractOptions,
+        sink: &mut S,
     ) -> Result<(), ExtractError>
     where
         P: AsRef<Path>,
         R: ReadableArchive,
+        S: ExtractSink,
     {
-        let mut extracted_file_path = PathBuf::new();
+        let extracted_file_path = output_path.as_ref().to_path_buf();
 
-        extracted_file_path.push(extract_path);
-        extracted_file_path.push(self.file_name());
+        if let Some(max_ratio) = extract_options.max_compression_ratio {
+            let ratio = compression_ratio(self);
 
-        if verbose {
+            if ratio > max_ratio {
+                return Err(ExtractError::CompressionRatioExceeded(ratio, max_ratio));
+            }
+        }
+
+        if extract_options.verbose {
             println!("Extracting {}", extracted_file_path.display());
         }
 
         //If the file is just a directory then just create the directory.
         if self.is_dir() {
-            return std::fs::create_dir_all(extracted_file_path)
+            return sink
+                .create_dir(&extracted_file_path)
                 .map_err(|err| ExtractError::IOError(err.to_string()));
         }
 
         // If the parent folder for the file is not created then create the parent folder before
         // creating the file.
         if let Some(parent_path) = extracted_file_path.parent() {
-            if !parent_path.exists() {
-                std::fs::create_dir_all(parent_path)
-                    .map_err(|err| ExtractError::IOError(err.to_string()))?;
-            }
+            sink.create_dir(parent_path)
+                .map_err(|err| ExtractError::IOError(err.to_string()))?;
         } else {
             return Err(ExtractError::InvalidZipFileParent(extracted_file_path));
         }
 
-        let mut file = File::create(extracted_file_path.clone()).map_err(|err| {
-            ExtractError::UnableToCreateExtractedFile(self.file_name().clone(), err.to_string())
-        })?;
-        let mut local_file_header_bytes = vec![0u8; MIN_LOCAL_FILE_HEADER_SIZE];
-
-        extract_file
-            .seek(std::io::SeekFrom::Start(self.offset() as u64))
-            .map_err(|err| ExtractError::IOError(err.to_string()))?;
-        extract_file
-            .read_exact(&mut local_file_header_bytes)
-            .map_err(|err| ExtractError::IOError(err.to_string()))?;
+        if self.entry_kind() == EntryKind::Symlink {
+            return extract_symlink(
+                self,
+                &extracted_file_path,
+                extract_file,
+                password,
+                extract_options,
+                sink,
+            );
+        }
 
-        let file_name_len = self.file_name().len();
-        let extra_field_len = LittleEndian::read_u16(&local_file_header_bytes[28..]) as usize;
-        let file_bytes_start_offset = file_name_len + extra_field_len;
+        // Entries whose size is missing from their ZIP64 extra field are only checked while they
+        // are decoded
+        let declared_size = Some(self.uncompressed_size().get())
+            .filter(|&uncompressed_size| uncompressed_size != u32::MAX as u64)
+            .unwrap_or(0);
+        let written_size = extract_options.totals.written_size.get();
+        let total_size_limit = extract_options
+            .max_total_size
+            .map(|max_total_size| (max_total_size, max_total_size.saturating_sub(written_size)));
 
-        extract_file
-            .seek(SeekFrom::Current(file_bytes_start_offset as i64))
-            .map_err(|err| ExtractError::IOError(err.to_string()))?;
+        if let Some(max_entry_size) = extract_options.max_entry_size {
+            if declared_size > max_entry_size {
+                return Err(ExtractError::EntrySizeExceeded(max_entry_size));
+            }
+        }
 
-        // Zip Crypto appends extra 12 bytes at the beginning of the file stream so we should also
-        // include those into our "take" consideration
-        let extra_encryption_len = match self.encryption_method() {
-            EncryptionMethod::NoEncryption => 0,
-            EncryptionMethod::ZipCrypto => ZIP_CRYPTO_RANDOM_BYTES_LEN as u64,
-            EncryptionMethod::Aes => {
-                return Err(ExtractError::UnsupportedEncryption(EncryptionMethod::Aes))
+        if let Some((max_total_size, remaining_size)) = total_size_limit {
+            if declared_size > remaining_size {
+                return Err(ExtractError::TotalSizeExceeded(max_total_size));
             }
+        }
+
+        let mut file = CountingWriter {
+            inner: sink.create_file(&extracted_file_path).map_err(|err| {
+                ExtractError::UnableToCreateExtractedFile(self.file_name().clone(), err.to_string())
+            })?,
+            written: 0,
         };
+        let mut entry_reader = entry_reader(self, extract_file, password)?;
 
-        let mut file_data_reader =
-            if let CompressionMethod::NoCompression = self.compression_method() {
-                extract_file.take((self.uncompressed_size().get() as u64) + extra_encryption_len)
-            } else {
-                extract_file.take(self.compressed_size().get() as u64 + extra_encryption_len)
-            };
-        let mut zip_crypto_reader;
-
-        let mut file_reader_by_encryption: &mut dyn BufRead = match self.encryption_method() {
-            EncryptionMethod::NoEncryption => &mut file_data_reader,
-            EncryptionMethod::ZipCrypto => {
-                let password = match password {
-                    Some(pass) => pass.clone(),
-                    None => {
-                        return Err(ExtractError::ZipCryptoError(ZipCryptoError::EmptyPassword))
-                    }
-                };
+        if let Some(max_entry_size) = extract_options.max_entry_size {
+            entry_reader = Box::new(SizeLimitedReader {
+                inner: entry_reader,
+                limit: max_entry_size,
+                read_size: 0,
+                exceeded: ExtractError::EntrySizeExceeded(max_entry_size),
+            });
+        }
+
+        if let Some((max_total_size, remaining_size)) = total_size_limit {
+            entry_reader = Box::new(SizeLimitedReader {
+                inner: entry_reader,
+                limit: remaining_size,
+                read_size: 0,
+                exceeded: ExtractError::TotalSizeExceeded(max_total_size),
+            });
+        }
+
+        //Decode the file while calculating CRC-32 of the written bytes
+        let copy_result = copy_and_calculate_crc32(
+            &mut entry_reader,
+            &mut file,
+            self.compression_method(),
+            extract_options.crc_algorithm,
+        );
+
+        // The bytes written before a failure count as well since they stay on the disk
+        extract_options
+            .totals
+            .written_size
+            .set(written_size + file.written);
+
+        verify_crc32(self, copy_result)?;
+        file.flush()
+            .map_err(|err| ExtractError::IOError(err.to_string()))?;
+        drop(file);
+        drop(entry_reader);
+
+        sink.set_metadata(self, &extracted_file_path, extract_file, extract_options)
+    }
+}
+
+// Creates a symlink pointing to the target stored as the data of the entry. Hosts without symlinks
+// get a regular file containing the target instead.
+fn extract_symlink<R, S>(
+    zip_file: &ZipFile,
+    extracted_file_path: &Path,
+    extract_file: &mut R,
+    password: &Option<String>,
+    extract_options: &ExtractOptions,
+    sink: &mut S,
+) -> Result<(), ExtractError>
+where
+    R: ReadableArchive,
+    S: ExtractSink,
+{
+    let budget = MemoryBudget::new(SYMLINK_TARGET_MAX_LEN);
+    let target = read_to_memory(
+        zip_file,
+        extract_file,
+        password,
+        &budget,
+        extract_options.crc_algorithm,
+    )?;
+    let target = String::from_utf8_lossy(&target).into_owned();
+
+    if extract_options.refuse_external_symlinks {
+        let extract_root = long_path(&extract_options.extract_root());
+        let link_path = extracted_file_path
+            .strip_prefix(&extract_root)
+            .unwrap_or(extracted_file_path);
+        // The links already on the disk are followed when the link is created on the disk, which
+        // the path alone doesn't tell about
+        let stays_inside = extracted_file_path
+            .parent()
+            .and_then(|link_dir| resolved_link_stays_inside(&extract_root, link_dir, &target, sink))
+            .unwrap_or(true);
+
+        if !stays_inside || !link_stays_inside(link_path, &target) {
+            return Err(ExtractError::ExternalSymlink(
+                zip_file.file_name().clone(),
+                target,
+            ));
+        }
+    }
+
+    sink.create_symlink(extracted_file_path, &target)
+        .map_err(|err| {
+            ExtractError::UnableToCreateExtractedFile(zip_file.file_name().clone(), err.to_string())
+        })?;
+    extract_options
+        .created_symlinks
+        .borrow_mut()
+        .insert(extracted_file_path.to_path_buf());
+
+    sink.set_metadata(zip_file, extracted_file_path, extract_file, extract_options)
+}
+
+// Returns whether the target of a link at the given path relative to the destination resolves to
+// a path inside the destination. Only the path is looked at, not the links already extracted.
+fn link_stays_inside(link_path: &Path, target: &str) -> bool {
+    let target = Path::new(target);
+
+    if target.has_root() {
+        return false;
+    }
 
-                zip_crypto_reader =
-                    ZipCryptoReader::new(password, self.crc32().get(), file_data_reader)
-                        .map_err(|err| ExtractError::ZipCryptoError(err))?;
+    let mut depth = 0usize;
+    let parent_components = link_path
+        .parent()
+        .map(|parent| parent.components().collect::<Vec<_>>())
+        .unwrap_or_default();
 
-                &mut zip_crypto_reader
+    for component in parent_components.into_iter().chain(target.components()) {
+        match component {
+            Component::Normal(_) => depth += 1,
+            Component::CurDir => (),
+            Component::ParentDir if depth > 0 => depth -= 1,
+            _ => return false,
+        }
+    }
+
+    true
+}
+
+// Returns whether the target of a link created in the given directory resolves to a path inside
+// the extraction root once the directory and the links the target goes through are resolved on
+// the sink. Returns `None` when they cannot be resolved, like when the sink doesn't follow
+// symlinks.
+fn resolved_link_stays_inside<S>(
+    extract_root: &Path,
+    link_dir: &Path,
+    target: &str,
+    sink: &S,
+) -> Option<bool>
+where
+    S: ExtractSink,
+{
+    let extract_root = if extract_root.as_os_str().is_empty() {
+        Path::new(".")
+    } else {
+        extract_root
+    };
+    let extract_root = sink.canonicalize(extract_root).ok()?;
+    let mut resolved_path = sink.canonicalize(link_dir).ok()?;
+    let target = Path::new(target);
+
+    if target.has_root() {
+        return Some(false);
+    }
+
+    for component in target.components() {
+        match component {
+            Component::Normal(name) => {
+                resolved_path.push(name);
+
+                if let Ok(canonical_path) = sink.canonicalize(&resolved_path) {
+                    resolved_path = canonical_path;
+                }
             }
-            EncryptionMethod::Aes => {
-                return Err(ExtractError::UnsupportedEncryption(EncryptionMethod::Aes))
+            Component::CurDir => (),
+            Component::ParentDir => {
+                resolved_path.pop();
             }
-        };
+            _ => return Some(false),
+        }
+    }
 
-        //Decode the file
-        let created_file_crc32 = match self.compression_method() {
-            CompressionMethod::NoCompression => {
-                //If no compression is set then just copy the file bytes into destination and
-                //calculate CRC-32
-                std::io::copy(&mut file_reader_by_encryption, &mut file)
-                    .map_err(|err| ExtractError::IOError(err.to_string()))?;
-                calculate_crc32(extracted_file_path)
-                    .map_err(|err| ExtractError::IOError(err.to_string()))?
-            }
-            CompressionMethod::Deflate(_) => decode_and_write_deflated_compressed_data(
-                &mut file_reader_by_encryption,
-                &mut file,
-            )?,
-        };
+    Some(resolved_path.starts_with(extract_root))
+}
 
-        //If we extract a file then make sure that CRC-32 checksums are matching
-        if !self.is_dir() {
-            let crc32 = self.crc32().get();
+/// Runs the operation on the entry with the passwords the callback gives for it in turn until one
+/// of them decrypts the entry. The callback is called with the name of the entry and the number of
+/// the attempt, starting from 0, and returns `None` once it has no more passwords. Zip Crypto only
+/// checks a single byte of the password, so a password passing the check may still turn out wrong
+/// while the entry is decoded and the next one is tried then. The entry is taken as corrupt when
+/// none of the remaining passwords decrypts it, returning the error of the decoding.
+pub fn with_entry_password<R, F, T>(
+    zip_file: &ZipFile,
+    readable: &mut R,
+    password_for: &mut F,
+    mut operation: impl FnMut(&mut R, &Option<String>) -> Result<T, ExtractError>,
+) -> Result<T, ExtractError>
+where
+    R: ReadableArchive,
+    F: FnMut(&str, usize) -> Option<String>,
+{
+    if !matches!(
+        zip_file.encryption_method(),
+        EncryptionMethod::ZipCrypto | EncryptionMethod::Aes
+    ) {
+        return operation(readable, &None);
+    }
+
+    let is_zip_crypto = zip_file.encryption_method() == &EncryptionMethod::ZipCrypto;
+    let mut attempt = 0;
+    let mut password = password_for(zip_file.file_name(), attempt);
+    let mut data_error = None;
+
+    loop {
+        // Checking the password first keeps a wrong one from creating the output
+        let result = entry_reader(zip_file, readable, &password)
+            .map(|_| ())
+            .and_then(|_| operation(readable, &password));
+
+        match result {
+            Err(err) if err.is_wrong_password() || (is_zip_crypto && err.is_data_error()) => {
+                if !err.is_wrong_password() {
+                    data_error.get_or_insert(err.clone());
+                }
 
-            // If checksums are not matching then quit extracting the file.
-            if crc32 != created_file_crc32 {
-                return Err(ExtractError::InvalidExtractedFile(
-                    crc32,
-                    created_file_crc32,
-                ));
+                attempt += 1;
+
+                let Some(next_password) = password_for(zip_file.file_name(), attempt) else {
+                    return Err(data_error.unwrap_or(err));
+                };
+
+                password = Some(next_password);
             }
+            result => return result,
         }
+    }
+}
 
+/// Checks the password against the given entry without writing anything. Zip Crypto entries only
+/// have their verification byte checked. AES entries are decrypted as well so that their
+/// authentication code is checked, since their 2 byte password verifier lets some wrong passwords
+/// through. The entry is not decompressed, so a corrupt compressed stream doesn't fail the check.
+pub fn check_entry_password<R>(
+    zip_file: &ZipFile,
+    readable: &mut R,
+    password: &Option<String>,
+) -> Result<(), ExtractError>
+where
+    R: ReadableArchive,
+{
+    let mut decrypted_reader = decrypted_entry_reader(zip_file, readable, password)?;
+
+    if zip_file.encryption_method() == &EncryptionMethod::Aes {
+        std::io::copy(&mut decrypted_reader, &mut std::io::sink())
+            .map_err(|err| read_error(&CompressionMethod::NoCompression, err))?;
+    }
+
+    Ok(())
+}
+
+/// Recognizes the type of the given entry from the start of its decoded content, as described in
+/// [`FileType::sniff`].
+pub fn sniff_file_type<R>(
+    zip_file: &ZipFile,
+    readable: &mut R,
+    password: &Option<String>,
+) -> Result<Option<FileType>, ExtractError>
+where
+    R: ReadableArchive,
+{
+    let mut prefix = Vec::with_capacity(SNIFFED_PREFIX_SIZE);
+
+    entry_reader(zip_file, readable, password)?
+        .take(SNIFFED_PREFIX_SIZE as u64)
+        .read_to_end(&mut prefix)
+        .map_err(|err| read_error(zip_file.compression_method(), err))?;
+
+    Ok(FileType::sniff(&prefix))
+}
+
+/// Decodes the given entry without writing it anywhere and verifies its CRC-32 and uncompressed
+/// size, like `unzip -t`.
+pub fn verify_entry<R>(
+    zip_file: &ZipFile,
+    readable: &mut R,
+    password: &Option<String>,
+) -> Result<(), ExtractError>
+where
+    R: ReadableArchive,
+{
+    if zip_file.is_dir() {
+        return Ok(());
+    }
+
+    let mut entry_reader = entry_reader(zip_file, readable, password)?;
+    let mut byte_counter = ByteCounter::default();
+
+    let copy_result = copy_and_calculate_crc32(
+        &mut entry_reader,
+        &mut byte_counter,
+        zip_file.compression_method(),
+        &CRC_32_ISO_HDLC,
+    );
+
+    verify_crc32(zip_file, copy_result)?;
+
+    // Entries whose size is missing from their ZIP64 extra field keep the placeholder
+    let uncompressed_size = zip_file.uncompressed_size().get();
+
+    if uncompressed_size != u32::MAX as u64 && byte_counter.0 != uncompressed_size {
+        return Err(ExtractError::InvalidExtractedSize(
+            uncompressed_size,
+            byte_counter.0,
+        ));
+    }
+
+    Ok(())
+}
+
+// Checks the CRC-32 of the decoded entry against the one stored in the archive.
+fn verify_crc32(
+    zip_file: &ZipFile,
+    copy_result: Result<Crc32, ExtractError>,
+) -> Result<(), ExtractError> {
+    let created_file_crc32 = copy_result?;
+
+    //If we extract a file then make sure that CRC-32 checksums are matching
+    if !zip_file.is_dir() && zip_file.stores_crc32() {
+        let crc32 = zip_file.crc32().get();
+
+        // If checksums are not matching then quit extracting the file.
+        if crc32 != created_file_crc32 {
+            return Err(ExtractError::InvalidExtractedFile(
+                crc32,
+                created_file_crc32,
+            ));
+        }
+    }
+
+    Ok(())
+}
+
+// Discards the written bytes, only counting them.
+#[derive(Default)]
+struct ByteCounter(u64);
+
+// Counts the bytes written to the inner writer
+struct CountingWriter<W> {
+    inner: W,
+    written: u64,
+}
+
+impl<W: Write> Write for CountingWriter<W> {
+    fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+        let written = self.inner.write(buf)?;
+        self.written += written as u64;
+        Ok(written)
+    }
+
+    fn flush(&mut self) -> std::io::Result<()> {
+        self.inner.flush()
+    }
+}
+
+impl Write for ByteCounter {
+    fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+        self.0 += buf.len() as u64;
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> std::io::Result<()> {
         Ok(())
     }
 }
 
-fn decode_and_write_deflated_compressed_data<R, W>(
+/// Opens a reader yielding the decrypted and decompressed bytes of the given entry. The reader
+/// borrows the archive so it must be dropped before another entry is read.
+pub fn entry_reader<'a, R>(
+    zip_file: &ZipFile,
+    readable: &'a mut R,
+    password: &Option<String>,
+) -> Result<Box<dyn Read + 'a>, ExtractError>
+where
+    R: ReadableArchive,
+{
+    let file_reader_by_encryption = decrypted_entry_reader(zip_file, readable, password)?;
+
+    // The decoder reads the decrypted stream through `Read` so that it never sees the encrypted
+    // bytes of the underlying reader.
+    let decoder: Box<dyn Read + 'a> = match zip_file.compression_method() {
+        CompressionMethod::NoCompression => file_reader_by_encryption,
+        CompressionMethod::Deflate(_) => Box::new(DeflateDecoder::new(file_reader_by_encryption)),
+        #[cfg(feature = "bzip2")]
+        CompressionMethod::Bzip2 => {
+            Box::new(bzip2::read::BzDecoder::new(file_reader_by_encryption))
+        }
+        #[cfg(feature = "lzma")]
+        compression_method @ CompressionMethod::Lzma { end_marker } => {
+            let uncompressed_size = (!end_marker).then(|| zip_file.uncompressed_size().get());
+
+            Box::new(
+                lzma_reader(file_reader_by_encryption, uncompressed_size).map_err(|err| {
+                    ExtractError::DecodingError(*compression_method, err.to_string())
+                })?,
+            )
+        }
+        #[cfg(feature = "zstd")]
+        compression_method @ CompressionMethod::Zstd => Box::new(
+            zstd::Decoder::new(file_reader_by_encryption)
+                .map_err(|err| ExtractError::DecodingError(*compression_method, err.to_string()))?,
+        ),
+        #[cfg(feature = "xz")]
+        CompressionMethod::Xz => Box::new(xz2::read::XzDecoder::new(file_reader_by_encryption)),
+    };
+
+    // Entries whose size is missing from their ZIP64 extra field keep the placeholder
+    match zip_file.uncompressed_size().get() {
+        uncompressed_size if uncompressed_size == u32::MAX as u64 => Ok(decoder),
+        uncompressed_size => Ok(Box::new(SizeLimitedReader {
+            inner: decoder,
+            limit: uncompressed_size,
+            read_size: 0,
+            exceeded: ExtractError::DeclaredSizeExceeded(uncompressed_size),
+        })),
+    }
+}
+
+// Opens a reader yielding the decrypted but still compressed bytes of the given entry.
+fn decrypted_entry_reader<'a, R>(
+    zip_file: &ZipFile,
+    readable: &'a mut R,
+    password: &Option<String>,
+) -> Result<Box<dyn Read + 'a>, ExtractError>
+where
+    R: ReadableArchive,
+{
+    let mut local_file_header_bytes = vec![0u8; MIN_LOCAL_FILE_HEADER_SIZE];
+
+    readable
+        .seek(SeekFrom::Start(zip_file.offset()))
+        .map_err(|err| ExtractError::IOError(err.to_string()))?;
+    readable
+        .read_exact(&mut local_file_header_bytes)
+        .map_err(|err| ExtractError::IOError(err.to_string()))?;
+
+    let file_name_len = LittleEndian::read_u16(&local_file_header_bytes[26..28]) as usize;
+    let extra_field_len = LittleEndian::read_u16(&local_file_header_bytes[28..]) as usize;
+    let file_bytes_start_offset = file_name_len + extra_field_len;
+
+    readable
+        .seek(SeekFrom::Current(file_bytes_start_offset as i64))
+        .map_err(|err| ExtractError::IOError(err.to_string()))?;
+
+    // Zip Crypto appends extra 12 bytes at the beginning of the file stream so we should also
+    // include those into our "take" consideration
+    let extra_encryption_len = match zip_file.encryption_method() {
+        EncryptionMethod::NoEncryption => 0,
+        EncryptionMethod::ZipCrypto => ZIP_CRYPTO_RANDOM_BYTES_LEN as u64,
+        // The compressed size of AES entries already covers the salt, the password verifier and
+        // the authentication code
+        EncryptionMethod::Aes => 0,
+        encryption_method @ EncryptionMethod::StrongEncryption => {
+            return Err(ExtractError::UnsupportedEncryption(*encryption_method))
+        }
+    };
+
+    let file_data_reader = match (zip_file.encryption_method(), zip_file.compression_method()) {
+        (EncryptionMethod::Aes, _) => readable.take(zip_file.compressed_size().get()),
+        (_, CompressionMethod::NoCompression) => {
+            readable.take(zip_file.uncompressed_size().get() + extra_encryption_len)
+        }
+        _ => readable.take(zip_file.compressed_size().get() + extra_encryption_len),
+    };
+
+    let file_reader_by_encryption: Box<dyn Read + 'a> = match zip_file.encryption_method() {
+        EncryptionMethod::NoEncryption => Box::new(file_data_reader),
+        EncryptionMethod::ZipCrypto => {
+            let password = match password {
+                Some(pass) => pass.clone(),
+                None => return Err(ExtractError::ZipCryptoError(ZipCryptoError::EmptyPassword)),
+            };
+
+            Box::new(
+                ZipCryptoReader::with_check_byte(
+                    password,
+                    zip_file.zip_crypto_check_byte(),
+                    file_data_reader,
+                )
+                .map_err(ExtractError::ZipCryptoError)?,
+            )
+        }
+        EncryptionMethod::Aes => {
+            let password = match password {
+                Some(pass) => pass,
+                None => return Err(ExtractError::AesError(AesError::EmptyPassword)),
+            };
+            // An AES encrypted entry always has its extra field
+            let aes_extra_field = zip_file.aes_extra_field().unwrap();
+
+            Box::new(
+                AesReader::new(
+                    password,
+                    aes_extra_field.strength,
+                    file_data_reader,
+                    zip_file.compressed_size().get(),
+                )
+                .map_err(ExtractError::AesError)?,
+            )
+        }
+        encryption_method @ EncryptionMethod::StrongEncryption => {
+            return Err(ExtractError::UnsupportedEncryption(*encryption_method))
+        }
+    };
+
+    Ok(file_reader_by_encryption)
+}
+
+// Returns how many times larger the declared uncompressed size of the entry is than its
+// compressed size. Entries below 1 MiB and entries whose size is missing from their ZIP64 extra
+// field are reported with a ratio of 1.
+fn compression_ratio(zip_file: &ZipFile) -> u64 {
+    let uncompressed_size = zip_file.uncompressed_size().get();
+
+    if uncompressed_size < COMPRESSION_RATIO_MIN_SIZE || uncompressed_size == u32::MAX as u64 {
+        return 1;
+    }
+
+    uncompressed_size / zip_file.compressed_size().get().max(1)
+}
+
+// Fails with the given error once more than `limit` bytes are read, like when an entry lying about
+// its size expands beyond the size it declares.
+struct SizeLimitedReader<R> {
+    inner: R,
+    limit: u64,
+    read_size: u64,
+    exceeded: ExtractError,
+}
+
+impl<R: Read> Read for SizeLimitedReader<R> {
+    fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+        let read = self.inner.read(buf)?;
+        self.read_size += read as u64;
+
+        if self.read_size > self.limit {
+            return Err(std::io::Error::new(
+                std::io::ErrorKind::InvalidData,
+                self.exceeded.clone(),
+            ));
+        }
+
+        Ok(read)
+    }
+}
+
+// Errors reading a compressed entry come from its decoder, so they are reported as decoding errors
+fn read_error(compression_method: &CompressionMethod, err: std::io::Error) -> ExtractError {
+    // A failed AES authentication and an entry exceeding a size limit are passed through the
+    // decoder as they are
+    if let Some(aes_error) = err
+        .get_ref()
+        .and_then(|inner_err| inner_err.downcast_ref::<AesError>())
+    {
+        return ExtractError::AesError(aes_error.clone());
+    }
+
+    if let Some(extract_error) = err
+        .get_ref()
+        .and_then(|inner_err| inner_err.downcast_ref::<ExtractError>())
+    {
+        return extract_error.clone();
+    }
+
+    match compression_method {
+        CompressionMethod::NoCompression => ExtractError::IOError(err.to_string()),
+        CompressionMethod::Deflate(_) => ExtractError::DeflateDecodingError(err.to_string()),
+        #[allow(unreachable_patterns)]
+        compression_method => ExtractError::DecodingError(*compression_method, err.to_string()),
+    }
+}
+
+fn copy_and_calculate_crc32<R, W>(
     reader: &mut R,
     writer: &mut W,
+    compression_method: &CompressionMethod,
+    crc_algorithm: &'static Algorithm<u32>,
 ) -> Result<Crc32, ExtractError>
 where
-    R: BufRead,
+    R: Read,
     W: Write,
 {
-    let mut deflate_decoder = DeflateDecoder::new(reader);
     let mut buf = vec![0u8; FILE_READ_WRITE_BUFFER_SIZE];
-    let crc = Crc::<u32>::new(&CRC_32_ISO_HDLC);
+    let crc = Crc::<u32>::new(crc_algorithm);
     let mut digest = crc.digest();
 
     loop {
-        let read_bytes = deflate_decoder
+        let read_bytes = reader
             .read(&mut buf)
-            .map_err(|err| ExtractError::DeflateDecodingError(err.to_string()))?;
+            .map_err(|err| read_error(compression_method, err))?;
 
         if read_bytes == 0 {
             break;
@@ -233,13 +1022,211 @@ where
     Ok(digest.finalize())
 }
 
-fn calculate_crc32<P>(file_path: P) -> Result<Crc32, std::io::Error>
+/// Decodes the given entry into memory and verifies its CRC-32 with the given algorithm. The size
+/// stored in the archive is not trusted, so the buffer is grown in steps charged to the budget as
+/// the bytes are decoded and the partially decoded buffer is released if the budget runs out.
+pub fn read_to_memory<'a, R>(
+    zip_file: &ZipFile,
+    readable: &mut R,
+    password: &Option<String>,
+    budget: &'a MemoryBudget,
+    crc_algorithm: &'static Algorithm<u32>,
+) -> Result<MemoryBuffer<'a>, ExtractError>
+where
+    R: ReadableArchive,
+{
+    let mut entry_reader = entry_reader(zip_file, readable, password)?;
+    let mut buf = vec![0u8; FILE_READ_WRITE_BUFFER_SIZE];
+    let mut data = MemoryBuffer::new(budget);
+
+    loop {
+        let read_bytes = entry_reader
+            .read(&mut buf)
+            .map_err(|err| read_error(zip_file.compression_method(), err))?;
+
+        if read_bytes == 0 {
+            break;
+        }
+
+        data.extend_from_slice(&buf[..read_bytes])?;
+    }
+
+    data.shrink_to_fit();
+
+    let crc32 = zip_file.crc32().get();
+    let data_crc32 = Crc::<u32>::new(crc_algorithm).checksum(&data);
+
+    if zip_file.stores_crc32() && crc32 != data_crc32 {
+        return Err(ExtractError::InvalidExtractedFile(crc32, data_crc32));
+    }
+
+    Ok(data)
+}
+
+/// Returns whether the entry was already extracted to the given path of the sink, meaning the
+/// target file exists and its size and CRC-32 match the ones stored in the archive. The size is
+/// compared first so that partially written files aren't read at all.
+pub fn is_already_extracted<S>(
+    zip_file: &ZipFile,
+    extracted_file_path: &Path,
+    crc_algorithm: &'static Algorithm<u32>,
+    sink: &S,
+) -> bool
+where
+    S: ExtractSink,
+{
+    if zip_file.is_dir() {
+        return false;
+    }
+
+    sink.metadata(extracted_file_path).is_some_and(|metadata| {
+        metadata.is_file && metadata.len == zip_file.uncompressed_size().get()
+    }) && sink
+        .open_file(extracted_file_path)
+        .and_then(|extracted_file| calculate_crc32(extracted_file, crc_algorithm))
+        .is_ok_and(|extracted_file_crc32| extracted_file_crc32 == zip_file.crc32().get())
+}
+
+/// Changes the owner of the file, directory or symlink extracted to the given path to the uid and
+/// gid stored in the Info-ZIP Unix extra fields of the entry. The older field only holds them in
+/// the local file header. Changing the owner to another user requires running as root.
+pub fn set_owner<P, R>(
+    zip_file: &ZipFile,
+    extracted_file_path: P,
+    readable: &mut R,
+) -> Result<(), ExtractError>
+where
+    P: AsRef<Path>,
+    R: ReadableArchive,
+{
+    let owner = match zip_file.unix_extra_field() {
+        Some(owner) => Some(owner),
+        None => UnixExtraField::find(ExtraFields::new(&local_extra_field(zip_file, readable)?)),
+    };
+
+    #[cfg(unix)]
+    if let Some(UnixExtraField { uid, gid }) = owner {
+        std::os::unix::fs::lchown(extracted_file_path, Some(uid), Some(gid))
+            .map_err(|err| ExtractError::IOError(err.to_string()))?;
+    }
+
+    #[cfg(not(unix))]
+    let _ = (owner, extracted_file_path);
+
+    Ok(())
+}
+
+// Reads the extra field of the local file header of the entry
+fn local_extra_field<R>(zip_file: &ZipFile, readable: &mut R) -> Result<Vec<u8>, ExtractError>
+where
+    R: ReadableArchive,
+{
+    let mut local_file_header_bytes = vec![0u8; MIN_LOCAL_FILE_HEADER_SIZE];
+
+    readable
+        .seek(SeekFrom::Start(zip_file.offset()))
+        .and_then(|_| readable.read_exact(&mut local_file_header_bytes))
+        .map_err(|err| ExtractError::IOError(err.to_string()))?;
+
+    let file_name_len = LittleEndian::read_u16(&local_file_header_bytes[26..28]);
+    let extra_field_len = LittleEndian::read_u16(&local_file_header_bytes[28..]);
+    let mut extra_field = vec![0u8; extra_field_len as usize];
+
+    readable
+        .seek(SeekFrom::Current(file_name_len as i64))
+        .and_then(|_| readable.read_exact(&mut extra_field))
+        .map_err(|err| ExtractError::IOError(err.to_string()))?;
+
+    Ok(extra_field)
+}
+
+/// Applies the Unix permissions stored in the entry to the file or directory extracted to the given
+/// path, unless the options override them. Entries archived on other hosts keep the permissions
+/// they were created with, minus the bits of the mode mask. The setuid, setgid and sticky bits of
+/// the entry are dropped unless the options preserve them, so that an untrusted archive cannot
+/// plant a setuid binary.
+pub fn set_permissions<P>(
+    zip_file: &ZipFile,
+    extracted_file_path: P,
+    extract_options: &ExtractOptions,
+) -> Result<(), ExtractError>
 where
     P: AsRef<Path>,
 {
-    let mut extracted_file = File::open(file_path)?;
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::PermissionsExt;
+
+        let extracted_file_path = extracted_file_path.as_ref();
+        let forced_mode = if zip_file.is_dir() {
+            extract_options.dir_mode
+        } else {
+            extract_options.file_mode
+        };
+        let archived_mode = zip_file.unix_permissions().map(|mode| {
+            if extract_options.preserve_special_permissions {
+                mode
+            } else {
+                mode & !SPECIAL_PERMISSIONS_MASK
+            }
+        });
+        let mode = match forced_mode.or(archived_mode) {
+            Some(mode) => Some(mode),
+            None if extract_options.mode_mask.is_some() => Some(
+                std::fs::metadata(extracted_file_path)
+                    .map_err(|err| ExtractError::IOError(err.to_string()))?
+                    .permissions()
+                    .mode(),
+            ),
+            None => None,
+        };
+
+        if let Some(mode) = mode {
+            let mode = mode & !extract_options.mode_mask.unwrap_or(0);
+
+            std::fs::set_permissions(extracted_file_path, std::fs::Permissions::from_mode(mode))
+                .map_err(|err| ExtractError::IOError(err.to_string()))?;
+        }
+    }
+
+    #[cfg(not(unix))]
+    let _ = (zip_file, extracted_file_path, extract_options);
+
+    Ok(())
+}
+
+/// Sets the modification time of the file or directory extracted to the given path to the one
+/// stored in the entry.
+pub fn set_modified_time<P>(zip_file: &ZipFile, extracted_file_path: P) -> Result<(), ExtractError>
+where
+    P: AsRef<Path>,
+{
+    let modified = FileTime::from_system_time(zip_file.modified_time());
+
+    filetime::set_file_mtime(extracted_file_path, modified)
+        .map_err(|err| ExtractError::IOError(err.to_string()))
+}
+
+/// Returns whether the file extracted to the given path of the sink was last modified before the
+/// entry.
+pub fn is_outdated<S>(zip_file: &ZipFile, extracted_file_path: &Path, sink: &S) -> bool
+where
+    S: ExtractSink,
+{
+    sink.metadata(extracted_file_path)
+        .and_then(|metadata| metadata.modified)
+        .is_some_and(|modified| modified < zip_file.modified_time())
+}
+
+fn calculate_crc32<R>(
+    mut extracted_file: R,
+    crc_algorithm: &'static Algorithm<u32>,
+) -> Result<Crc32, std::io::Error>
+where
+    R: Read,
+{
     let mut buf = vec![0u8; FILE_READ_WRITE_BUFFER_SIZE];
-    let crc = Crc::<u32>::new(&CRC_32_ISO_HDLC);
+    let crc = Crc::<u32>::new(crc_algorithm);
     let mut digest = crc.digest();
 
     loop {
@@ -1,6 +1,14 @@
 use std::path::PathBuf;
 
-use clap::{Parser, Subcommand};
+use clap::{ArgGroup, Parser, Subcommand};
+
+use crate::commands::{
+    CaseCollisionPolicy, CollisionPolicy, DuplicatePolicy, EncryptionKind, OverwritePolicy,
+    DEFAULT_MAX_COMPRESSION_RATIO,
+};
+use crate::encoding::FileNameEncoding;
+use crate::file_type::FileType;
+use crate::writer::MIN_SPLIT_SIZE;
 
 #[derive(Parser)]
 #[command(author, version, about)]
@@ -20,14 +28,35 @@ pub enum ArchiveCommand {
 
 #[derive(Debug, clap::Args)]
 #[group(required = true)]
+#[command(group(ArgGroup::new("extracted").args(["extract", "extract_all"])))]
+#[command(group(ArgGroup::new("freshened").args(["extract", "extract_all", "create"])))]
+#[command(group(ArgGroup::new("decoded").args(["extract", "extract_all", "list"])))]
+#[command(group(ArgGroup::new("passworded").args(["extract", "extract_all", "test", "test_password", "encrypt"]).multiple(true)))]
 pub struct ZipCommand {
     #[arg(
         short = 'x',
         long,
-        help = "Extracts the given ZIP file.",
-        value_name = "ZIP_FILE_PATH"
+        help = "Extracts the given ZIP files, or the ones matching a glob like 'backups/*.zip'. The files after the first one must end in .zip, the arguments following them are glob patterns and only the entries matching one of them are extracted if any",
+        num_args = 1..,
+        value_names = ["ZIP_FILE_PATHS", "PATTERNS"]
+    )]
+    pub extract: Option<Vec<PathBuf>>,
+
+    #[arg(
+        last = true,
+        requires = "extracted",
+        help = "Glob patterns of the entries to extract, given after '--'. Every argument of --extract is then a ZIP file",
+        value_name = "PATTERNS"
+    )]
+    pub patterns: Vec<PathBuf>,
+
+    #[arg(
+        long,
+        help = "Extracts every ZIP file found under the given directory, each into a directory named after it next to it",
+        value_name = "DIR",
+        conflicts_with = "extract"
     )]
-    pub extract: Option<PathBuf>,
+    pub extract_all: Option<PathBuf>,
 
     #[arg(
         short,
@@ -44,11 +73,543 @@ pub struct ZipCommand {
     )]
     pub destination: Option<PathBuf>,
 
+    #[arg(
+        long,
+        help = "Extract only the first N files of the given zip file",
+        value_name = "N"
+    )]
+    pub limit: Option<usize>,
+
+    #[arg(
+        long,
+        help = "Extract only the entries whose path matches the given regular expression",
+        value_name = "PATTERN"
+    )]
+    pub regex: Option<String>,
+
+    #[arg(
+        long,
+        help = "Skip the files that were already extracted with a matching size and CRC-32, to continue an interrupted extraction"
+    )]
+    pub resume: bool,
+
+    #[arg(
+        long,
+        help = "Choose what happens when an extracted file already exists",
+        value_name = "POLICY",
+        default_value = "always"
+    )]
+    pub overwrite: OverwritePolicy,
+
+    #[arg(
+        long,
+        help = "Print the extraction plan as JSON without extracting any files"
+    )]
+    pub plan_json: bool,
+
+    #[arg(
+        long,
+        help = "Extract all entries under the given directory inside the destination",
+        value_name = "DIR"
+    )]
+    pub into: Option<PathBuf>,
+
+    #[arg(
+        long,
+        help = "Extract into a temporary directory next to the --into directory and rename it into place only when every file is extracted. The --into directory must not exist yet",
+        requires = "into"
+    )]
+    pub atomic: bool,
+
+    #[arg(
+        long,
+        help = "Extract the .zip files found in the archive as well, each into a directory named after it, down to the given depth",
+        value_name = "DEPTH",
+        num_args = 0..=1,
+        default_missing_value = "3",
+        requires = "extracted"
+    )]
+    pub recursive: Option<usize>,
+
+    #[arg(
+        long,
+        help = "Remove the given number of leading path components from the entry names when extracting, skipping the entries with no components left",
+        value_name = "N",
+        requires = "extracted"
+    )]
+    pub strip_components: Option<usize>,
+
     #[arg(
         short,
         long,
-        help = "List all files listed in a given zip file",
+        help = "Extract all files into the destination without their directories",
+        requires = "extracted"
+    )]
+    pub junk_paths: bool,
+
+    #[arg(
+        long,
+        help = "Choose which file is kept when files extracted with --junk-paths have the same name",
+        value_name = "POLICY",
+        default_value = "overwrite",
+        requires = "junk_paths"
+    )]
+    pub collision: CollisionPolicy,
+
+    #[arg(
+        long,
+        help = "Choose which entry is extracted when the archive has more than one file with the same name",
+        value_name = "POLICY",
+        default_value = "last",
+        requires = "extracted"
+    )]
+    pub duplicates: DuplicatePolicy,
+
+    #[arg(
+        long,
+        help = "Choose whether files whose names differ only by case warn or get renamed. Only Windows and macOS look for them",
+        value_name = "POLICY",
+        default_value = "warn",
+        requires = "extracted"
+    )]
+    pub case_collisions: CaseCollisionPolicy,
+
+    #[arg(
+        long,
+        visible_alias = "keep-going",
+        help = "Keep extracting the other files when a file is corrupt or cannot be decoded, and list the failed files at the end",
+        requires = "extracted"
+    )]
+    pub salvage: bool,
+
+    #[arg(
+        long,
+        help = "Refuse to extract the files of at least 1 MiB that expand more than the given number of times, which is likely a zip bomb",
+        value_name = "RATIO",
+        default_value_t = DEFAULT_MAX_COMPRESSION_RATIO,
+        requires = "extracted"
+    )]
+    pub max_ratio: u64,
+
+    #[arg(
+        long,
+        help = "Extract the files regardless of their compression ratio",
+        requires = "extracted",
+        conflicts_with = "max_ratio"
+    )]
+    pub no_max_ratio: bool,
+
+    #[arg(
+        long,
+        help = "Don't restore the modification times stored in the zip file on the extracted files",
+        requires = "extracted"
+    )]
+    pub ignore_timestamps: bool,
+
+    #[arg(
+        long,
+        help = "Refuse to extract the symlinks pointing outside of the destination",
+        requires = "extracted"
+    )]
+    pub refuse_external_symlinks: bool,
+
+    #[arg(
+        long,
+        help = "Hard link the files with the same CRC-32 and size as a file extracted before instead of writing them again",
+        requires = "extracted"
+    )]
+    pub link_duplicates: bool,
+
+    #[arg(
+        long,
+        help = "Restore the owners of the extracted files stored in the zip file when running as root",
+        requires = "extracted"
+    )]
+    pub same_owner: bool,
+
+    #[arg(
+        long,
+        help = "Clear the given octal permission bits like 022 on the extracted files and directories",
+        value_name = "MASK",
+        value_parser = parse_mode,
+        requires = "extracted"
+    )]
+    pub mode_mask: Option<u32>,
+
+    #[arg(
+        long,
+        help = "Give the extracted directories the given octal permissions like 755 instead of the ones stored in the zip file",
+        value_name = "MODE",
+        value_parser = parse_mode,
+        requires = "extracted"
+    )]
+    pub dir_mode: Option<u32>,
+
+    #[arg(
+        long,
+        help = "Give the extracted files the given octal permissions like 644 instead of the ones stored in the zip file",
+        value_name = "MODE",
+        value_parser = parse_mode,
+        requires = "extracted"
+    )]
+    pub file_mode: Option<u32>,
+
+    #[arg(
+        long,
+        help = "Keep the setuid, setgid and sticky bits stored in the zip file instead of clearing them",
+        requires = "extracted"
+    )]
+    pub preserve_special_permissions: bool,
+
+    #[arg(
+        long,
+        help = "Keep the names Windows cannot create like CON or a:b instead of renaming them. Only Windows renames them",
+        requires = "extracted"
+    )]
+    pub no_sanitize: bool,
+
+    #[arg(
+        long,
+        help = "Rename the files so that Linux, macOS and Windows can all create them, shortening names longer than 255 bytes",
+        requires = "extracted",
+        conflicts_with = "no_sanitize"
+    )]
+    pub portable_names: bool,
+
+    #[arg(
+        long,
+        help = "Encoding of the file names not flagged as UTF-8 like cp437, shift_jis or cp936. By default they are read as UTF-8 when valid and as cp437 otherwise",
+        value_name = "ENCODING",
+        value_parser = parse_encoding,
+        requires = "decoded"
+    )]
+    pub encoding: Option<FileNameEncoding>,
+
+    #[arg(
+        long,
+        help = "Leave out the __MACOSX/, ._* and .DS_Store entries macOS adds to zip files",
+        requires = "decoded"
+    )]
+    pub skip_macos_metadata: bool,
+
+    #[arg(
+        long,
+        help = "Only extract the files with the given comma separated extensions like pdf,docx",
+        value_name = "EXTENSIONS",
+        value_delimiter = ',',
+        requires = "extracted"
+    )]
+    pub only_ext: Vec<String>,
+
+    #[arg(
+        long,
+        help = "Only extract the files of the given comma separated types like images,video. Files whose extension tells no type are recognized by their content",
+        value_name = "TYPES",
+        value_delimiter = ',',
+        requires = "extracted"
+    )]
+    pub only_types: Vec<FileType>,
+
+    #[arg(
+        short,
+        long,
+        help = "List all files listed in the given zip files, or the ones matching a glob like 'backups/*.zip'",
+        num_args = 1..,
+        value_name = "ZIP_FILE_PATHS"
+    )]
+    pub list: Option<Vec<PathBuf>>,
+
+    #[arg(
+        long,
+        help = "Write a manifest of the listed entries in central directory order",
+        value_name = "MANIFEST_FILE_PATH",
+        requires = "list"
+    )]
+    pub manifest: Option<PathBuf>,
+
+    #[arg(
+        long,
+        help = "List entries as tab separated name, size and CRC-32 lines for scripting",
+        requires = "list"
+    )]
+    pub porcelain: bool,
+
+    #[arg(
+        long,
+        help = "Creates a zip file from the given files and directories. Use - as the path to write the zip file to stdout",
+        num_args = 1..,
+        value_names = ["ZIP_FILE_PATH", "INPUTS"]
+    )]
+    pub create: Option<Vec<PathBuf>>,
+
+    #[arg(
+        long,
+        help = "Only replace the entries of the existing zip file whose file is newer. When extracting, only the existing files older than their entry are replaced",
+        requires = "freshened"
+    )]
+    pub freshen: bool,
+
+    #[arg(
+        long,
+        help = "Add the inputs to the existing zip file without rewriting its entries",
+        requires = "create",
+        conflicts_with = "freshen"
+    )]
+    pub append: bool,
+
+    #[arg(
+        long,
+        help = "Create the same zip file for the same files by sorting the entries and fixing their timestamps to SOURCE_DATE_EPOCH. Encrypted zip files cannot be deterministic since the encryption is salted randomly",
+        requires = "create",
+        conflicts_with = "encrypt"
+    )]
+    pub deterministic: bool,
+
+    #[arg(
+        long,
+        help = "Skip the files matched by .gitignore and .zippyignore files while walking the inputs",
+        requires = "create"
+    )]
+    pub use_ignore_files: bool,
+
+    #[arg(
+        long,
+        help = "Skip the files and directories matching the glob while walking the inputs. Can be given multiple times",
+        value_name = "GLOB",
+        requires = "create"
+    )]
+    pub exclude: Vec<String>,
+
+    #[arg(
+        long,
+        help = "Add the paths listed in the file, one per line or separated by NUL characters. Listed directories are not walked",
+        value_name = "FILE",
+        requires = "create"
+    )]
+    pub files_from: Option<PathBuf>,
+
+    #[arg(
+        short = '@',
+        help = "Add the paths read from stdin like --files-from",
+        requires = "create",
+        conflicts_with = "files_from"
+    )]
+    pub files_from_stdin: bool,
+
+    #[arg(
+        long,
+        help = "Also write non-ASCII file names to the Info-ZIP Unicode Path extra field",
+        requires = "create"
+    )]
+    pub unicode_extra_field: bool,
+
+    #[arg(
+        long,
+        help = "Add the data read from stdin as an entry with the given name",
+        value_name = "ENTRY_NAME",
+        requires = "create",
+        conflicts_with_all = ["freshen", "files_from_stdin"]
+    )]
+    pub add_from_stdin: Option<String>,
+
+    #[arg(
+        long,
+        help = "Store files with the given comma separated suffixes like png,jpg,mp4 without compression",
+        value_name = "SUFFIXES",
+        value_delimiter = ',',
+        requires = "create"
+    )]
+    pub store_suffixes: Vec<String>,
+
+    #[arg(
+        long,
+        help = "Compression level from 0 (store) to 9 (maximum compression)",
+        value_name = "LEVEL",
+        value_parser = clap::value_parser!(u32).range(0..=9),
+        requires = "create"
+    )]
+    pub level: Option<u32>,
+
+    #[arg(
+        long,
+        help = "Give every entry the RFC 3339 modification time like 2023-09-25T20:59:31Z instead of the time of its file",
+        value_name = "RFC3339",
+        requires = "create"
+    )]
+    pub mtime: Option<String>,
+
+    #[arg(
+        long,
+        help = "Set the comment of the created zip file",
+        value_name = "TEXT",
+        requires = "create"
+    )]
+    pub comment: Option<String>,
+
+    #[arg(
+        long,
+        help = "Set the comment of the created zip file to the content of the given file",
+        value_name = "FILE",
+        requires = "create",
+        conflicts_with = "comment"
+    )]
+    pub comment_file: Option<PathBuf>,
+
+    #[arg(
+        long,
+        help = "Add the mimetype file first and uncompressed as required by EPUB and OpenDocument files",
+        requires = "create",
+        conflicts_with_all = ["freshen", "append"]
+    )]
+    pub mimetype_first: bool,
+
+    #[arg(
+        long,
+        help = "Align the data of stored entries to N bytes like zipalign so they can be memory mapped",
+        value_name = "N",
+        value_parser = clap::value_parser!(u16).range(1..),
+        requires = "create"
+    )]
+    pub align: Option<u16>,
+
+    #[arg(
+        long,
+        help = "Encrypt the added files with the given method. The password is asked for before the files are added unless --password is given",
+        value_name = "METHOD",
+        requires = "create"
+    )]
+    pub encrypt: Option<EncryptionKind>,
+
+    #[arg(
+        long,
+        help = "Password of the encrypted files instead of asking for it. Give it more than once for entries encrypted with different passwords. Other users of the system may see it in the process list, so prefer --password-file or the ZIPPY_PASSWORD environment variable",
+        value_name = "PASSWORD",
+        requires = "passworded"
+    )]
+    pub password: Vec<String>,
+
+    #[arg(
+        long,
+        help = "Read the passwords of the encrypted files from the given file, one per line, instead of asking for them",
+        value_name = "PATH",
+        requires = "passworded",
+        conflicts_with = "password"
+    )]
+    pub password_file: Option<PathBuf>,
+
+    #[arg(
+        long,
+        help = "Split the created zip file into .z01, .z02, ... segments of at most the given size like 100M. The suffixes k, m and g are multiples of 1024 and the smallest size is 64k",
+        value_name = "SIZE",
+        value_parser = parse_split_size,
+        requires = "create",
+        conflicts_with_all = ["freshen", "append"]
+    )]
+    pub split_size: Option<u64>,
+
+    #[cfg(feature = "zstd")]
+    #[arg(
+        long,
+        help = "Compress files with Zstandard (method 93) instead of DEFLATE. Not every tool can extract them",
+        requires = "create"
+    )]
+    pub zstd: bool,
+
+    #[arg(
+        long,
+        help = "Renames an entry of the given zip file. Renaming a directory moves the entries under it",
+        num_args = 3,
+        value_names = ["OLD_NAME", "NEW_NAME", "ZIP_FILE_PATH"]
+    )]
+    pub rename: Option<Vec<String>>,
+
+    #[arg(
+        long,
+        help = "Print the fields of the end of central directory record of the given zip file",
         value_name = "ZIP_FILE_PATH"
     )]
-    pub list: Option<PathBuf>,
+    pub eocd: Option<PathBuf>,
+
+    #[arg(
+        long,
+        help = "Compare the entries of two zip files and print the ones added, removed or changed in the second one",
+        num_args = 2,
+        value_names = ["OLD_ZIP_FILE_PATH", "NEW_ZIP_FILE_PATH"]
+    )]
+    pub diff: Option<Vec<PathBuf>>,
+
+    #[arg(
+        long,
+        help = "Print a summary of the given zip file including the data found before and after it",
+        value_name = "ZIP_FILE_PATH"
+    )]
+    pub info: Option<PathBuf>,
+
+    #[arg(
+        short,
+        long,
+        help = "Test the integrity of the given zip file by decoding every entry and verifying its CRC-32 and size without writing any files",
+        value_name = "ZIP_FILE_PATH"
+    )]
+    pub test: Option<PathBuf>,
+
+    #[arg(
+        long,
+        help = "Check whether the password is correct for each encrypted entry of the given zip file without extracting anything",
+        value_name = "ZIP_FILE_PATH"
+    )]
+    pub test_password: Option<PathBuf>,
+
+    #[arg(
+        long,
+        help = "Writes the entries that can be recovered from a damaged zip file into a new zip file with a rebuilt central directory",
+        num_args = 2,
+        value_names = ["ZIP_FILE_PATH", "OUTPUT_PATH"]
+    )]
+    pub repair: Option<Vec<PathBuf>>,
+
+    #[arg(
+        long,
+        help = "Sets or replaces the comment of the given zip file",
+        num_args = 2,
+        value_names = ["COMMENT", "ZIP_FILE_PATH"]
+    )]
+    pub set_comment: Option<Vec<String>>,
+}
+
+fn parse_encoding(value: &str) -> Result<FileNameEncoding, String> {
+    FileNameEncoding::for_label(value)
+        .ok_or_else(|| format!("\"{}\" is not a known encoding", value))
+}
+
+// Parses octal permissions like 755 or 0644
+fn parse_mode(value: &str) -> Result<u32, String> {
+    u32::from_str_radix(value, 8)
+        .ok()
+        .filter(|mode| *mode <= 0o7777)
+        .ok_or_else(|| format!("\"{}\" is not octal permissions like 755", value))
+}
+
+// Parses a size like 100M or 650m, the suffixes are binary multiples like in Info-ZIP
+fn parse_split_size(value: &str) -> Result<u64, String> {
+    let (digits, multiplier) = match value.char_indices().last() {
+        Some((index, 'k' | 'K')) => (&value[..index], 1 << 10),
+        Some((index, 'm' | 'M')) => (&value[..index], 1 << 20),
+        Some((index, 'g' | 'G')) => (&value[..index], 1 << 30),
+        _ => (value, 1),
+    };
+    let split_size = digits
+        .parse::<u64>()
+        .ok()
+        .and_then(|size| size.checked_mul(multiplier))
+        .ok_or_else(|| format!("\"{}\" is not a size like 100M", value))?;
+
+    if split_size < MIN_SPLIT_SIZE {
+        return Err(format!(
+            "The segments must be at least {} bytes",
+            MIN_SPLIT_SIZE
+        ));
+    }
+
+    Ok(split_size)
 }
@@ -1,18 +1,322 @@
-use std::fs::File;
-use std::io::BufReader;
-use std::path::{Path, PathBuf};
+use std::borrow::Cow;
+use std::cell::{Cell, RefCell};
+use std::collections::{HashMap, HashSet};
+use std::error::Error;
+use std::ffi::{OsStr, OsString};
+use std::fmt::Display;
+use std::fs::{File, OpenOptions};
+use std::io::{BufReader, BufWriter, Read, Seek, SeekFrom, Write};
+use std::path::{Component, Path, PathBuf};
+use std::rc::Rc;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
 
-use crate::archive::{Archive, ExtractError};
-use crate::pretty_printer::pretty_print_zip_files;
-use crate::zip::Zip;
+use byteorder::{LittleEndian, WriteBytesExt};
+use crc::{Algorithm, CRC_32_ISO_HDLC};
+use globset::{Glob, GlobSet, GlobSetBuilder};
+use ignore::WalkBuilder;
+use regex::Regex;
+
+use crate::archive::{Archive, ExtractError, ReadableArchive};
+use crate::date_time::{parse_rfc3339, ZipDateTime};
+use crate::encoding::FileNameEncoding;
+use crate::file_type::FileType;
+use crate::headers::{
+    CompressionMethod, DeflateCompressionMode, EncryptionMethod, EndOfCentralDirectory,
+    EndOfCentralDirectoryError, EntryKind, FileNamePolicy, ParseOptions, ZipFile,
+    EOF_CENTRAL_DIR_COMMENT_LEN_OFFSET, LOCAL_FILE_HEADER_SIGN, MAX_COMMENT_LEN,
+    MIN_EOF_CENTRAL_DIR_SIZE,
+};
+use crate::plan::PlanAction;
+use crate::pretty_printer::{
+    format_archive_info, format_end_of_central_dir, format_porcelain, pretty_print_zip_files,
+};
+use crate::sink::FileSystemSink;
+use crate::split::{write_segments, SplitArchive};
+use crate::util::{long_path, portable_file_name, sanitize_file_name, split_file_name};
+use crate::writer::{Encryption, EntryOptions, WriteError, ZipWriter};
+use crate::zip::{Zip, ZipError};
 
 const UNABLE_TO_OPEN_FILE_ERROR_RETURN_CODE: i32 = -3;
 const ZIP_FILE_PARSING_ERROR_RETURN_CODE: i32 = -2;
+const MANIFEST_WRITE_ERROR_RETURN_CODE: i32 = -5;
+// Times the password is asked for before the extraction is given up
+const PASSWORD_ATTEMPTS: usize = 3;
+const SOURCE_DATE_EPOCH_ENV_VAR: &str = "SOURCE_DATE_EPOCH";
+const ZIPPY_IGNORE_FILE_NAME: &str = ".zippyignore";
+const MIMETYPE_ENTRY_NAME: &str = "mimetype";
+/// Archive path that writes the created archive to stdout.
+pub const STDOUT_FILE_PATH: &str = "-";
+/// Compression ratio above which entries are refused unless the limit is lifted. Regular files
+/// rarely compress beyond 100:1 with DEFLATE.
+pub const DEFAULT_MAX_COMPRESSION_RATIO: u64 = 100;
+// Deflate can make incompressible data slightly larger, so files close to 4 GB are written as
+// large files too
+const LARGE_FILE_SIZE: u64 = 0xF000_0000;
+
+#[derive(Debug)]
+pub enum CommentError {
+    CommentTooLong(usize),
+    EndOfCentralDirectoryError(EndOfCentralDirectoryError),
+    IOError(String),
+}
+
+impl Display for CommentError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            CommentError::CommentTooLong(len) => write!(
+                f,
+                "Archive comment cannot be longer than {} bytes. Given comment length: {}",
+                MAX_COMMENT_LEN, len
+            ),
+            CommentError::EndOfCentralDirectoryError(err) => {
+                write!(f, "An error occurred while reading ZIP file.\n{}", err)
+            }
+            CommentError::IOError(error_msg) => write!(
+                f,
+                "An I/O error occurred while writing the archive comment. Message: {}",
+                error_msg
+            ),
+        }
+    }
+}
+
+impl Error for CommentError {}
+
+#[derive(Debug)]
+pub enum UpdateError {
+    ZipError(ZipError),
+    WriteError(WriteError),
+    EntryNotFound(String),
+    EntryAlreadyExists(String),
+    InvalidSourceDateEpoch(String),
+    InvalidMtime(String),
+    InvalidExcludePattern(String),
+    UpdateOnStdout,
+    SplitNotSupported,
+    MimetypeNotFound,
+    RepairIntoInput,
+    DeterministicEncryption,
+    IOError(String),
+}
+
+impl Display for UpdateError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            UpdateError::ZipError(err) => write!(f, "{}", err),
+            UpdateError::WriteError(err) => write!(f, "{}", err),
+            UpdateError::EntryNotFound(file_name) => {
+                write!(f, "No entry named \"{}\" found in the archive", file_name)
+            }
+            UpdateError::EntryAlreadyExists(file_name) => write!(
+                f,
+                "An entry named \"{}\" already exists in the archive",
+                file_name
+            ),
+            UpdateError::InvalidSourceDateEpoch(source_date_epoch) => write!(
+                f,
+                "{} must be the number of seconds since the Unix epoch. Given value: {}",
+                SOURCE_DATE_EPOCH_ENV_VAR, source_date_epoch
+            ),
+            UpdateError::InvalidMtime(mtime) => write!(
+                f,
+                "The modification time must be an RFC 3339 date time like 2023-09-25T20:59:31Z. Given value: {}",
+                mtime
+            ),
+            UpdateError::InvalidExcludePattern(error_msg) => {
+                write!(f, "Invalid exclude pattern. Message: {}", error_msg)
+            }
+            UpdateError::UpdateOnStdout => write!(
+                f,
+                "Only new archives can be written to stdout, existing archives cannot be updated"
+            ),
+            UpdateError::SplitNotSupported => write!(
+                f,
+                "Only new archives written to a file can be split, existing archives cannot be updated"
+            ),
+            UpdateError::MimetypeNotFound => write!(
+                f,
+                "No \"{}\" file found at the root of the inputs to add first",
+                MIMETYPE_ENTRY_NAME
+            ),
+            UpdateError::RepairIntoInput => write!(
+                f,
+                "The repaired archive must be written to another file than the archive being repaired"
+            ),
+            UpdateError::DeterministicEncryption => write!(
+                f,
+                "Encrypted archives cannot be deterministic since the encryption uses random salts and headers"
+            ),
+            UpdateError::IOError(error_msg) => write!(
+                f,
+                "An I/O error occurred while updating the archive. Message: {}",
+                error_msg
+            ),
+        }
+    }
+}
+
+impl Error for UpdateError {}
+
+/// Encryption method of the files added to a created archive.
+#[derive(Debug, PartialEq, Eq, Clone, Copy, clap::ValueEnum)]
+pub enum EncryptionKind {
+    /// WinZip AES with a 256-bit key.
+    Aes256,
+    /// Traditional PKWARE encryption supported by every unzip tool.
+    #[value(name = "zipcrypto")]
+    ZipCrypto,
+}
+
+/// Controls what happens when an extracted file already exists.
+#[derive(Debug, Default, PartialEq, Eq, Clone, Copy, clap::ValueEnum)]
+pub enum OverwritePolicy {
+    #[default]
+    Always,
+    Skip,
+}
+
+/// Controls which file is kept when entries extracted with junked paths share a file name.
+#[derive(Debug, Default, PartialEq, Eq, Clone, Copy, clap::ValueEnum)]
+pub enum CollisionPolicy {
+    /// The last entry with the name is kept.
+    #[default]
+    Overwrite,
+    /// The first entry with the name is kept.
+    Skip,
+    /// Every entry is kept, the later ones with a `-1`, `-2`, ... suffix added to their name.
+    Rename,
+}
+
+/// Controls which copy is extracted when the archive has more than one file entry with the same
+/// name.
+#[derive(Debug, Default, PartialEq, Eq, Clone, Copy, clap::ValueEnum)]
+pub enum DuplicatePolicy {
+    /// The first entry with the name is extracted.
+    First,
+    /// The last entry with the name is extracted.
+    #[default]
+    Last,
+    /// Every entry is extracted, the later ones with a `-1`, `-2`, ... suffix added to their name.
+    KeepAllRenamed,
+    /// Nothing is extracted.
+    Error,
+}
+
+/// Controls what happens to the entries whose paths differ from an earlier one's only by case,
+/// which would overwrite it on a case-insensitive filesystem.
+#[derive(Debug, Default, PartialEq, Eq, Clone, Copy, clap::ValueEnum)]
+pub enum CaseCollisionPolicy {
+    /// The later entry overwrites the earlier one with a warning.
+    #[default]
+    Warn,
+    /// The later entry gets a `-1`, `-2`, ... suffix added to its name.
+    Rename,
+}
+
+/// Running totals of an extraction, shared with the extractions of the archives nested in it so
+/// that the limits apply to all of them together.
+#[derive(Default)]
+pub(crate) struct ExtractTotals {
+    // Bytes written so far, checked against `max_total_size`
+    pub(crate) written_size: Cell<u64>,
+    // Entries in the archives extracted so far, checked against `max_entry_count`
+    pub(crate) entry_count: Cell<usize>,
+}
 
+#[derive(Clone)]
 pub struct ExtractOptions {
     pub path: PathBuf,
     pub verbose: bool,
     pub destination_path: Option<PathBuf>,
+    /// Maximum number of files to extract, in central directory order.
+    pub limit: Option<usize>,
+    /// Only entries whose full stored path matches the pattern are extracted.
+    pub regex: Option<Regex>,
+    /// Only entries whose full stored path matches one of the glob patterns are extracted.
+    pub patterns: Option<GlobSet>,
+    /// Skip the files that were already extracted with a matching size and CRC-32.
+    pub resume: bool,
+    /// Checksum algorithm the extracted files are verified with. Virtually all archives use
+    /// CRC-32/ISO-HDLC.
+    pub crc_algorithm: &'static Algorithm<u32>,
+    pub overwrite_policy: OverwritePolicy,
+    /// Print the extraction plan as JSON instead of extracting.
+    pub plan_json: bool,
+    // Directory prepended to the path of every extracted entry, set with `set_into`
+    pub(crate) into: Option<PathBuf>,
+    /// Extract into a staging directory next to the `into` directory and rename it into place
+    /// only once every entry is extracted. The `into` directory must not exist yet.
+    pub atomic: bool,
+    /// Keep extracting the other entries when an entry fails to extract.
+    pub salvage: bool,
+    /// How many levels of `.zip` entries are extracted in turn, each into a directory named after
+    /// it next to it. Nested archives are left as they are when zero.
+    pub recursion_depth: usize,
+    /// Number of leading path components removed from the entry names like
+    /// `tar --strip-components`. Entries with no components left are skipped.
+    pub strip_components: usize,
+    /// Extract all files into the extraction root without their directories.
+    pub junk_paths: bool,
+    pub collision_policy: CollisionPolicy,
+    pub duplicate_policy: DuplicatePolicy,
+    /// Look for entries differing only by case. Enabled by default on Windows and macOS, whose
+    /// filesystems are case-insensitive.
+    pub case_insensitive: bool,
+    pub case_collision_policy: CaseCollisionPolicy,
+    /// Only replace the existing files whose entry was modified later, without creating new ones.
+    pub freshen: bool,
+    /// Refuse to extract the entries whose declared uncompressed size is more than this many
+    /// times their compressed size, to defend against zip bombs.
+    pub max_compression_ratio: Option<u64>,
+    /// Refuse to extract archives with more entries than this, counting the entries of the nested
+    /// archives extracted along with them.
+    pub max_entry_count: Option<usize>,
+    /// Maximum number of bytes written for a single entry.
+    pub max_entry_size: Option<u64>,
+    /// Maximum number of bytes written for all of the entries together, including the entries of
+    /// the nested archives.
+    pub max_total_size: Option<u64>,
+    /// Leave the modification times of the extracted files at the time they were written
+    /// instead of the ones stored in the entries.
+    pub ignore_timestamps: bool,
+    /// Refuse to create the symlinks whose target is outside of the extraction root.
+    pub refuse_external_symlinks: bool,
+    /// Hard link the files having the same CRC-32, size and permissions as a file extracted
+    /// before instead of writing their data again.
+    pub link_duplicates: bool,
+    /// Restore the owners stored in the Info-ZIP Unix extra fields, which requires running as
+    /// root.
+    pub same_owner: bool,
+    /// Permission bits cleared on the extracted files and directories like a umask.
+    pub mode_mask: Option<u32>,
+    /// Permissions of the extracted directories instead of the ones stored in the entries.
+    pub dir_mode: Option<u32>,
+    /// Permissions of the extracted files instead of the ones stored in the entries.
+    pub file_mode: Option<u32>,
+    /// Keep the setuid, setgid and sticky bits stored in the entries, which are cleared otherwise.
+    pub preserve_special_permissions: bool,
+    /// Rename the entries whose names Windows cannot create, like `CON` or `a:b`. Enabled by
+    /// default on Windows only.
+    pub sanitize_names: bool,
+    /// Rename the entries so that Linux, macOS and Windows can all create them, shortening the
+    /// names too long for any of them.
+    pub portable_names: bool,
+    /// Encoding of the entry names without the UTF-8 flag.
+    pub file_name_encoding: Option<FileNameEncoding>,
+    /// Leave out the `__MACOSX/`, `._*` and `.DS_Store` entries macOS adds to archives.
+    pub skip_macos_metadata: bool,
+    /// Only the files with one of these extensions, like `pdf`, are extracted when given. Along
+    /// with `only_types`, the files passing either of them are extracted.
+    pub only_extensions: Vec<String>,
+    /// Only the files of these types are extracted when given. Files whose extension tells no
+    /// type are recognized by the start of their content when they are extracted.
+    pub only_types: Vec<FileType>,
+    /// Passwords tried on each of the encrypted entries. One is asked for when none is given.
+    pub passwords: Vec<String>,
+    // Running totals checked against the limits, shared with the nested archives
+    pub(crate) totals: Rc<ExtractTotals>,
+    // Paths of the symlinks created so far, which no later entry is written through
+    pub(crate) created_symlinks: Rc<RefCell<HashSet<PathBuf>>>,
 }
 
 impl ExtractOptions {
@@ -21,61 +325,3073 @@ impl ExtractOptions {
             path,
             verbose,
             destination_path,
+            limit: None,
+            regex: None,
+            patterns: None,
+            resume: false,
+            crc_algorithm: &CRC_32_ISO_HDLC,
+            overwrite_policy: OverwritePolicy::default(),
+            plan_json: false,
+            into: None,
+            atomic: false,
+            salvage: false,
+            recursion_depth: 0,
+            strip_components: 0,
+            junk_paths: false,
+            collision_policy: CollisionPolicy::default(),
+            duplicate_policy: DuplicatePolicy::default(),
+            case_insensitive: cfg!(any(windows, target_os = "macos")),
+            case_collision_policy: CaseCollisionPolicy::default(),
+            freshen: false,
+            max_compression_ratio: Some(DEFAULT_MAX_COMPRESSION_RATIO),
+            max_entry_count: None,
+            max_entry_size: None,
+            max_total_size: None,
+            ignore_timestamps: false,
+            refuse_external_symlinks: false,
+            link_duplicates: false,
+            same_owner: false,
+            mode_mask: None,
+            dir_mode: None,
+            file_mode: None,
+            preserve_special_permissions: false,
+            sanitize_names: cfg!(windows),
+            portable_names: false,
+            file_name_encoding: None,
+            skip_macos_metadata: false,
+            only_extensions: Vec::new(),
+            only_types: Vec::new(),
+            passwords: Vec::new(),
+            totals: Rc::default(),
+            created_symlinks: Rc::default(),
+        }
+    }
+
+    /// Sets the directory prepended to the path of every extracted entry. Only plain relative
+    /// directories are accepted so that the entries cannot be moved outside of the destination.
+    pub fn set_into(&mut self, into: PathBuf) -> Result<(), ExtractError> {
+        if !into
+            .components()
+            .all(|component| matches!(component, Component::Normal(_)))
+        {
+            return Err(ExtractError::InvalidInto(into));
+        }
+
+        self.into = Some(into);
+
+        Ok(())
+    }
+
+    /// Returns the directory the entries are extracted into, which is the destination when one is
+    /// given and the directory of the archive otherwise.
+    pub fn extract_root(&self) -> PathBuf {
+        match &self.destination_path {
+            Some(destination_path) => destination_path.clone(),
+            None => self.path.parent().map(PathBuf::from).unwrap_or_default(),
+        }
+    }
+
+    /// Resolves where the given entry is written under the extraction root. On Windows the path
+    /// is in its long form so that deeply nested entries can be extracted.
+    pub fn output_path(&self, extract_root: &Path, zip_file: &ZipFile) -> PathBuf {
+        let entry_path = self.entry_path(zip_file).unwrap_or_default();
+        let output_path = match &self.into {
+            Some(into) => extract_root.join(into).join(entry_path),
+            None => extract_root.join(entry_path),
+        };
+
+        long_path(&output_path)
+    }
+
+    /// Returns the path of the entry relative to the extraction root once the leading components
+    /// are stripped, or `None` when the entry has no components left or its path would leave the
+    /// extraction root. Only the file name is kept when the paths are junked, so directories have
+    /// no path at all.
+    pub fn entry_path(&self, zip_file: &ZipFile) -> Option<PathBuf> {
+        self.stripped_path(zip_file)
+            .filter(|entry_path| stays_inside(entry_path))
+    }
+
+    /// Returns whether the path of the entry, once stripped and sanitized, would leave the
+    /// extraction root through a `..` component or by being absolute.
+    pub fn escapes_root(&self, zip_file: &ZipFile) -> bool {
+        self.stripped_path(zip_file)
+            .is_some_and(|entry_path| !stays_inside(&entry_path))
+    }
+
+    fn stripped_path(&self, zip_file: &ZipFile) -> Option<PathBuf> {
+        let file_name = zip_file.os_file_name();
+
+        if self.strip_components == 0 && !self.junk_paths && !self.is_sanitized(zip_file) {
+            return Some(PathBuf::from(file_name));
+        }
+
+        if self.junk_paths && zip_file.is_dir() {
+            return None;
+        }
+
+        let components: Vec<Cow<OsStr>> = split_file_name(&file_name)
+            .filter(|component| !component.is_empty())
+            .skip(self.strip_components)
+            .map(|component| match component.to_str() {
+                Some(component) => match self.sanitize_component(component) {
+                    Cow::Borrowed(component) => Cow::Borrowed(OsStr::new(component)),
+                    Cow::Owned(component) => Cow::Owned(OsString::from(component)),
+                },
+                // Names not valid in the encoding of the host cannot be created elsewhere
+                None if self.portable_names => Cow::Owned(OsString::from(
+                    portable_file_name(&component.to_string_lossy()).as_ref(),
+                )),
+                None => Cow::Borrowed(component),
+            })
+            .collect();
+
+        let entry_path = if self.junk_paths {
+            components.last().map(PathBuf::from)
+        } else {
+            Some(components.iter().collect::<PathBuf>())
+        };
+
+        entry_path.filter(|entry_path| !entry_path.as_os_str().is_empty())
+    }
+
+    /// Returns whether the name of the given entry is renamed so that Windows, or every platform
+    /// with portable names, can create it.
+    pub fn is_sanitized(&self, zip_file: &ZipFile) -> bool {
+        (self.portable_names && zip_file.os_file_name().to_str().is_none())
+            || zip_file
+                .file_name()
+                .split('/')
+                .any(|component| matches!(self.sanitize_component(component), Cow::Owned(_)))
+    }
+
+    fn sanitize_component<'a>(&self, component: &'a str) -> Cow<'a, str> {
+        if self.portable_names {
+            portable_file_name(component)
+        } else if self.sanitize_names {
+            sanitize_file_name(component)
+        } else {
+            Cow::Borrowed(component)
+        }
+    }
+
+    /// Returns the options for extracting the archive extracted to the given path into the
+    /// directory named after it, one recursion level lower. The limits and the handling of the
+    /// names and metadata are kept, and so are the given passwords. The totals checked against the
+    /// limits are shared with these options, so the nested archives count towards the same limits.
+    pub fn for_nested_archive(&self, path: PathBuf) -> Self {
+        let into = path.file_stem().map(PathBuf::from);
+        let mut nested_options = Self::new(path, self.verbose, None);
+
+        nested_options.into = into;
+        nested_options.recursion_depth = self.recursion_depth.saturating_sub(1);
+        nested_options.overwrite_policy = self.overwrite_policy;
+        nested_options.salvage = self.salvage;
+        nested_options.max_compression_ratio = self.max_compression_ratio;
+        nested_options.max_entry_count = self.max_entry_count;
+        nested_options.max_entry_size = self.max_entry_size;
+        nested_options.max_total_size = self.max_total_size;
+        nested_options.ignore_timestamps = self.ignore_timestamps;
+        nested_options.refuse_external_symlinks = self.refuse_external_symlinks;
+        nested_options.same_owner = self.same_owner;
+        nested_options.mode_mask = self.mode_mask;
+        nested_options.dir_mode = self.dir_mode;
+        nested_options.file_mode = self.file_mode;
+        nested_options.preserve_special_permissions = self.preserve_special_permissions;
+        nested_options.sanitize_names = self.sanitize_names;
+        nested_options.portable_names = self.portable_names;
+        nested_options.file_name_encoding = self.file_name_encoding;
+        nested_options.skip_macos_metadata = self.skip_macos_metadata;
+        nested_options.passwords = self.passwords.clone();
+        nested_options.created_symlinks = Rc::clone(&self.created_symlinks);
+        nested_options.totals = Rc::clone(&self.totals);
+
+        nested_options
+    }
+
+    /// Returns whether the entry written to the given path would go through a symlink created
+    /// earlier in the extraction. Symlinks replace an existing link instead of following it, so
+    /// only their parents are looked at.
+    pub fn passes_through_symlink(&self, zip_file: &ZipFile, output_path: &Path) -> bool {
+        let created_symlinks = self.created_symlinks.borrow();
+        let mut paths = output_path.ancestors();
+
+        if zip_file.entry_kind() == EntryKind::Symlink {
+            paths.next();
         }
+
+        paths.any(|path| created_symlinks.contains(path))
+    }
+
+    /// Returns whether the given entry passes the entry filters of the options.
+    pub fn is_selected(&self, zip_file: &ZipFile) -> bool {
+        self.regex
+            .as_ref()
+            .is_none_or(|regex| regex.is_match(zip_file.file_name()))
+            && self
+                .patterns
+                .as_ref()
+                .is_none_or(|patterns| patterns.is_match(zip_file.file_name()))
+    }
+
+    /// Returns whether the extraction is limited to some extensions or file types.
+    pub fn filters_file_types(&self) -> bool {
+        !self.only_extensions.is_empty() || !self.only_types.is_empty()
+    }
+
+    /// Returns whether the given entry passes the extension and file type filters judging by its
+    /// name, or `None` when its extension tells no type and its content has to be sniffed.
+    pub fn is_selected_file_type(&self, zip_file: &ZipFile) -> Option<bool> {
+        if !self.filters_file_types() || has_suffix(zip_file.file_name(), &self.only_extensions) {
+            return Some(true);
+        }
+
+        if self.only_types.is_empty() {
+            return Some(false);
+        }
+
+        let extension = Path::new(zip_file.file_name())
+            .extension()
+            .and_then(OsStr::to_str)
+            .and_then(FileType::from_extension);
+
+        extension.map(|file_type| self.only_types.contains(&file_type))
     }
 }
 
-pub fn extract_files(extract_options: ExtractOptions) -> Result<(), ExtractError> {
-    let zip_file = match File::open(extract_options.path.clone()) {
-        Ok(file) => BufReader::new(file),
-        Err(err) => {
-            eprintln!(
-                "An error occurred while trying to open the input file.\n\"{}\"",
-                err.to_string()
-            );
-            std::process::exit(UNABLE_TO_OPEN_FILE_ERROR_RETURN_CODE);
+#[derive(Clone)]
+pub struct ListOptions {
+    pub path: PathBuf,
+    /// Write a manifest of the entries to the given path.
+    pub manifest_path: Option<PathBuf>,
+    /// Print one tab separated line per entry instead of the table.
+    pub porcelain: bool,
+    /// Encoding of the entry names without the UTF-8 flag.
+    pub file_name_encoding: Option<FileNameEncoding>,
+    /// Leave out the `__MACOSX/`, `._*` and `.DS_Store` entries macOS adds to archives.
+    pub skip_macos_metadata: bool,
+}
+
+impl ListOptions {
+    pub fn new(path: PathBuf) -> Self {
+        Self {
+            path,
+            manifest_path: None,
+            porcelain: false,
+            file_name_encoding: None,
+            skip_macos_metadata: false,
+        }
+    }
+}
+
+pub struct CreateOptions {
+    /// Path of the archive. `-` writes a new archive to stdout.
+    pub path: PathBuf,
+    /// Files and directories added to the archive. Directories are added recursively.
+    pub inputs: Vec<PathBuf>,
+    pub verbose: bool,
+    /// Only replace the entries of an existing archive whose file is newer, like `zip -f`.
+    pub freshen: bool,
+    /// Add the inputs to an existing archive without rewriting its entries.
+    pub append: bool,
+    /// Produce the same archive for the same files regardless of the order of the inputs and
+    /// the file timestamps. Cannot be combined with `encryption`, whose salts and headers are
+    /// random.
+    pub deterministic: bool,
+    /// Skip the files matched by the `.gitignore` and `.zippyignore` files of the walked
+    /// directories.
+    pub use_ignore_files: bool,
+    /// Glob patterns of the files and directories skipped while walking the inputs. A pattern
+    /// matches an entry name or any of its trailing parts, so `*.o` and `node_modules/**` skip
+    /// the matching files at any depth.
+    pub exclude: Vec<String>,
+    /// Paths read from a file list like `--files-from`. They are added as they are given,
+    /// directories are not walked.
+    pub listed_paths: Vec<PathBuf>,
+    /// Also write non-ASCII names to the Unicode Path extra field.
+    pub unicode_path_extra_field: bool,
+    /// Name of an entry holding the data read from stdin, added after the inputs.
+    pub stdin_entry_name: Option<String>,
+    /// File name suffixes of already compressed formats like `png` or `mp4`. Matching files are
+    /// stored without compression.
+    pub store_suffixes: Vec<String>,
+    /// Compression level from 0 to 9 like `zip -0` to `zip -9`. Level 0 stores the files
+    /// without compression.
+    pub compression_level: Option<u32>,
+    /// RFC 3339 date time given to every entry instead of the modification time of its file.
+    pub mtime: Option<String>,
+    /// Archive comment written to the end of central directory record. Freshening or appending
+    /// keeps the comment of the existing archive when it is not given.
+    pub comment: Option<Vec<u8>>,
+    /// Add the `mimetype` file first and without compression as required by EPUB and
+    /// OpenDocument files.
+    pub mimetype_first: bool,
+    /// Align the data of the stored entries to the given number of bytes like `zipalign`.
+    pub alignment: Option<u16>,
+    /// Encrypt the added files with the given method and password. Freshening or appending
+    /// leaves the existing entries as they are.
+    pub encryption: Option<Encryption>,
+    /// Split the new archive into `.z01`, `.z02`, ... segments of at most the given number of
+    /// bytes. The `.zip` file is the last segment holding the central directory.
+    pub split_size: Option<u64>,
+    /// Compress the files with Zstandard instead of deflate.
+    #[cfg(feature = "zstd")]
+    pub zstd: bool,
+}
+
+impl CreateOptions {
+    pub fn new(path: PathBuf, inputs: Vec<PathBuf>, verbose: bool) -> Self {
+        Self {
+            path,
+            inputs,
+            verbose,
+            freshen: false,
+            append: false,
+            deterministic: false,
+            use_ignore_files: false,
+            exclude: Vec::new(),
+            listed_paths: Vec::new(),
+            unicode_path_extra_field: false,
+            stdin_entry_name: None,
+            store_suffixes: Vec::new(),
+            compression_level: None,
+            mtime: None,
+            comment: None,
+            mimetype_first: false,
+            alignment: None,
+            encryption: None,
+            split_size: None,
+            #[cfg(feature = "zstd")]
+            zstd: false,
         }
+    }
+}
+
+/// Opens the archive at `path` for reading. The segments of split archives are stitched together
+/// and the returned parse options translate the offsets recorded per disk and decode the names
+/// with the given encoding.
+fn open_archive(
+    path: &Path,
+    file_name_encoding: Option<FileNameEncoding>,
+) -> std::io::Result<(BufReader<SplitArchive>, ParseOptions)> {
+    let split_archive = SplitArchive::open(path)?;
+    let mut parse_options = ParseOptions {
+        file_name_encoding,
+        ..Default::default()
     };
 
-    let mut zip = match Zip::from_readable(zip_file) {
+    if split_archive.is_split() {
+        parse_options.disk_start_offsets = split_archive.disk_start_offsets().to_vec();
+    }
+
+    Ok((BufReader::new(split_archive), parse_options))
+}
+
+// Reasons an archive cannot be read, each exiting with its own return code
+#[derive(Debug)]
+enum ReadArchiveError {
+    Open(std::io::Error),
+    Parse(ZipError),
+}
+
+impl Display for ReadArchiveError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ReadArchiveError::Open(err) => write!(
+                f,
+                "An error occurred while trying to open the input file.\n\"{}\"",
+                err
+            ),
+            ReadArchiveError::Parse(err) => write!(f, "{}", err),
+        }
+    }
+}
+
+impl ReadArchiveError {
+    fn return_code(&self) -> i32 {
+        match self {
+            ReadArchiveError::Open(_) => UNABLE_TO_OPEN_FILE_ERROR_RETURN_CODE,
+            ReadArchiveError::Parse(_) => ZIP_FILE_PARSING_ERROR_RETURN_CODE,
+        }
+    }
+}
+
+// Opens and parses the archive at `path`, exiting when it cannot be read.
+fn read_archive(
+    path: &Path,
+    file_name_encoding: Option<FileNameEncoding>,
+) -> Zip<BufReader<SplitArchive>> {
+    match try_read_archive(path, file_name_encoding) {
         Ok(zip) => zip,
         Err(err) => {
             eprintln!("{}", err);
-            std::process::exit(ZIP_FILE_PARSING_ERROR_RETURN_CODE);
+            std::process::exit(err.return_code());
         }
-    };
+    }
+}
+
+// Opens and parses the archive at `path`. The entries of archives whose central directory is
+// missing or damaged are recovered from their local file headers.
+fn try_read_archive(
+    path: &Path,
+    file_name_encoding: Option<FileNameEncoding>,
+) -> Result<Zip<BufReader<SplitArchive>>, ReadArchiveError> {
+    let (zip_file, mut parse_options) =
+        open_archive(path, file_name_encoding).map_err(ReadArchiveError::Open)?;
+    // Entries whose name cannot be decoded are still read, and extracted with the name as stored
+    parse_options.file_name_policy = FileNamePolicy::Lossy;
+
+    match Zip::from_readable_with_options(zip_file, &parse_options) {
+        Err(err) if err.central_dir_damaged() => {
+            eprintln!("{}", err);
+            eprintln!("Recovering the entries from their local file headers");
+
+            let (zip_file, _) =
+                open_archive(path, file_name_encoding).map_err(ReadArchiveError::Open)?;
+
+            Zip::from_local_headers(zip_file, &parse_options)
+        }
+        zip_result => zip_result,
+    }
+    .map_err(ReadArchiveError::Parse)
+}
+
+pub fn extract_files(extract_options: ExtractOptions) -> Result<(), ExtractError> {
+    let mut zip = read_archive(&extract_options.path, extract_options.file_name_encoding);
+
+    plan_or_extract(&mut zip, extract_options)
+}
+
+/// Extracts each of the given archives with the same options, going on with the next archive when
+/// one cannot be read or extracted. A line with the status of every archive is printed at the end
+/// and `ExtractError::ArchivesFailed` is returned when any of them failed.
+pub fn extract_archives(
+    paths: &[PathBuf],
+    extract_options: ExtractOptions,
+) -> Result<(), ExtractError> {
+    let archive_options = paths
+        .iter()
+        .map(|path| {
+            let mut archive_options = extract_options.clone();
+            archive_options.path = path.clone();
+            archive_options.totals = Rc::default();
+            archive_options
+        })
+        .collect();
+
+    extract_each(archive_options)
+}
+
+/// Extracts every ZIP file found under the given directory, recognized by the local file header
+/// signature it starts with, into a directory named after it next to it. With a destination, the
+/// directories are created under it at the same relative paths instead. Reports the status of
+/// every archive like [`extract_archives`].
+pub fn extract_all(dir: &Path, extract_options: ExtractOptions) -> Result<(), ExtractError> {
+    let mut paths: Vec<PathBuf> = WalkBuilder::new(dir)
+        .standard_filters(false)
+        .build()
+        .filter_map(Result::ok)
+        .filter(|entry| {
+            entry
+                .file_type()
+                .is_some_and(|file_type| file_type.is_file())
+        })
+        .map(|entry| entry.into_path())
+        .filter(|path| starts_with_local_file_header(path))
+        .collect();
+
+    paths.sort();
+
+    if paths.is_empty() {
+        println!("No archives found under {}", dir.display());
+        return Ok(());
+    }
+
+    let archive_options = paths
+        .into_iter()
+        .map(|path| {
+            let mut archive_options = extract_options.clone();
+            archive_options.into = path.file_stem().map(PathBuf::from);
+            archive_options.destination_path =
+                extract_options
+                    .destination_path
+                    .as_ref()
+                    .map(|destination_path| {
+                        let relative_dir = path
+                            .parent()
+                            .and_then(|parent| parent.strip_prefix(dir).ok())
+                            .unwrap_or(Path::new(""));
+
+                        destination_path.join(relative_dir)
+                    });
+            archive_options.path = path;
+            archive_options.totals = Rc::default();
+            archive_options
+        })
+        .collect();
+
+    extract_each(archive_options)
+}
+
+// Extracts the archive of each of the options in turn, going on with the next one when an archive
+// cannot be read or extracted, and prints the status of every archive at the end
+fn extract_each(archive_options: Vec<ExtractOptions>) -> Result<(), ExtractError> {
+    let mut statuses = Vec::new();
+
+    for archive_options in archive_options {
+        let path = archive_options.path.clone();
+
+        println!("Archive: {}", path.display());
+
+        let status = match try_read_archive(&path, archive_options.file_name_encoding) {
+            Ok(mut zip) => {
+                plan_or_extract(&mut zip, archive_options).map_err(|err| err.to_string())
+            }
+            Err(err) => Err(err.to_string()),
+        };
+
+        if let Err(err) = &status {
+            eprintln!("{}", err);
+        }
+
+        statuses.push((path, status));
+    }
+
+    let mut failed_count = 0;
+
+    for (path, status) in statuses.iter() {
+        match status {
+            Ok(()) => println!("OK\t{}", path.display()),
+            Err(err) => {
+                failed_count += 1;
+                println!("FAILED\t{}: {}", path.display(), err.replace('\n', " "));
+            }
+        }
+    }
+
+    if failed_count > 0 {
+        eprintln!(
+            "{} of {} archives could not be extracted",
+            failed_count,
+            statuses.len()
+        );
+
+        return Err(ExtractError::ArchivesFailed(failed_count));
+    }
 
-    let password = if zip.files_encrypted() {
-        rpassword::prompt_password("Password: ").ok()
+    Ok(())
+}
+
+// Prints the extraction plan as JSON when the options ask for it and extracts the archive otherwise
+fn plan_or_extract<R: ReadableArchive>(
+    zip: &mut Zip<R>,
+    extract_options: ExtractOptions,
+) -> Result<(), ExtractError> {
+    if extract_options.plan_json {
+        let plan = zip.plan_extraction(&extract_options, &FileSystemSink);
+        let plan_json = serde_json::to_string_pretty(&plan)
+            .map_err(|err| ExtractError::IOError(err.to_string()))?;
+
+        println!("{}", plan_json);
+
+        return Ok(());
+    }
+
+    extract_archive(zip, extract_options)
+}
+
+// Extracts the archive, then the archives extracted from it while the recursion depth allows
+fn extract_archive<R: ReadableArchive>(
+    zip: &mut Zip<R>,
+    extract_options: ExtractOptions,
+) -> Result<(), ExtractError> {
+    let encrypted_entry = first_encrypted_entry(zip, &extract_options);
+    let passwords = entry_passwords(zip, encrypted_entry, &extract_options.passwords)?;
+    let nested_archives = nested_archive_options(zip, &extract_options);
+
+    if extract_options.atomic {
+        extract_atomically(zip, extract_options, passwords)?;
     } else {
-        None
+        zip.extract_items(extract_options, passwords)?;
+    }
+
+    for nested_options in nested_archives {
+        if !starts_with_local_file_header(&nested_options.path) {
+            continue;
+        }
+
+        let nested_zip = File::open(&nested_options.path)
+            .map_err(|err| err.to_string())
+            .and_then(|file| {
+                Zip::from_readable(BufReader::new(file)).map_err(|err| err.to_string())
+            });
+
+        match nested_zip {
+            Ok(mut nested_zip) => {
+                println!(
+                    "Extracting the nested archive {}",
+                    nested_options.path.display()
+                );
+                extract_archive(&mut nested_zip, nested_options)?;
+            }
+            // A file which only looks like an archive is left as it is
+            Err(err) => eprintln!(
+                "Skipping the nested archive \"{}\".\n{}",
+                nested_options.path.display(),
+                err
+            ),
+        }
+    }
+
+    Ok(())
+}
+
+// Returns the name of the first entry the plan extracts which is encrypted with a password
+fn first_encrypted_entry<R: ReadableArchive>(
+    zip: &Zip<R>,
+    extract_options: &ExtractOptions,
+) -> Option<String> {
+    zip.plan_extraction(extract_options, &FileSystemSink)
+        .into_iter()
+        .zip(zip.zip_files())
+        .find(|(planned_entry, zip_file)| {
+            planned_entry.action != PlanAction::Skip
+                && matches!(
+                    zip_file.encryption_method(),
+                    EncryptionMethod::ZipCrypto | EncryptionMethod::Aes
+                )
+        })
+        .map(|(_, zip_file)| zip_file.file_name().clone())
+}
+
+// Returns the passwords to try on the encrypted entries. When none are given and there is an
+// encrypted entry, the password is asked for again while the entry rejects it, up to
+// `PASSWORD_ATTEMPTS` times.
+fn entry_passwords<R: ReadableArchive>(
+    zip: &mut Zip<R>,
+    encrypted_entry: Option<String>,
+    given_passwords: &[String],
+) -> Result<Vec<String>, ExtractError> {
+    let Some(encrypted_entry) = encrypted_entry.filter(|_| given_passwords.is_empty()) else {
+        return Ok(given_passwords.to_vec());
     };
 
-    zip.extract_items(extract_options, password).map(|_| ())
+    let mut attempts = 1;
+
+    loop {
+        let password = rpassword::prompt_password("Password: ").ok();
+
+        match zip.check_password(&encrypted_entry, &password) {
+            Err(err) if err.is_wrong_password() && attempts < PASSWORD_ATTEMPTS => {
+                eprintln!("{}", err);
+                attempts += 1;
+            }
+            result => return result.map(|_| password.into_iter().collect()),
+        }
+    }
 }
 
-pub fn list_files<P>(zip_file_path: P)
-where
-    P: AsRef<Path>,
-{
-    let zip_file = match File::open(zip_file_path) {
-        Ok(file) => BufReader::new(file),
-        Err(err) => {
+// Options for extracting every `.zip` file the plan extracts from the archive, computed before the
+// extraction since the options are consumed by it
+fn nested_archive_options<R: ReadableArchive>(
+    zip: &Zip<R>,
+    extract_options: &ExtractOptions,
+) -> Vec<ExtractOptions> {
+    if extract_options.recursion_depth == 0 {
+        return Vec::new();
+    }
+
+    zip.plan_extraction(extract_options, &FileSystemSink)
+        .into_iter()
+        .zip(zip.zip_files())
+        .filter(|(planned_entry, zip_file)| {
+            planned_entry.action != PlanAction::Skip
+                && zip_file.entry_kind() == EntryKind::File
+                && planned_entry
+                    .output_path
+                    .extension()
+                    .is_some_and(|extension| extension.eq_ignore_ascii_case("zip"))
+        })
+        .map(|(planned_entry, _)| extract_options.for_nested_archive(planned_entry.output_path))
+        .collect()
+}
+
+fn starts_with_local_file_header(path: &Path) -> bool {
+    let mut signature = [0u8; 4];
+
+    File::open(path)
+        .and_then(|mut file| file.read_exact(&mut signature))
+        .is_ok_and(|_| u32::from_le_bytes(signature) == LOCAL_FILE_HEADER_SIGN)
+}
+
+// Only `..`, the root and Windows prefixes can take a joined path out of the directory it is
+// joined onto
+fn stays_inside(path: &Path) -> bool {
+    path.components().all(|component| {
+        !matches!(
+            component,
+            Component::ParentDir | Component::RootDir | Component::Prefix(_)
+        )
+    })
+}
+
+/// Extracts the archive into a hidden sibling of the `into` directory, which is renamed to it once
+/// every entry is extracted and removed otherwise. Nothing ever sees a partially extracted `into`
+/// directory since the rename happens at once.
+fn extract_atomically<A: Archive>(
+    archive: &mut A,
+    mut extract_options: ExtractOptions,
+    passwords: Vec<String>,
+) -> Result<usize, ExtractError> {
+    let into = extract_options.into.clone().unwrap_or_default();
+    let into_name = into.file_name().ok_or_else(|| {
+        ExtractError::IOError(
+            "The --into directory of an atomic extraction has no name".to_string(),
+        )
+    })?;
+    let mut staging_name = OsString::from(".");
+    staging_name.push(into_name);
+    staging_name.push(format!(".partial-{}", std::process::id()));
+
+    let extract_root = extract_options.extract_root();
+    let destination = extract_root.join(&into);
+    let staging_into = into.with_file_name(staging_name);
+    let staging_dir = extract_root.join(&staging_into);
+
+    if destination.exists() {
+        return Err(ExtractError::DestinationExists(destination));
+    }
+
+    std::fs::create_dir_all(&staging_dir).map_err(|err| ExtractError::IOError(err.to_string()))?;
+    extract_options.into = Some(staging_into);
+
+    let extract_result =
+        archive
+            .extract_items(extract_options, passwords)
+            .and_then(|extracted_count| {
+                std::fs::rename(&staging_dir, &destination)
+                    .map(|_| extracted_count)
+                    .map_err(|err| ExtractError::IOError(err.to_string()))
+            });
+
+    if extract_result.is_err() {
+        let _ = std::fs::remove_dir_all(&staging_dir);
+    }
+
+    extract_result
+}
+
+pub fn list_files(list_options: ListOptions) {
+    let mut zip = read_archive(&list_options.path, list_options.file_name_encoding);
+
+    print_listing(&mut zip, list_options);
+}
+
+/// Lists each of the given archives under a line with its path, going on with the next archive
+/// when one cannot be read. Exits once all of them are listed if any could not be read.
+pub fn list_archives(paths: &[PathBuf], list_options: ListOptions) {
+    let mut failed_count = 0;
+
+    for path in paths {
+        println!("Archive: {}", path.display());
+
+        match try_read_archive(path, list_options.file_name_encoding) {
+            Ok(mut zip) => {
+                let mut archive_options = list_options.clone();
+                archive_options.path = path.clone();
+
+                print_listing(&mut zip, archive_options);
+            }
+            Err(err) => {
+                failed_count += 1;
+                eprintln!("{}", err);
+            }
+        }
+
+        println!();
+    }
+
+    if failed_count > 0 {
+        eprintln!(
+            "{} of {} archives could not be listed",
+            failed_count,
+            paths.len()
+        );
+        std::process::exit(ZIP_FILE_PARSING_ERROR_RETURN_CODE);
+    }
+}
+
+fn print_listing<R: ReadableArchive>(zip: &mut Zip<R>, list_options: ListOptions) {
+    if list_options.skip_macos_metadata {
+        zip.retain_zip_files(|zip_file| !zip_file.is_macos_metadata());
+    }
+
+    if list_options.porcelain {
+        print!("{}", format_porcelain(zip));
+    } else {
+        pretty_print_zip_files(zip);
+    }
+
+    if let Some(manifest_path) = list_options.manifest_path {
+        let write_result = File::create(manifest_path)
+            .and_then(|mut manifest_file| zip.write_manifest(&mut manifest_file));
+
+        if let Err(err) = write_result {
             eprintln!(
-                "An error occurred while trying to open the input file.\n\"{}\"",
-                err.to_string()
+                "An error occurred while writing the manifest file.\n\"{}\"",
+                err
             );
-            std::process::exit(UNABLE_TO_OPEN_FILE_ERROR_RETURN_CODE);
+            std::process::exit(MANIFEST_WRITE_ERROR_RETURN_CODE);
         }
+    }
+}
+
+/// A file or directory found while walking the inputs of `CreateOptions`.
+struct InputEntry {
+    name: String,
+    path: PathBuf,
+    is_dir: bool,
+    // Store the file without compression
+    store: bool,
+    last_modified: ZipDateTime,
+}
+
+/// Creates a zip file from the inputs of the options. Each input is stored relative to its parent
+/// directory, so adding `path/to/dir` creates entries starting with `dir/`.
+pub fn create_archive(create_options: CreateOptions) -> Result<(), UpdateError> {
+    create_archive_with_stdin(create_options, std::io::stdin().lock())
+}
+
+fn create_archive_with_stdin<R>(
+    create_options: CreateOptions,
+    mut stdin: R,
+) -> Result<(), UpdateError>
+where
+    R: Read,
+{
+    let mut input_entries = collect_input_entries(&create_options)?;
+    let mut entry_options = EntryOptions {
+        unicode_path_extra_field: create_options.unicode_path_extra_field,
+        alignment: create_options.alignment,
+        ..Default::default()
     };
 
-    let zip = match Zip::from_readable(zip_file) {
-        Ok(zip) => zip,
-        Err(err) => {
-            eprintln!("{}", err);
-            std::process::exit(ZIP_FILE_PARSING_ERROR_RETURN_CODE);
+    match create_options.compression_level {
+        Some(0) => entry_options.compression_method = CompressionMethod::NoCompression,
+        Some(level) => {
+            entry_options.compression_method =
+                CompressionMethod::Deflate(DeflateCompressionMode::from_level(level));
+            entry_options.compression_level = Some(level);
         }
+        None => (),
+    }
+
+    #[cfg(feature = "zstd")]
+    if create_options.zstd && create_options.compression_level != Some(0) {
+        entry_options.compression_method = CompressionMethod::Zstd;
+    }
+
+    // Entries are sorted by name rather than in the order the inputs are given and all of them
+    // get the same timestamp, so the archive only depends on the names and contents of the files
+    if create_options.deterministic {
+        input_entries.sort_by(|a, b| a.name.cmp(&b.name));
+    }
+
+    // Readers detect EPUB and OpenDocument files by the stored `mimetype` entry at the start of
+    // the archive
+    if create_options.mimetype_first {
+        let index = input_entries
+            .iter()
+            .position(|input_entry| !input_entry.is_dir && input_entry.name == MIMETYPE_ENTRY_NAME)
+            .ok_or(UpdateError::MimetypeNotFound)?;
+        let mut mimetype_entry = input_entries.remove(index);
+
+        mimetype_entry.store = true;
+        input_entries.insert(0, mimetype_entry);
+    }
+
+    let fixed_last_modified = match &create_options.mtime {
+        Some(mtime) => Some(
+            parse_rfc3339(mtime)
+                .map(ZipDateTime::from_system_time)
+                .ok_or_else(|| UpdateError::InvalidMtime(mtime.clone()))?,
+        ),
+        None if create_options.deterministic => Some(source_date_time(
+            std::env::var(SOURCE_DATE_EPOCH_ENV_VAR).ok(),
+        )?),
+        None => None,
     };
 
-    pretty_print_zip_files(&zip);
+    // Entries keep the modification time of their files unless a timestamp is given. Data read
+    // from stdin has no file so it gets the current time.
+    match fixed_last_modified {
+        Some(last_modified) => {
+            entry_options.last_modified = last_modified;
+            input_entries
+                .iter_mut()
+                .for_each(|input_entry| input_entry.last_modified = last_modified);
+        }
+        None => entry_options.last_modified = ZipDateTime::from_system_time(SystemTime::now()),
+    }
+
+    let writes_to_stdout = create_options.path == Path::new(STDOUT_FILE_PATH);
+
+    if create_options.deterministic && create_options.encryption.is_some() {
+        return Err(UpdateError::DeterministicEncryption);
+    } else if writes_to_stdout && (create_options.freshen || create_options.append) {
+        return Err(UpdateError::UpdateOnStdout);
+    } else if create_options.split_size.is_some()
+        && (writes_to_stdout || create_options.freshen || create_options.append)
+    {
+        return Err(UpdateError::SplitNotSupported);
+    } else if create_options.freshen {
+        return freshen_archive(&create_options, &input_entries, &entry_options);
+    } else if create_options.append {
+        return append_to_archive(&create_options, &input_entries, &entry_options, &mut stdin);
+    }
+
+    // Stdout can be a pipe so the entries are written with data descriptors, and the progress
+    // goes to stderr to keep the archive intact
+    if writes_to_stdout {
+        let mut zip_writer = ZipWriter::streaming(BufWriter::new(std::io::stdout().lock()));
+
+        if let Some(encryption) = &create_options.encryption {
+            zip_writer.set_encryption(encryption.clone());
+        }
+
+        if let Some(comment) = &create_options.comment {
+            zip_writer
+                .set_comment(comment)
+                .map_err(UpdateError::WriteError)?;
+        }
+
+        return add_input_entries(
+            &mut zip_writer,
+            input_entries.iter(),
+            create_options.stdin_entry_name.as_deref(),
+            &mut stdin,
+            &entry_options,
+            |name| {
+                if create_options.verbose {
+                    eprintln!("Adding {}", name);
+                }
+            },
+        )
+        .and_then(|_| zip_writer.finish().map(|_| ()))
+        .map_err(UpdateError::WriteError);
+    }
+
+    let write_result = File::create(&create_options.path)
+        .map_err(|err| WriteError::IOError(err.to_string()))
+        .and_then(|zip_file| {
+            let mut zip_writer = ZipWriter::new(BufWriter::new(zip_file));
+
+            if let Some(encryption) = &create_options.encryption {
+                zip_writer.set_encryption(encryption.clone());
+            }
+
+            if let Some(comment) = &create_options.comment {
+                zip_writer.set_comment(comment)?;
+            }
+
+            if let Some(split_size) = create_options.split_size {
+                zip_writer.set_split_size(split_size)?;
+            }
+
+            add_input_entries(
+                &mut zip_writer,
+                input_entries.iter(),
+                create_options.stdin_entry_name.as_deref(),
+                &mut stdin,
+                &entry_options,
+                |name| {
+                    if create_options.verbose {
+                        println!("Adding {}", name);
+                    }
+                },
+            )?;
+
+            zip_writer
+                .finish_split()
+                .map(|(_, disk_start_offsets)| disk_start_offsets)
+        })
+        .and_then(|disk_start_offsets| match create_options.split_size {
+            Some(_) => write_segments(&create_options.path, &disk_start_offsets)
+                .map_err(|err| WriteError::IOError(err.to_string())),
+            None => Ok(()),
+        });
+
+    write_result.map_err(UpdateError::WriteError)
+}
+
+// Adds the input entries followed by the stdin entry if it is given
+fn add_input_entries<'a, W, I, R, F>(
+    zip_writer: &mut ZipWriter<W>,
+    input_entries: I,
+    stdin_entry_name: Option<&str>,
+    stdin: &mut R,
+    entry_options: &EntryOptions,
+    on_add: F,
+) -> Result<(), WriteError>
+where
+    W: Write + Seek,
+    I: IntoIterator<Item = &'a InputEntry>,
+    R: Read,
+    F: Fn(&str),
+{
+    for input_entry in input_entries {
+        on_add(&input_entry.name);
+        add_input_entry(zip_writer, input_entry, entry_options)?;
+    }
+
+    if let Some(stdin_entry_name) = stdin_entry_name {
+        // The size of the data is not known up front so it is written as a large file
+        let stdin_entry_options = EntryOptions {
+            large_file: true,
+            ..*entry_options
+        };
+
+        on_add(stdin_entry_name);
+        zip_writer.add_file(stdin_entry_name, stdin, &stdin_entry_options)?;
+    }
+
+    Ok(())
+}
+
+/// Replaces the entries of an existing archive whose file on disk is newer than the entry. Files
+/// that are not in the archive yet are not added.
+fn freshen_archive(
+    create_options: &CreateOptions,
+    input_entries: &[InputEntry],
+    entry_options: &EntryOptions,
+) -> Result<(), UpdateError> {
+    let zip_file = File::open(&create_options.path)
+        .map(BufReader::new)
+        .map_err(|err| UpdateError::IOError(err.to_string()))?;
+    let mut zip = Zip::from_readable(zip_file).map_err(UpdateError::ZipError)?;
+
+    let freshened_entries: Vec<Option<&InputEntry>> = zip
+        .zip_files()
+        .iter()
+        .map(|zip_file| {
+            input_entries
+                .iter()
+                .filter(|input_entry| !input_entry.is_dir)
+                .find(|input_entry| &input_entry.name == zip_file.file_name())
+                .filter(|input_entry| {
+                    std::fs::metadata(&input_entry.path)
+                        .and_then(|metadata| metadata.modified())
+                        .is_ok_and(|modified| {
+                            ZipDateTime::from_system_time(modified) > *zip_file.date_time()
+                        })
+                })
+        })
+        .collect();
+
+    rewrite_archive(&create_options.path, |zip_writer| {
+        if let Some(encryption) = &create_options.encryption {
+            zip_writer.set_encryption(encryption.clone());
+        }
+
+        for (index, freshened_entry) in freshened_entries.iter().enumerate() {
+            match freshened_entry {
+                Some(input_entry) => {
+                    if create_options.verbose {
+                        println!("Freshening {}", input_entry.name);
+                    }
+
+                    add_input_entry(zip_writer, input_entry, entry_options)?
+                }
+                None => {
+                    let file_name = zip.zip_files()[index].file_name().clone();
+                    zip.copy_entry(index, zip_writer, &file_name)?
+                }
+            }
+        }
+
+        zip_writer.set_comment(create_options.comment.as_deref().unwrap_or(zip.comment()))
+    })
+}
+
+/// Adds the inputs to an existing archive in place. The archive is truncated at its central
+/// directory and the new entries are written in its place followed by a central directory listing
+/// both the old and the new entries, so the existing entries are never copied. Directories that
+/// are already in the archive are skipped. The archive is put back as it was when any of the new
+/// entries fails to be written.
+fn append_to_archive<R>(
+    create_options: &CreateOptions,
+    input_entries: &[InputEntry],
+    entry_options: &EntryOptions,
+    stdin: &mut R,
+) -> Result<(), UpdateError>
+where
+    R: Read,
+{
+    let zip_file = File::open(&create_options.path)
+        .map(BufReader::new)
+        .map_err(|err| UpdateError::IOError(err.to_string()))?;
+    let mut zip = Zip::from_readable(zip_file).map_err(UpdateError::ZipError)?;
+
+    let is_in_archive = |input_entry: &&InputEntry| {
+        zip.zip_files()
+            .iter()
+            .any(|zip_file| zip_file.file_name() == &input_entry.name)
+    };
+
+    if let Some(input_entry) = input_entries
+        .iter()
+        .filter(|input_entry| !input_entry.is_dir)
+        .find(is_in_archive)
+    {
+        return Err(UpdateError::EntryAlreadyExists(input_entry.name.clone()));
+    }
+
+    if let Some(stdin_entry_name) = create_options.stdin_entry_name.as_ref().filter(|name| {
+        zip.zip_files()
+            .iter()
+            .any(|zip_file| zip_file.file_name() == *name)
+    }) {
+        return Err(UpdateError::EntryAlreadyExists(stdin_entry_name.clone()));
+    }
+
+    let input_entries: Vec<&InputEntry> = input_entries
+        .iter()
+        .filter(|input_entry| !is_in_archive(input_entry))
+        .collect();
+    let central_dir_records = zip.central_dir_records().map_err(UpdateError::ZipError)?;
+    let central_dir_start_offset = zip.central_dir_start_offset();
+    let comment = create_options
+        .comment
+        .clone()
+        .unwrap_or_else(|| zip.comment().to_vec());
+
+    // The central directory and the end records are kept aside so that they can be put back when
+    // any of the new entries fails to be written
+    let mut saved_end = Vec::new();
+    let mut zip_file = OpenOptions::new()
+        .read(true)
+        .write(true)
+        .open(&create_options.path)
+        .and_then(|mut zip_file| {
+            zip_file.seek(SeekFrom::Start(central_dir_start_offset))?;
+            zip_file.read_to_end(&mut saved_end)?;
+            zip_file.set_len(central_dir_start_offset)?;
+
+            Ok(zip_file)
+        })
+        .map_err(|err| UpdateError::IOError(err.to_string()))?;
+
+    let write_result = zip_file
+        .try_clone()
+        .map_err(|err| WriteError::IOError(err.to_string()))
+        .and_then(|appended_file| {
+            ZipWriter::append(
+                BufWriter::new(appended_file),
+                central_dir_start_offset,
+                central_dir_records,
+            )
+            .and_then(|mut zip_writer| {
+                zip_writer.set_comment(&comment)?;
+
+                if let Some(encryption) = &create_options.encryption {
+                    zip_writer.set_encryption(encryption.clone());
+                }
+
+                add_input_entries(
+                    &mut zip_writer,
+                    input_entries,
+                    create_options.stdin_entry_name.as_deref(),
+                    stdin,
+                    entry_options,
+                    |name| {
+                        if create_options.verbose {
+                            println!("Adding {}", name);
+                        }
+                    },
+                )?;
+
+                zip_writer.finish().map(|_| ())
+            })
+        });
+
+    // Whatever was written of the new entries is dropped along with the file the writer held
+    if write_result.is_err() {
+        zip_file
+            .set_len(central_dir_start_offset)
+            .and_then(|_| zip_file.seek(SeekFrom::Start(central_dir_start_offset)))
+            .and_then(|_| zip_file.write_all(&saved_end))
+            .map_err(|err| UpdateError::IOError(err.to_string()))?;
+    }
+
+    write_result.map_err(UpdateError::WriteError)
+}
+
+// Walks the inputs in the order they are given, visiting the entries of directories sorted by
+// name so that the order of the entries doesn't depend on the file system
+fn collect_input_entries(create_options: &CreateOptions) -> Result<Vec<InputEntry>, UpdateError> {
+    // The archive itself must not be added when it is created inside one of the inputs
+    let zip_file_path = create_options.path.canonicalize().ok();
+    let exclude_set = exclude_set(&create_options.exclude)?;
+    let mut input_entries = Vec::new();
+
+    for input in create_options.inputs.iter() {
+        let input_root = match input.file_name() {
+            Some(_) => input.parent().map(PathBuf::from).unwrap_or_default(),
+            None => input.clone(),
+        };
+
+        // Hidden files are archived like any other file and ignore files are only honored when
+        // asked for
+        let mut walk_builder = WalkBuilder::new(input);
+        walk_builder
+            .standard_filters(false)
+            .follow_links(true)
+            .sort_by_file_name(|a, b| a.cmp(b));
+
+        if create_options.use_ignore_files {
+            walk_builder
+                .git_ignore(true)
+                .require_git(false)
+                .add_custom_ignore_filename(ZIPPY_IGNORE_FILE_NAME);
+        }
+
+        // Excluded directories are pruned so nothing under them is walked
+        if !exclude_set.is_empty() {
+            let exclude_set = exclude_set.clone();
+            let input_root = input_root.clone();
+
+            walk_builder.filter_entry(move |dir_entry| {
+                let name = input_entry_name(dir_entry.path(), &input_root);
+                !is_excluded(&exclude_set, &name, dir_entry.path().is_dir())
+            });
+        }
+
+        for dir_entry in walk_builder.build() {
+            let dir_entry = dir_entry.map_err(|err| UpdateError::IOError(err.to_string()))?;
+            let path = dir_entry.path();
+
+            if zip_file_path.is_some() && path.canonicalize().ok() == zip_file_path {
+                continue;
+            }
+
+            let name = input_entry_name(path, &input_root);
+
+            if !path.is_dir() {
+                input_entries.push(InputEntry {
+                    store: has_suffix(&name, &create_options.store_suffixes),
+                    name,
+                    path: path.to_path_buf(),
+                    is_dir: false,
+                    last_modified: file_last_modified(path),
+                });
+            } else if !name.is_empty() {
+                input_entries.push(InputEntry {
+                    name: format!("{}/", name),
+                    path: path.to_path_buf(),
+                    is_dir: true,
+                    store: false,
+                    last_modified: file_last_modified(path),
+                });
+            }
+        }
+    }
+
+    for path in create_options.listed_paths.iter() {
+        if zip_file_path.is_some() && path.canonicalize().ok() == zip_file_path {
+            continue;
+        }
+
+        // Listed paths are stored as they are given without the root and `.` or `..` parts
+        let name = path
+            .components()
+            .filter_map(|component| match component {
+                Component::Normal(part) => Some(part.to_string_lossy()),
+                _ => None,
+            })
+            .collect::<Vec<_>>()
+            .join("/");
+        let is_dir = path.is_dir();
+
+        if name.is_empty() || is_excluded(&exclude_set, &name, is_dir) {
+            continue;
+        }
+
+        if !path.exists() {
+            return Err(UpdateError::IOError(format!(
+                "\"{}\" does not exist",
+                path.display()
+            )));
+        }
+
+        input_entries.push(InputEntry {
+            store: !is_dir && has_suffix(&name, &create_options.store_suffixes),
+            name: if is_dir { format!("{}/", name) } else { name },
+            path: path.clone(),
+            is_dir,
+            last_modified: file_last_modified(path),
+        });
+    }
+
+    Ok(input_entries)
+}
+
+/// Reads the paths of a file list like the output of `find`. Paths are separated by NUL
+/// characters if the list contains any, like the output of `find -print0`, otherwise by new
+/// lines. On Unix the paths are kept as the bytes they are, so they need not be valid UTF-8.
+pub fn read_file_list<R>(mut reader: R) -> Result<Vec<PathBuf>, UpdateError>
+where
+    R: Read,
+{
+    let mut file_list = Vec::new();
+
+    reader
+        .read_to_end(&mut file_list)
+        .map_err(|err| UpdateError::IOError(err.to_string()))?;
+
+    let paths: Vec<&[u8]> = if file_list.contains(&b'\0') {
+        file_list.split(|&byte| byte == b'\0').collect()
+    } else {
+        file_list
+            .split(|&byte| byte == b'\n')
+            .map(|line| line.strip_suffix(b"\r").unwrap_or(line))
+            .collect()
+    };
+
+    Ok(paths
+        .into_iter()
+        .filter(|path| !path.is_empty())
+        .map(path_from_bytes)
+        .collect())
+}
+
+fn path_from_bytes(bytes: &[u8]) -> PathBuf {
+    #[cfg(unix)]
+    {
+        use std::os::unix::ffi::OsStrExt;
+
+        PathBuf::from(OsStr::from_bytes(bytes))
+    }
+
+    #[cfg(not(unix))]
+    PathBuf::from(String::from_utf8_lossy(bytes).into_owned())
+}
+
+fn input_entry_name(path: &Path, input_root: &Path) -> String {
+    path.strip_prefix(input_root)
+        .unwrap_or(path)
+        .components()
+        .map(|component| component.as_os_str().to_string_lossy())
+        .collect::<Vec<_>>()
+        .join("/")
+}
+
+fn file_last_modified(path: &Path) -> ZipDateTime {
+    std::fs::metadata(path)
+        .and_then(|metadata| metadata.modified())
+        .map(ZipDateTime::from_system_time)
+        .unwrap_or_default()
+}
+
+// Suffixes are compared case insensitively and may be given with or without the leading dot
+fn has_suffix(name: &str, suffixes: &[String]) -> bool {
+    let name = name.to_lowercase();
+
+    suffixes.iter().any(|suffix| {
+        let suffix = suffix.trim_start_matches('.').to_lowercase();
+        !suffix.is_empty() && name.ends_with(&format!(".{}", suffix))
+    })
+}
+
+fn exclude_set(patterns: &[String]) -> Result<GlobSet, UpdateError> {
+    let mut glob_set_builder = GlobSetBuilder::new();
+
+    for pattern in patterns {
+        let glob = Glob::new(pattern)
+            .map_err(|err| UpdateError::InvalidExcludePattern(err.to_string()))?;
+        glob_set_builder.add(glob);
+    }
+
+    glob_set_builder
+        .build()
+        .map_err(|err| UpdateError::InvalidExcludePattern(err.to_string()))
+}
+
+/// Checks the entry name and every trailing part of it against the exclude patterns.
+/// Directory names are also checked with a trailing slash so `build/` only matches
+/// directories.
+fn is_excluded(exclude_set: &GlobSet, name: &str, is_dir: bool) -> bool {
+    if name.is_empty() {
+        return false;
+    }
+
+    let matches = |name: &str| {
+        exclude_set.is_match(name) || (is_dir && exclude_set.is_match(format!("{}/", name)))
+    };
+
+    matches(name)
+        || name
+            .match_indices('/')
+            .any(|(index, _)| matches(&name[index + 1..]))
+}
+
+fn add_input_entry<W>(
+    zip_writer: &mut ZipWriter<W>,
+    input_entry: &InputEntry,
+    entry_options: &EntryOptions,
+) -> Result<(), WriteError>
+where
+    W: Write + Seek,
+{
+    let mut entry_options = *entry_options;
+    entry_options.last_modified = input_entry.last_modified;
+
+    let metadata =
+        std::fs::metadata(&input_entry.path).map_err(|err| WriteError::IOError(err.to_string()))?;
+
+    entry_options.large_file = metadata.len() >= LARGE_FILE_SIZE;
+
+    if input_entry.store {
+        entry_options.compression_method = CompressionMethod::NoCompression;
+    }
+
+    // Keep the permissions of the file so executables stay executable when extracted
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::MetadataExt;
+
+        entry_options.unix_mode = Some(metadata.mode());
+    }
+
+    if input_entry.is_dir {
+        return zip_writer.add_directory(&input_entry.name, &entry_options);
+    }
+
+    let mut file = File::open(&input_entry.path)
+        .map(BufReader::new)
+        .map_err(|err| WriteError::IOError(err.to_string()))?;
+
+    zip_writer.add_file(&input_entry.name, &mut file, &entry_options)
+}
+
+/// Returns the timestamp of the entries of deterministic archives. It is the time given by
+/// `SOURCE_DATE_EPOCH` as seconds since the Unix epoch if it is set, taken in UTC so that the
+/// archive doesn't depend on the time zone, otherwise the earliest date time MS-DOS format can
+/// represent.
+fn source_date_time(source_date_epoch: Option<String>) -> Result<ZipDateTime, UpdateError> {
+    match source_date_epoch {
+        Some(source_date_epoch) => source_date_epoch
+            .trim()
+            .parse::<u64>()
+            .map(|seconds| {
+                ZipDateTime::from_utc_system_time(UNIX_EPOCH + Duration::from_secs(seconds))
+            })
+            .map_err(|_| UpdateError::InvalidSourceDateEpoch(source_date_epoch)),
+        None => Ok(ZipDateTime::default()),
+    }
+}
+
+/// Writes a new version of the archive into a temporary file next to it with the given function
+/// and replaces the archive with it only after it is written completely.
+fn rewrite_archive<F>(zip_file_path: &Path, write_entries: F) -> Result<(), UpdateError>
+where
+    F: FnOnce(&mut ZipWriter<BufWriter<File>>) -> Result<(), WriteError>,
+{
+    let mut tmp_file_name = zip_file_path.file_name().unwrap_or_default().to_owned();
+    tmp_file_name.push(".tmp");
+    let tmp_file_path = zip_file_path.with_file_name(tmp_file_name);
+
+    let write_result = File::create(&tmp_file_path)
+        .map_err(|err| WriteError::IOError(err.to_string()))
+        .and_then(|tmp_file| {
+            let mut zip_writer = ZipWriter::new(BufWriter::new(tmp_file));
+
+            write_entries(&mut zip_writer)?;
+            zip_writer.finish().map(|_| ())
+        });
+
+    if let Err(err) = write_result {
+        let _ = std::fs::remove_file(&tmp_file_path);
+        return Err(UpdateError::WriteError(err));
+    }
+
+    std::fs::rename(&tmp_file_path, zip_file_path)
+        .map_err(|err| UpdateError::IOError(err.to_string()))
+}
+
+/// Renames an entry by rewriting the archive with the entry data copied as is. Renaming a
+/// directory moves all of the entries under it.
+pub fn rename_entry<P>(zip_file_path: P, old_name: &str, new_name: &str) -> Result<(), UpdateError>
+where
+    P: AsRef<Path>,
+{
+    let zip_file_path = zip_file_path.as_ref();
+    let zip_file = File::open(zip_file_path)
+        .map(BufReader::new)
+        .map_err(|err| UpdateError::IOError(err.to_string()))?;
+    let mut zip = Zip::from_readable(zip_file).map_err(UpdateError::ZipError)?;
+
+    let renamed = |file_name: &str| -> Option<String> {
+        if file_name == old_name {
+            Some(new_name.to_string())
+        } else if old_name.ends_with('/') && new_name.ends_with('/') {
+            file_name
+                .strip_prefix(old_name)
+                .map(|rest| format!("{}{}", new_name, rest))
+        } else {
+            None
+        }
+    };
+
+    let file_names: Vec<String> = zip
+        .zip_files()
+        .iter()
+        .map(|zip_file| zip_file.file_name().clone())
+        .collect();
+
+    if !file_names
+        .iter()
+        .any(|file_name| renamed(file_name).is_some())
+    {
+        return Err(UpdateError::EntryNotFound(old_name.to_string()));
+    }
+
+    if let Some(existing_name) = file_names
+        .iter()
+        .filter(|file_name| renamed(file_name).is_none())
+        .find(|file_name| {
+            file_names
+                .iter()
+                .any(|name| renamed(name).as_ref() == Some(file_name))
+        })
+    {
+        return Err(UpdateError::EntryAlreadyExists(existing_name.clone()));
+    }
+
+    rewrite_archive(zip_file_path, |zip_writer| {
+        for (index, file_name) in file_names.iter().enumerate() {
+            let file_name = renamed(file_name).unwrap_or_else(|| file_name.clone());
+            zip.copy_entry(index, zip_writer, &file_name)?;
+        }
+
+        zip_writer.set_comment(zip.comment())
+    })
+}
+
+/// Writes the entries that can be recovered from the archive at `zip_file_path` into a new
+/// archive with a rebuilt central directory, returning the number of entries written. The
+/// entries are taken from the central directory when it can still be read and from the local
+/// file headers otherwise.
+pub fn repair_archive<P, Q>(zip_file_path: P, output_path: Q) -> Result<usize, UpdateError>
+where
+    P: AsRef<Path>,
+    Q: AsRef<Path>,
+{
+    let zip_file_path = zip_file_path.as_ref();
+    let output_path = output_path.as_ref();
+
+    if output_path
+        .canonicalize()
+        .is_ok_and(|output_path| zip_file_path.canonicalize().ok() == Some(output_path))
+    {
+        return Err(UpdateError::RepairIntoInput);
+    }
+
+    let (zip_file, parse_options) =
+        open_archive(zip_file_path, None).map_err(|err| UpdateError::IOError(err.to_string()))?;
+    let mut zip = match Zip::from_readable_with_options(zip_file, &parse_options) {
+        Err(err) if err.central_dir_damaged() => {
+            let (zip_file, parse_options) = open_archive(zip_file_path, None)
+                .map_err(|err| UpdateError::IOError(err.to_string()))?;
+
+            Zip::from_local_headers(zip_file, &parse_options)
+        }
+        zip_result => zip_result,
+    }
+    .map_err(UpdateError::ZipError)?;
+
+    let file_names: Vec<String> = zip
+        .zip_files()
+        .iter()
+        .map(|zip_file| zip_file.file_name().clone())
+        .collect();
+
+    let output_file =
+        File::create(output_path).map_err(|err| UpdateError::IOError(err.to_string()))?;
+    let mut zip_writer = ZipWriter::new(BufWriter::new(output_file));
+
+    for (index, file_name) in file_names.iter().enumerate() {
+        zip.copy_entry(index, &mut zip_writer, file_name)
+            .map_err(UpdateError::WriteError)?;
+    }
+
+    zip_writer
+        .set_comment(zip.comment())
+        .and_then(|_| zip_writer.finish())
+        .map_err(UpdateError::WriteError)?;
+
+    Ok(file_names.len())
+}
+
+/// Prints the raw fields of the end of central directory record of the given zip file.
+pub fn print_end_of_central_dir<P>(zip_file_path: P)
+where
+    P: AsRef<Path>,
+{
+    let mut zip_file = match File::open(zip_file_path) {
+        Ok(file) => BufReader::new(file),
+        Err(err) => {
+            eprintln!(
+                "An error occurred while trying to open the input file.\n\"{}\"",
+                err
+            );
+            std::process::exit(UNABLE_TO_OPEN_FILE_ERROR_RETURN_CODE);
+        }
+    };
+
+    match EndOfCentralDirectory::from_readable(&mut zip_file) {
+        Ok(end_of_central_dir) => print!("{}", format_end_of_central_dir(&end_of_central_dir)),
+        Err(err) => {
+            eprintln!("{}", err);
+            std::process::exit(ZIP_FILE_PARSING_ERROR_RETURN_CODE);
+        }
+    }
+}
+
+/// Prints a summary of the given zip file.
+pub fn print_archive_info<P>(zip_file_path: P)
+where
+    P: AsRef<Path>,
+{
+    print!(
+        "{}",
+        format_archive_info(&read_archive(zip_file_path.as_ref(), None))
+    );
+}
+
+/// Decodes every entry of the archive without writing any files and prints whether its CRC-32 and
+/// size match, like `unzip -t`. Returns the number of tested entries, or
+/// `ExtractError::EntriesFailed` when any of them failed. Each of the passwords is tried on the
+/// encrypted entries, and one is asked for when none is given.
+pub fn test_archive<P>(zip_file_path: P, passwords: Vec<String>) -> Result<usize, ExtractError>
+where
+    P: AsRef<Path>,
+{
+    let mut zip = read_archive(zip_file_path.as_ref(), None);
+    let encrypted_entry = zip
+        .zip_files()
+        .iter()
+        .find(|zip_file| {
+            matches!(
+                zip_file.encryption_method(),
+                EncryptionMethod::ZipCrypto | EncryptionMethod::Aes
+            )
+        })
+        .map(|zip_file| zip_file.file_name().clone());
+    let passwords = entry_passwords(&mut zip, encrypted_entry, &passwords)?;
+
+    let results = zip.verify_entries(&passwords);
+    let mut failed_count = 0;
+
+    for (file_name, result) in results.iter() {
+        match result {
+            Ok(()) => println!("OK\t{}", file_name),
+            Err(err) => {
+                failed_count += 1;
+                println!("FAILED\t{}: {}", file_name, err);
+            }
+        }
+    }
+
+    if failed_count > 0 {
+        eprintln!(
+            "{} of {} entries failed the test",
+            failed_count,
+            results.len()
+        );
+
+        return Err(ExtractError::EntriesFailed(failed_count));
+    }
+
+    println!("No errors detected in {} entries", results.len());
+
+    Ok(results.len())
+}
+
+/// Checks the password against every encrypted entry of the archive without decoding it to disk
+/// and prints whether it is correct for the entry. Entries failing for another reason than the
+/// password are reported as corrupt. Returns the number of encrypted entries,
+/// `ExtractError::EntriesFailed` when the password is wrong for any of them, or
+/// `ExtractError::EntriesCorrupt` when some of them are corrupt otherwise. Each of the passwords is
+/// tried on the entries, and one is asked for when none is given.
+pub fn test_password<P>(zip_file_path: P, passwords: Vec<String>) -> Result<usize, ExtractError>
+where
+    P: AsRef<Path>,
+{
+    let mut zip = read_archive(zip_file_path.as_ref(), None);
+    let has_encrypted_entries = zip.zip_files().iter().any(|zip_file| {
+        matches!(
+            zip_file.encryption_method(),
+            EncryptionMethod::ZipCrypto | EncryptionMethod::Aes
+        )
+    });
+
+    if !has_encrypted_entries {
+        println!("The archive has no entries encrypted with a password");
+
+        return Ok(0);
+    }
+
+    let passwords = if passwords.is_empty() {
+        rpassword::prompt_password("Password: ")
+            .into_iter()
+            .collect()
+    } else {
+        passwords
+    };
+
+    let results = zip.check_passwords(&passwords);
+    let mut failed_count = 0;
+    let mut corrupt_count = 0;
+
+    for (file_name, result) in results.iter() {
+        match result {
+            Ok(()) => println!("OK\t{}", file_name),
+            Err(err) if err.is_password_failure() => {
+                failed_count += 1;
+                println!("FAILED\t{}: {}", file_name, err);
+            }
+            Err(err) => {
+                corrupt_count += 1;
+                println!("CORRUPT\t{}: {}", file_name, err);
+            }
+        }
+    }
+
+    if corrupt_count > 0 {
+        eprintln!(
+            "{} of {} encrypted entries are corrupt",
+            corrupt_count,
+            results.len()
+        );
+    }
+
+    if failed_count > 0 {
+        eprintln!(
+            "The password is wrong for {} of {} encrypted entries",
+            failed_count,
+            results.len()
+        );
+
+        return Err(ExtractError::EntriesFailed(failed_count));
+    }
+
+    if corrupt_count > 0 {
+        return Err(ExtractError::EntriesCorrupt(corrupt_count));
+    }
+
+    println!(
+        "The password is correct for all {} encrypted entries",
+        results.len()
+    );
+
+    Ok(results.len())
+}
+
+/// Entries of an archive compared with an older version of it, in central directory order.
+#[derive(Debug, Default, PartialEq, Eq)]
+pub struct ArchiveDiff {
+    /// Entries only found in the new archive.
+    pub added: Vec<String>,
+    /// Entries only found in the old archive.
+    pub removed: Vec<String>,
+    /// Entries found in both archives whose CRC-32 or size differ.
+    pub changed: Vec<String>,
+}
+
+impl ArchiveDiff {
+    pub fn is_empty(&self) -> bool {
+        self.added.is_empty() && self.removed.is_empty() && self.changed.is_empty()
+    }
+}
+
+/// Compares the entries of two archives by name using only their central directory records.
+pub fn diff_archives<R, S>(old_zip: &Zip<R>, new_zip: &Zip<S>) -> ArchiveDiff
+where
+    R: ReadableArchive,
+    S: ReadableArchive,
+{
+    let old_entries: HashMap<&String, &ZipFile> = old_zip
+        .zip_files()
+        .iter()
+        .map(|zip_file| (zip_file.file_name(), zip_file))
+        .collect();
+    let new_entries: HashMap<&String, &ZipFile> = new_zip
+        .zip_files()
+        .iter()
+        .map(|zip_file| (zip_file.file_name(), zip_file))
+        .collect();
+    let mut archive_diff = ArchiveDiff::default();
+
+    for zip_file in new_zip.zip_files() {
+        match old_entries.get(zip_file.file_name()) {
+            None => archive_diff.added.push(zip_file.file_name().clone()),
+            Some(old_zip_file)
+                if old_zip_file.crc32().get() != zip_file.crc32().get()
+                    || old_zip_file.uncompressed_size().get()
+                        != zip_file.uncompressed_size().get() =>
+            {
+                archive_diff.changed.push(zip_file.file_name().clone())
+            }
+            Some(_) => (),
+        }
+    }
+
+    archive_diff.removed = old_zip
+        .zip_files()
+        .iter()
+        .filter(|zip_file| !new_entries.contains_key(zip_file.file_name()))
+        .map(|zip_file| zip_file.file_name().clone())
+        .collect();
+
+    archive_diff
+}
+
+/// Prints the entries added, removed and changed in the new archive compared with the old one.
+pub fn print_archive_diff<P, Q>(old_zip_file_path: P, new_zip_file_path: Q)
+where
+    P: AsRef<Path>,
+    Q: AsRef<Path>,
+{
+    let old_zip = read_archive(old_zip_file_path.as_ref(), None);
+    let new_zip = read_archive(new_zip_file_path.as_ref(), None);
+    let archive_diff = diff_archives(&old_zip, &new_zip);
+
+    if archive_diff.is_empty() {
+        println!("The archives have the same entries");
+
+        return;
+    }
+
+    let sections = [
+        ("Added", &archive_diff.added),
+        ("Removed", &archive_diff.removed),
+        ("Changed", &archive_diff.changed),
+    ];
+
+    for (title, file_names) in sections {
+        if file_names.is_empty() {
+            continue;
+        }
+
+        println!("{} ({}):", title, file_names.len());
+
+        for file_name in file_names {
+            println!("  {}", file_name);
+        }
+    }
+}
+
+/// Replaces the archive comment in place. The comment is the last field of the end of central
+/// directory record, so only the comment length and the bytes following it are rewritten.
+pub fn set_comment<P>(zip_file_path: P, comment: &str) -> Result<(), CommentError>
+where
+    P: AsRef<Path>,
+{
+    if comment.len() as u64 > MAX_COMMENT_LEN {
+        return Err(CommentError::CommentTooLong(comment.len()));
+    }
+
+    let mut zip_file = OpenOptions::new()
+        .read(true)
+        .write(true)
+        .open(zip_file_path)
+        .map_err(|err| CommentError::IOError(err.to_string()))?;
+
+    let end_of_central_dir = EndOfCentralDirectory::from_readable(&mut zip_file)
+        .map_err(CommentError::EndOfCentralDirectoryError)?;
+
+    zip_file
+        .seek(SeekFrom::Start(
+            end_of_central_dir.offset() + EOF_CENTRAL_DIR_COMMENT_LEN_OFFSET,
+        ))
+        .and_then(|_| zip_file.write_u16::<LittleEndian>(comment.len() as u16))
+        .and_then(|_| zip_file.write_all(comment.as_bytes()))
+        .and_then(|_| {
+            zip_file.set_len(
+                end_of_central_dir.offset() + MIN_EOF_CENTRAL_DIR_SIZE + comment.len() as u64,
+            )
+        })
+        .map_err(|err| CommentError::IOError(err.to_string()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::archive::MemoryBudget;
+    use crate::test_utils::{build_archive, TestEntry};
+    use crate::zip_crypto::ZipCryptoError;
+    use std::io::Cursor;
+
+    #[test]
+    fn test_set_comment() {
+        let dir = tempfile::tempdir().unwrap();
+        let zip_path = dir.path().join("archive.zip");
+
+        std::fs::write(
+            &zip_path,
+            build_archive(&[TestEntry::file("a.txt", b"hello")]),
+        )
+        .unwrap();
+
+        set_comment(&zip_path, "first comment").unwrap();
+        set_comment(&zip_path, "second").unwrap();
+
+        let mut zip_file = File::open(&zip_path).unwrap();
+        let end_of_central_dir = EndOfCentralDirectory::from_readable(&mut zip_file).unwrap();
+
+        assert_eq!(end_of_central_dir.comment(), b"second");
+        assert_eq!(end_of_central_dir.central_dir_size(), 1);
+    }
+
+    #[test]
+    fn test_create_archive() {
+        let dir = tempfile::tempdir().unwrap();
+        let input_dir = dir.path().join("project");
+
+        std::fs::create_dir_all(input_dir.join("src/empty")).unwrap();
+        std::fs::write(input_dir.join("src/main.rs"), b"fn main() {}").unwrap();
+        std::fs::write(input_dir.join("README"), b"readme").unwrap();
+        std::fs::write(dir.path().join("notes.txt"), b"notes").unwrap();
+
+        let zip_path = input_dir.join("archive.zip");
+
+        create_archive(CreateOptions::new(
+            zip_path.clone(),
+            vec![input_dir, dir.path().join("notes.txt")],
+            false,
+        ))
+        .unwrap();
+
+        let zip = Zip::from_readable(BufReader::new(File::open(&zip_path).unwrap())).unwrap();
+        let file_names: Vec<&str> = zip
+            .zip_files()
+            .iter()
+            .map(|zip_file| zip_file.file_name().as_str())
+            .collect();
+
+        assert_eq!(
+            file_names,
+            vec![
+                "project/",
+                "project/README",
+                "project/src/",
+                "project/src/empty/",
+                "project/src/main.rs",
+                "notes.txt"
+            ]
+        );
+    }
+
+    #[test]
+    fn test_freshen_archive() {
+        let dir = tempfile::tempdir().unwrap();
+        let zip_path = dir.path().join("archive.zip");
+        let input_dir = dir.path().join("input");
+        // 2023-09-25 20:59:30
+        let (date, time) = (0x5739, 0xA76F);
+
+        std::fs::write(
+            &zip_path,
+            build_archive(&[
+                TestEntry::file("input/newer.txt", b"old").with_date_time(date, time),
+                TestEntry::file("input/older.txt", b"old").with_date_time(date, time),
+            ]),
+        )
+        .unwrap();
+
+        std::fs::create_dir_all(&input_dir).unwrap();
+        std::fs::write(input_dir.join("newer.txt"), b"new").unwrap();
+        std::fs::write(input_dir.join("older.txt"), b"new").unwrap();
+        std::fs::write(input_dir.join("added.txt"), b"new").unwrap();
+
+        File::options()
+            .write(true)
+            .open(input_dir.join("older.txt"))
+            .and_then(|file| {
+                file.set_modified(
+                    std::time::UNIX_EPOCH + std::time::Duration::from_secs(1600000000),
+                )
+            })
+            .unwrap();
+
+        let mut create_options = CreateOptions::new(zip_path.clone(), vec![input_dir], false);
+        create_options.freshen = true;
+        create_archive(create_options).unwrap();
+
+        let mut zip = Zip::from_readable(BufReader::new(File::open(&zip_path).unwrap())).unwrap();
+        let entries: Vec<(String, Vec<u8>)> = zip
+            .extract_all_to_memory(&None, &MemoryBudget::new(usize::MAX), &CRC_32_ISO_HDLC)
+            .unwrap()
+            .into_iter()
+            .map(|(entry_info, data)| (entry_info.file_name, data.to_vec()))
+            .collect();
+
+        assert_eq!(
+            entries,
+            vec![
+                ("input/newer.txt".to_string(), b"new".to_vec()),
+                ("input/older.txt".to_string(), b"old".to_vec())
+            ]
+        );
+    }
+
+    #[test]
+    fn test_append_to_archive() {
+        let dir = tempfile::tempdir().unwrap();
+        let zip_path = dir.path().join("archive.zip");
+        let input_dir = dir.path().join("input");
+
+        std::fs::write(
+            &zip_path,
+            build_archive(&[
+                TestEntry::dir("input/"),
+                TestEntry::file("input/a.txt", b"hello").deflated(),
+            ]),
+        )
+        .unwrap();
+        set_comment(&zip_path, "comment").unwrap();
+
+        std::fs::create_dir_all(&input_dir).unwrap();
+        std::fs::write(input_dir.join("b.txt"), b"world").unwrap();
+
+        let mut create_options =
+            CreateOptions::new(zip_path.clone(), vec![input_dir.clone()], false);
+        create_options.append = true;
+        create_archive(create_options).unwrap();
+
+        let mut zip = Zip::from_readable(BufReader::new(File::open(&zip_path).unwrap())).unwrap();
+        let entries: Vec<(String, Vec<u8>)> = zip
+            .extract_all_to_memory(&None, &MemoryBudget::new(usize::MAX), &CRC_32_ISO_HDLC)
+            .unwrap()
+            .into_iter()
+            .map(|(entry_info, data)| (entry_info.file_name, data.to_vec()))
+            .collect();
+
+        assert_eq!(zip.dir_count(), 1);
+        assert_eq!(zip.comment(), b"comment");
+        assert_eq!(
+            entries,
+            vec![
+                ("input/a.txt".to_string(), b"hello".to_vec()),
+                ("input/b.txt".to_string(), b"world".to_vec())
+            ]
+        );
+
+        let mut create_options = CreateOptions::new(zip_path, vec![input_dir], false);
+        create_options.append = true;
+
+        assert!(matches!(
+            create_archive(create_options),
+            Err(UpdateError::EntryAlreadyExists(file_name)) if file_name == "input/b.txt"
+        ));
+    }
+
+    #[test]
+    fn test_append_to_archive_restores_on_failure() {
+        struct FailingReader;
+
+        impl Read for FailingReader {
+            fn read(&mut self, _: &mut [u8]) -> std::io::Result<usize> {
+                Err(std::io::Error::other("read failed"))
+            }
+        }
+
+        let dir = tempfile::tempdir().unwrap();
+        let zip_path = dir.path().join("archive.zip");
+        let notes_path = dir.path().join("notes.txt");
+        let archive = build_archive(&[TestEntry::file("a.txt", b"hello").deflated()]);
+
+        std::fs::write(&zip_path, &archive).unwrap();
+        std::fs::write(&notes_path, b"notes").unwrap();
+
+        // The notes are written before reading stdin fails
+        let mut create_options = CreateOptions::new(zip_path.clone(), vec![notes_path], false);
+        create_options.append = true;
+        create_options.stdin_entry_name = Some("dump.sql".to_string());
+
+        assert!(matches!(
+            create_archive_with_stdin(create_options, FailingReader),
+            Err(UpdateError::WriteError(_))
+        ));
+        assert_eq!(std::fs::read(&zip_path).unwrap(), archive);
+    }
+
+    #[test]
+    fn test_deterministic_archive() {
+        let dir = tempfile::tempdir().unwrap();
+        let input_dir = dir.path().join("input");
+
+        std::fs::create_dir_all(input_dir.join("b")).unwrap();
+        std::fs::write(input_dir.join("b/c.txt"), b"c").unwrap();
+        std::fs::write(input_dir.join("a.txt"), b"a").unwrap();
+
+        let create = |zip_name: &str, inputs: Vec<PathBuf>| {
+            let zip_path = dir.path().join(zip_name);
+            let mut create_options = CreateOptions::new(zip_path.clone(), inputs, false);
+            create_options.deterministic = true;
+            create_archive(create_options).unwrap();
+
+            std::fs::read(zip_path).unwrap()
+        };
+
+        let mut create_options = CreateOptions::new(
+            dir.path().join("encrypted.zip"),
+            vec![input_dir.clone()],
+            false,
+        );
+        create_options.deterministic = true;
+        create_options.encryption = Some(Encryption::ZipCrypto("password".to_string()));
+
+        assert!(matches!(
+            create_archive(create_options),
+            Err(UpdateError::DeterministicEncryption)
+        ));
+
+        let first = create(
+            "first.zip",
+            vec![input_dir.join("b"), input_dir.join("a.txt")],
+        );
+
+        File::options()
+            .write(true)
+            .open(input_dir.join("a.txt"))
+            .and_then(|file| file.set_modified(UNIX_EPOCH + Duration::from_secs(1600000000)))
+            .unwrap();
+
+        let second = create(
+            "second.zip",
+            vec![input_dir.join("a.txt"), input_dir.join("b")],
+        );
+
+        assert_eq!(first, second);
+
+        let zip = Zip::from_readable(BufReader::new(Cursor::new(first))).unwrap();
+        let file_names: Vec<&str> = zip
+            .zip_files()
+            .iter()
+            .map(|zip_file| zip_file.file_name().as_str())
+            .collect();
+
+        assert_eq!(file_names, vec!["a.txt", "b/", "b/c.txt"]);
+    }
+
+    #[test]
+    fn test_source_date_time() {
+        assert_eq!(source_date_time(None).unwrap(), ZipDateTime::default());
+        assert_eq!(
+            source_date_time(Some("1695675571".to_string())).unwrap(),
+            ZipDateTime::from_bytes(0x5739, 0xA76F)
+        );
+        assert!(matches!(
+            source_date_time(Some("yesterday".to_string())),
+            Err(UpdateError::InvalidSourceDateEpoch(_))
+        ));
+    }
+
+    #[test]
+    fn test_create_archive_with_ignore_files() {
+        let dir = tempfile::tempdir().unwrap();
+        let input_dir = dir.path().join("project");
+
+        std::fs::create_dir_all(input_dir.join("target/debug")).unwrap();
+        std::fs::create_dir_all(input_dir.join("src")).unwrap();
+        std::fs::write(input_dir.join(".gitignore"), b"target/\n").unwrap();
+        std::fs::write(input_dir.join("src/.zippyignore"), b"*.bak\n").unwrap();
+        std::fs::write(input_dir.join("target/debug/zippy"), b"").unwrap();
+        std::fs::write(input_dir.join("src/main.rs"), b"fn main() {}").unwrap();
+        std::fs::write(input_dir.join("src/main.rs.bak"), b"").unwrap();
+
+        let create = |use_ignore_files: bool| {
+            let zip_path = dir.path().join("archive.zip");
+            let mut create_options =
+                CreateOptions::new(zip_path.clone(), vec![input_dir.clone()], false);
+            create_options.use_ignore_files = use_ignore_files;
+            create_archive(create_options).unwrap();
+
+            let zip = Zip::from_readable(BufReader::new(File::open(zip_path).unwrap())).unwrap();
+
+            zip.zip_files()
+                .iter()
+                .map(|zip_file| zip_file.file_name().clone())
+                .collect::<Vec<String>>()
+        };
+
+        assert_eq!(
+            create(true),
+            vec![
+                "project/",
+                "project/.gitignore",
+                "project/src/",
+                "project/src/.zippyignore",
+                "project/src/main.rs"
+            ]
+        );
+        assert_eq!(create(false).len(), 9);
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_create_archive_unix_permissions() {
+        use std::os::unix::fs::PermissionsExt;
+
+        let dir = tempfile::tempdir().unwrap();
+        let script_path = dir.path().join("run.sh");
+        let zip_path = dir.path().join("archive.zip");
+
+        std::fs::write(&script_path, b"#!/bin/sh").unwrap();
+        std::fs::set_permissions(&script_path, std::fs::Permissions::from_mode(0o754)).unwrap();
+
+        create_archive(CreateOptions::new(
+            zip_path.clone(),
+            vec![script_path],
+            false,
+        ))
+        .unwrap();
+
+        let zip = Zip::from_readable(BufReader::new(File::open(zip_path).unwrap())).unwrap();
+
+        assert_eq!(zip.zip_files()[0].external_attributes() >> 16, 0o100754);
+    }
+
+    #[test]
+    fn test_create_archive_with_stdin_entry() {
+        let dir = tempfile::tempdir().unwrap();
+        let zip_path = dir.path().join("archive.zip");
+        let notes_path = dir.path().join("notes.txt");
+
+        std::fs::write(&notes_path, b"notes").unwrap();
+
+        let mut create_options = CreateOptions::new(zip_path.clone(), vec![notes_path], false);
+        create_options.stdin_entry_name = Some("dump.sql".to_string());
+        create_archive_with_stdin(create_options, Cursor::new(b"CREATE TABLE t;")).unwrap();
+
+        let mut create_options = CreateOptions::new(zip_path.clone(), vec![], false);
+        create_options.append = true;
+        create_options.stdin_entry_name = Some("more/dump.sql".to_string());
+        create_archive_with_stdin(create_options, Cursor::new(b"DROP TABLE t;")).unwrap();
+
+        let mut create_options = CreateOptions::new(zip_path.clone(), vec![], false);
+        create_options.append = true;
+        create_options.stdin_entry_name = Some("dump.sql".to_string());
+
+        assert!(matches!(
+            create_archive_with_stdin(create_options, Cursor::new(b"")),
+            Err(UpdateError::EntryAlreadyExists(file_name)) if file_name == "dump.sql"
+        ));
+
+        let mut zip = Zip::from_readable(BufReader::new(File::open(zip_path).unwrap())).unwrap();
+        let budget = MemoryBudget::new(usize::MAX);
+        let entries = zip
+            .extract_all_to_memory(&None, &budget, &CRC_32_ISO_HDLC)
+            .unwrap();
+        let entries = entries
+            .iter()
+            .map(|(entry_info, data)| (entry_info.file_name.as_str(), &data[..]))
+            .collect::<Vec<_>>();
+
+        assert_eq!(
+            entries,
+            vec![
+                ("notes.txt", &b"notes"[..]),
+                ("dump.sql", &b"CREATE TABLE t;"[..]),
+                ("more/dump.sql", &b"DROP TABLE t;"[..])
+            ]
+        );
+    }
+
+    #[test]
+    fn test_create_archive_with_store_suffixes() {
+        let dir = tempfile::tempdir().unwrap();
+        let input_dir = dir.path().join("media");
+        let zip_path = dir.path().join("archive.zip");
+
+        std::fs::create_dir_all(&input_dir).unwrap();
+        std::fs::write(input_dir.join("photo.PNG"), b"png").unwrap();
+        std::fs::write(input_dir.join("movie.mp4"), b"mp4").unwrap();
+        std::fs::write(input_dir.join("notes.txt"), b"txt").unwrap();
+
+        let mut create_options = CreateOptions::new(zip_path.clone(), vec![input_dir], false);
+        create_options.store_suffixes = vec!["png".to_string(), ".mp4".to_string()];
+        create_archive(create_options).unwrap();
+
+        let zip = Zip::from_readable(BufReader::new(File::open(zip_path).unwrap())).unwrap();
+        let compression_methods = zip
+            .zip_files()
+            .iter()
+            .map(|zip_file| {
+                (
+                    zip_file.file_name().as_str(),
+                    *zip_file.compression_method(),
+                )
+            })
+            .collect::<Vec<_>>();
+
+        assert_eq!(
+            compression_methods,
+            vec![
+                ("media/", CompressionMethod::NoCompression),
+                ("media/movie.mp4", CompressionMethod::NoCompression),
+                (
+                    "media/notes.txt",
+                    CompressionMethod::Deflate(DeflateCompressionMode::Normal)
+                ),
+                ("media/photo.PNG", CompressionMethod::NoCompression),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_create_archive_timestamps() {
+        let dir = tempfile::tempdir().unwrap();
+        let file_path = dir.path().join("a.txt");
+        let zip_path = dir.path().join("archive.zip");
+        // 2023-09-25 20:59:31 UTC
+        let modified = UNIX_EPOCH + Duration::from_secs(1695675571);
+
+        std::fs::write(&file_path, b"a").unwrap();
+        File::options()
+            .write(true)
+            .open(&file_path)
+            .and_then(|file| file.set_modified(modified))
+            .unwrap();
+
+        let date_time = |mtime: Option<&str>| {
+            let mut create_options =
+                CreateOptions::new(zip_path.clone(), vec![file_path.clone()], false);
+            create_options.mtime = mtime.map(str::to_string);
+            create_archive(create_options)?;
+
+            let zip = Zip::from_readable(BufReader::new(File::open(&zip_path).unwrap())).unwrap();
+
+            Ok::<_, UpdateError>(*zip.zip_files()[0].date_time())
+        };
+
+        // The entry keeps the modification time in local time
+        assert_eq!(
+            date_time(None).unwrap(),
+            ZipDateTime::from_system_time(modified)
+        );
+        assert_eq!(
+            date_time(Some("2000-01-02T03:04:06+01:00")).unwrap(),
+            ZipDateTime::from_system_time(UNIX_EPOCH + Duration::from_secs(946778646))
+        );
+        assert!(matches!(
+            date_time(Some("yesterday")),
+            Err(UpdateError::InvalidMtime(_))
+        ));
+    }
+
+    #[test]
+    fn test_create_archive_with_comment() {
+        let dir = tempfile::tempdir().unwrap();
+        let zip_path = dir.path().join("archive.zip");
+
+        std::fs::write(dir.path().join("a.txt"), b"a").unwrap();
+        std::fs::write(dir.path().join("b.txt"), b"b").unwrap();
+
+        let comment = |file_name: &str, comment: Option<Vec<u8>>, append: bool| {
+            let mut create_options =
+                CreateOptions::new(zip_path.clone(), vec![dir.path().join(file_name)], false);
+            create_options.comment = comment;
+            create_options.append = append;
+            create_archive(create_options)?;
+
+            let zip = Zip::from_readable(BufReader::new(File::open(&zip_path).unwrap())).unwrap();
+
+            Ok::<_, UpdateError>(zip.comment().to_vec())
+        };
+
+        assert_eq!(comment("a.txt", None, false).unwrap(), b"");
+        assert_eq!(
+            comment("a.txt", Some(b"first comment".to_vec()), false).unwrap(),
+            b"first comment"
+        );
+        assert_eq!(comment("b.txt", None, true).unwrap(), b"first comment");
+        assert!(matches!(
+            comment("a.txt", Some(vec![b'a'; 0x10000]), false),
+            Err(UpdateError::WriteError(WriteError::CommentTooLong(0x10000)))
+        ));
+    }
+
+    #[test]
+    fn test_create_archive_mimetype_first() {
+        let dir = tempfile::tempdir().unwrap();
+        let zip_path = dir.path().join("book.epub");
+        let mimetype = b"application/epub+zip";
+
+        std::fs::create_dir_all(dir.path().join("META-INF")).unwrap();
+        std::fs::write(dir.path().join("META-INF/container.xml"), b"<container/>").unwrap();
+        std::fs::write(dir.path().join("mimetype"), mimetype).unwrap();
+
+        let mut create_options = CreateOptions::new(
+            zip_path.clone(),
+            vec![dir.path().join("META-INF"), dir.path().join("mimetype")],
+            false,
+        );
+        create_options.deterministic = true;
+        create_options.mimetype_first = true;
+        create_archive(create_options).unwrap();
+
+        let zip_bytes = std::fs::read(&zip_path).unwrap();
+        let zip = Zip::from_readable(BufReader::new(Cursor::new(zip_bytes.as_slice()))).unwrap();
+
+        assert_eq!(zip.zip_files()[0].file_name(), "mimetype");
+        assert_eq!(
+            *zip.zip_files()[0].compression_method(),
+            CompressionMethod::NoCompression
+        );
+        assert_eq!(&zip_bytes[30..38], b"mimetype");
+        assert_eq!(&zip_bytes[38..38 + mimetype.len()], mimetype);
+
+        let create_options = CreateOptions::new(zip_path, vec![dir.path().join("META-INF")], false);
+
+        assert!(matches!(
+            create_archive(CreateOptions {
+                mimetype_first: true,
+                ..create_options
+            }),
+            Err(UpdateError::MimetypeNotFound)
+        ));
+    }
+
+    #[test]
+    fn test_read_file_list() {
+        assert_eq!(
+            read_file_list("a.txt\r\ndir/b c.txt\n\n".as_bytes()).unwrap(),
+            vec![PathBuf::from("a.txt"), PathBuf::from("dir/b c.txt")]
+        );
+        assert_eq!(
+            read_file_list("a.txt\0new\nline.txt\0".as_bytes()).unwrap(),
+            vec![PathBuf::from("a.txt"), PathBuf::from("new\nline.txt")]
+        );
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_read_file_list_non_utf8() {
+        use std::os::unix::ffi::OsStrExt;
+
+        // Latin-1 encoded "café.txt" as written by `find` on a filesystem using that encoding
+        assert_eq!(
+            read_file_list(&b"caf\xE9.txt\nb.txt\n"[..]).unwrap(),
+            vec![
+                PathBuf::from(OsStr::from_bytes(b"caf\xE9.txt")),
+                PathBuf::from("b.txt")
+            ]
+        );
+        assert_eq!(
+            read_file_list(&b"caf\xE9.txt\0"[..]).unwrap(),
+            vec![PathBuf::from(OsStr::from_bytes(b"caf\xE9.txt"))]
+        );
+    }
+
+    #[test]
+    fn test_create_archive_from_file_list() {
+        let dir = tempfile::tempdir().unwrap();
+        let input_dir = dir.path().join("input");
+        let zip_path = dir.path().join("archive.zip");
+
+        std::fs::create_dir_all(input_dir.join("docs")).unwrap();
+        std::fs::write(input_dir.join("docs/a.txt"), b"a").unwrap();
+        std::fs::write(input_dir.join("docs/b.txt"), b"b").unwrap();
+
+        let mut create_options = CreateOptions::new(zip_path.clone(), vec![], false);
+        create_options.listed_paths = vec![input_dir.join("docs"), input_dir.join("docs/b.txt")];
+        create_archive(create_options).unwrap();
+
+        let zip = Zip::from_readable(BufReader::new(File::open(&zip_path).unwrap())).unwrap();
+        let file_names = zip
+            .zip_files()
+            .iter()
+            .map(|zip_file| zip_file.file_name().clone())
+            .collect::<Vec<String>>();
+        let input_name = input_entry_name(&input_dir, Path::new("/"));
+
+        assert_eq!(
+            file_names,
+            vec![
+                format!("{}/docs/", input_name),
+                format!("{}/docs/b.txt", input_name)
+            ]
+        );
+
+        let mut create_options = CreateOptions::new(zip_path, vec![], false);
+        create_options.listed_paths = vec![input_dir.join("missing.txt")];
+
+        assert!(matches!(
+            create_archive(create_options),
+            Err(UpdateError::IOError(_))
+        ));
+    }
+
+    #[test]
+    fn test_create_archive_with_exclude() {
+        let dir = tempfile::tempdir().unwrap();
+        let input_dir = dir.path().join("project");
+        let zip_path = dir.path().join("archive.zip");
+
+        std::fs::create_dir_all(input_dir.join("node_modules/left-pad")).unwrap();
+        std::fs::create_dir_all(input_dir.join("src/build")).unwrap();
+        std::fs::write(input_dir.join("node_modules/left-pad/index.js"), b"").unwrap();
+        std::fs::write(input_dir.join("src/main.c"), b"int main() {}").unwrap();
+        std::fs::write(input_dir.join("src/main.o"), b"").unwrap();
+        std::fs::write(input_dir.join("src/build/out"), b"").unwrap();
+
+        let mut create_options = CreateOptions::new(zip_path.clone(), vec![input_dir], false);
+        create_options.exclude = vec![
+            "*.o".to_string(),
+            "node_modules/**".to_string(),
+            "build/".to_string(),
+        ];
+        create_archive(create_options).unwrap();
+
+        let zip = Zip::from_readable(BufReader::new(File::open(&zip_path).unwrap())).unwrap();
+        let file_names = zip
+            .zip_files()
+            .iter()
+            .map(|zip_file| zip_file.file_name().clone())
+            .collect::<Vec<String>>();
+
+        assert_eq!(
+            file_names,
+            vec!["project/", "project/src/", "project/src/main.c"]
+        );
+
+        let mut create_options = CreateOptions::new(zip_path, vec![], false);
+        create_options.exclude = vec!["[".to_string()];
+
+        assert!(matches!(
+            create_archive(create_options),
+            Err(UpdateError::InvalidExcludePattern(_))
+        ));
+    }
+
+    #[test]
+    fn test_rename_entry() {
+        let dir = tempfile::tempdir().unwrap();
+        let zip_path = dir.path().join("archive.zip");
+
+        std::fs::write(
+            &zip_path,
+            build_archive(&[
+                TestEntry::file("a.txt", b"hello").deflated(),
+                TestEntry::dir("docs/"),
+                TestEntry::file("docs/b.txt", b"world"),
+            ]),
+        )
+        .unwrap();
+        set_comment(&zip_path, "comment").unwrap();
+
+        rename_entry(&zip_path, "a.txt", "renamed/first.txt").unwrap();
+        rename_entry(&zip_path, "docs/", "documents/").unwrap();
+
+        assert!(matches!(
+            rename_entry(&zip_path, "missing.txt", "b.txt"),
+            Err(UpdateError::EntryNotFound(_))
+        ));
+        assert!(matches!(
+            rename_entry(&zip_path, "renamed/first.txt", "documents/b.txt"),
+            Err(UpdateError::EntryAlreadyExists(_))
+        ));
+
+        let mut zip = Zip::from_readable(BufReader::new(File::open(&zip_path).unwrap())).unwrap();
+        let file_names: Vec<&str> = zip
+            .zip_files()
+            .iter()
+            .map(|zip_file| zip_file.file_name().as_str())
+            .collect();
+
+        assert_eq!(
+            file_names,
+            vec!["renamed/first.txt", "documents/", "documents/b.txt"]
+        );
+        assert_eq!(zip.comment(), b"comment");
+
+        let budget = MemoryBudget::new(usize::MAX);
+        let entries = zip
+            .extract_all_to_memory(&None, &budget, &CRC_32_ISO_HDLC)
+            .unwrap();
+
+        assert_eq!(entries[0].1, b"hello");
+        assert_eq!(entries[1].1, b"world");
+    }
+
+    #[test]
+    fn test_repair_archive() {
+        use crate::writer::EntryOptions;
+
+        let dir = tempfile::tempdir().unwrap();
+        let zip_path = dir.path().join("archive.zip");
+        let repaired_zip_path = dir.path().join("repaired.zip");
+        let mut zip_writer = ZipWriter::streaming(Vec::new());
+
+        zip_writer
+            .add_directory("docs", &EntryOptions::default())
+            .unwrap();
+        zip_writer
+            .add_file("docs/a.txt", &mut &b"hello"[..], &EntryOptions::default())
+            .unwrap();
+        zip_writer
+            .add_file("b.txt", &mut &b"world"[..], &EntryOptions::default())
+            .unwrap();
+
+        // Cut off in the middle of the central directory
+        let archive = zip_writer.finish().unwrap().into_inner();
+        std::fs::write(&zip_path, &archive[..archive.len() - 40]).unwrap();
+
+        assert!(matches!(
+            repair_archive(&zip_path, &zip_path),
+            Err(UpdateError::RepairIntoInput)
+        ));
+        assert_eq!(repair_archive(&zip_path, &repaired_zip_path).unwrap(), 3);
+
+        let mut zip =
+            Zip::from_readable(BufReader::new(File::open(&repaired_zip_path).unwrap())).unwrap();
+        let file_names: Vec<&str> = zip
+            .zip_files()
+            .iter()
+            .map(|zip_file| zip_file.file_name().as_str())
+            .collect();
+
+        assert!(!zip.recovered());
+        assert_eq!(file_names, vec!["docs/", "docs/a.txt", "b.txt"]);
+        assert!(zip.zip_files()[0].is_dir());
+
+        let budget = MemoryBudget::new(usize::MAX);
+        let entries = zip
+            .extract_all_to_memory(&None, &budget, &CRC_32_ISO_HDLC)
+            .unwrap();
+
+        assert_eq!(entries[0].1, b"hello");
+        assert_eq!(entries[1].1, b"world");
+    }
+
+    #[test]
+    fn test_repair_archive_skips_incomplete_entry() {
+        let dir = tempfile::tempdir().unwrap();
+        let zip_path = dir.path().join("archive.zip");
+        let repaired_zip_path = dir.path().join("repaired.zip");
+        let mut archive = build_archive(&[
+            TestEntry::file("a.txt", b"hello"),
+            TestEntry::file("b.txt", b"broke"),
+            TestEntry::file("c.txt", b"world"),
+        ]);
+
+        // The second entry claims more data than the file holds and the central directory is gone
+        archive[40 + 18..40 + 22].copy_from_slice(&0x00FF0000u32.to_le_bytes());
+        std::fs::write(&zip_path, &archive[..120]).unwrap();
+
+        assert_eq!(repair_archive(&zip_path, &repaired_zip_path).unwrap(), 2);
+
+        let mut zip =
+            Zip::from_readable(BufReader::new(File::open(&repaired_zip_path).unwrap())).unwrap();
+
+        // The bytes of the left out entry aren't copied along with the first one
+        assert_eq!(zip.zip_files()[1].offset(), 40);
+
+        let budget = MemoryBudget::new(usize::MAX);
+        let entries = zip
+            .extract_all_to_memory(&None, &budget, &CRC_32_ISO_HDLC)
+            .unwrap();
+
+        assert_eq!(entries[0].1, b"hello");
+        assert_eq!(entries[1].1, b"world");
+    }
+
+    #[test]
+    fn test_extract_atomically() {
+        let dir = tempfile::tempdir().unwrap();
+        let zip_path = dir.path().join("archive.zip");
+        let mut archive = build_archive(&[
+            TestEntry::file("a.txt", b"hello"),
+            TestEntry::file("b.txt", b"world"),
+        ]);
+
+        std::fs::write(&zip_path, &archive).unwrap();
+
+        let atomic_options = |into: &str| {
+            let mut extract_options = ExtractOptions::new(zip_path.clone(), false, None);
+            extract_options.into = Some(PathBuf::from(into));
+            extract_options.atomic = true;
+            extract_options
+        };
+        let mut zip = read_archive(&zip_path, None);
+
+        assert_eq!(
+            extract_atomically(&mut zip, atomic_options("out"), Vec::new()).unwrap(),
+            2
+        );
+        assert_eq!(
+            std::fs::read(dir.path().join("out/b.txt")).unwrap(),
+            b"world"
+        );
+        assert_eq!(
+            extract_atomically(&mut zip, atomic_options("out"), Vec::new()),
+            Err(ExtractError::DestinationExists(dir.path().join("out")))
+        );
+
+        // Corrupt the data of the second entry so its CRC-32 doesn't match
+        let b_offset = 30 + "a.txt".len() + "hello".len();
+        archive[b_offset + 30 + "b.txt".len()] = b'j';
+        std::fs::write(&zip_path, &archive).unwrap();
+
+        let mut zip = read_archive(&zip_path, None);
+
+        assert!(extract_atomically(&mut zip, atomic_options("corrupt"), Vec::new()).is_err());
+        assert_eq!(std::fs::read_dir(dir.path()).unwrap().count(), 2);
+    }
+
+    #[test]
+    fn test_extract_nested_archives() {
+        let dir = tempfile::tempdir().unwrap();
+        let zip_path = dir.path().join("bundle.zip");
+        let deepest = build_archive(&[TestEntry::file("deepest.txt", b"deepest")]);
+        let inner = build_archive(&[
+            TestEntry::file("inner.txt", b"inner"),
+            TestEntry::file("deepest.zip", &deepest),
+        ]);
+
+        std::fs::write(
+            &zip_path,
+            build_archive(&[
+                TestEntry::dir("vendor/"),
+                TestEntry::file("vendor/inner.ZIP", &inner),
+                TestEntry::file("vendor/fake.zip", b"not an archive"),
+            ]),
+        )
+        .unwrap();
+
+        let mut zip = read_archive(&zip_path, None);
+        let mut extract_options = ExtractOptions::new(zip_path, false, None);
+        extract_options.recursion_depth = 1;
+
+        extract_archive(&mut zip, extract_options).unwrap();
+
+        let vendor_dir = dir.path().join("vendor");
+
+        assert!(vendor_dir.join("inner.ZIP").is_file());
+        assert_eq!(
+            std::fs::read(vendor_dir.join("inner/inner.txt")).unwrap(),
+            b"inner"
+        );
+        assert!(vendor_dir.join("inner/deepest.zip").is_file());
+        assert!(!vendor_dir.join("inner/deepest").exists());
+        assert!(!vendor_dir.join("fake").exists());
+    }
+
+    #[test]
+    fn test_extract_nested_archives_share_limits() {
+        let dir = tempfile::tempdir().unwrap();
+        let zip_path = dir.path().join("bundle.zip");
+        let nested = |name: &str| build_archive(&[TestEntry::file(name, &[b'a'; 600])]);
+        let bundle = build_archive(&[
+            TestEntry::file("a.zip", &nested("a.txt")),
+            TestEntry::file("b.zip", &nested("b.txt")),
+        ]);
+
+        std::fs::write(&zip_path, bundle).unwrap();
+
+        // Each nested archive fits the limit on its own, but not along with the other one
+        let nested_archives_size = (nested("a.txt").len() + nested("b.txt").len()) as u64;
+        let mut extract_options = ExtractOptions::new(zip_path.clone(), false, None);
+        extract_options.recursion_depth = 1;
+        extract_options.max_total_size = Some(nested_archives_size + 1000);
+
+        assert_eq!(
+            extract_archive(&mut read_archive(&zip_path, None), extract_options),
+            Err(ExtractError::TotalSizeExceeded(nested_archives_size + 1000))
+        );
+        assert_eq!(
+            std::fs::read(dir.path().join("a/a.txt")).unwrap().len(),
+            600
+        );
+        assert!(!dir.path().join("b/b.txt").exists());
+
+        let mut extract_options = ExtractOptions::new(zip_path.clone(), false, None);
+        extract_options.recursion_depth = 1;
+        extract_options.max_entry_count = Some(3);
+
+        assert_eq!(
+            extract_archive(&mut read_archive(&zip_path, None), extract_options),
+            Err(ExtractError::EntryCountExceeded(3))
+        );
+    }
+
+    #[test]
+    fn test_first_encrypted_entry() {
+        let archive = build_archive(&[
+            TestEntry::file("a.txt", b"hello"),
+            TestEntry::file("b.txt", b"secret").encrypted("password"),
+        ]);
+        let zip = Zip::from_readable(BufReader::new(Cursor::new(archive))).unwrap();
+        let mut extract_options = ExtractOptions::new(PathBuf::from("archive.zip"), false, None);
+
+        assert_eq!(
+            first_encrypted_entry(&zip, &extract_options),
+            Some("b.txt".to_string())
+        );
+
+        // No password is needed when only the plain entries are extracted
+        extract_options.patterns = Some(
+            GlobSetBuilder::new()
+                .add(Glob::new("a.txt").unwrap())
+                .build()
+                .unwrap(),
+        );
+
+        assert_eq!(first_encrypted_entry(&zip, &extract_options), None);
+    }
+
+    #[test]
+    fn test_extract_with_given_password() {
+        let dir = tempfile::tempdir().unwrap();
+        let zip_path = dir.path().join("archive.zip");
+
+        std::fs::write(
+            &zip_path,
+            build_archive(&[TestEntry::file("a.txt", b"secret").encrypted("password")]),
+        )
+        .unwrap();
+
+        let mut extract_options = ExtractOptions::new(zip_path.clone(), false, None);
+        extract_options.passwords = vec!["wrong".to_string()];
+
+        assert_eq!(
+            extract_files(extract_options),
+            Err(ExtractError::ZipCryptoError(
+                ZipCryptoError::IncorrectPassword
+            ))
+        );
+        assert!(!dir.path().join("a.txt").exists());
+
+        let mut extract_options = ExtractOptions::new(zip_path.clone(), false, None);
+        extract_options.passwords = vec!["password".to_string()];
+
+        extract_files(extract_options).unwrap();
+
+        assert_eq!(std::fs::read(dir.path().join("a.txt")).unwrap(), b"secret");
+        assert_eq!(test_archive(&zip_path, vec!["password".to_string()]), Ok(1));
+    }
+
+    #[test]
+    fn test_extract_skips_entries_outside_root() {
+        let dir = tempfile::tempdir().unwrap();
+        let zip_path = dir.path().join("archive.zip");
+        let destination = dir.path().join("out");
+        let absolute_name = format!("{}/absolute.txt", dir.path().display());
+
+        std::fs::write(
+            &zip_path,
+            build_archive(&[
+                TestEntry::file("../escaped.txt", b"escaped"),
+                TestEntry::file(&absolute_name, b"absolute"),
+                TestEntry::file("top/../../nested.txt", b"nested"),
+                TestEntry::file("top/inside.txt", b"inside"),
+            ]),
+        )
+        .unwrap();
+
+        extract_files(ExtractOptions::new(
+            zip_path.clone(),
+            false,
+            Some(destination.clone()),
+        ))
+        .unwrap();
+
+        assert_eq!(
+            std::fs::read(destination.join("top/inside.txt")).unwrap(),
+            b"inside"
+        );
+        assert!(!dir.path().join("escaped.txt").exists());
+        assert!(!dir.path().join("absolute.txt").exists());
+        assert!(!dir.path().join("nested.txt").exists());
+
+        // Stripping the leading components must not uncover a `..` either
+        let stripped_destination = dir.path().join("stripped");
+        let mut extract_options =
+            ExtractOptions::new(zip_path, false, Some(stripped_destination.clone()));
+        extract_options.strip_components = 1;
+
+        extract_files(extract_options).unwrap();
+
+        assert_eq!(
+            std::fs::read(stripped_destination.join("inside.txt")).unwrap(),
+            b"inside"
+        );
+        assert!(!dir.path().join("nested.txt").exists());
+    }
+
+    #[test]
+    fn test_extract_to_destination() {
+        let dir = tempfile::tempdir().unwrap();
+        let zip_path = dir.path().join("archive.zip");
+        let destination = dir.path().join("out/nested");
+
+        std::fs::write(
+            &zip_path,
+            build_archive(&[
+                TestEntry::dir("docs/"),
+                TestEntry::file("docs/a.txt", b"hello"),
+            ]),
+        )
+        .unwrap();
+
+        extract_files(ExtractOptions::new(
+            zip_path.clone(),
+            false,
+            Some(destination.clone()),
+        ))
+        .unwrap();
+
+        assert_eq!(
+            std::fs::read(destination.join("docs/a.txt")).unwrap(),
+            b"hello"
+        );
+        assert!(!dir.path().join("docs").exists());
+
+        // The destination is created even when nothing is extracted into it
+        let empty_destination = dir.path().join("empty");
+        let mut extract_options =
+            ExtractOptions::new(zip_path, false, Some(empty_destination.clone()));
+        extract_options.limit = Some(0);
+
+        extract_files(extract_options).unwrap();
+
+        assert!(empty_destination.is_dir());
+    }
+
+    #[test]
+    fn test_extract_archives() {
+        let dir = tempfile::tempdir().unwrap();
+        let first_path = dir.path().join("first.zip");
+        let broken_path = dir.path().join("broken.zip");
+        let second_path = dir.path().join("second.zip");
+
+        std::fs::write(
+            &first_path,
+            build_archive(&[TestEntry::file("a.txt", b"first")]),
+        )
+        .unwrap();
+        std::fs::write(&broken_path, b"not an archive").unwrap();
+        std::fs::write(
+            &second_path,
+            build_archive(&[TestEntry::file("b.txt", b"second")]),
+        )
+        .unwrap();
+
+        let paths = [first_path.clone(), broken_path, second_path];
+
+        assert_eq!(
+            extract_archives(&paths, ExtractOptions::new(first_path, false, None)),
+            Err(ExtractError::ArchivesFailed(1))
+        );
+        assert_eq!(std::fs::read(dir.path().join("a.txt")).unwrap(), b"first");
+        assert_eq!(std::fs::read(dir.path().join("b.txt")).unwrap(), b"second");
+    }
+
+    #[test]
+    fn test_extract_all() {
+        let dir = tempfile::tempdir().unwrap();
+
+        std::fs::create_dir(dir.path().join("logs")).unwrap();
+        std::fs::write(
+            dir.path().join("logs/january.zip"),
+            build_archive(&[TestEntry::file("1.log", b"january")]),
+        )
+        .unwrap();
+        std::fs::write(
+            dir.path().join("backup.bin"),
+            build_archive(&[TestEntry::file("data.txt", b"data")]),
+        )
+        .unwrap();
+        std::fs::write(dir.path().join("notes.txt"), b"notes").unwrap();
+
+        let extract_options = ExtractOptions::new(PathBuf::new(), false, None);
+
+        extract_all(dir.path(), extract_options).unwrap();
+
+        assert_eq!(
+            std::fs::read(dir.path().join("logs/january/1.log")).unwrap(),
+            b"january"
+        );
+        assert_eq!(
+            std::fs::read(dir.path().join("backup/data.txt")).unwrap(),
+            b"data"
+        );
+        assert!(!dir.path().join("notes").exists());
+    }
+
+    #[test]
+    fn test_extract_all_to_destination() {
+        let dir = tempfile::tempdir().unwrap();
+        let destination = tempfile::tempdir().unwrap();
+
+        std::fs::create_dir(dir.path().join("logs")).unwrap();
+        std::fs::write(
+            dir.path().join("logs/january.zip"),
+            build_archive(&[TestEntry::file("1.log", b"january")]),
+        )
+        .unwrap();
+
+        let extract_options = ExtractOptions::new(
+            PathBuf::new(),
+            false,
+            Some(destination.path().to_path_buf()),
+        );
+
+        extract_all(dir.path(), extract_options).unwrap();
+
+        assert_eq!(
+            std::fs::read(destination.path().join("logs/january/1.log")).unwrap(),
+            b"january"
+        );
+        assert!(!dir.path().join("logs/january").exists());
+    }
+
+    #[test]
+    fn test_diff_archives() {
+        let old_zip = Zip::from_readable(BufReader::new(Cursor::new(build_archive(&[
+            TestEntry::file("same.txt", b"same"),
+            TestEntry::file("changed.txt", b"old"),
+            TestEntry::file("removed.txt", b"removed"),
+        ]))))
+        .unwrap();
+        let new_zip = Zip::from_readable(BufReader::new(Cursor::new(build_archive(&[
+            TestEntry::file("same.txt", b"same"),
+            TestEntry::file("changed.txt", b"new"),
+            TestEntry::file("added.txt", b"added"),
+        ]))))
+        .unwrap();
+
+        assert_eq!(
+            diff_archives(&old_zip, &new_zip),
+            ArchiveDiff {
+                added: vec![String::from("added.txt")],
+                removed: vec![String::from("removed.txt")],
+                changed: vec![String::from("changed.txt")],
+            }
+        );
+        assert!(diff_archives(&old_zip, &old_zip).is_empty());
+    }
+
+    #[test]
+    fn test_set_comment_too_long() {
+        let comment = "a".repeat(MAX_COMMENT_LEN as usize + 1);
+        let result = set_comment("archive.zip", &comment);
+
+        assert!(matches!(
+            result,
+            Err(CommentError::CommentTooLong(len)) if len == comment.len()
+        ));
+    }
 }
@@ -1,17 +1,26 @@
+use std::cmp::Ordering;
 use std::fmt::Display;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
 
+const MIN_YEAR: u16 = 1980;
+const MAX_YEAR: u16 = 2107;
+const SECONDS_IN_DAY: i64 = 86400;
+
+#[derive(Debug, Clone, Copy)]
 struct ZipDate {
     day: u8,
     month: u8,
     year: u16,
 }
 
+#[derive(Debug, Clone, Copy)]
 struct ZipTime {
     hour: u8,
     min: u8,
     second: u8,
 }
 
+#[derive(Debug, Clone, Copy)]
 pub struct ZipDateTime {
     date: ZipDate,
     time: ZipTime,
@@ -33,6 +42,291 @@ impl ZipDateTime {
 
         ZipDateTime { date, time }
     }
+
+    /// Returns the MS-DOS date and time fields in this order.
+    pub fn to_bytes(&self) -> (u16, u16) {
+        let date = ((self.date.year - MIN_YEAR) << 9)
+            | ((self.date.month as u16) << 5)
+            | self.date.day as u16;
+        let time = ((self.time.hour as u16) << 11)
+            | ((self.time.min as u16) << 5)
+            | (self.time.second as u16 / 2);
+
+        (date, time)
+    }
+
+    /// Converts the given time to MS-DOS date time in local time, which is how MS-DOS date time
+    /// is interpreted. Seconds are rounded down to an even number and times outside of the
+    /// 1980-2107 range MS-DOS format covers are clamped.
+    pub fn from_system_time(system_time: SystemTime) -> ZipDateTime {
+        let unix_seconds = unix_seconds(system_time);
+
+        Self::from_seconds(unix_seconds + utc_offset(unix_seconds))
+    }
+
+    /// Converts the given time to MS-DOS date time in UTC. Used where the result must not depend
+    /// on the time zone like the timestamps of deterministic archives.
+    pub fn from_utc_system_time(system_time: SystemTime) -> ZipDateTime {
+        Self::from_seconds(unix_seconds(system_time))
+    }
+
+    /// Converts the MS-DOS date time, which is in local time, to a system time. The inverse of
+    /// `from_system_time`.
+    pub fn to_system_time(&self) -> SystemTime {
+        let local_seconds = self.seconds();
+        // The offset is looked up at the converted time so that it matches across DST changes
+        let unix_seconds = local_seconds - utc_offset(local_seconds - utc_offset(local_seconds));
+
+        // MS-DOS dates start in 1980 so they are always after the Unix epoch
+        UNIX_EPOCH + Duration::from_secs(unix_seconds.max(0) as u64)
+    }
+
+    // Builds the date time from seconds since 1970-01-01 00:00:00 without any time zone applied
+    fn from_seconds(seconds: i64) -> ZipDateTime {
+        let days = seconds.div_euclid(SECONDS_IN_DAY);
+        let seconds_of_day = seconds.rem_euclid(SECONDS_IN_DAY);
+        let (year, month, day) = civil_from_days(days);
+
+        if year < MIN_YEAR as i64 {
+            return ZipDateTime::default();
+        } else if year > MAX_YEAR as i64 {
+            return ZipDateTime::from_bytes(0xFF9F, 0xBF7D);
+        }
+
+        let date = ZipDate {
+            day: day as u8,
+            month: month as u8,
+            year: year as u16,
+        };
+        let time = ZipTime {
+            hour: (seconds_of_day / 3600) as u8,
+            min: (seconds_of_day % 3600 / 60) as u8,
+            second: (seconds_of_day % 60 / 2 * 2) as u8,
+        };
+
+        ZipDateTime { date, time }
+    }
+
+    // Returns the seconds since 1970-01-01 00:00:00 without any time zone applied
+    fn seconds(&self) -> i64 {
+        let days = days_from_civil(
+            self.date.year as i64,
+            self.date.month as i64,
+            self.date.day as i64,
+        );
+        let seconds_of_day =
+            self.time.hour as i64 * 3600 + self.time.min as i64 * 60 + self.time.second as i64;
+
+        days * SECONDS_IN_DAY + seconds_of_day
+    }
+
+    fn sort_key(&self) -> (u16, u8, u8, u8, u8, u8) {
+        (
+            self.date.year,
+            self.date.month,
+            self.date.day,
+            self.time.hour,
+            self.time.min,
+            self.time.second,
+        )
+    }
+}
+
+/// Formats the given time in local time the way `ZipDateTime` is displayed, keeping odd seconds
+/// which MS-DOS date time cannot represent.
+pub fn format_system_time(system_time: SystemTime) -> String {
+    let unix_seconds = unix_seconds(system_time);
+    let unix_seconds = unix_seconds + utc_offset(unix_seconds);
+    let seconds_of_day = unix_seconds.rem_euclid(SECONDS_IN_DAY);
+    let (year, month, day) = civil_from_days(unix_seconds.div_euclid(SECONDS_IN_DAY));
+
+    format!(
+        "{:02}/{}/{} {}:{}:{}",
+        month,
+        day,
+        year,
+        seconds_of_day / 3600,
+        seconds_of_day % 3600 / 60,
+        seconds_of_day % 60
+    )
+}
+
+fn unix_seconds(system_time: SystemTime) -> i64 {
+    match system_time.duration_since(UNIX_EPOCH) {
+        Ok(duration) => duration.as_secs() as i64,
+        Err(err) => -(err.duration().as_secs() as i64),
+    }
+}
+
+// Returns the offset of the local time zone from UTC in seconds at the given time
+#[cfg(unix)]
+fn utc_offset(unix_seconds: i64) -> i64 {
+    let time = unix_seconds as libc::time_t;
+    let mut tm = unsafe { std::mem::zeroed::<libc::tm>() };
+
+    if unsafe { libc::localtime_r(&time, &mut tm) }.is_null() {
+        return 0;
+    }
+
+    tm.tm_gmtoff as i64
+}
+
+// The local time zone isn't looked up on other platforms, so times are taken as UTC
+#[cfg(not(unix))]
+fn utc_offset(_unix_seconds: i64) -> i64 {
+    0
+}
+
+// Converts days since 1970-01-01 to a (year, month, day) date in the proleptic Gregorian calendar
+fn civil_from_days(days: i64) -> (i64, i64, i64) {
+    let days = days + 719468;
+    let era = days.div_euclid(146097);
+    let day_of_era = days - era * 146097;
+    let year_of_era =
+        (day_of_era - day_of_era / 1460 + day_of_era / 36524 - day_of_era / 146096) / 365;
+    let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+    let shifted_month = (5 * day_of_year + 2) / 153;
+    let day = day_of_year - (153 * shifted_month + 2) / 5 + 1;
+    let month = if shifted_month < 10 {
+        shifted_month + 3
+    } else {
+        shifted_month - 9
+    };
+    let year = year_of_era + era * 400 + if month <= 2 { 1 } else { 0 };
+
+    (year, month, day)
+}
+
+// Converts a (year, month, day) date in the proleptic Gregorian calendar to days since 1970-01-01
+fn days_from_civil(year: i64, month: i64, day: i64) -> i64 {
+    let year = if month <= 2 { year - 1 } else { year };
+    let era = year.div_euclid(400);
+    let year_of_era = year - era * 400;
+    let shifted_month = if month > 2 { month - 3 } else { month + 9 };
+    let day_of_year = (153 * shifted_month + 2) / 5 + day - 1;
+    let day_of_era = year_of_era * 365 + year_of_era / 4 - year_of_era / 100 + day_of_year;
+
+    era * 146097 + day_of_era - 719468
+}
+
+fn is_leap_year(year: i64) -> bool {
+    (year % 4 == 0 && year % 100 != 0) || year % 400 == 0
+}
+
+/// Parses an RFC 3339 date time like `2023-09-25T20:59:31Z` or `2023-09-25T22:59:31.5+02:00`.
+/// Fractions of a second are ignored since MS-DOS date time only has 2 second precision.
+pub fn parse_rfc3339(value: &str) -> Option<SystemTime> {
+    let bytes = value.as_bytes();
+    let number = |range: std::ops::Range<usize>| -> Option<i64> {
+        let digits = bytes.get(range)?;
+
+        if !digits.iter().all(u8::is_ascii_digit) {
+            return None;
+        }
+
+        std::str::from_utf8(digits).ok()?.parse().ok()
+    };
+
+    let separators_valid = bytes.len() >= 20
+        && bytes[4] == b'-'
+        && bytes[7] == b'-'
+        && matches!(bytes[10], b'T' | b't' | b' ')
+        && bytes[13] == b':'
+        && bytes[16] == b':';
+
+    if !separators_valid {
+        return None;
+    }
+
+    let (year, month, day) = (number(0..4)?, number(5..7)?, number(8..10)?);
+    let (hour, min, second) = (number(11..13)?, number(14..16)?, number(17..19)?);
+    let days_in_month = match month {
+        1 | 3 | 5 | 7 | 8 | 10 | 12 => 31,
+        4 | 6 | 9 | 11 => 30,
+        2 if is_leap_year(year) => 29,
+        2 => 28,
+        _ => return None,
+    };
+
+    if day < 1 || day > days_in_month || hour > 23 || min > 59 || second > 60 {
+        return None;
+    }
+
+    // Skip the fraction of a second
+    let mut offset_start = 19;
+
+    if bytes[offset_start] == b'.' {
+        offset_start += 1;
+
+        let fraction_len = bytes[offset_start..]
+            .iter()
+            .take_while(|byte| byte.is_ascii_digit())
+            .count();
+
+        if fraction_len == 0 {
+            return None;
+        }
+
+        offset_start += fraction_len;
+    }
+
+    let offset_seconds = match &bytes[offset_start..] {
+        [b'Z' | b'z'] => 0,
+        [sign @ (b'+' | b'-'), _, _, b':', _, _] => {
+            let offset_hour = number(offset_start + 1..offset_start + 3)?;
+            let offset_min = number(offset_start + 4..offset_start + 6)?;
+
+            if offset_hour > 23 || offset_min > 59 {
+                return None;
+            }
+
+            let offset_seconds = offset_hour * 3600 + offset_min * 60;
+
+            if *sign == b'+' {
+                offset_seconds
+            } else {
+                -offset_seconds
+            }
+        }
+        _ => return None,
+    };
+
+    let unix_seconds =
+        days_from_civil(year, month, day) * SECONDS_IN_DAY + hour * 3600 + min * 60 + second
+            - offset_seconds;
+
+    if unix_seconds >= 0 {
+        UNIX_EPOCH.checked_add(Duration::from_secs(unix_seconds as u64))
+    } else {
+        UNIX_EPOCH.checked_sub(Duration::from_secs(unix_seconds.unsigned_abs()))
+    }
+}
+
+/// 1980-01-01 00:00:00, the earliest date time MS-DOS format can represent.
+impl Default for ZipDateTime {
+    fn default() -> Self {
+        ZipDateTime::from_bytes(0x0021, 0x0000)
+    }
+}
+
+impl PartialEq for ZipDateTime {
+    fn eq(&self, other: &Self) -> bool {
+        self.sort_key() == other.sort_key()
+    }
+}
+
+impl Eq for ZipDateTime {}
+
+impl PartialOrd for ZipDateTime {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for ZipDateTime {
+    fn cmp(&self, other: &Self) -> Ordering {
+        self.sort_key().cmp(&other.sort_key())
+    }
 }
 
 impl Display for ZipDateTime {
@@ -68,5 +362,80 @@ mod tests {
         assert_eq!(zip_date_time.time.hour, 20);
         assert_eq!(zip_date_time.time.min, 59);
         assert_eq!(zip_date_time.time.second, 30);
+
+        // 2023-09-25 20:59:30 in local time
+        let unix_seconds = 1695675570 - utc_offset(1695675570);
+
+        assert_eq!(
+            zip_date_time.to_system_time(),
+            UNIX_EPOCH + Duration::from_secs(unix_seconds as u64)
+        );
+        assert_eq!(
+            format_system_time(zip_date_time.to_system_time()),
+            zip_date_time.to_string()
+        );
+        assert_eq!(
+            format_system_time(
+                UNIX_EPOCH + Duration::from_millis(unix_seconds as u64 * 1000 + 1500)
+            ),
+            "09/25/2023 20:59:31"
+        );
+    }
+
+    #[test]
+    fn test_parse_rfc3339() {
+        let expected = UNIX_EPOCH + Duration::from_secs(1695675571);
+
+        assert_eq!(parse_rfc3339("2023-09-25T20:59:31Z"), Some(expected));
+        assert_eq!(
+            parse_rfc3339("2023-09-25t22:59:31.250+02:00"),
+            Some(expected)
+        );
+        assert_eq!(parse_rfc3339("2023-09-25 15:59:31-05:00"), Some(expected));
+        assert_eq!(
+            parse_rfc3339("1969-12-31T23:59:59Z"),
+            Some(UNIX_EPOCH - Duration::from_secs(1))
+        );
+        assert_eq!(
+            parse_rfc3339("2024-02-29T00:00:00Z"),
+            Some(UNIX_EPOCH + Duration::from_secs(1709164800))
+        );
+        assert_eq!(parse_rfc3339("2023-02-29T00:00:00Z"), None);
+        assert_eq!(parse_rfc3339("2023-09-25T20:59:31"), None);
+        assert_eq!(parse_rfc3339("2023-09-25T24:00:00Z"), None);
+        assert_eq!(parse_rfc3339("2023-09-25T20:59:31.Z"), None);
+        assert_eq!(parse_rfc3339("2023-9-25T20:59:31Z"), None);
+        assert_eq!(parse_rfc3339("2023-09-25T20:59:31+0200"), None);
+    }
+
+    #[test]
+    fn test_from_system_time() {
+        // 2023-09-25 20:59:31 in local time
+        let system_time =
+            UNIX_EPOCH + Duration::from_secs((1695675571 - utc_offset(1695675571)) as u64);
+
+        assert_eq!(
+            ZipDateTime::from_system_time(system_time),
+            ZipDateTime::from_bytes(0x5739, 0xA76F)
+        );
+        assert_eq!(
+            ZipDateTime::from_system_time(UNIX_EPOCH),
+            ZipDateTime::from_bytes(0x0021, 0x0000)
+        );
+        assert!(
+            ZipDateTime::from_system_time(system_time) > ZipDateTime::from_bytes(0x5739, 0xA76E)
+        );
+        assert_eq!(
+            ZipDateTime::from_system_time(system_time).to_bytes(),
+            (0x5739, 0xA76F)
+        );
+        assert_eq!(
+            ZipDateTime::from_system_time(system_time).to_system_time(),
+            system_time - Duration::from_secs(1)
+        );
+        assert_eq!(
+            ZipDateTime::from_utc_system_time(UNIX_EPOCH + Duration::from_secs(1695675571)),
+            ZipDateTime::from_bytes(0x5739, 0xA76F)
+        );
     }
 }
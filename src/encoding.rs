@@ -0,0 +1,113 @@
+use encoding_rs::Encoding;
+
+// Characters of the bytes 0x80-0xFF in IBM code page 437. The lower half matches ASCII.
+const CP437_HIGH_HALF: [char; 128] = [
+    'Ç', 'ü', 'é', 'â', 'ä', 'à', 'å', 'ç', 'ê', 'ë', 'è', 'ï', 'î', 'ì', 'Ä', 'Å', 'É', 'æ', 'Æ',
+    'ô', 'ö', 'ò', 'û', 'ù', 'ÿ', 'Ö', 'Ü', '¢', '£', '¥', '₧', 'ƒ', 'á', 'í', 'ó', 'ú', 'ñ', 'Ñ',
+    'ª', 'º', '¿', '⌐', '¬', '½', '¼', '¡', '«', '»', '░', '▒', '▓', '│', '┤', '╡', '╢', '╖', '╕',
+    '╣', '║', '╗', '╝', '╜', '╛', '┐', '└', '┴', '┬', '├', '─', '┼', '╞', '╟', '╚', '╔', '╩', '╦',
+    '╠', '═', '╬', '╧', '╨', '╤', '╥', '╙', '╘', '╒', '╓', '╫', '╪', '┘', '┌', '█', '▄', '▌', '▐',
+    '▀', 'α', 'ß', 'Γ', 'π', 'Σ', 'σ', 'µ', 'τ', 'Φ', 'Θ', 'Ω', 'δ', '∞', 'φ', 'ε', '∩', '≡', '±',
+    '≥', '≤', '⌠', '⌡', '÷', '≈', '°', '∙', '·', '√', 'ⁿ', '²', '■', '\u{A0}',
+];
+
+// Windows code pages of the East Asian encodings, which are not labels of `encoding_rs`
+const CODE_PAGE_ALIASES: [(&str, &str); 4] = [
+    ("cp932", "shift_jis"),
+    ("cp936", "gbk"),
+    ("cp949", "euc-kr"),
+    ("cp950", "big5"),
+];
+
+/// Encoding of the entry names which don't have the UTF-8 flag set.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FileNameEncoding {
+    /// IBM code page 437, which the ZIP specification defaults to.
+    Cp437,
+    /// Any other encoding known to `encoding_rs` like Shift_JIS or GBK.
+    Other(&'static Encoding),
+}
+
+impl FileNameEncoding {
+    /// Looks up the encoding with the given label like `cp437`, `shift_jis` or `cp936`.
+    pub fn for_label(label: &str) -> Option<Self> {
+        let label = label.trim();
+
+        if ["cp437", "ibm437", "437"]
+            .iter()
+            .any(|cp437_label| cp437_label.eq_ignore_ascii_case(label))
+        {
+            return Some(Self::Cp437);
+        }
+
+        let label = CODE_PAGE_ALIASES
+            .iter()
+            .find(|(code_page, _)| code_page.eq_ignore_ascii_case(label))
+            .map_or(label, |(_, alias)| alias);
+
+        Encoding::for_label(label.as_bytes()).map(Self::Other)
+    }
+
+    /// Decodes the given name, or returns `None` if it has bytes which are invalid in the
+    /// encoding. Every byte is valid in code page 437.
+    pub fn decode(&self, bytes: &[u8]) -> Option<String> {
+        match self {
+            Self::Cp437 => Some(decode_cp437(bytes)),
+            Self::Other(encoding) => encoding
+                .decode_without_bom_handling_and_without_replacement(bytes)
+                .map(|name| name.into_owned()),
+        }
+    }
+
+    /// Decodes the given name replacing the invalid bytes with U+FFFD.
+    pub fn decode_lossy(&self, bytes: &[u8]) -> String {
+        match self {
+            Self::Cp437 => decode_cp437(bytes),
+            Self::Other(encoding) => encoding.decode_without_bom_handling(bytes).0.into_owned(),
+        }
+    }
+}
+
+fn decode_cp437(bytes: &[u8]) -> String {
+    bytes
+        .iter()
+        .map(|&byte| {
+            if byte.is_ascii() {
+                byte as char
+            } else {
+                CP437_HIGH_HALF[byte as usize - 0x80]
+            }
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_file_name_encoding() {
+        assert_eq!(
+            FileNameEncoding::for_label("CP437"),
+            Some(FileNameEncoding::Cp437)
+        );
+        assert_eq!(
+            FileNameEncoding::Cp437.decode(b"r\x82sum\x82 \xB0.txt"),
+            Some("résumé ░.txt".to_string())
+        );
+
+        let shift_jis = FileNameEncoding::for_label("shift_jis").unwrap();
+
+        assert_eq!(
+            shift_jis.decode(b"\x93\xFA\x96\x7B\x8C\xEA.txt"),
+            Some("日本語.txt".to_string())
+        );
+        assert_eq!(shift_jis.decode(b"\x93.txt"), None);
+        assert_eq!(shift_jis.decode_lossy(b"\x93.txt"), "\u{FFFD}.txt");
+        assert_eq!(
+            FileNameEncoding::for_label("CP936"),
+            Some(FileNameEncoding::Other(encoding_rs::GBK))
+        );
+        assert_eq!(FileNameEncoding::for_label("klingon"), None);
+    }
+}
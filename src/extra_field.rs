@@ -0,0 +1,347 @@
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use byteorder::{ByteOrder, LittleEndian};
+use crc::{Crc, CRC_32_ISO_HDLC};
+
+pub const ZIP64_EXTRA_FIELD_ID: u16 = 0x0001;
+/// NTFS extra field holding the modification, access and creation times in 100ns precision.
+pub const NTFS_EXTRA_FIELD_ID: u16 = 0x000A;
+/// Strong encryption header of entries encrypted with PKWARE Strong Encryption.
+pub const STRONG_ENCRYPTION_EXTRA_FIELD_ID: u16 = 0x0017;
+/// Info-ZIP Unicode Path extra field holding the UTF-8 name of the entry.
+pub const UNICODE_PATH_EXTRA_FIELD_ID: u16 = 0x7075;
+pub const UNICODE_PATH_EXTRA_FIELD_VERSION: u8 = 1;
+/// Info-ZIP Unix extra field holding 16-bit uid and gid, only in local file headers.
+pub const UNIX_EXTRA_FIELD_ID: u16 = 0x7855;
+/// Info-ZIP Unix extra field holding uid and gid of variable size.
+pub const NEW_UNIX_EXTRA_FIELD_ID: u16 = 0x7875;
+pub const AES_EXTRA_FIELD_ID: u16 = 0x9901;
+/// Android extra field padding the local file header so the entry data is aligned.
+pub const ALIGNMENT_EXTRA_FIELD_ID: u16 = 0xD935;
+
+pub const EXTRA_FIELD_HEADER_SIZE: usize = 4;
+
+const NTFS_TIMES_TAG: u16 = 0x0001;
+const NTFS_TIMES_SIZE: usize = 24;
+// Seconds between 1601-01-01, where NTFS times start, and the Unix epoch
+const NTFS_EPOCH_OFFSET: u64 = 11_644_473_600;
+const NTFS_TICKS_PER_SECOND: u64 = 10_000_000;
+
+/// A single record of the extra field block found in local file headers and central directory
+/// records.
+#[derive(Debug, PartialEq, Eq)]
+pub struct ExtraField<'a> {
+    pub header_id: u16,
+    pub data: &'a [u8],
+}
+
+impl<'a> ExtraField<'a> {
+    /// Returns the record as it is written to an extra field block.
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut bytes = Vec::with_capacity(EXTRA_FIELD_HEADER_SIZE + self.data.len());
+
+        bytes.extend_from_slice(&self.header_id.to_le_bytes());
+        bytes.extend_from_slice(&(self.data.len() as u16).to_le_bytes());
+        bytes.extend_from_slice(self.data);
+
+        bytes
+    }
+}
+
+pub struct ExtraFields<'a> {
+    bytes: &'a [u8],
+}
+
+impl<'a> ExtraFields<'a> {
+    pub fn new(bytes: &'a [u8]) -> Self {
+        Self { bytes }
+    }
+}
+
+impl<'a> Iterator for ExtraFields<'a> {
+    type Item = ExtraField<'a>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.bytes.len() < EXTRA_FIELD_HEADER_SIZE {
+            return None;
+        }
+
+        let header_id = LittleEndian::read_u16(&self.bytes[0..2]);
+        let data_len = LittleEndian::read_u16(&self.bytes[2..4]) as usize;
+
+        // A truncated record means the rest of the block is malformed so stop iterating instead
+        // of reading past it.
+        if self.bytes.len() < EXTRA_FIELD_HEADER_SIZE + data_len {
+            self.bytes = &[];
+            return None;
+        }
+
+        let data = &self.bytes[EXTRA_FIELD_HEADER_SIZE..EXTRA_FIELD_HEADER_SIZE + data_len];
+        self.bytes = &self.bytes[EXTRA_FIELD_HEADER_SIZE + data_len..];
+
+        Some(ExtraField { header_id, data })
+    }
+}
+
+/// Returns the UTF-8 name stored in the Unicode Path extra field. The field is ignored when the CRC-32
+/// it holds doesn't match the name in the header, which means a tool unaware of the field renamed
+/// the entry.
+pub fn unicode_path(extra_fields: ExtraFields, header_file_name: &[u8]) -> Option<String> {
+    let extra_field = extra_fields
+        .into_iter()
+        .find(|extra_field| extra_field.header_id == UNICODE_PATH_EXTRA_FIELD_ID)?;
+    let (&version, data) = extra_field.data.split_first()?;
+
+    if version != UNICODE_PATH_EXTRA_FIELD_VERSION || data.len() < 4 {
+        return None;
+    }
+
+    let name_crc32 = Crc::<u32>::new(&CRC_32_ISO_HDLC).checksum(header_file_name);
+
+    if LittleEndian::read_u32(&data[..4]) != name_crc32 {
+        return None;
+    }
+
+    String::from_utf8(data[4..].to_vec()).ok()
+}
+
+/// Returns the modification time stored in the NTFS extra field.
+pub fn ntfs_modified_time(extra_fields: ExtraFields) -> Option<SystemTime> {
+    let extra_field = extra_fields
+        .into_iter()
+        .find(|extra_field| extra_field.header_id == NTFS_EXTRA_FIELD_ID)?;
+    // The attributes follow 4 reserved bytes, each with a tag and a size
+    let mut attributes = extra_field.data.get(4..)?;
+
+    while attributes.len() >= EXTRA_FIELD_HEADER_SIZE {
+        let tag = LittleEndian::read_u16(&attributes[0..2]);
+        let size = LittleEndian::read_u16(&attributes[2..4]) as usize;
+        let data = attributes.get(EXTRA_FIELD_HEADER_SIZE..EXTRA_FIELD_HEADER_SIZE + size)?;
+
+        if tag == NTFS_TIMES_TAG && size == NTFS_TIMES_SIZE {
+            return ntfs_time(LittleEndian::read_u64(&data[0..8]));
+        }
+
+        attributes = &attributes[EXTRA_FIELD_HEADER_SIZE + size..];
+    }
+
+    None
+}
+
+// Converts a count of 100ns intervals since 1601-01-01 UTC to a system time
+fn ntfs_time(ticks: u64) -> Option<SystemTime> {
+    let ntfs_time = Duration::new(
+        ticks / NTFS_TICKS_PER_SECOND,
+        (ticks % NTFS_TICKS_PER_SECOND * 100) as u32,
+    );
+    let epoch_offset = Duration::from_secs(NTFS_EPOCH_OFFSET);
+
+    match ntfs_time.checked_sub(epoch_offset) {
+        Some(since_epoch) => UNIX_EPOCH.checked_add(since_epoch),
+        None => UNIX_EPOCH.checked_sub(epoch_offset - ntfs_time),
+    }
+}
+
+/// Owner of an entry stored in the Info-ZIP Unix extra fields.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct UnixExtraField {
+    pub uid: u32,
+    pub gid: u32,
+}
+
+impl UnixExtraField {
+    /// Parses the older field with 16-bit ids or the newer one with ids of variable size. The older
+    /// field has no data in central directory records.
+    pub fn from_extra_field(extra_field: &ExtraField) -> Option<Self> {
+        match extra_field.header_id {
+            UNIX_EXTRA_FIELD_ID if extra_field.data.len() >= 4 => Some(Self {
+                uid: LittleEndian::read_u16(&extra_field.data[0..2]) as u32,
+                gid: LittleEndian::read_u16(&extra_field.data[2..4]) as u32,
+            }),
+            NEW_UNIX_EXTRA_FIELD_ID => Self::from_new_field_data(extra_field.data),
+            _ => None,
+        }
+    }
+
+    /// Returns the owner stored in the given extra fields, preferring the newer field since it
+    /// isn't limited to 16 bits.
+    pub fn find(extra_fields: ExtraFields) -> Option<Self> {
+        let mut owner = None;
+
+        for extra_field in extra_fields {
+            match Self::from_extra_field(&extra_field) {
+                Some(new_owner) if extra_field.header_id == NEW_UNIX_EXTRA_FIELD_ID => {
+                    return Some(new_owner)
+                }
+                Some(old_owner) => owner = Some(old_owner),
+                None => (),
+            }
+        }
+
+        owner
+    }
+
+    // Version 1 of the newer field is a version byte followed by the size and value of the uid
+    // and the gid
+    fn from_new_field_data(data: &[u8]) -> Option<Self> {
+        let (&version, data) = data.split_first()?;
+
+        if version != 1 {
+            return None;
+        }
+
+        let (uid, data) = sized_id(data)?;
+        let (gid, _) = sized_id(data)?;
+
+        Some(Self { uid, gid })
+    }
+}
+
+fn sized_id(data: &[u8]) -> Option<(u32, &[u8])> {
+    let (&size, data) = data.split_first()?;
+    let size = size as usize;
+
+    if !(1..=8).contains(&size) || data.len() < size {
+        return None;
+    }
+
+    let id = LittleEndian::read_uint(&data[..size], size);
+
+    Some((u32::try_from(id).ok()?, &data[size..]))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_extra_fields() {
+        let bytes = [
+            0x01, 0x00, 0x08, 0x00, 0x01, 0x02, 0x03, 0x04, 0x05, 0x06, 0x07, 0x08, 0x75, 0x70,
+            0x01, 0x00, 0xFF,
+        ];
+        let extra_fields: Vec<ExtraField> = ExtraFields::new(&bytes).collect();
+
+        assert_eq!(
+            extra_fields,
+            vec![
+                ExtraField {
+                    header_id: ZIP64_EXTRA_FIELD_ID,
+                    data: &bytes[4..12]
+                },
+                ExtraField {
+                    header_id: 0x7075,
+                    data: &bytes[16..17]
+                }
+            ]
+        );
+    }
+
+    #[test]
+    fn test_extra_field_to_bytes() {
+        let extra_field = ExtraField {
+            header_id: UNICODE_PATH_EXTRA_FIELD_ID,
+            data: &[0x01, 0xFF],
+        };
+
+        assert_eq!(
+            extra_field.to_bytes(),
+            vec![0x75, 0x70, 0x02, 0x00, 0x01, 0xFF]
+        );
+    }
+
+    #[test]
+    fn test_unix_extra_field() {
+        // Unix extra field with uid 1000 and gid 100 and the newer one with uid 100000 and gid 20
+        let bytes = [
+            0x55, 0x78, 0x04, 0x00, 0xE8, 0x03, 0x64, 0x00, 0x75, 0x78, 0x0B, 0x00, 0x01, 0x04,
+            0xA0, 0x86, 0x01, 0x00, 0x04, 0x14, 0x00, 0x00, 0x00,
+        ];
+
+        assert_eq!(
+            UnixExtraField::find(ExtraFields::new(&bytes)),
+            Some(UnixExtraField {
+                uid: 100000,
+                gid: 20
+            })
+        );
+        assert_eq!(
+            UnixExtraField::find(ExtraFields::new(&bytes[..8])),
+            Some(UnixExtraField {
+                uid: 1000,
+                gid: 100
+            })
+        );
+        // The central directory record of the older field has no data
+        assert_eq!(
+            UnixExtraField::find(ExtraFields::new(&[0x55, 0x78, 0x00, 0x00])),
+            None
+        );
+        // Ids wider than 32 bits and unknown versions of the newer field are not supported
+        let wide_uid = [
+            0x75, 0x78, 0x0C, 0x00, 0x01, 0x08, 0, 0, 0, 0, 1, 0, 0, 0, 0x01, 0x00,
+        ];
+
+        assert_eq!(UnixExtraField::find(ExtraFields::new(&wide_uid)), None);
+        assert_eq!(
+            UnixExtraField::from_extra_field(&ExtraField {
+                header_id: NEW_UNIX_EXTRA_FIELD_ID,
+                data: &[0x02, 0x01, 0x00, 0x01, 0x00],
+            }),
+            None
+        );
+    }
+
+    #[test]
+    fn test_unicode_path() {
+        // Name in CP1252 which is not valid UTF-8
+        let header_file_name = b"r\xE9sum\xE9.txt";
+        let name_crc32 = Crc::<u32>::new(&CRC_32_ISO_HDLC).checksum(header_file_name);
+        let mut data = vec![UNICODE_PATH_EXTRA_FIELD_VERSION];
+        data.extend_from_slice(&name_crc32.to_le_bytes());
+        data.extend_from_slice("résumé.txt".as_bytes());
+
+        let bytes = ExtraField {
+            header_id: UNICODE_PATH_EXTRA_FIELD_ID,
+            data: &data,
+        }
+        .to_bytes();
+
+        assert_eq!(
+            unicode_path(ExtraFields::new(&bytes), header_file_name),
+            Some("résumé.txt".to_string())
+        );
+        // The header name was changed after the extra field was written
+        assert_eq!(unicode_path(ExtraFields::new(&bytes), b"resume.txt"), None);
+    }
+
+    #[test]
+    fn test_ntfs_modified_time() {
+        // 2023-09-25 20:59:31.5 UTC as modification time, followed by access and creation times
+        let mut bytes = vec![
+            0x0A, 0x00, 0x20, 0x00, 0x00, 0x00, 0x00, 0x00, 0x01, 0x00, 0x18, 0x00,
+        ];
+        bytes.extend_from_slice(&133401491715000000u64.to_le_bytes());
+        bytes.extend_from_slice(&[0; 16]);
+
+        assert_eq!(
+            ntfs_modified_time(ExtraFields::new(&bytes)),
+            Some(UNIX_EPOCH + Duration::from_millis(1695675571500))
+        );
+        // Times before the Unix epoch
+        assert_eq!(
+            ntfs_time(0),
+            UNIX_EPOCH.checked_sub(Duration::from_secs(NTFS_EPOCH_OFFSET))
+        );
+        // The times attribute is cut off
+        assert_eq!(ntfs_modified_time(ExtraFields::new(&bytes[..28])), None);
+        assert_eq!(ntfs_modified_time(ExtraFields::new(&[])), None);
+    }
+
+    #[test]
+    fn test_truncated_extra_field() {
+        let bytes = [0x01, 0x00, 0x08, 0x00, 0x01, 0x02];
+
+        assert_eq!(ExtraFields::new(&bytes).count(), 0);
+    }
+}
@@ -0,0 +1,180 @@
+/// Number of bytes at the start of a file looked at to recognize its type, enough to reach the
+/// `ustar` magic of tar files.
+pub const SNIFFED_PREFIX_SIZE: usize = 262;
+
+const TAR_MAGIC_OFFSET: usize = 257;
+
+/// Broad kinds of files the extraction can be limited to.
+#[derive(Debug, PartialEq, Eq, Clone, Copy, clap::ValueEnum)]
+pub enum FileType {
+    Images,
+    Documents,
+    Audio,
+    Video,
+    Archives,
+}
+
+impl FileType {
+    /// Returns the type of the files with the given extension, in any case and without the dot.
+    pub fn from_extension(extension: &str) -> Option<Self> {
+        let file_type = match extension.to_lowercase().as_str() {
+            "jpg" | "jpeg" | "png" | "gif" | "bmp" | "tif" | "tiff" | "webp" | "svg" | "ico"
+            | "heic" | "heif" | "avif" | "psd" => FileType::Images,
+            "pdf" | "doc" | "docx" | "xls" | "xlsx" | "ppt" | "pptx" | "odt" | "ods" | "odp"
+            | "rtf" | "txt" | "md" | "csv" | "epub" => FileType::Documents,
+            "mp3" | "wav" | "flac" | "ogg" | "oga" | "opus" | "m4a" | "aac" | "wma" | "aif"
+            | "aiff" | "mid" | "midi" => FileType::Audio,
+            "mp4" | "m4v" | "mkv" | "webm" | "avi" | "mov" | "wmv" | "flv" | "mpg" | "mpeg"
+            | "3gp" => FileType::Video,
+            "zip" | "jar" | "7z" | "rar" | "tar" | "gz" | "tgz" | "bz2" | "xz" | "zst" => {
+                FileType::Archives
+            }
+            _ => return None,
+        };
+
+        Some(file_type)
+    }
+
+    /// Recognizes the type of a file from the magic bytes at its start, given up to
+    /// [`SNIFFED_PREFIX_SIZE`] bytes of it.
+    pub fn sniff(prefix: &[u8]) -> Option<Self> {
+        let starts_with = |magic: &[u8]| prefix.starts_with(magic);
+        let bytes_at = |offset: usize, len: usize| prefix.get(offset..offset + len);
+
+        if starts_with(b"\x89PNG\r\n\x1a\n")
+            || starts_with(b"\xff\xd8\xff")
+            || starts_with(b"GIF87a")
+            || starts_with(b"GIF89a")
+            || starts_with(b"II*\0")
+            || starts_with(b"MM\0*")
+            || starts_with(b"8BPS")
+        {
+            return Some(FileType::Images);
+        }
+
+        if starts_with(b"%PDF-")
+            || starts_with(b"{\\rtf")
+            || starts_with(b"\xd0\xcf\x11\xe0\xa1\xb1\x1a\xe1")
+        {
+            return Some(FileType::Documents);
+        }
+
+        if starts_with(b"ID3")
+            || starts_with(b"fLaC")
+            || starts_with(b"OggS")
+            || starts_with(b"MThd")
+            || (prefix.len() >= 2 && prefix[0] == 0xff && prefix[1] & 0xf6 == 0xf2)
+        {
+            return Some(FileType::Audio);
+        }
+
+        if starts_with(b"\x1a\x45\xdf\xa3")
+            || starts_with(b"FLV\x01")
+            || starts_with(b"\0\0\x01\xba")
+        {
+            return Some(FileType::Video);
+        }
+
+        if starts_with(b"RIFF") {
+            return match bytes_at(8, 4)? {
+                b"WEBP" => Some(FileType::Images),
+                b"WAVE" => Some(FileType::Audio),
+                b"AVI " => Some(FileType::Video),
+                _ => None,
+            };
+        }
+
+        if starts_with(b"FORM") {
+            return matches!(bytes_at(8, 4)?, b"AIFF" | b"AIFC").then_some(FileType::Audio);
+        }
+
+        // ISO base media files name their major brand after the ftyp box
+        if bytes_at(4, 4) == Some(b"ftyp") {
+            return match bytes_at(8, 4)? {
+                b"heic" | b"heix" | b"mif1" | b"avif" => Some(FileType::Images),
+                b"M4A " | b"M4B " => Some(FileType::Audio),
+                _ => Some(FileType::Video),
+            };
+        }
+
+        // Office and OpenDocument files are zip files starting with a well-known entry
+        if starts_with(b"PK\x03\x04") {
+            let file_name_len = u16::from_le_bytes([*prefix.get(26)?, *prefix.get(27)?]) as usize;
+
+            return match bytes_at(30, file_name_len) {
+                Some(b"[Content_Types].xml") | Some(b"mimetype") => Some(FileType::Documents),
+                _ => Some(FileType::Archives),
+            };
+        }
+
+        if starts_with(b"\x1f\x8b")
+            || starts_with(b"BZh")
+            || starts_with(b"\xfd7zXZ\0")
+            || starts_with(b"7z\xbc\xaf\x27\x1c")
+            || starts_with(b"Rar!\x1a\x07")
+            || starts_with(b"\x28\xb5\x2f\xfd")
+            || bytes_at(TAR_MAGIC_OFFSET, 5) == Some(b"ustar")
+        {
+            return Some(FileType::Archives);
+        }
+
+        None
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_from_extension() {
+        assert_eq!(FileType::from_extension("JPG"), Some(FileType::Images));
+        assert_eq!(FileType::from_extension("docx"), Some(FileType::Documents));
+        assert_eq!(FileType::from_extension("flac"), Some(FileType::Audio));
+        assert_eq!(FileType::from_extension("mkv"), Some(FileType::Video));
+        assert_eq!(FileType::from_extension("zip"), Some(FileType::Archives));
+        assert_eq!(FileType::from_extension("bin"), None);
+    }
+
+    #[test]
+    fn test_sniff() {
+        assert_eq!(
+            FileType::sniff(b"\x89PNG\r\n\x1a\n\0\0\0\rIHDR"),
+            Some(FileType::Images)
+        );
+        assert_eq!(FileType::sniff(b"%PDF-1.7\n"), Some(FileType::Documents));
+        assert_eq!(
+            FileType::sniff(b"RIFF\0\0\0\0WAVEfmt "),
+            Some(FileType::Audio)
+        );
+        assert_eq!(
+            FileType::sniff(b"RIFF\0\0\0\0WEBPVP8 "),
+            Some(FileType::Images)
+        );
+        assert_eq!(
+            FileType::sniff(b"\0\0\0\x18ftypisom"),
+            Some(FileType::Video)
+        );
+        assert_eq!(
+            FileType::sniff(b"\0\0\0\x18ftypheic"),
+            Some(FileType::Images)
+        );
+
+        let mut docx = b"PK\x03\x04".to_vec();
+        docx.extend_from_slice(&[0; 22]);
+        docx.extend_from_slice(&19u16.to_le_bytes());
+        docx.extend_from_slice(&[0; 2]);
+        docx.extend_from_slice(b"[Content_Types].xml");
+        assert_eq!(FileType::sniff(&docx), Some(FileType::Documents));
+
+        docx[30..49].copy_from_slice(b"content_types.xml\0\0");
+        assert_eq!(FileType::sniff(&docx), Some(FileType::Archives));
+
+        let mut tar = vec![0; SNIFFED_PREFIX_SIZE];
+        tar[TAR_MAGIC_OFFSET..TAR_MAGIC_OFFSET + 5].copy_from_slice(b"ustar");
+        assert_eq!(FileType::sniff(&tar), Some(FileType::Archives));
+
+        assert_eq!(FileType::sniff(b"hello world"), None);
+        assert_eq!(FileType::sniff(b""), None);
+    }
+}
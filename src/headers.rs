@@ -1,24 +1,65 @@
 use byteorder::{ByteOrder, LittleEndian};
 use std::cell::Cell;
 use std::error::Error;
+use std::ffi::OsString;
 use std::fmt::Display;
-use std::io::{Read, Seek, SeekFrom};
+use std::io::{Cursor, Read, Seek, SeekFrom};
+use std::time::SystemTime;
 
+use crate::aes::{AesExtraField, AesVendorVersion};
 use crate::date_time::ZipDateTime;
-
-const MIN_EOF_CENTRAL_DIR_SIZE: u64 = 0x16;
-const MIN_CENTRAL_DIR_SIZE: u64 = 0x2E;
-const EOF_CENTRAL_DIR_SIGN: u32 = 0x06054b50;
-const CENTRAL_DIR_SIGN: u32 = 0x02014b50;
+use crate::encoding::FileNameEncoding;
+use crate::extra_field::{
+    ntfs_modified_time, unicode_path, ExtraFields, UnixExtraField, AES_EXTRA_FIELD_ID,
+    STRONG_ENCRYPTION_EXTRA_FIELD_ID, ZIP64_EXTRA_FIELD_ID,
+};
+
+pub const MIN_EOF_CENTRAL_DIR_SIZE: u64 = 0x16;
+pub const MIN_CENTRAL_DIR_SIZE: u64 = 0x2E;
+pub const MAX_COMMENT_LEN: u64 = 0xFFFF;
+pub const EOF_CENTRAL_DIR_COMMENT_LEN_OFFSET: u64 = 0x14;
+pub const EOF_CENTRAL_DIR_SIGN: u32 = 0x06054b50;
+pub const CENTRAL_DIR_SIGN: u32 = 0x02014b50;
+pub const LOCAL_FILE_HEADER_SIGN: u32 = 0x04034b50;
+pub const ZIP64_EOF_CENTRAL_DIR_SIGN: u32 = 0x06064b50;
+pub const ZIP64_EOF_CENTRAL_DIR_LOCATOR_SIGN: u32 = 0x07064b50;
+// Precedes the central directory when it is encrypted with PKWARE Strong Encryption
+pub const ARCHIVE_EXTRA_DATA_SIGN: u32 = 0x08064b50;
+const ZIP64_EOF_CENTRAL_DIR_LOCATOR_SIZE: u64 = 0x14;
+const MIN_ZIP64_EOF_CENTRAL_DIR_SIZE: usize = 0x38;
+// Optional signature some writers put before the data descriptor
+const DATA_DESCRIPTOR_SIGN: u32 = 0x08074b50;
+// CRC-32 followed by the compressed and uncompressed sizes, stored in 4 bytes or in 8 bytes in
+// ZIP64 archives
 const DATA_DESCRIPTOR_SIZE: usize = 12;
-
-const DATA_DESCRIPTOR_READ_FAILURE_EXIT_CODE: i32 = -4;
+const ZIP64_DATA_DESCRIPTOR_SIZE: usize = 20;
+const MIN_LOCAL_FILE_HEADER_SIZE: usize = 0x1E;
+// Fields that do not fit are set to this value and kept in the ZIP64 extra field
+const ZIP64_SIZE_MARKER: u32 = 0xFFFFFFFF;
+// Entries encrypted with WinZip AES use this compression method
+const AES_COMPRESSION_METHOD: u16 = 0x63;
+
+const UNIX_FILE_TYPE_MASK: u32 = 0o170000;
+const UNIX_DIRECTORY_MODE: u32 = 0o040000;
+const UNIX_SYMLINK_MODE: u32 = 0o120000;
+const UNIX_PERMISSIONS_MASK: u32 = 0o7777;
+const MS_DOS_DIRECTORY_ATTRIBUTE: u32 = 0x10;
+// Bit 11 of the general purpose bit flag marks names encoded in UTF-8
+const UTF8_FILE_NAME_FLAG: u16 = 1 << 11;
 
 #[derive(Debug, PartialEq, Eq)]
 pub enum EndOfCentralDirectoryError {
     InvalidZipFile(u64),
     InvalidSignature(u32),
     EmptyZipFile,
+    InvalidDiskNumbers {
+        disk_number: u16,
+        central_dir_start_disk: u16,
+    },
+    SpannedArchive {
+        disk_count: u32,
+        central_dir_start_disk: u16,
+    },
     IOError(String),
 }
 
@@ -27,6 +68,8 @@ pub enum ZipFileError {
     InvalidSignature(u32),
     UnsupportedZipVersion(u8),
     UnsupportedCompression(u16),
+    InvalidAesExtraField(String),
+    MissingDisk(String, u16),
     FileEnvironmentError(FileEnvironmentError),
     IOError(String),
 }
@@ -42,12 +85,50 @@ pub enum FileEnvironment {
     Unix = 3,
 }
 
+/// Controls how file names that cannot be decoded are handled while parsing.
+#[derive(Debug, Default, PartialEq, Eq, Clone, Copy)]
+pub enum FileNamePolicy {
+    /// Fail parsing the entry.
+    #[default]
+    Strict,
+    /// Replace invalid sequences with U+FFFD and keep parsing.
+    Lossy,
+}
+
+#[derive(Debug, Default, Clone)]
+pub struct ParseOptions {
+    pub file_name_policy: FileNamePolicy,
+    /// Encoding of the names without the UTF-8 flag. They are decoded as UTF-8 when valid and as
+    /// code page 437 otherwise unless an encoding is given.
+    pub file_name_encoding: Option<FileNameEncoding>,
+    /// Start offsets of the disks of a split archive within the stitched segments. Empty unless
+    /// the archive is split, in which case the offsets recorded per disk are translated with it.
+    pub disk_start_offsets: Vec<u64>,
+}
+
+impl ParseOptions {
+    pub(crate) fn disk_start_offset(&self, disk_number: u16) -> Option<u64> {
+        if self.disk_start_offsets.is_empty() {
+            Some(0)
+        } else {
+            self.disk_start_offsets.get(disk_number as usize).copied()
+        }
+    }
+}
+
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub enum EntryKind {
+    File,
+    Directory,
+    Symlink,
+}
+
 #[derive(Debug, PartialEq, Eq)]
 pub enum FileEnvironmentError {
     InvalidFileEnvironment(u8),
 }
 
-#[derive(Debug, PartialEq, Eq)]
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
 pub enum DeflateCompressionMode {
     Normal,
     Maximum,
@@ -55,17 +136,80 @@ pub enum DeflateCompressionMode {
     SuperFast,
 }
 
-#[derive(Debug, PartialEq, Eq)]
+impl DeflateCompressionMode {
+    /// Returns the mode recorded for a deflate level from 1 to 9, following Info-ZIP which marks
+    /// levels 1 and 2 as super fast and fast and levels 8 and 9 as maximum compression.
+    pub fn from_level(level: u32) -> Self {
+        match level {
+            0..=1 => DeflateCompressionMode::SuperFast,
+            2 => DeflateCompressionMode::Fast,
+            8.. => DeflateCompressionMode::Maximum,
+            _ => DeflateCompressionMode::Normal,
+        }
+    }
+}
+
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
 pub enum CompressionMethod {
     NoCompression,
     Deflate(DeflateCompressionMode),
+    #[cfg(feature = "bzip2")]
+    Bzip2,
+    /// LZMA streams either end with an end marker or after the uncompressed size of the entry.
+    #[cfg(feature = "lzma")]
+    Lzma {
+        end_marker: bool,
+    },
+    #[cfg(feature = "zstd")]
+    Zstd,
+    #[cfg(feature = "xz")]
+    Xz,
 }
 
-#[derive(Debug, PartialEq, Eq)]
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
 pub enum EncryptionMethod {
     NoEncryption,
     ZipCrypto,
     Aes,
+    StrongEncryption,
+}
+
+/// Encryption algorithm of an entry encrypted with PKWARE Strong Encryption, read from its strong
+/// encryption header extra field.
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub struct StrongEncryptionAlgorithm {
+    pub algorithm_id: u16,
+    pub bit_len: u16,
+}
+
+impl StrongEncryptionAlgorithm {
+    // The extra field holds the format, the algorithm ID, the key length and the flags
+    fn from_bytes(data: &[u8]) -> Option<Self> {
+        if data.len() < 8 {
+            return None;
+        }
+
+        Some(Self {
+            algorithm_id: LittleEndian::read_u16(&data[2..4]),
+            bit_len: LittleEndian::read_u16(&data[4..6]),
+        })
+    }
+}
+
+impl Display for StrongEncryptionAlgorithm {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self.algorithm_id {
+            0x6601 => write!(f, "DES"),
+            0x6602 | 0x6702 => write!(f, "RC2"),
+            0x6603 => write!(f, "3DES-168"),
+            0x6609 => write!(f, "3DES-112"),
+            0x660E..=0x6610 => write!(f, "AES-{}", self.bit_len),
+            0x6720 => write!(f, "Blowfish"),
+            0x6721 => write!(f, "Twofish"),
+            0x6801 => write!(f, "RC4"),
+            algorithm_id => write!(f, "unknown algorithm 0x{:04X}", algorithm_id),
+        }
+    }
 }
 
 impl Display for EncryptionMethod {
@@ -74,6 +218,7 @@ impl Display for EncryptionMethod {
             EncryptionMethod::NoEncryption => write!(f, "No Encryption"),
             EncryptionMethod::ZipCrypto => write!(f, "Zip Crypto"),
             EncryptionMethod::Aes => write!(f, "AES"),
+            EncryptionMethod::StrongEncryption => write!(f, "PKWARE Strong Encryption"),
         }
     }
 }
@@ -88,6 +233,16 @@ impl Display for EndOfCentralDirectoryError {
                 sign
             ),
             Self::EmptyZipFile => write!(f, "A zip file must contain at least 1 file"),
+            Self::InvalidDiskNumbers { disk_number, central_dir_start_disk } => write!(
+                f,
+                "Invalid disk numbers. The central directory starts on disk {} after the last disk {}",
+                central_dir_start_disk, disk_number
+            ),
+            Self::SpannedArchive { disk_count, .. } => write!(
+                f,
+                "The ZIP file is the last segment of a split archive with {} disks. Put all the .z01, .z02, ... segments next to it",
+                disk_count
+            ),
             Self::IOError(error_msg) => write!(
                 f,
                 "An I/O error occured while parsing end of central directory. Message: {}",
@@ -122,6 +277,17 @@ impl Display for ZipFileError {
                 "Unsupported compression method. Read compression method: {}",
                 comp
             ),
+            ZipFileError::InvalidAesExtraField(file_name) => write!(
+                f,
+                "The AES encrypted entry \"{}\" has no valid AES extra field",
+                file_name
+            ),
+            ZipFileError::MissingDisk(file_name, disk_number) => write!(
+                f,
+                "The entry \"{}\" starts on disk {} which is not part of the split archive",
+                file_name,
+                disk_number + 1
+            ),
             ZipFileError::FileEnvironmentError(err) => write!(f, "{}", err),
             Self::IOError(error_msg) => write!(
                 f,
@@ -165,18 +331,35 @@ impl Display for CompressionMethod {
         match self {
             CompressionMethod::NoCompression => write!(f, "No Compression"),
             CompressionMethod::Deflate(_) => write!(f, "DEFLATE"),
+            #[cfg(feature = "bzip2")]
+            CompressionMethod::Bzip2 => write!(f, "BZIP2"),
+            #[cfg(feature = "lzma")]
+            CompressionMethod::Lzma { .. } => write!(f, "LZMA"),
+            #[cfg(feature = "zstd")]
+            CompressionMethod::Zstd => write!(f, "Zstandard"),
+            #[cfg(feature = "xz")]
+            CompressionMethod::Xz => write!(f, "XZ"),
         }
     }
 }
 
 #[derive(Debug)]
 pub struct EndOfCentralDirectory {
-    central_dir_size: u8,
-    central_dir_start_offset: u32,
+    offset: u64,
+    disk_number: u16,
+    central_dir_start_disk: u16,
+    central_dir_size: u64,
+    central_dir_size_in_bytes: u64,
+    central_dir_start_offset: u64,
+    comment_len: u16,
+    comment: Vec<u8>,
+    zip64_locator_found: bool,
+    zip64_record_offset: Option<u64>,
+    trailing_data_len: u64,
 }
 
 pub struct ZipFile {
-    offset: u32,
+    offset: u64,
     environment: FileEnvironment,
     encryption_method: EncryptionMethod,
     compression_method: CompressionMethod,
@@ -186,9 +369,18 @@ pub struct ZipFile {
     data_descriptor_used: bool,
     date_time: ZipDateTime,
     crc32: Cell<u32>,
-    compressed_size: Cell<u32>,
-    uncompressed_size: Cell<u32>,
+    compressed_size: Cell<u64>,
+    uncompressed_size: Cell<u64>,
+    // Start and length of the central directory record of the entry
+    central_header_range: (u64, u64),
     file_name: String,
+    raw_file_name: Vec<u8>,
+    // Whether bytes of the name that could not be decoded were replaced
+    file_name_lossy: bool,
+    extra_field: Vec<u8>,
+    aes_extra_field: Option<AesExtraField>,
+    external_attributes: u32,
+    entry_kind: EntryKind,
     is_dir: bool,
 }
 
@@ -207,6 +399,33 @@ impl FileEnvironment {
     }
 }
 
+impl EntryKind {
+    /// Determines the kind of an entry. Unix hosts keep the file type in the upper 16 bits of the
+    /// external attributes while MS-DOS compatible hosts set the directory attribute in the lower
+    /// byte. Some archivers set neither, so a trailing slash in the name still marks a directory.
+    pub fn from_attributes(
+        file_name: &str,
+        environment: &FileEnvironment,
+        external_attributes: u32,
+    ) -> Self {
+        let unix_mode = external_attributes >> 16;
+
+        if matches!(environment, FileEnvironment::Unix | FileEnvironment::OSX) && unix_mode != 0 {
+            match unix_mode & UNIX_FILE_TYPE_MASK {
+                UNIX_DIRECTORY_MODE => return EntryKind::Directory,
+                UNIX_SYMLINK_MODE => return EntryKind::Symlink,
+                _ => (),
+            }
+        }
+
+        if (external_attributes & MS_DOS_DIRECTORY_ATTRIBUTE) != 0 || file_name.ends_with('/') {
+            EntryKind::Directory
+        } else {
+            EntryKind::File
+        }
+    }
+}
+
 impl EndOfCentralDirectory {
     pub fn from_readable<T>(readable: &mut T) -> Result<Self, EndOfCentralDirectoryError>
     where
@@ -220,43 +439,250 @@ impl EndOfCentralDirectory {
             return Err(EndOfCentralDirectoryError::InvalidZipFile(size));
         }
 
-        let mut eof_central_dir_bytes = vec![0; MIN_EOF_CENTRAL_DIR_SIZE as usize];
+        // The end of central directory record is followed by a variable length comment, so scan
+        // backwards through the last bytes of the file for a signature whose comment length
+        // matches the remaining bytes. Archives with padding or a signature appended after the
+        // comment have no such record, the last record whose comment fits is then used instead.
+        let tail_len = size.min(MIN_EOF_CENTRAL_DIR_SIZE + MAX_COMMENT_LEN);
+        let tail_start = size - tail_len;
+        let mut tail_bytes = vec![0; tail_len as usize];
 
         readable
-            .seek(SeekFrom::End(-0x16))
+            .seek(SeekFrom::Start(tail_start))
             .map_err(|err| EndOfCentralDirectoryError::IOError(err.to_string()))?;
 
         readable
-            .read_exact(&mut eof_central_dir_bytes)
+            .read_exact(&mut tail_bytes)
             .map_err(|err| EndOfCentralDirectoryError::IOError(err.to_string()))?;
 
-        let sign = LittleEndian::read_u32(&eof_central_dir_bytes[0..4]);
+        let last_record_start = tail_bytes.len() - MIN_EOF_CENTRAL_DIR_SIZE as usize;
+        let record_end = |index: usize| {
+            index
+                + MIN_EOF_CENTRAL_DIR_SIZE as usize
+                + LittleEndian::read_u16(&tail_bytes[index + 20..index + 22]) as usize
+        };
+        let mut record_starts = (0..=last_record_start)
+            .rev()
+            .filter(|&index| {
+                LittleEndian::read_u32(&tail_bytes[index..index + 4]) == EOF_CENTRAL_DIR_SIGN
+            })
+            .filter(|&index| record_end(index) <= tail_bytes.len());
+        let record_start = record_starts
+            .clone()
+            .find(|&index| record_end(index) == tail_bytes.len())
+            .or_else(|| {
+                // The signature may also appear in the trailing data, but then the central
+                // directory it points to rarely ends before it. Records of stored archives are
+                // followed by the records of the archive holding them instead of trailing data,
+                // while trailing data may hold their signatures without any record parsing there.
+                record_starts.find(|&index| {
+                    let record_bytes = &tail_bytes[index..];
+                    let central_dir_size = LittleEndian::read_u32(&record_bytes[12..16]);
+                    let central_dir_start_offset = LittleEndian::read_u32(&record_bytes[16..20]);
+                    let trailing_records_found = (record_end(index)..tail_bytes.len())
+                        .any(|offset| record_parses_at(&tail_bytes[offset..]));
+
+                    !trailing_records_found
+                        && (central_dir_size == u32::MAX
+                            || central_dir_start_offset == u32::MAX
+                            || central_dir_start_offset as u64 + central_dir_size as u64
+                                <= tail_start + index as u64)
+                })
+            });
+
+        let record_start = match record_start {
+            Some(record_start) => record_start,
+            None => {
+                let sign = LittleEndian::read_u32(&tail_bytes[last_record_start..]);
+                return Err(EndOfCentralDirectoryError::InvalidSignature(sign));
+            }
+        };
+
+        let eof_central_dir_bytes = &tail_bytes[record_start..record_end(record_start)];
+        let trailing_data_len = (tail_bytes.len() - record_end(record_start)) as u64;
 
-        if sign != EOF_CENTRAL_DIR_SIGN {
-            return Err(EndOfCentralDirectoryError::InvalidSignature(sign));
+        let disk_number = LittleEndian::read_u16(&eof_central_dir_bytes[4..6]);
+        let central_dir_start_disk = LittleEndian::read_u16(&eof_central_dir_bytes[6..8]);
+
+        // The record is on the last disk so the central directory cannot start after it
+        if central_dir_start_disk > disk_number {
+            return Err(EndOfCentralDirectoryError::InvalidDiskNumbers {
+                disk_number,
+                central_dir_start_disk,
+            });
+        }
+
+        let comment_len = LittleEndian::read_u16(&eof_central_dir_bytes[20..22]);
+        let comment = eof_central_dir_bytes[MIN_EOF_CENTRAL_DIR_SIZE as usize..].to_vec();
+        let offset = tail_start + record_start as u64;
+
+        // ZIP64 archives place a locator right before the end of central directory record
+        let zip64_locator_offset = offset.checked_sub(ZIP64_EOF_CENTRAL_DIR_LOCATOR_SIZE);
+        let mut zip64_locator_bytes = [0u8; ZIP64_EOF_CENTRAL_DIR_LOCATOR_SIZE as usize];
+
+        if let Some(zip64_locator_offset) = zip64_locator_offset {
+            readable
+                .seek(SeekFrom::Start(zip64_locator_offset))
+                .and_then(|_| readable.read_exact(&mut zip64_locator_bytes))
+                .map_err(|err| EndOfCentralDirectoryError::IOError(err.to_string()))?;
         }
 
-        let central_dir_size = eof_central_dir_bytes[10];
+        let zip64_locator_found = zip64_locator_offset.is_some()
+            && LittleEndian::read_u32(&zip64_locator_bytes[..4])
+                == ZIP64_EOF_CENTRAL_DIR_LOCATOR_SIGN;
+
+        let mut zip64_record_offset = None;
+
+        // The fields of the ZIP64 end of central directory record are authoritative since some
+        // tools always write it and leave the fields of the end of central directory record set
+        // to 0xFFFF or 0xFFFFFFFF
+        let (central_dir_size, central_dir_size_in_bytes, central_dir_start_offset) =
+            match zip64_locator_offset.filter(|_| zip64_locator_found) {
+                Some(zip64_locator_offset) => {
+                    let recorded_offset = LittleEndian::read_u64(&zip64_locator_bytes[8..16]);
+                    // Prepended data shifts the recorded offset towards the start of the file,
+                    // so the record is then looked for right before its locator where archivers
+                    // write it
+                    let adjacent_offset =
+                        zip64_locator_offset.saturating_sub(MIN_ZIP64_EOF_CENTRAL_DIR_SIZE as u64);
+
+                    let (record_offset, fields) =
+                        match read_zip64_end_of_central_dir(readable, recorded_offset) {
+                            Ok(fields) => (recorded_offset, fields),
+                            Err(err) if recorded_offset >= adjacent_offset => return Err(err),
+                            Err(err) => (
+                                adjacent_offset,
+                                read_zip64_end_of_central_dir(readable, adjacent_offset)
+                                    .map_err(|_| err)?,
+                            ),
+                        };
+
+                    zip64_record_offset = Some(record_offset);
+                    fields
+                }
+                None => (
+                    LittleEndian::read_u16(&eof_central_dir_bytes[10..12]) as u64,
+                    LittleEndian::read_u32(&eof_central_dir_bytes[12..16]) as u64,
+                    LittleEndian::read_u32(&eof_central_dir_bytes[16..20]) as u64,
+                ),
+            };
 
         if central_dir_size == 0 {
             return Err(EndOfCentralDirectoryError::EmptyZipFile);
         }
 
-        let central_dir_start_offset = LittleEndian::read_u32(&eof_central_dir_bytes[16..20]);
-
         Ok(Self {
+            offset,
+            disk_number,
+            central_dir_start_disk,
             central_dir_size,
+            central_dir_size_in_bytes,
             central_dir_start_offset,
+            comment_len,
+            comment,
+            zip64_locator_found,
+            zip64_record_offset,
+            trailing_data_len,
         })
     }
 
-    pub fn central_dir_start_offset(&self) -> u32 {
+    pub fn central_dir_start_offset(&self) -> u64 {
         self.central_dir_start_offset
     }
 
-    pub fn central_dir_size(&self) -> u8 {
+    pub fn central_dir_size(&self) -> u64 {
         self.central_dir_size
     }
+
+    /// Number of the disk this record is on. It is 0 unless the archive is split.
+    pub fn disk_number(&self) -> u16 {
+        self.disk_number
+    }
+
+    /// Number of disks of the archive. The record is on the last disk.
+    pub fn disk_count(&self) -> u32 {
+        self.disk_number as u32 + 1
+    }
+
+    /// Number of the disk the central directory starts on.
+    pub fn central_dir_start_disk(&self) -> u16 {
+        self.central_dir_start_disk
+    }
+
+    /// Length of the whole central directory in bytes.
+    pub fn central_dir_size_in_bytes(&self) -> u64 {
+        self.central_dir_size_in_bytes
+    }
+
+    pub fn comment_len(&self) -> u16 {
+        self.comment_len
+    }
+
+    /// Position of the end of central directory record from the start of the file.
+    pub fn offset(&self) -> u64 {
+        self.offset
+    }
+
+    pub fn comment(&self) -> &[u8] {
+        &self.comment
+    }
+
+    pub fn zip64_locator_found(&self) -> bool {
+        self.zip64_locator_found
+    }
+
+    /// Offset the ZIP64 end of central directory record was read from.
+    pub fn zip64_record_offset(&self) -> Option<u64> {
+        self.zip64_record_offset
+    }
+
+    /// Length of the data following the comment like padding or an appended signature.
+    pub fn trailing_data_len(&self) -> u64 {
+        self.trailing_data_len
+    }
+}
+
+// Returns whether a central directory record or a local file header parses at the start of the
+// bytes, rather than only their signature appearing there.
+fn record_parses_at(bytes: &[u8]) -> bool {
+    let mut cursor = Cursor::new(bytes);
+
+    match bytes.get(0..4).map(LittleEndian::read_u32) {
+        Some(CENTRAL_DIR_SIGN) => ZipFile::from_readable(&mut cursor).is_ok(),
+        Some(LOCAL_FILE_HEADER_SIGN) => {
+            ZipFile::from_local_header_with_options(&mut cursor, &ParseOptions::default()).is_ok()
+        }
+        _ => false,
+    }
+}
+
+// Returns the entry count, size and offset of the central directory from the ZIP64 end of central
+// directory record at the given offset
+fn read_zip64_end_of_central_dir<T>(
+    readable: &mut T,
+    offset: u64,
+) -> Result<(u64, u64, u64), EndOfCentralDirectoryError>
+where
+    T: Read + Seek,
+{
+    let mut zip64_eof_central_dir_bytes = [0u8; MIN_ZIP64_EOF_CENTRAL_DIR_SIZE];
+
+    readable
+        .seek(SeekFrom::Start(offset))
+        .and_then(|_| readable.read_exact(&mut zip64_eof_central_dir_bytes))
+        .map_err(|err| EndOfCentralDirectoryError::IOError(err.to_string()))?;
+
+    let sign = LittleEndian::read_u32(&zip64_eof_central_dir_bytes[..4]);
+
+    if sign != ZIP64_EOF_CENTRAL_DIR_SIGN {
+        return Err(EndOfCentralDirectoryError::InvalidSignature(sign));
+    }
+
+    Ok((
+        LittleEndian::read_u64(&zip64_eof_central_dir_bytes[32..40]),
+        LittleEndian::read_u64(&zip64_eof_central_dir_bytes[40..48]),
+        LittleEndian::read_u64(&zip64_eof_central_dir_bytes[48..56]),
+    ))
 }
 
 impl ZipFile {
@@ -264,6 +690,20 @@ impl ZipFile {
     where
         T: Read + Seek,
     {
+        Self::from_readable_with_options(readable, &ParseOptions::default())
+    }
+
+    pub fn from_readable_with_options<T>(
+        readable: &mut T,
+        parse_options: &ParseOptions,
+    ) -> Result<Self, ZipFileError>
+    where
+        T: Read + Seek,
+    {
+        let central_header_start = readable
+            .stream_position()
+            .map_err(|err| ZipFileError::IOError(err.to_string()))?;
+
         let mut central_dir_bytes = vec![0; MIN_CENTRAL_DIR_SIZE as usize];
 
         readable
@@ -279,63 +719,32 @@ impl ZipFile {
         let zip_version = central_dir_bytes[0x04];
 
         // We currently only support ZIP 2.0 and 3.0
-        let supported_zip_versions = vec![0x14, 0x1E, 0x3F];
+        let supported_zip_versions = [0x14, 0x1E, 0x3F];
 
         if !supported_zip_versions.contains(&zip_version) {
             return Err(ZipFileError::UnsupportedZipVersion(zip_version));
         }
 
         let environment = FileEnvironment::from_byte(central_dir_bytes[0x05])
-            .map_err(|err| ZipFileError::FileEnvironmentError(err))?;
+            .map_err(ZipFileError::FileEnvironmentError)?;
 
         let compression_method_bytes = LittleEndian::read_u16(&central_dir_bytes[10..12]);
         let general_purpose_bit_flag = LittleEndian::read_u16(&central_dir_bytes[8..10]);
 
-        let is_encrypted = (general_purpose_bit_flag & 0x0001) == 1;
-
-        let encryption_method = if is_encrypted {
-            if ((general_purpose_bit_flag >> 6) & 0x0001) > 0 {
-                EncryptionMethod::Aes
-            } else {
-                EncryptionMethod::ZipCrypto
-            }
-        } else {
-            EncryptionMethod::NoEncryption
-        };
-
-        let compression_method = match compression_method_bytes {
-            0x00 => CompressionMethod::NoCompression,
-            0x08 => {
-                // DEFLATE compression
-                let deflate_mode = (general_purpose_bit_flag >> 1) & 0x0003;
-
-                match deflate_mode {
-                    0b00 => CompressionMethod::Deflate(DeflateCompressionMode::Normal),
-                    0b01 => CompressionMethod::Deflate(DeflateCompressionMode::Maximum),
-                    0b10 => CompressionMethod::Deflate(DeflateCompressionMode::Fast),
-                    0b11 => CompressionMethod::Deflate(DeflateCompressionMode::SuperFast),
-                    _ => CompressionMethod::Deflate(DeflateCompressionMode::Normal),
-                }
-            }
-            _ => {
-                return Err(ZipFileError::UnsupportedCompression(
-                    compression_method_bytes,
-                ))
-            }
-        };
-
         let data_descriptor_used = ((general_purpose_bit_flag >> 3) & 0x0001) == 1;
         let date = LittleEndian::read_u16(&central_dir_bytes[14..16]);
         let time = LittleEndian::read_u16(&central_dir_bytes[12..14]);
 
         let zip_date_time = ZipDateTime::from_bytes(date, time);
         let crc32 = LittleEndian::read_u32(&central_dir_bytes[16..20]);
-        let compressed_size = LittleEndian::read_u32(&central_dir_bytes[20..24]);
-        let uncompressed_size = LittleEndian::read_u32(&central_dir_bytes[24..28]);
+        let mut compressed_size = LittleEndian::read_u32(&central_dir_bytes[20..24]) as u64;
+        let mut uncompressed_size = LittleEndian::read_u32(&central_dir_bytes[24..28]) as u64;
         let file_name_len = LittleEndian::read_u16(&central_dir_bytes[28..30]) as usize;
         let extra_field_len = LittleEndian::read_u16(&central_dir_bytes[30..32]) as u64;
         let comment_len = LittleEndian::read_u16(&central_dir_bytes[32..34]) as u64;
-        let offset = LittleEndian::read_u32(&central_dir_bytes[42..46]);
+        let disk_number = LittleEndian::read_u16(&central_dir_bytes[34..36]);
+        let external_attributes = LittleEndian::read_u32(&central_dir_bytes[38..42]);
+        let mut offset = LittleEndian::read_u32(&central_dir_bytes[42..46]) as u64;
 
         let mut file_name_bytes = vec![0; file_name_len];
 
@@ -343,16 +752,46 @@ impl ZipFile {
             .read_exact(&mut file_name_bytes)
             .map_err(|err| ZipFileError::IOError(err.to_string()))?;
 
-        let file_name = String::from_utf8(file_name_bytes)
+        let current_file_pos = readable
+            .stream_position()
             .map_err(|err| ZipFileError::IOError(err.to_string()))?;
 
-        let is_dir = file_name.ends_with("/");
+        let mut extra_field = Vec::with_capacity(extra_field_len as usize);
 
-        let current_file_pos = readable
-            .seek(SeekFrom::Current(0))
+        readable
+            .take(extra_field_len)
+            .read_to_end(&mut extra_field)
             .map_err(|err| ZipFileError::IOError(err.to_string()))?;
 
+        let (file_name, file_name_lossy) = decode_file_name(
+            &file_name_bytes,
+            general_purpose_bit_flag,
+            &extra_field,
+            parse_options,
+        )?;
+
+        read_zip64_extra_field(
+            &extra_field,
+            &mut [&mut uncompressed_size, &mut compressed_size, &mut offset],
+        );
+
+        let offset = parse_options
+            .disk_start_offset(disk_number)
+            .map(|disk_start_offset| disk_start_offset + offset)
+            .ok_or_else(|| ZipFileError::MissingDisk(file_name.clone(), disk_number))?;
+
+        let entry_kind = EntryKind::from_attributes(&file_name, &environment, external_attributes);
+        let is_dir = entry_kind == EntryKind::Directory;
+
+        let (encryption_method, compression_method, aes_extra_field) = entry_methods(
+            general_purpose_bit_flag,
+            compression_method_bytes,
+            &extra_field,
+            &file_name,
+        )?;
+
         let new_zip_file_pos = current_file_pos + extra_field_len + comment_len;
+        let central_header_len = new_zip_file_pos - central_header_start;
 
         readable
             .seek(SeekFrom::Start(new_zip_file_pos))
@@ -368,59 +807,272 @@ impl ZipFile {
             crc32: Cell::new(crc32),
             compressed_size: Cell::new(compressed_size),
             uncompressed_size: Cell::new(uncompressed_size),
+            central_header_range: (central_header_start, central_header_len),
             file_name,
+            raw_file_name: file_name_bytes,
+            file_name_lossy,
+            extra_field,
+            aes_extra_field,
+            external_attributes,
+            entry_kind,
             is_dir,
         })
     }
 
-    pub fn update_with_data_descriptor<F>(&self, readable: &mut F, descriptor_end_index: u32)
+    /// Parses the local file header at the current position of the readable. Local file headers
+    /// lack the fields only kept in the central directory, so the entry is treated as created on
+    /// MS-DOS without any attributes. The sizes and the CRC-32 of entries using a data
+    /// descriptor are left as 0 when the local file header doesn't record them.
+    pub fn from_local_header_with_options<T>(
+        readable: &mut T,
+        parse_options: &ParseOptions,
+    ) -> Result<Self, ZipFileError>
     where
-        F: Read + Seek,
+        T: Read + Seek,
     {
-        let mut data_descriptor_bytes = vec![0u8; DATA_DESCRIPTOR_SIZE];
-        let read_result = readable
-            .seek(SeekFrom::Start(
-                (descriptor_end_index - (DATA_DESCRIPTOR_SIZE as u32)) as u64,
-            ))
-            .and_then(|_| readable.read_exact(&mut data_descriptor_bytes));
-
-        if let Err(err) = read_result {
-            eprintln!(
-                "An error occurred while reading data descriptor of the file {}\n{}",
-                self.file_name, err
-            );
-            std::process::exit(DATA_DESCRIPTOR_READ_FAILURE_EXIT_CODE);
+        let offset = readable
+            .stream_position()
+            .map_err(|err| ZipFileError::IOError(err.to_string()))?;
+
+        let mut local_file_header_bytes = [0u8; MIN_LOCAL_FILE_HEADER_SIZE];
+
+        readable
+            .read_exact(&mut local_file_header_bytes)
+            .map_err(|err| ZipFileError::IOError(err.to_string()))?;
+
+        let sign = LittleEndian::read_u32(&local_file_header_bytes[0..4]);
+
+        if sign != LOCAL_FILE_HEADER_SIGN {
+            return Err(ZipFileError::InvalidSignature(sign));
         }
 
-        self.crc32
-            .set(LittleEndian::read_u32(&data_descriptor_bytes[..4]));
-        self.compressed_size
-            .set(LittleEndian::read_u32(&data_descriptor_bytes[4..8]));
-        self.uncompressed_size
-            .set(LittleEndian::read_u32(&data_descriptor_bytes[8..]));
+        let general_purpose_bit_flag = LittleEndian::read_u16(&local_file_header_bytes[6..8]);
+        let compression_method_bytes = LittleEndian::read_u16(&local_file_header_bytes[8..10]);
+        let time = LittleEndian::read_u16(&local_file_header_bytes[10..12]);
+        let date = LittleEndian::read_u16(&local_file_header_bytes[12..14]);
+        let crc32 = LittleEndian::read_u32(&local_file_header_bytes[14..18]);
+        let mut compressed_size = LittleEndian::read_u32(&local_file_header_bytes[18..22]) as u64;
+        let mut uncompressed_size = LittleEndian::read_u32(&local_file_header_bytes[22..26]) as u64;
+        let file_name_len = LittleEndian::read_u16(&local_file_header_bytes[26..28]) as usize;
+        let extra_field_len = LittleEndian::read_u16(&local_file_header_bytes[28..30]) as usize;
+
+        let mut file_name_bytes = vec![0; file_name_len];
+        let mut extra_field = vec![0; extra_field_len];
+
+        readable
+            .read_exact(&mut file_name_bytes)
+            .and_then(|_| readable.read_exact(&mut extra_field))
+            .map_err(|err| ZipFileError::IOError(err.to_string()))?;
+
+        let (file_name, file_name_lossy) = decode_file_name(
+            &file_name_bytes,
+            general_purpose_bit_flag,
+            &extra_field,
+            parse_options,
+        )?;
+
+        read_zip64_extra_field(
+            &extra_field,
+            &mut [&mut uncompressed_size, &mut compressed_size],
+        );
+
+        let (encryption_method, compression_method, aes_extra_field) = entry_methods(
+            general_purpose_bit_flag,
+            compression_method_bytes,
+            &extra_field,
+            &file_name,
+        )?;
+
+        let environment = FileEnvironment::MsDos;
+        let entry_kind = EntryKind::from_attributes(&file_name, &environment, 0);
+
+        Ok(Self {
+            offset,
+            environment,
+            encryption_method,
+            compression_method,
+            data_descriptor_used: ((general_purpose_bit_flag >> 3) & 0x0001) == 1,
+            date_time: ZipDateTime::from_bytes(date, time),
+            crc32: Cell::new(crc32),
+            compressed_size: Cell::new(compressed_size),
+            uncompressed_size: Cell::new(uncompressed_size),
+            // There is no central directory record to point at
+            central_header_range: (offset, 0),
+            file_name,
+            raw_file_name: file_name_bytes,
+            file_name_lossy,
+            extra_field,
+            aes_extra_field,
+            external_attributes: 0,
+            is_dir: entry_kind == EntryKind::Directory,
+            entry_kind,
+        })
+    }
+
+    /// Updates the CRC-32 and the sizes from the data descriptor at the given offset, which follows
+    /// compressed data of the given size, and returns the offset the descriptor ends at. The
+    /// descriptor may start with a signature and stores the sizes in 4 bytes, or in 8 bytes in
+    /// ZIP64 archives, so the layout whose compressed size matches the data is picked. The ZIP64
+    /// extra field of the entry decides between layouts that both match.
+    pub fn update_with_data_descriptor<F>(
+        &self,
+        readable: &mut F,
+        descriptor_offset: u64,
+        compressed_size: u64,
+    ) -> std::io::Result<u64>
+    where
+        F: Read + Seek,
+    {
+        let mut data_descriptor_bytes = Vec::with_capacity(ZIP64_DATA_DESCRIPTOR_SIZE + 4);
+
+        readable.seek(SeekFrom::Start(descriptor_offset))?;
+        readable
+            .take(ZIP64_DATA_DESCRIPTOR_SIZE as u64 + 4)
+            .read_to_end(&mut data_descriptor_bytes)?;
+
+        let descriptor_start = match data_descriptor_bytes.get(..4) {
+            Some(sign) if LittleEndian::read_u32(sign) == DATA_DESCRIPTOR_SIGN => 4,
+            _ => 0,
+        };
+        let descriptors: Vec<&[u8]> = [DATA_DESCRIPTOR_SIZE, ZIP64_DATA_DESCRIPTOR_SIZE]
+            .into_iter()
+            .filter_map(|size| data_descriptor_bytes.get(descriptor_start..descriptor_start + size))
+            .collect();
+        let matching: Vec<&[u8]> = descriptors
+            .iter()
+            .copied()
+            .filter(|descriptor| read_data_descriptor(descriptor).1 == compressed_size)
+            .collect();
+        let candidates = if matching.is_empty() {
+            descriptors
+        } else {
+            matching
+        };
+
+        let descriptor = candidates
+            .iter()
+            .find(|descriptor| {
+                (descriptor.len() == ZIP64_DATA_DESCRIPTOR_SIZE) == self.uses_zip64_extra_field()
+            })
+            .or(candidates.first())
+            .ok_or(std::io::ErrorKind::UnexpectedEof)?;
+        let (crc32, compressed_size, uncompressed_size) = read_data_descriptor(descriptor);
+
+        self.crc32.set(crc32);
+        self.compressed_size.set(compressed_size);
+        self.uncompressed_size.set(uncompressed_size);
+
+        Ok(descriptor_offset + (descriptor_start + descriptor.len()) as u64)
     }
 
     pub fn file_name(&self) -> &String {
         &self.file_name
     }
 
+    /// Returns the name as it is stored in the header, before decoding it.
+    pub fn raw_file_name(&self) -> &[u8] {
+        &self.raw_file_name
+    }
+
+    /// Returns the name used for the extracted file. On Unix, names whose bytes could not be
+    /// decoded are taken as they are stored instead of with the replacement characters.
+    pub fn os_file_name(&self) -> OsString {
+        #[cfg(unix)]
+        if self.file_name_lossy {
+            use std::os::unix::ffi::OsStringExt;
+
+            return OsString::from_vec(self.raw_file_name.clone());
+        }
+
+        OsString::from(&self.file_name)
+    }
+
     pub fn date_time(&self) -> &ZipDateTime {
         &self.date_time
     }
 
+    /// Returns the modification time of the entry, taken from the NTFS extra field when present
+    /// as it is more precise and in UTC, unlike the MS-DOS date time which is in local time.
+    pub fn modified_time(&self) -> SystemTime {
+        ntfs_modified_time(self.extra_fields()).unwrap_or_else(|| self.date_time.to_system_time())
+    }
+
     pub fn compression_method(&self) -> &CompressionMethod {
         &self.compression_method
     }
 
+    /// Returns whether the entry is metadata added by macOS, like the `__MACOSX/` directory, the
+    /// AppleDouble `._*` files or `.DS_Store`.
+    pub fn is_macos_metadata(&self) -> bool {
+        let mut components = self
+            .file_name
+            .split('/')
+            .filter(|component| !component.is_empty());
+
+        components.clone().next() == Some("__MACOSX")
+            || components.any(|component| component == ".DS_Store" || component.starts_with("._"))
+    }
+
     pub fn is_dir(&self) -> bool {
         self.is_dir
     }
 
-    pub fn uncompressed_size(&self) -> &Cell<u32> {
+    pub fn entry_kind(&self) -> EntryKind {
+        self.entry_kind
+    }
+
+    pub fn external_attributes(&self) -> u32 {
+        self.external_attributes
+    }
+
+    /// Returns the Unix permission bits of the entry including the setuid, setgid and sticky bits,
+    /// if it was archived on a Unix host that stored them.
+    pub fn unix_permissions(&self) -> Option<u32> {
+        let unix_mode = self.external_attributes >> 16;
+
+        (matches!(
+            self.environment,
+            FileEnvironment::Unix | FileEnvironment::OSX
+        ) && unix_mode != 0)
+            .then_some(unix_mode & UNIX_PERMISSIONS_MASK)
+    }
+
+    /// Returns the owner stored in the Info-ZIP Unix extra fields of the header the entry was read
+    /// from. Central directory records only hold it in the newer field.
+    pub fn unix_extra_field(&self) -> Option<UnixExtraField> {
+        UnixExtraField::find(self.extra_fields())
+    }
+
+    pub fn uid(&self) -> Option<u32> {
+        self.unix_extra_field()
+            .map(|unix_extra_field| unix_extra_field.uid)
+    }
+
+    pub fn gid(&self) -> Option<u32> {
+        self.unix_extra_field()
+            .map(|unix_extra_field| unix_extra_field.gid)
+    }
+
+    /// Returns the start offset and the length in bytes of the central directory record of the
+    /// entry.
+    pub fn central_header_range(&self) -> (u64, u64) {
+        self.central_header_range
+    }
+
+    pub fn extra_fields(&self) -> ExtraFields<'_> {
+        ExtraFields::new(&self.extra_field)
+    }
+
+    pub fn uses_zip64_extra_field(&self) -> bool {
+        self.extra_fields()
+            .any(|extra_field| extra_field.header_id == ZIP64_EXTRA_FIELD_ID)
+    }
+
+    pub fn uncompressed_size(&self) -> &Cell<u64> {
         &self.uncompressed_size
     }
 
-    pub fn compressed_size(&self) -> &Cell<u32> {
+    pub fn compressed_size(&self) -> &Cell<u64> {
         &self.compressed_size
     }
 
@@ -436,18 +1088,230 @@ impl ZipFile {
         self.data_descriptor_used
     }
 
-    pub fn offset(&self) -> u32 {
+    pub fn offset(&self) -> u64 {
         self.offset
     }
 
     pub fn encryption_method(&self) -> &EncryptionMethod {
         &self.encryption_method
     }
+
+    /// Returns the byte the last byte of the Zip Crypto encryption header is checked against.
+    /// It is the high order byte of the CRC-32, or of the DOS time when the entry uses a data
+    /// descriptor.
+    pub fn zip_crypto_check_byte(&self) -> u8 {
+        if self.data_descriptor_used {
+            let (_, time) = self.date_time.to_bytes();
+
+            (time >> 8) as u8
+        } else {
+            (self.crc32.get() >> 24) as u8
+        }
+    }
+
+    /// Returns the algorithm of an entry encrypted with PKWARE Strong Encryption if its strong
+    /// encryption header is found in the extra field.
+    pub fn strong_encryption_algorithm(&self) -> Option<StrongEncryptionAlgorithm> {
+        self.extra_fields()
+            .find(|extra_field| extra_field.header_id == STRONG_ENCRYPTION_EXTRA_FIELD_ID)
+            .and_then(|extra_field| StrongEncryptionAlgorithm::from_bytes(extra_field.data))
+    }
+
+    pub fn aes_extra_field(&self) -> Option<&AesExtraField> {
+        self.aes_extra_field.as_ref()
+    }
+
+    /// AE-2 encrypted entries don't store their CRC-32 as the authentication code already covers
+    /// the data.
+    pub fn stores_crc32(&self) -> bool {
+        !matches!(
+            self.aes_extra_field,
+            Some(AesExtraField {
+                vendor_version: AesVendorVersion::Ae2,
+                ..
+            })
+        )
+    }
+}
+
+// Replaces the fields set to the ZIP64 marker with the 8-byte values of the ZIP64 extra field. The
+// extra field only holds the fields that do not fit, in the order they are given: the uncompressed
+// size, the compressed size and the offset of the local file header.
+fn read_zip64_extra_field(extra_field: &[u8], fields: &mut [&mut u64]) {
+    let zip64_data = match ExtraFields::new(extra_field)
+        .find(|extra_field| extra_field.header_id == ZIP64_EXTRA_FIELD_ID)
+    {
+        Some(zip64_extra_field) => zip64_extra_field.data,
+        None => return,
+    };
+    let mut values = zip64_data.chunks_exact(8).map(LittleEndian::read_u64);
+
+    for field in fields.iter_mut() {
+        if **field == ZIP64_SIZE_MARKER as u64 {
+            match values.next() {
+                Some(value) => **field = value,
+                None => return,
+            }
+        }
+    }
+}
+
+// Determines the encryption and the compression method of an entry from the fields shared by its
+// central directory record and its local file header
+fn entry_methods(
+    general_purpose_bit_flag: u16,
+    compression_method_bytes: u16,
+    extra_field: &[u8],
+    file_name: &str,
+) -> Result<(EncryptionMethod, CompressionMethod, Option<AesExtraField>), ZipFileError> {
+    let is_encrypted = (general_purpose_bit_flag & 0x0001) == 1;
+
+    // Bit 6 marks the proprietary strong encryption scheme which can also encrypt the central
+    // directory itself. Some archivers only mark strong encryption through its extra field.
+    let encryption_method = if is_encrypted {
+        if ((general_purpose_bit_flag >> 6) & 0x0001) > 0
+            || ExtraFields::new(extra_field)
+                .any(|extra_field| extra_field.header_id == STRONG_ENCRYPTION_EXTRA_FIELD_ID)
+        {
+            EncryptionMethod::StrongEncryption
+        } else {
+            EncryptionMethod::ZipCrypto
+        }
+    } else {
+        EncryptionMethod::NoEncryption
+    };
+
+    // The real compression method of AES encrypted entries is kept in their extra field
+    if compression_method_bytes != AES_COMPRESSION_METHOD {
+        let compression_method =
+            compression_method_from_bytes(compression_method_bytes, general_purpose_bit_flag)?;
+
+        return Ok((encryption_method, compression_method, None));
+    }
+
+    let aes_extra_field = ExtraFields::new(extra_field)
+        .find(|extra_field| extra_field.header_id == AES_EXTRA_FIELD_ID)
+        .and_then(|extra_field| AesExtraField::from_bytes(extra_field.data))
+        .ok_or_else(|| ZipFileError::InvalidAesExtraField(file_name.to_string()))?;
+    let compression_method = compression_method_from_bytes(
+        aes_extra_field.compression_method,
+        general_purpose_bit_flag,
+    )?;
+
+    Ok((
+        EncryptionMethod::Aes,
+        compression_method,
+        Some(aes_extra_field),
+    ))
+}
+
+fn compression_method_from_bytes(
+    compression_method_bytes: u16,
+    general_purpose_bit_flag: u16,
+) -> Result<CompressionMethod, ZipFileError> {
+    let compression_method = match compression_method_bytes {
+        0x00 => CompressionMethod::NoCompression,
+        0x08 => {
+            // DEFLATE compression
+            let deflate_mode = (general_purpose_bit_flag >> 1) & 0x0003;
+
+            match deflate_mode {
+                0b00 => CompressionMethod::Deflate(DeflateCompressionMode::Normal),
+                0b01 => CompressionMethod::Deflate(DeflateCompressionMode::Maximum),
+                0b10 => CompressionMethod::Deflate(DeflateCompressionMode::Fast),
+                0b11 => CompressionMethod::Deflate(DeflateCompressionMode::SuperFast),
+                _ => CompressionMethod::Deflate(DeflateCompressionMode::Normal),
+            }
+        }
+        #[cfg(feature = "bzip2")]
+        0x0C => CompressionMethod::Bzip2,
+        #[cfg(feature = "lzma")]
+        0x0E => CompressionMethod::Lzma {
+            end_marker: ((general_purpose_bit_flag >> 1) & 0x0001) == 1,
+        },
+        #[cfg(feature = "zstd")]
+        0x5D => CompressionMethod::Zstd,
+        #[cfg(feature = "xz")]
+        0x5F => CompressionMethod::Xz,
+        _ => {
+            return Err(ZipFileError::UnsupportedCompression(
+                compression_method_bytes,
+            ))
+        }
+    };
+
+    Ok(compression_method)
+}
+
+// The UTF-8 name of the Unicode Path extra field takes precedence over the name in the header,
+// which older tools wrote in the code page of the system. Names without the UTF-8 flag are still
+// taken as UTF-8 when they are valid since tools like macOS Archive Utility don't set the flag.
+// Whether invalid bytes were replaced is returned along with the name.
+fn decode_file_name(
+    file_name_bytes: &[u8],
+    general_purpose_bit_flag: u16,
+    extra_field: &[u8],
+    parse_options: &ParseOptions,
+) -> Result<(String, bool), ZipFileError> {
+    if let Some(file_name) = unicode_path(ExtraFields::new(extra_field), file_name_bytes) {
+        return Ok((file_name, false));
+    }
+
+    if general_purpose_bit_flag & UTF8_FILE_NAME_FLAG == 0 {
+        return match (
+            parse_options.file_name_encoding,
+            parse_options.file_name_policy,
+        ) {
+            (Some(encoding), policy) => match encoding.decode(file_name_bytes) {
+                Some(file_name) => Ok((file_name, false)),
+                None if policy == FileNamePolicy::Lossy => {
+                    Ok((encoding.decode_lossy(file_name_bytes), true))
+                }
+                None => Err(ZipFileError::IOError(format!(
+                    "file name {:02X?} is not valid in the given encoding",
+                    file_name_bytes
+                ))),
+            },
+            (None, _) => Ok((
+                String::from_utf8(file_name_bytes.to_vec())
+                    .unwrap_or_else(|_| FileNameEncoding::Cp437.decode_lossy(file_name_bytes)),
+                false,
+            )),
+        };
+    }
+
+    match String::from_utf8(file_name_bytes.to_vec()) {
+        Ok(file_name) => Ok((file_name, false)),
+        Err(_) if parse_options.file_name_policy == FileNamePolicy::Lossy => {
+            Ok((String::from_utf8_lossy(file_name_bytes).into_owned(), true))
+        }
+        Err(err) => Err(ZipFileError::IOError(err.to_string())),
+    }
+}
+
+// Reads the CRC-32 and the sizes of a data descriptor without its signature
+fn read_data_descriptor(descriptor: &[u8]) -> (u32, u64, u64) {
+    let crc32 = LittleEndian::read_u32(&descriptor[..4]);
+
+    if descriptor.len() == ZIP64_DATA_DESCRIPTOR_SIZE {
+        (
+            crc32,
+            LittleEndian::read_u64(&descriptor[4..12]),
+            LittleEndian::read_u64(&descriptor[12..]),
+        )
+    } else {
+        (
+            crc32,
+            LittleEndian::read_u32(&descriptor[4..8]) as u64,
+            LittleEndian::read_u32(&descriptor[8..]) as u64,
+        )
+    }
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::aes::AesStrength;
     use std::{io::Cursor, path::PathBuf};
 
     #[test]
@@ -508,6 +1372,106 @@ mod tests {
         assert_eq!(eof_central_dir.central_dir_start_offset, 0x00000120);
     }
 
+    #[test]
+    fn test_eof_central_dir_with_comment() {
+        let mut cursor = Cursor::new(vec![
+            0x50, 0x4B, 0x05, 0x06, 0x00, 0x00, 0x00, 0x00, 0x01, 0x00, 0x01, 0x00, 0x5A, 0x00,
+            0x00, 0x00, 0x20, 0x01, 0x00, 0x00, 0x05, 0x00, 0x68, 0x65, 0x6C, 0x6C, 0x6F,
+        ]);
+        let eof_central_dir = EndOfCentralDirectory::from_readable(&mut cursor).unwrap();
+
+        assert_eq!(eof_central_dir.offset(), 0);
+        assert_eq!(eof_central_dir.comment(), b"hello");
+        assert_eq!(eof_central_dir.central_dir_start_offset, 0x00000120);
+    }
+
+    #[test]
+    fn test_eof_central_dir_getters() {
+        let mut cursor = Cursor::new(vec![
+            0x50, 0x4B, 0x05, 0x06, 0x02, 0x00, 0x01, 0x00, 0x03, 0x00, 0x07, 0x00, 0x5A, 0x01,
+            0x00, 0x00, 0x20, 0x01, 0x00, 0x00, 0x02, 0x00, 0x68, 0x69,
+        ]);
+        let eof_central_dir = EndOfCentralDirectory::from_readable(&mut cursor).unwrap();
+
+        assert_eq!(eof_central_dir.offset(), 0);
+        assert_eq!(eof_central_dir.disk_number(), 2);
+        assert_eq!(eof_central_dir.disk_count(), 3);
+        assert_eq!(eof_central_dir.central_dir_start_disk(), 1);
+        assert_eq!(eof_central_dir.central_dir_size(), 7);
+        assert_eq!(eof_central_dir.central_dir_size_in_bytes(), 0x015A);
+        assert_eq!(eof_central_dir.central_dir_start_offset(), 0x0120);
+        assert_eq!(eof_central_dir.comment_len(), 2);
+        assert_eq!(eof_central_dir.comment(), b"hi");
+        assert!(!eof_central_dir.zip64_locator_found());
+    }
+
+    #[test]
+    fn test_eof_central_dir_with_trailing_data() {
+        let mut archive = vec![0u8; 0x5A];
+        archive.extend_from_slice(&[
+            0x50, 0x4B, 0x05, 0x06, 0x00, 0x00, 0x00, 0x00, 0x01, 0x00, 0x01, 0x00, 0x5A, 0x00,
+            0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x02, 0x00, 0x68, 0x69,
+        ]);
+        archive.extend_from_slice(b"padding");
+
+        let eof_central_dir =
+            EndOfCentralDirectory::from_readable(&mut Cursor::new(&archive)).unwrap();
+
+        assert_eq!(eof_central_dir.offset(), 0x5A);
+        assert_eq!(eof_central_dir.comment(), b"hi");
+        assert_eq!(eof_central_dir.trailing_data_len(), 7);
+
+        // A signature in the trailing data whose central directory would overlap it is skipped
+        archive.extend_from_slice(&[
+            0x50, 0x4B, 0x05, 0x06, 0x00, 0x00, 0x00, 0x00, 0x01, 0x00, 0x01, 0x00, 0x5A, 0x00,
+            0x00, 0x00, 0x00, 0x10, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00,
+        ]);
+
+        let eof_central_dir =
+            EndOfCentralDirectory::from_readable(&mut Cursor::new(&archive)).unwrap();
+
+        assert_eq!(eof_central_dir.offset(), 0x5A);
+        assert_eq!(eof_central_dir.trailing_data_len(), 31);
+
+        // Trailing data holding the signature of a central directory record without the record
+        archive.extend_from_slice(&[0x50, 0x4B, 0x01, 0x02]);
+
+        let eof_central_dir =
+            EndOfCentralDirectory::from_readable(&mut Cursor::new(&archive)).unwrap();
+
+        assert_eq!(eof_central_dir.offset(), 0x5A);
+        assert_eq!(eof_central_dir.trailing_data_len(), 35);
+
+        // The record of a stored archive followed by the central directory holding it
+        let mut central_dir_record = vec![0u8; MIN_CENTRAL_DIR_SIZE as usize];
+        LittleEndian::write_u32(&mut central_dir_record[0..4], CENTRAL_DIR_SIGN);
+        central_dir_record[4] = 0x14;
+        LittleEndian::write_u16(&mut central_dir_record[28..30], 5);
+        central_dir_record.extend_from_slice(b"a.txt");
+        archive.extend_from_slice(&central_dir_record);
+
+        assert!(matches!(
+            EndOfCentralDirectory::from_readable(&mut Cursor::new(&archive)),
+            Err(EndOfCentralDirectoryError::InvalidSignature(_))
+        ));
+    }
+
+    #[test]
+    fn test_eof_central_dir_invalid_disk_numbers() {
+        let mut cursor = Cursor::new(vec![
+            0x50, 0x4B, 0x05, 0x06, 0x01, 0x00, 0x02, 0x00, 0x03, 0x00, 0x07, 0x00, 0x5A, 0x01,
+            0x00, 0x00, 0x20, 0x01, 0x00, 0x00, 0x00, 0x00,
+        ]);
+
+        assert_eq!(
+            EndOfCentralDirectory::from_readable(&mut cursor).err(),
+            Some(EndOfCentralDirectoryError::InvalidDiskNumbers {
+                disk_number: 1,
+                central_dir_start_disk: 2
+            })
+        );
+    }
+
     #[test]
     fn test_zip_file_invalid_signature_error() {
         let mut cursor = Cursor::new(vec![
@@ -624,10 +1588,83 @@ mod tests {
         assert!(zip_file_result.is_ok());
         assert_eq!(
             zip_file_result.unwrap().encryption_method(),
-            &EncryptionMethod::Aes
+            &EncryptionMethod::StrongEncryption
         )
     }
 
+    #[test]
+    fn test_aes_extra_field() {
+        let mut central_dir_bytes = vec![
+            0x50, 0x4B, 0x01, 0x02, 0x3F, 0x03, 0x33, 0x00, 0x01, 0x00, 0x63, 0x00, 0x6F, 0xA7,
+            0x39, 0x57, 0x00, 0x00, 0x00, 0x00, 0xE2, 0x00, 0x00, 0x00, 0x30, 0x01, 0x00, 0x00,
+            0x0C, 0x00, 0x0B, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0xA4, 0x81,
+            0x00, 0x00, 0x00, 0x00, 0x63, 0x76, 0x5F, 0x64, 0x65, 0x62, 0x75, 0x67, 0x2E, 0x6C,
+            0x6F, 0x67,
+        ];
+        // AE-2 with AES-256 and DEFLATE as the real compression method
+        let aes_extra_field = [
+            0x01, 0x99, 0x07, 0x00, 0x02, 0x00, 0x41, 0x45, 0x03, 0x08, 0x00,
+        ];
+
+        central_dir_bytes.extend_from_slice(&aes_extra_field);
+
+        let zip_file = ZipFile::from_readable(&mut Cursor::new(central_dir_bytes.clone())).unwrap();
+
+        assert_eq!(zip_file.encryption_method(), &EncryptionMethod::Aes);
+        assert_eq!(
+            zip_file.compression_method(),
+            &CompressionMethod::Deflate(DeflateCompressionMode::Normal)
+        );
+        assert_eq!(
+            zip_file.aes_extra_field(),
+            Some(&AesExtraField {
+                vendor_version: AesVendorVersion::Ae2,
+                strength: AesStrength::Aes256,
+                compression_method: 0x08,
+            })
+        );
+        assert!(!zip_file.stores_crc32());
+
+        // Replace the vendor ID of the extra field
+        let vendor_id_index = central_dir_bytes.len() - 5;
+        central_dir_bytes[vendor_id_index] = b'X';
+
+        assert_eq!(
+            ZipFile::from_readable(&mut Cursor::new(central_dir_bytes)).err(),
+            Some(ZipFileError::InvalidAesExtraField(
+                "cv_debug.log".to_string()
+            ))
+        );
+    }
+
+    #[test]
+    fn test_strong_encryption_extra_field() {
+        let mut central_dir_bytes = vec![
+            0x50, 0x4B, 0x01, 0x02, 0x3F, 0x03, 0x33, 0x00, 0x41, 0x00, 0x08, 0x00, 0x6F, 0xA7,
+            0x39, 0x57, 0x7D, 0x99, 0xD7, 0xB2, 0xE2, 0x00, 0x00, 0x00, 0x30, 0x01, 0x00, 0x00,
+            0x0C, 0x00, 0x0C, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0xA4, 0x81,
+            0x00, 0x00, 0x00, 0x00, 0x63, 0x76, 0x5F, 0x64, 0x65, 0x62, 0x75, 0x67, 0x2E, 0x6C,
+            0x6F, 0x67,
+        ];
+        // Format 2 with AES-256
+        let strong_encryption_extra_field = [
+            0x17, 0x00, 0x08, 0x00, 0x02, 0x00, 0x10, 0x66, 0x00, 0x01, 0x00, 0x00,
+        ];
+
+        central_dir_bytes.extend_from_slice(&strong_encryption_extra_field);
+
+        let zip_file = ZipFile::from_readable(&mut Cursor::new(central_dir_bytes)).unwrap();
+
+        assert_eq!(
+            zip_file.encryption_method(),
+            &EncryptionMethod::StrongEncryption
+        );
+        assert_eq!(
+            zip_file.strong_encryption_algorithm().unwrap().to_string(),
+            "AES-256"
+        );
+    }
+
     #[test]
     fn test_data_descriptor_used() {
         let mut cursor = Cursor::new(vec![
@@ -709,13 +1746,171 @@ mod tests {
         let mut data_descriptor_cursor = Cursor::new(vec![
             0x50, 0x4B, 0x01, 0x02, 0x14, 0x03, 0x14, 0x00, 0x00, 0x00, 0x08, 0x00,
         ]);
-        zip_file.update_with_data_descriptor(&mut data_descriptor_cursor, 12);
+        let descriptor_end = zip_file
+            .update_with_data_descriptor(&mut data_descriptor_cursor, 0, 0x00140314)
+            .unwrap();
 
+        assert_eq!(descriptor_end, 12);
         assert_eq!(zip_file.compressed_size().get(), 0x00140314);
         assert_eq!(zip_file.crc32().get(), 0x02014B50);
         assert_eq!(zip_file.uncompressed_size().get(), 0x00080000);
     }
 
+    #[test]
+    fn test_signed_and_zip64_data_descriptor_update() {
+        let mut cursor = Cursor::new(vec![
+            0x50, 0x4B, 0x01, 0x02, 0x14, 0x03, 0x14, 0x00, 0x00, 0x00, 0x08, 0x00, 0x6F, 0xA7,
+            0x39, 0x57, 0x7D, 0x99, 0xD7, 0xB2, 0xC6, 0x00, 0x00, 0x00, 0x30, 0x01, 0x00, 0x00,
+            0x0C, 0x00, 0x20, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0xA4, 0x81,
+            0x00, 0x00, 0x00, 0x00, 0x63, 0x76, 0x5F, 0x64, 0x65, 0x62, 0x75, 0x67, 0x2E, 0x6C,
+            0x6F, 0x67,
+        ]);
+        let zip_file = ZipFile::from_readable(&mut cursor).unwrap();
+
+        // Signed descriptor with 4 byte sizes after some compressed data
+        let mut data_descriptor_cursor = Cursor::new(vec![
+            0xAA, 0xBB, 0xCC, 0xDD, 0xEE, 0xFF, 0x50, 0x4B, 0x07, 0x08, 0x01, 0x02, 0x03, 0x04,
+            0x06, 0x00, 0x00, 0x00, 0x20, 0x00, 0x00, 0x00, 0x50, 0x4B, 0x03, 0x04,
+        ]);
+        let descriptor_end = zip_file
+            .update_with_data_descriptor(&mut data_descriptor_cursor, 6, 6)
+            .unwrap();
+
+        assert_eq!(descriptor_end, 22);
+        assert_eq!(zip_file.crc32().get(), 0x04030201);
+        assert_eq!(zip_file.compressed_size().get(), 6);
+        assert_eq!(zip_file.uncompressed_size().get(), 0x20);
+
+        // Unsigned descriptor with 8 byte sizes, told apart by its compressed size
+        let mut data_descriptor_cursor = Cursor::new(vec![
+            0x7D, 0x99, 0xD7, 0xB2, 0x40, 0x00, 0x00, 0x00, 0x01, 0x00, 0x00, 0x00, 0x50, 0x00,
+            0x00, 0x00, 0x01, 0x00, 0x00, 0x00,
+        ]);
+        let descriptor_end = zip_file
+            .update_with_data_descriptor(&mut data_descriptor_cursor, 0, 0x1_0000_0040)
+            .unwrap();
+
+        assert_eq!(descriptor_end, 20);
+        assert_eq!(zip_file.crc32().get(), 0xB2D7997D);
+        assert_eq!(zip_file.compressed_size().get(), 0x1_0000_0040);
+        assert_eq!(zip_file.uncompressed_size().get(), 0x1_0000_0050);
+
+        // Cut off descriptor
+        let mut data_descriptor_cursor = Cursor::new(vec![0x50, 0x4B, 0x07, 0x08, 0x01, 0x02]);
+
+        assert!(zip_file
+            .update_with_data_descriptor(&mut data_descriptor_cursor, 0, 0)
+            .is_err());
+    }
+
+    #[test]
+    fn test_zip64_data_descriptor_update() {
+        // Local file header of a streamed entry with a ZIP64 extra field
+        let mut cursor = Cursor::new(vec![
+            0x50, 0x4B, 0x03, 0x04, 0x2D, 0x00, 0x08, 0x00, 0x08, 0x00, 0x6F, 0xA7, 0x39, 0x57,
+            0x00, 0x00, 0x00, 0x00, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0x01, 0x00,
+            0x14, 0x00, 0x61, 0x01, 0x00, 0x10, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00,
+            0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00,
+        ]);
+        let zip_file =
+            ZipFile::from_local_header_with_options(&mut cursor, &ParseOptions::default()).unwrap();
+
+        // The compressed size also matches the 4 byte layout, whose uncompressed size would be 0
+        let mut data_descriptor_cursor = Cursor::new(vec![
+            0x50, 0x4B, 0x07, 0x08, 0x05, 0x06, 0x07, 0x08, 0x30, 0x00, 0x00, 0x00, 0x00, 0x00,
+            0x00, 0x00, 0x90, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00,
+        ]);
+        let descriptor_end = zip_file
+            .update_with_data_descriptor(&mut data_descriptor_cursor, 0, 0x30)
+            .unwrap();
+
+        assert_eq!(descriptor_end, 24);
+        assert_eq!(zip_file.crc32().get(), 0x08070605);
+        assert_eq!(zip_file.compressed_size().get(), 0x30);
+        assert_eq!(zip_file.uncompressed_size().get(), 0x90);
+    }
+
+    #[test]
+    fn test_invalid_utf8_file_name() {
+        // The name is flagged as UTF-8
+        let mut central_dir_bytes = vec![
+            0x50, 0x4B, 0x01, 0x02, 0x14, 0x03, 0x14, 0x00, 0x00, 0x08, 0x00, 0x00, 0x6F, 0xA7,
+            0x39, 0x57, 0x7D, 0x99, 0xD7, 0xB2, 0x05, 0x00, 0x00, 0x00, 0x05, 0x00, 0x00, 0x00,
+            0x05, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0xA4, 0x81,
+            0x00, 0x00, 0x00, 0x00, 0x61, 0xFF, 0xFE, 0x2E, 0x62,
+        ];
+
+        let zip_file_result = ZipFile::from_readable(&mut Cursor::new(central_dir_bytes.clone()));
+
+        assert!(matches!(zip_file_result, Err(ZipFileError::IOError(_))));
+
+        let parse_options = ParseOptions {
+            file_name_policy: FileNamePolicy::Lossy,
+            ..Default::default()
+        };
+        let zip_file = ZipFile::from_readable_with_options(
+            &mut Cursor::new(central_dir_bytes.clone()),
+            &parse_options,
+        )
+        .unwrap();
+
+        assert_eq!(zip_file.file_name(), "a\u{FFFD}\u{FFFD}.b");
+        assert_eq!(zip_file.raw_file_name(), b"a\xFF\xFE.b");
+
+        // The extracted file keeps the name as stored
+        #[cfg(unix)]
+        {
+            use std::os::unix::ffi::OsStrExt;
+
+            assert_eq!(zip_file.os_file_name().as_bytes(), b"a\xFF\xFE.b");
+        }
+
+        // Names without the flag fall back to code page 437
+        central_dir_bytes[9] = 0x00;
+        let zip_file = ZipFile::from_readable(&mut Cursor::new(central_dir_bytes.clone())).unwrap();
+
+        assert_eq!(zip_file.file_name(), "a\u{A0}■.b");
+        assert_eq!(zip_file.os_file_name(), "a\u{A0}■.b");
+
+        // Unless another encoding is given
+        let parse_options = ParseOptions {
+            file_name_encoding: FileNameEncoding::for_label("shift_jis"),
+            ..Default::default()
+        };
+        let zip_file_result = ZipFile::from_readable_with_options(
+            &mut Cursor::new(central_dir_bytes),
+            &parse_options,
+        );
+
+        assert!(matches!(zip_file_result, Err(ZipFileError::IOError(_))));
+    }
+
+    #[test]
+    fn test_entry_kind_from_attributes() {
+        let unix = FileEnvironment::Unix;
+
+        assert_eq!(
+            EntryKind::from_attributes("bin", &unix, 0o040755 << 16),
+            EntryKind::Directory
+        );
+        assert_eq!(
+            EntryKind::from_attributes("link", &unix, 0o120777 << 16),
+            EntryKind::Symlink
+        );
+        assert_eq!(
+            EntryKind::from_attributes("docs/", &unix, 0o100644 << 16),
+            EntryKind::Directory
+        );
+        assert_eq!(
+            EntryKind::from_attributes("docs", &FileEnvironment::MsDos, 0x10),
+            EntryKind::Directory
+        );
+        assert_eq!(
+            EntryKind::from_attributes("a.txt", &unix, 0o100644 << 16),
+            EntryKind::File
+        );
+    }
+
     #[test]
     fn test() {
         let current_dir = std::env::current_dir().unwrap();
@@ -1,11 +1,25 @@
+pub mod aes;
 pub mod archive;
 pub mod clap;
 pub mod commands;
 pub mod date_time;
+pub mod encoding;
+pub mod extra_field;
+pub mod file_type;
 pub mod headers;
+#[cfg(feature = "lzma")]
+pub mod lzma;
+pub mod plan;
 pub mod pretty_printer;
+pub mod recovery;
+pub mod sink;
+pub mod split;
 pub mod util;
+pub mod writer;
 pub mod zip;
 pub mod zip_crypto;
 
+#[cfg(test)]
+mod test_utils;
+
 pub type Crc32 = u32;
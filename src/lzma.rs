@@ -0,0 +1,51 @@
+use std::io::{Cursor, Read};
+
+use byteorder::{ByteOrder, LittleEndian};
+use xz2::read::XzDecoder;
+use xz2::stream::Stream;
+
+const LZMA_HEADER_SIZE: usize = 4;
+const LZMA_PROPERTIES_SIZE: usize = 5;
+// liblzma only reads the `.lzma` format whose header holds the properties followed by the
+// uncompressed size
+const LZMA_ALONE_HEADER_SIZE: usize = LZMA_PROPERTIES_SIZE + 8;
+const UNKNOWN_UNCOMPRESSED_SIZE: u64 = u64::MAX;
+
+/// Returns a reader decoding the data of an LZMA entry. The data starts with the version of the
+/// LZMA SDK and the properties of the stream. Streams without an end marker end after the given
+/// uncompressed size.
+pub fn lzma_reader<'a, R>(
+    mut reader: R,
+    uncompressed_size: Option<u64>,
+) -> std::io::Result<impl Read + 'a>
+where
+    R: Read + 'a,
+{
+    let mut header = [0u8; LZMA_HEADER_SIZE];
+
+    reader.read_exact(&mut header)?;
+
+    let properties_size = LittleEndian::read_u16(&header[2..]) as usize;
+
+    if properties_size != LZMA_PROPERTIES_SIZE {
+        return Err(std::io::Error::new(
+            std::io::ErrorKind::InvalidData,
+            format!("Invalid LZMA properties size: {}", properties_size),
+        ));
+    }
+
+    let mut alone_header = [0u8; LZMA_ALONE_HEADER_SIZE];
+
+    reader.read_exact(&mut alone_header[..LZMA_PROPERTIES_SIZE])?;
+    LittleEndian::write_u64(
+        &mut alone_header[LZMA_PROPERTIES_SIZE..],
+        uncompressed_size.unwrap_or(UNKNOWN_UNCOMPRESSED_SIZE),
+    );
+
+    let stream = Stream::new_lzma_decoder(u64::MAX)?;
+
+    Ok(XzDecoder::new_stream(
+        Cursor::new(alone_header).chain(reader),
+        stream,
+    ))
+}
@@ -1,18 +1,316 @@
+use zippy::archive::ExtractError;
 use zippy::clap::{ArchiveCommand, Cli};
-use zippy::commands::{self, ExtractOptions};
-use zippy::util::get_file_path;
+use zippy::commands::{
+    self, CreateOptions, EncryptionKind, ExtractOptions, ListOptions, UpdateError,
+};
+use zippy::util::{self, get_file_path};
+use zippy::writer::Encryption;
+
+use std::fs::File;
+use std::path::PathBuf;
 
 use clap::Parser;
+use globset::{Glob, GlobSet, GlobSetBuilder};
+use regex::Regex;
 
 const INVALID_PATH_ERROR_RETURN_CODE: i32 = -10;
+const INVALID_REGEX_ERROR_RETURN_CODE: i32 = -11;
+const INVALID_PASSWORD_ERROR_RETURN_CODE: i32 = -12;
+const SALVAGE_FAILURE_ERROR_RETURN_CODE: i32 = -13;
+const INVALID_PATTERN_ERROR_RETURN_CODE: i32 = -14;
+const TEST_FAILURE_ERROR_RETURN_CODE: i32 = -15;
+const ARCHIVES_FAILURE_ERROR_RETURN_CODE: i32 = -16;
+const WRONG_PASSWORD_ERROR_RETURN_CODE: i32 = -17;
+const LIMIT_EXCEEDED_ERROR_RETURN_CODE: i32 = -18;
+const DUPLICATE_ENTRY_ERROR_RETURN_CODE: i32 = -19;
+const DESTINATION_EXISTS_ERROR_RETURN_CODE: i32 = -20;
+const PASSWORD_ENV_VAR: &str = "ZIPPY_PASSWORD";
 
 fn main() {
     let cli = Cli::parse();
 
     match cli.archive_command {
         ArchiveCommand::Zip { zip_command } => {
+            let passwords = given_passwords(zip_command.password, zip_command.password_file);
+
             //EXTRACT COMMAND
-            if let Some(path) = zip_command.extract {
+            if zip_command.extract.is_some() || zip_command.extract_all.is_some() {
+                let (paths, patterns) = match zip_command.extract {
+                    Some(args) => {
+                        let (archives, patterns) =
+                            util::split_archive_args(args, zip_command.patterns);
+
+                        (archive_paths(archives), patterns)
+                    }
+                    None => (Vec::new(), zip_command.patterns),
+                };
+
+                let mut extract_options = ExtractOptions::new(
+                    paths.first().cloned().unwrap_or_default(),
+                    zip_command.verbose,
+                    zip_command.destination,
+                );
+                extract_options.limit = zip_command.limit;
+                extract_options.resume = zip_command.resume;
+                extract_options.overwrite_policy = zip_command.overwrite;
+                extract_options.plan_json = zip_command.plan_json;
+                extract_options.atomic = zip_command.atomic;
+                extract_options.recursion_depth = zip_command.recursive.unwrap_or(0);
+                extract_options.salvage = zip_command.salvage;
+                extract_options.strip_components = zip_command.strip_components.unwrap_or(0);
+                extract_options.junk_paths = zip_command.junk_paths;
+                extract_options.collision_policy = zip_command.collision;
+                extract_options.duplicate_policy = zip_command.duplicates;
+                extract_options.case_collision_policy = zip_command.case_collisions;
+                extract_options.freshen = zip_command.freshen;
+                extract_options.max_compression_ratio =
+                    (!zip_command.no_max_ratio).then_some(zip_command.max_ratio);
+                extract_options.ignore_timestamps = zip_command.ignore_timestamps;
+                extract_options.refuse_external_symlinks = zip_command.refuse_external_symlinks;
+                extract_options.link_duplicates = zip_command.link_duplicates;
+                extract_options.mode_mask = zip_command.mode_mask;
+                extract_options.dir_mode = zip_command.dir_mode;
+                extract_options.file_mode = zip_command.file_mode;
+                extract_options.preserve_special_permissions =
+                    zip_command.preserve_special_permissions;
+                extract_options.sanitize_names &= !zip_command.no_sanitize;
+                extract_options.portable_names = zip_command.portable_names;
+                extract_options.file_name_encoding = zip_command.encoding;
+                extract_options.skip_macos_metadata = zip_command.skip_macos_metadata;
+                extract_options.only_extensions = zip_command.only_ext;
+                extract_options.only_types = zip_command.only_types;
+                extract_options.passwords = passwords.clone();
+                // Only root can give files away to other users
+                extract_options.same_owner = zip_command.same_owner && util::is_root();
+
+                if zip_command.same_owner && !extract_options.same_owner {
+                    eprintln!(
+                        "--same-owner is ignored since the owners can only be restored by root"
+                    );
+                }
+                if let Some(Err(err)) = zip_command.into.map(|into| extract_options.set_into(into))
+                {
+                    eprintln!("{}", err);
+                    std::process::exit(INVALID_PATH_ERROR_RETURN_CODE);
+                }
+                extract_options.regex = match zip_command.regex.map(|regex| Regex::new(&regex)) {
+                    Some(Ok(regex)) => Some(regex),
+                    Some(Err(err)) => {
+                        eprintln!("Invalid regular expression.\n{}", err);
+                        std::process::exit(INVALID_REGEX_ERROR_RETURN_CODE);
+                    }
+                    None => None,
+                };
+                extract_options.patterns = match pattern_set(&patterns) {
+                    Ok(patterns) => patterns,
+                    Err(err) => {
+                        eprintln!("Invalid glob pattern.\n{}", err);
+                        std::process::exit(INVALID_PATTERN_ERROR_RETURN_CODE);
+                    }
+                };
+
+                let extract_result = match zip_command.extract_all {
+                    Some(dir) => match std::path::absolute(&dir) {
+                        Ok(dir) => commands::extract_all(&dir, extract_options),
+                        Err(err) => Err(ExtractError::IOError(err.to_string())),
+                    },
+                    None if paths.len() == 1 => commands::extract_files(extract_options),
+                    None => commands::extract_archives(&paths, extract_options),
+                };
+
+                match extract_result {
+                    Ok(_) => (),
+                    // The failed files were already listed
+                    Err(ExtractError::EntriesFailed(_)) => {
+                        std::process::exit(SALVAGE_FAILURE_ERROR_RETURN_CODE)
+                    }
+                    // So were the failed archives
+                    Err(ExtractError::ArchivesFailed(_)) => {
+                        std::process::exit(ARCHIVES_FAILURE_ERROR_RETURN_CODE)
+                    }
+                    Err(
+                        err @ (ExtractError::CompressionRatioExceeded(..)
+                        | ExtractError::EntrySizeExceeded(_)
+                        | ExtractError::TotalSizeExceeded(_)
+                        | ExtractError::EntryCountExceeded(_)),
+                    ) => {
+                        eprintln!("{}", err);
+                        std::process::exit(LIMIT_EXCEEDED_ERROR_RETURN_CODE);
+                    }
+                    Err(err @ ExtractError::DuplicateEntry(_)) => {
+                        eprintln!("{}", err);
+                        std::process::exit(DUPLICATE_ENTRY_ERROR_RETURN_CODE);
+                    }
+                    Err(err @ ExtractError::DestinationExists(_)) => {
+                        eprintln!("{}", err);
+                        std::process::exit(DESTINATION_EXISTS_ERROR_RETURN_CODE);
+                    }
+                    Err(err) => eprintln!("{}", err),
+                }
+            }
+
+            //LIST COMMAND
+            if let Some(args) = zip_command.list {
+                let paths = archive_paths(args);
+
+                if paths.len() > 1 && zip_command.manifest.is_some() {
+                    eprintln!("--manifest can only be written when listing a single archive");
+                    std::process::exit(INVALID_PATH_ERROR_RETURN_CODE);
+                }
+
+                let mut list_options = ListOptions::new(paths[0].clone());
+                list_options.manifest_path = zip_command.manifest;
+                list_options.porcelain = zip_command.porcelain;
+                list_options.file_name_encoding = zip_command.encoding;
+                list_options.skip_macos_metadata = zip_command.skip_macos_metadata;
+
+                if paths.len() == 1 {
+                    commands::list_files(list_options);
+                } else {
+                    commands::list_archives(&paths, list_options);
+                }
+            }
+
+            //CREATE COMMAND
+            if let Some(mut args) = zip_command.create {
+                let inputs = args.split_off(1);
+                let path = args.remove(0);
+
+                // `-` writes the archive to stdout instead of a file
+                let path = if path.as_os_str() == commands::STDOUT_FILE_PATH {
+                    path
+                } else {
+                    match get_file_path(path) {
+                        Ok(path) => path,
+                        Err(err) => {
+                            eprintln!("{}", err);
+                            std::process::exit(INVALID_PATH_ERROR_RETURN_CODE);
+                        }
+                    }
+                };
+
+                let mut create_options = CreateOptions::new(path, inputs, zip_command.verbose);
+                create_options.freshen = zip_command.freshen;
+                create_options.append = zip_command.append;
+                create_options.deterministic = zip_command.deterministic;
+                create_options.use_ignore_files = zip_command.use_ignore_files;
+                create_options.exclude = zip_command.exclude;
+                create_options.unicode_path_extra_field = zip_command.unicode_extra_field;
+                create_options.stdin_entry_name = zip_command.add_from_stdin;
+                create_options.store_suffixes = zip_command.store_suffixes;
+                create_options.compression_level = zip_command.level;
+                create_options.mtime = zip_command.mtime;
+                create_options.mimetype_first = zip_command.mimetype_first;
+                create_options.alignment = zip_command.align;
+                create_options.split_size = zip_command.split_size;
+                create_options.comment = match zip_command.comment_file {
+                    Some(comment_file) => match std::fs::read(&comment_file) {
+                        Ok(comment) => Some(comment),
+                        Err(err) => {
+                            eprintln!("Cannot read {}: {}", comment_file.display(), err);
+                            std::process::exit(INVALID_PATH_ERROR_RETURN_CODE);
+                        }
+                    },
+                    None => zip_command.comment.map(String::into_bytes),
+                };
+                #[cfg(feature = "zstd")]
+                {
+                    create_options.zstd = zip_command.zstd;
+                }
+
+                if let Some(encryption_kind) = zip_command.encrypt {
+                    let password = match passwords.as_slice() {
+                        [] => prompt_new_password(),
+                        [password] if password.is_empty() => {
+                            eprintln!("The password cannot be empty");
+                            std::process::exit(INVALID_PASSWORD_ERROR_RETURN_CODE);
+                        }
+                        [password] => password.clone(),
+                        _ => {
+                            eprintln!("The files can only be encrypted with a single password");
+                            std::process::exit(INVALID_PASSWORD_ERROR_RETURN_CODE);
+                        }
+                    };
+
+                    create_options.encryption = Some(match encryption_kind {
+                        EncryptionKind::Aes256 => Encryption::Aes256(password),
+                        EncryptionKind::ZipCrypto => Encryption::ZipCrypto(password),
+                    });
+                }
+
+                let file_list = if zip_command.files_from_stdin {
+                    Some(commands::read_file_list(std::io::stdin().lock()))
+                } else {
+                    zip_command.files_from.map(|files_from| {
+                        File::open(files_from)
+                            .map_err(|err| UpdateError::IOError(err.to_string()))
+                            .and_then(commands::read_file_list)
+                    })
+                };
+
+                match file_list {
+                    Some(Ok(listed_paths)) => create_options.listed_paths = listed_paths,
+                    Some(Err(err)) => {
+                        eprintln!("{}", err);
+                        std::process::exit(INVALID_PATH_ERROR_RETURN_CODE);
+                    }
+                    None if create_options.inputs.is_empty()
+                        && create_options.stdin_entry_name.is_none() =>
+                    {
+                        eprintln!("No files or directories given to add to the zip file");
+                        std::process::exit(INVALID_PATH_ERROR_RETURN_CODE);
+                    }
+                    None => {}
+                }
+
+                if let Err(err) = commands::create_archive(create_options) {
+                    eprintln!("{}", err);
+                }
+            }
+
+            //RENAME COMMAND
+            if let Some(args) = zip_command.rename {
+                let path = match get_file_path(PathBuf::from(&args[2])) {
+                    Ok(path) => path,
+                    Err(err) => {
+                        eprintln!("{}", err);
+                        std::process::exit(INVALID_PATH_ERROR_RETURN_CODE);
+                    }
+                };
+
+                if let Err(err) = commands::rename_entry(path, &args[0], &args[1]) {
+                    eprintln!("{}", err);
+                }
+            }
+
+            //EOCD COMMAND
+            if let Some(path) = zip_command.eocd {
+                let path = match get_file_path(path) {
+                    Ok(path) => path,
+                    Err(err) => {
+                        eprintln!("{}", err);
+                        std::process::exit(INVALID_PATH_ERROR_RETURN_CODE);
+                    }
+                };
+
+                commands::print_end_of_central_dir(path);
+            }
+
+            //INFO COMMAND
+            if let Some(path) = zip_command.info {
+                let path = match get_file_path(path) {
+                    Ok(path) => path,
+                    Err(err) => {
+                        eprintln!("{}", err);
+                        std::process::exit(INVALID_PATH_ERROR_RETURN_CODE);
+                    }
+                };
+
+                commands::print_archive_info(path);
+            }
+
+            //TEST COMMAND
+            if let Some(path) = zip_command.test {
                 let path = match get_file_path(path) {
                     Ok(path) => path,
                     Err(err) => {
@@ -21,16 +319,18 @@ fn main() {
                     }
                 };
 
-                let extract_options =
-                    ExtractOptions::new(path, zip_command.verbose, zip_command.destination);
-                match commands::extract_files(extract_options) {
+                match commands::test_archive(path, passwords.clone()) {
                     Ok(_) => (),
+                    // The failed entries were already reported
+                    Err(ExtractError::EntriesFailed(_)) => {
+                        std::process::exit(TEST_FAILURE_ERROR_RETURN_CODE)
+                    }
                     Err(err) => eprintln!("{}", err),
                 }
             }
 
-            //LIST COMMAND
-            if let Some(path) = zip_command.list {
+            //TEST PASSWORD COMMAND
+            if let Some(path) = zip_command.test_password {
                 let path = match get_file_path(path) {
                     Ok(path) => path,
                     Err(err) => {
@@ -38,8 +338,156 @@ fn main() {
                         std::process::exit(INVALID_PATH_ERROR_RETURN_CODE);
                     }
                 };
-                commands::list_files(path);
+
+                match commands::test_password(path, passwords.clone()) {
+                    Ok(_) => (),
+                    // The entries the password is wrong for were already reported
+                    Err(ExtractError::EntriesFailed(_)) => {
+                        std::process::exit(WRONG_PASSWORD_ERROR_RETURN_CODE)
+                    }
+                    // So were the corrupt ones
+                    Err(ExtractError::EntriesCorrupt(_)) => {
+                        std::process::exit(TEST_FAILURE_ERROR_RETURN_CODE)
+                    }
+                    Err(err) => eprintln!("{}", err),
+                }
+            }
+
+            //REPAIR COMMAND
+            if let Some(paths) = zip_command.repair {
+                let path = match get_file_path(paths[0].clone()) {
+                    Ok(path) => path,
+                    Err(err) => {
+                        eprintln!("{}", err);
+                        std::process::exit(INVALID_PATH_ERROR_RETURN_CODE);
+                    }
+                };
+
+                match commands::repair_archive(path, &paths[1]) {
+                    Ok(entry_count) => println!("{} entries recovered", entry_count),
+                    Err(err) => eprintln!("{}", err),
+                }
+            }
+
+            //DIFF COMMAND
+            if let Some(paths) = zip_command.diff {
+                let paths: Vec<PathBuf> = match paths.into_iter().map(get_file_path).collect() {
+                    Ok(paths) => paths,
+                    Err(err) => {
+                        eprintln!("{}", err);
+                        std::process::exit(INVALID_PATH_ERROR_RETURN_CODE);
+                    }
+                };
+
+                commands::print_archive_diff(&paths[0], &paths[1]);
+            }
+
+            //SET COMMENT COMMAND
+            if let Some(args) = zip_command.set_comment {
+                let path = match get_file_path(PathBuf::from(&args[1])) {
+                    Ok(path) => path,
+                    Err(err) => {
+                        eprintln!("{}", err);
+                        std::process::exit(INVALID_PATH_ERROR_RETURN_CODE);
+                    }
+                };
+
+                if let Err(err) = commands::set_comment(path, &args[0]) {
+                    eprintln!("{}", err);
+                }
+            }
+        }
+    }
+}
+
+// Expands the globs among the given archive paths and makes the paths absolute, exiting when a path
+// is invalid or nothing matches
+fn archive_paths(args: Vec<PathBuf>) -> Vec<PathBuf> {
+    let mut paths = Vec::new();
+
+    for arg in args {
+        let expanded_paths = match util::expand_glob(arg.clone()) {
+            Ok(expanded_paths) if expanded_paths.is_empty() => {
+                eprintln!("No archive matches \"{}\"", arg.display());
+                std::process::exit(INVALID_PATH_ERROR_RETURN_CODE);
+            }
+            Ok(expanded_paths) => expanded_paths,
+            Err(err) => {
+                eprintln!("{}", err);
+                std::process::exit(INVALID_PATTERN_ERROR_RETURN_CODE);
+            }
+        };
+
+        for path in expanded_paths {
+            match get_file_path(path) {
+                Ok(path) => paths.push(path),
+                Err(err) => {
+                    eprintln!("{}", err);
+                    std::process::exit(INVALID_PATH_ERROR_RETURN_CODE);
+                }
             }
         }
     }
+
+    paths
+}
+
+// Builds the set of entry name patterns to extract, no patterns selecting every entry
+fn pattern_set(patterns: &[PathBuf]) -> Result<Option<GlobSet>, globset::Error> {
+    if patterns.is_empty() {
+        return Ok(None);
+    }
+
+    let mut glob_set_builder = GlobSetBuilder::new();
+
+    for pattern in patterns {
+        glob_set_builder.add(Glob::new(&pattern.to_string_lossy())?);
+    }
+
+    glob_set_builder.build().map(Some)
+}
+
+// Takes the passwords from --password, then from the non-empty lines of --password-file and then
+// from the ZIPPY_PASSWORD environment variable. Exits when the password file cannot be read.
+fn given_passwords(passwords: Vec<String>, password_file: Option<PathBuf>) -> Vec<String> {
+    if let Some(password_file) = password_file {
+        return match std::fs::read_to_string(&password_file) {
+            Ok(contents) => contents
+                .lines()
+                .filter(|line| !line.is_empty())
+                .map(String::from)
+                .collect(),
+            Err(err) => {
+                eprintln!("Cannot read {}: {}", password_file.display(), err);
+                std::process::exit(INVALID_PASSWORD_ERROR_RETURN_CODE);
+            }
+        };
+    }
+
+    if passwords.is_empty() {
+        std::env::var(PASSWORD_ENV_VAR).into_iter().collect()
+    } else {
+        passwords
+    }
+}
+
+// Asks for the password of a created archive twice so a typo doesn't lock the files away
+fn prompt_new_password() -> String {
+    let password = rpassword::prompt_password("Password: ").unwrap_or_default();
+
+    if password.is_empty() {
+        eprintln!("The password cannot be empty");
+        std::process::exit(INVALID_PASSWORD_ERROR_RETURN_CODE);
+    }
+
+    if rpassword::prompt_password("Verify password: ")
+        .ok()
+        .as_ref()
+        != Some(&password)
+    {
+        eprintln!("The passwords don't match");
+        std::process::exit(INVALID_PASSWORD_ERROR_RETURN_CODE);
+    }
+
+    password
 }
@@ -0,0 +1,20 @@
+use std::path::PathBuf;
+
+use serde::Serialize;
+
+#[derive(Debug, PartialEq, Eq, Clone, Copy, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum PlanAction {
+    Extract,
+    Skip,
+    Overwrite,
+}
+
+/// The decision made for a single entry before extracting it.
+#[derive(Debug, Serialize)]
+pub struct PlannedEntry {
+    pub name: String,
+    pub output_path: PathBuf,
+    pub action: PlanAction,
+    pub reason: &'static str,
+}
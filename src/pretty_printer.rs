@@ -1,43 +1,328 @@
-use crate::{archive::ReadableArchive, zip::Zip};
+use crate::{
+    archive::{ExtractError, ReadableArchive},
+    date_time::format_system_time,
+    headers::{EncryptionMethod, EndOfCentralDirectory, ZipFile},
+    zip::Zip,
+};
 
-const COLUMNS: [&'static str; 4] = ["Size (Bytes)", "Date Time", "Environment", "Name"];
+const COLUMNS: [&str; 5] = ["Size (Bytes)", "Date Time", "Environment", "Method", "Name"];
+const FAILURE_COLUMNS: [&str; 2] = ["Name", "Error"];
+const COLUMN_SEPARATOR: &str = "  ";
 
 pub fn pretty_print_zip_files<R>(zip: &Zip<R>)
 where
     R: ReadableArchive,
 {
     println!(
-        "\nFile Count: {}, Directory Count: {}\n",
+        "\nFile Count: {}, Directory Count: {}",
         zip.file_count(),
         zip.dir_count()
     );
 
-    println!(
-        "{}\t{}\t\t{}\t{}",
-        COLUMNS[0], COLUMNS[1], COLUMNS[2], COLUMNS[3]
-    );
+    if zip.is_zip64() {
+        println!("Archive uses ZIP64 extensions");
+    }
 
-    let column_separator_1 = String::from_utf8(vec![b'-'; COLUMNS[0].len()]).unwrap();
-    let column_separator_2 = String::from_utf8(vec![b'-'; 19]).unwrap();
-    let column_separator_3 = String::from_utf8(vec![b'-'; 12]).unwrap();
-    let column_separator_4 = String::from_utf8(vec![b'-'; 20]).unwrap();
+    println!();
 
-    println!(
-        "{}\t{}\t{}\t{}",
-        column_separator_1, column_separator_2, column_separator_3, column_separator_4
+    print!("{}", format_zip_files(zip));
+}
+
+/// Formats the entries of the given zip file as a table. Each column is as wide as its widest
+/// value so that long file names and large sizes don't break the alignment.
+pub fn format_zip_files<R>(zip: &Zip<R>) -> String
+where
+    R: ReadableArchive,
+{
+    let rows: Vec<[String; 5]> = zip
+        .zip_files()
+        .iter()
+        .map(|zip_file| {
+            [
+                zip_file.uncompressed_size().get().to_string(),
+                format_system_time(zip_file.modified_time()),
+                zip_file.environment().to_string(),
+                format_method(zip_file),
+                zip_file.file_name().clone(),
+            ]
+        })
+        .collect();
+
+    let mut widths = COLUMNS.map(|column| column.len());
+
+    for row in rows.iter() {
+        for (width, value) in widths.iter_mut().zip(row.iter()) {
+            *width = (*width).max(value.chars().count());
+        }
+    }
+
+    let mut table = String::new();
+
+    table.push_str(&format!(
+        "{:<size_width$}{sep}{:<date_time_width$}{sep}{:<environment_width$}{sep}{:<method_width$}{sep}{}\n",
+        COLUMNS[0],
+        COLUMNS[1],
+        COLUMNS[2],
+        COLUMNS[3],
+        COLUMNS[4],
+        size_width = widths[0],
+        date_time_width = widths[1],
+        environment_width = widths[2],
+        method_width = widths[3],
+        sep = COLUMN_SEPARATOR,
+    ));
+
+    let separators = widths.map(|width| "-".repeat(width));
+    table.push_str(&separators.join(COLUMN_SEPARATOR));
+    table.push('\n');
+
+    for row in rows.iter() {
+        table.push_str(&format!(
+            "{:>size_width$}{sep}{:<date_time_width$}{sep}{:<environment_width$}{sep}{:<method_width$}{sep}{}\n",
+            row[0],
+            row[1],
+            row[2],
+            row[3],
+            row[4],
+            size_width = widths[0],
+            date_time_width = widths[1],
+            environment_width = widths[2],
+            method_width = widths[3],
+            sep = COLUMN_SEPARATOR,
+        ));
+    }
+
+    table
+}
+
+/// Formats the entries which could not be extracted as a table of their names and errors. Errors
+/// spanning several lines are joined into one.
+pub fn format_failures(failures: &[(&String, &ExtractError)]) -> String {
+    let name_width = failures
+        .iter()
+        .map(|(file_name, _)| file_name.chars().count())
+        .fold(FAILURE_COLUMNS[0].len(), usize::max);
+    let mut table = format!(
+        "{:<name_width$}{sep}{}\n{}{sep}{}\n",
+        FAILURE_COLUMNS[0],
+        FAILURE_COLUMNS[1],
+        "-".repeat(name_width),
+        "-".repeat(FAILURE_COLUMNS[1].len()),
+        sep = COLUMN_SEPARATOR,
     );
 
-    zip.zip_files().iter().for_each(|zip_file| {
-        let first_column_padding =
-            COLUMNS[0].len() - zip_file.uncompressed_size().get().to_string().len();
-
-        println!(
-            "{}{}\t{}\t{}\t\t{}",
-            String::from_utf8(vec![b' '; first_column_padding]).unwrap(),
-            zip_file.uncompressed_size().get(),
-            zip_file.date_time(),
-            zip_file.environment(),
-            zip_file.file_name()
+    for (file_name, err) in failures {
+        table.push_str(&format!(
+            "{:<name_width$}{sep}{}\n",
+            file_name,
+            err.to_string().replace('\n', " "),
+            sep = COLUMN_SEPARATOR,
+        ));
+    }
+
+    table
+}
+
+/// Formats the compression method of the entry followed by its encryption if it is encrypted. AES
+/// encrypted entries show the compression method and the key strength read from their AES extra
+/// field and strong encrypted entries show their algorithm.
+fn format_method(zip_file: &ZipFile) -> String {
+    let compression_method = zip_file.compression_method();
+
+    match (zip_file.encryption_method(), zip_file.aes_extra_field()) {
+        (EncryptionMethod::NoEncryption, _) => compression_method.to_string(),
+        (EncryptionMethod::Aes, Some(aes_extra_field)) => {
+            format!("{}, {}", compression_method, aes_extra_field.strength)
+        }
+        (encryption_method @ EncryptionMethod::StrongEncryption, _) => {
+            match zip_file.strong_encryption_algorithm() {
+                Some(algorithm) => format!(
+                    "{}, {} ({})",
+                    compression_method, encryption_method, algorithm
+                ),
+                None => format!("{}, {}", compression_method, encryption_method),
+            }
+        }
+        (encryption_method, _) => format!("{}, {}", compression_method, encryption_method),
+    }
+}
+
+/// Formats the entries as `name\tuncompressed_size\tcrc32` lines without any decoration so the
+/// output can be consumed by scripts.
+pub fn format_porcelain<R>(zip: &Zip<R>) -> String
+where
+    R: ReadableArchive,
+{
+    zip.zip_files()
+        .iter()
+        .map(|zip_file| {
+            format!(
+                "{}\t{}\t{:08X}\n",
+                zip_file.file_name(),
+                zip_file.uncompressed_size().get(),
+                zip_file.crc32().get()
+            )
+        })
+        .collect()
+}
+
+/// Formats a summary of the archive as `field: value` lines.
+pub fn format_archive_info<R>(zip: &Zip<R>) -> String
+where
+    R: ReadableArchive,
+{
+    format!(
+        "Entry count: {}\n\
+         File count: {}\n\
+         Directory count: {}\n\
+         ZIP64: {}\n\
+         Encrypted entries: {}\n\
+         Central directory offset: {}\n\
+         Prepended data length: {}\n\
+         Trailing data length: {}\n\
+         Recovered from local headers: {}\n\
+         Comment: {}\n",
+        zip.zip_file_couunt(),
+        zip.file_count(),
+        zip.dir_count(),
+        zip.is_zip64(),
+        zip.files_encrypted(),
+        zip.central_dir_start_offset(),
+        zip.prepended_data_len(),
+        zip.trailing_data_len(),
+        zip.recovered(),
+        String::from_utf8_lossy(zip.comment()),
+    )
+}
+
+/// Formats the fields of the end of central directory record as `field: value` lines.
+pub fn format_end_of_central_dir(end_of_central_dir: &EndOfCentralDirectory) -> String {
+    format!(
+        "Record offset: {}\n\
+         Disk number: {}\n\
+         Disk count: {}\n\
+         Central directory start disk: {}\n\
+         Central directory entry count: {}\n\
+         Central directory size: {}\n\
+         Central directory offset: {}\n\
+         Comment length: {}\n\
+         Comment: {}\n\
+         ZIP64 locator found: {}\n\
+         Trailing data length: {}\n",
+        end_of_central_dir.offset(),
+        end_of_central_dir.disk_number(),
+        end_of_central_dir.disk_count(),
+        end_of_central_dir.central_dir_start_disk(),
+        end_of_central_dir.central_dir_size(),
+        end_of_central_dir.central_dir_size_in_bytes(),
+        end_of_central_dir.central_dir_start_offset(),
+        end_of_central_dir.comment_len(),
+        String::from_utf8_lossy(end_of_central_dir.comment()),
+        end_of_central_dir.zip64_locator_found(),
+        end_of_central_dir.trailing_data_len(),
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_utils::{build_archive, TestEntry};
+    use std::io::{BufReader, Cursor};
+
+    #[test]
+    fn test_column_alignment() {
+        let archive = build_archive(&[
+            TestEntry::file("a", b""),
+            TestEntry::file(
+                "a/very/long/path/to/some/deeply/nested/file/name.txt",
+                &[0u8; 123456],
+            ),
+            TestEntry::file("b.txt", b"hello"),
+        ]);
+        let zip = Zip::from_readable(BufReader::new(Cursor::new(archive))).unwrap();
+        let table = format_zip_files(&zip);
+        let lines: Vec<&str> = table.lines().collect();
+
+        assert_eq!(lines.len(), 5);
+
+        let name_column_start = lines[0].find(COLUMNS[4]).unwrap();
+        let names = [
+            "a",
+            "a/very/long/path/to/some/deeply/nested/file/name.txt",
+            "b.txt",
+        ];
+
+        for (line, name) in lines[2..].iter().zip(names.iter()) {
+            assert_eq!(&line[name_column_start..], *name);
+        }
+
+        assert!(lines[1]
+            .split(COLUMN_SEPARATOR)
+            .all(|separator| separator.chars().all(|c| c == '-')));
+        assert_eq!(lines[1].len(), name_column_start + names[1].len());
+    }
+
+    #[test]
+    fn test_failures_table() {
+        let long_name = "docs/guide/setup.md".to_string();
+        let short_name = "a".to_string();
+        let table = format_failures(&[
+            (&long_name, &ExtractError::InvalidExtractedFile(1, 2)),
+            (
+                &short_name,
+                &ExtractError::UnableToCreateExtractedFile("a".to_string(), "denied".to_string()),
+            ),
+        ]);
+        let lines: Vec<&str> = table.lines().collect();
+
+        assert_eq!(lines.len(), 4);
+        assert_eq!(lines[1], format!("{}  -----", "-".repeat(long_name.len())));
+
+        let error_column_start = lines[0].find(FAILURE_COLUMNS[1]).unwrap();
+
+        assert_eq!(error_column_start, long_name.len() + COLUMN_SEPARATOR.len());
+        assert!(lines[2][error_column_start..].starts_with("Extracted file corruption."));
+        assert_eq!(
+            &lines[3][error_column_start..],
+            "Unable to create the extracted file \"a\". Error: denied"
         );
-    });
+    }
+
+    #[test]
+    fn test_porcelain_output() {
+        let archive = build_archive(&[
+            TestEntry::dir("docs/"),
+            TestEntry::file("docs/readme.txt", b"hello"),
+            TestEntry::file("b.txt", b""),
+        ]);
+        let zip = Zip::from_readable(BufReader::new(Cursor::new(archive))).unwrap();
+        let porcelain = format_porcelain(&zip);
+        let lines: Vec<&str> = porcelain.lines().collect();
+
+        assert_eq!(
+            lines,
+            vec![
+                "docs/\t0\t00000000",
+                "docs/readme.txt\t5\t3610A686",
+                "b.txt\t0\t00000000"
+            ]
+        );
+    }
+
+    #[test]
+    fn test_archive_info() {
+        let mut archive = vec![0x4Du8; 16];
+        archive.extend_from_slice(&build_archive(&[
+            TestEntry::dir("docs/"),
+            TestEntry::file("docs/readme.txt", b"hello"),
+        ]));
+        archive.extend_from_slice(&[0u8; 32]);
+
+        let zip = Zip::from_readable(BufReader::new(Cursor::new(archive))).unwrap();
+        let info = format_archive_info(&zip);
+
+        assert!(info.starts_with("Entry count: 2\nFile count: 1\nDirectory count: 1\n"));
+        assert!(info.contains("Prepended data length: 16\n"));
+        assert!(info.contains("Trailing data length: 32\n"));
+    }
 }
@@ -0,0 +1,288 @@
+use std::io::{self, Read, Seek, SeekFrom};
+
+use byteorder::{ByteOrder, LittleEndian};
+use flate2::{Decompress, FlushDecompress, Status};
+
+use crate::headers::{
+    CompressionMethod, EncryptionMethod, ParseOptions, ZipFile, CENTRAL_DIR_SIGN,
+    EOF_CENTRAL_DIR_SIGN, LOCAL_FILE_HEADER_SIGN,
+};
+
+const DATA_DESCRIPTOR_SIGN: u32 = 0x08074b50;
+// Long enough to hold a data descriptor with its signature followed by the next signature
+const WINDOW_LEN: usize = 16;
+const CHUNK_LEN: usize = 64 * 1024;
+
+/// Builds the entries of an archive from its local file headers, for archives whose central
+/// directory is missing or truncated like partially downloaded ones. The file is scanned forward
+/// for local file header signatures, and entries using a data descriptor take their sizes and
+/// CRC-32 from it. Entries whose data is cut off are left out. Each entry is returned along with
+/// the offset where its data and data descriptor end.
+pub fn scan_local_headers<R>(
+    readable: &mut R,
+    parse_options: &ParseOptions,
+) -> io::Result<Vec<(ZipFile, u64)>>
+where
+    R: Read + Seek,
+{
+    let mut zip_files = Vec::new();
+    let mut scan_offset = 0;
+
+    while let Some(header_offset) = find_forward(readable, scan_offset, |window, _| {
+        LittleEndian::read_u32(&window[..4]) == LOCAL_FILE_HEADER_SIGN
+    })? {
+        readable.seek(SeekFrom::Start(header_offset))?;
+
+        let entry_end = match ZipFile::from_local_header_with_options(readable, parse_options) {
+            Ok(zip_file) => {
+                let data_start_offset = readable.stream_position()?;
+                let entry_end = entry_end(readable, &zip_file, data_start_offset)?;
+
+                if let Some(entry_end) = entry_end {
+                    zip_files.push((zip_file, entry_end));
+                }
+
+                entry_end
+            }
+            Err(_) => None,
+        };
+
+        // The data of an entry may contain signatures of its own, so the scan resumes after it.
+        // A signature which didn't start a complete entry is just skipped.
+        scan_offset = entry_end.unwrap_or(header_offset + 4);
+    }
+
+    Ok(zip_files)
+}
+
+// Returns where the data of the entry and its data descriptor end, or `None` when the entry is
+// cut off. The sizes and CRC-32 of entries using a data descriptor are taken from it.
+fn entry_end<R>(
+    readable: &mut R,
+    zip_file: &ZipFile,
+    data_start_offset: u64,
+) -> io::Result<Option<u64>>
+where
+    R: Read + Seek,
+{
+    let file_size = readable.seek(SeekFrom::End(0))?;
+
+    if !zip_file.data_descriptor_used() {
+        let entry_end = data_start_offset + zip_file.compressed_size().get();
+
+        return Ok(Some(entry_end).filter(|&entry_end| entry_end <= file_size));
+    }
+
+    let Some(data_descriptor_offset) = find_data_descriptor(readable, zip_file, data_start_offset)?
+    else {
+        return Ok(None);
+    };
+
+    match zip_file.update_with_data_descriptor(
+        readable,
+        data_descriptor_offset,
+        data_descriptor_offset - data_start_offset,
+    ) {
+        Ok(entry_end) => Ok(Some(entry_end).filter(|&entry_end| entry_end <= file_size)),
+        Err(err) if err.kind() == io::ErrorKind::UnexpectedEof => Ok(None),
+        Err(err) => Err(err),
+    }
+}
+
+/// Returns the offset of the data descriptor following the data of the entry, which starts at the
+/// given offset, or `None` when it isn't found. The data ends after the compressed size when a
+/// header records it and Deflate streams are decompressed up to their end. Otherwise the data
+/// descriptor is searched for where its compressed size matches the data read so far.
+pub fn find_data_descriptor<R>(
+    readable: &mut R,
+    zip_file: &ZipFile,
+    data_start_offset: u64,
+) -> io::Result<Option<u64>>
+where
+    R: Read + Seek,
+{
+    if zip_file.compressed_size().get() != 0 {
+        return Ok(Some(data_start_offset + zip_file.compressed_size().get()));
+    }
+
+    if zip_file.encryption_method() == &EncryptionMethod::NoEncryption
+        && matches!(zip_file.compression_method(), CompressionMethod::Deflate(_))
+    {
+        return Ok(deflate_stream_len(readable, data_start_offset)?
+            .map(|stream_len| data_start_offset + stream_len));
+    }
+
+    // The data descriptor either starts with its own signature or is followed by the signature of
+    // the next record
+    find_forward(readable, data_start_offset, |window, offset| {
+        let compressed_size = (offset - data_start_offset) as u32;
+        let next_sign = LittleEndian::read_u32(&window[12..16]);
+
+        (LittleEndian::read_u32(&window[..4]) == DATA_DESCRIPTOR_SIGN
+            && LittleEndian::read_u32(&window[8..12]) == compressed_size)
+            || (LittleEndian::read_u32(&window[4..8]) == compressed_size
+                && [
+                    LOCAL_FILE_HEADER_SIGN,
+                    CENTRAL_DIR_SIGN,
+                    EOF_CENTRAL_DIR_SIGN,
+                ]
+                .contains(&next_sign))
+    })
+}
+
+// Decompresses the Deflate stream starting at the given offset and returns how many compressed
+// bytes it spans, or `None` when the stream is invalid or cut off.
+fn deflate_stream_len<R>(readable: &mut R, start_offset: u64) -> io::Result<Option<u64>>
+where
+    R: Read + Seek,
+{
+    let mut decompress = Decompress::new(false);
+    let mut chunk = vec![0u8; CHUNK_LEN];
+    let mut output = vec![0u8; CHUNK_LEN];
+
+    readable.seek(SeekFrom::Start(start_offset))?;
+
+    loop {
+        let chunk_len = readable.read(&mut chunk)?;
+        let mut input = &chunk[..chunk_len];
+
+        // The output is thrown away, it is only decompressed until more input is needed
+        loop {
+            let (total_in, total_out) = (decompress.total_in(), decompress.total_out());
+
+            match decompress.decompress(input, &mut output, FlushDecompress::None) {
+                Ok(Status::StreamEnd) => return Ok(Some(decompress.total_in())),
+                Ok(_) => {}
+                Err(_) => return Ok(None),
+            }
+
+            let consumed = (decompress.total_in() - total_in) as usize;
+
+            if consumed == 0 && decompress.total_out() == total_out {
+                break;
+            }
+
+            input = &input[consumed..];
+        }
+
+        if chunk_len == 0 {
+            return Ok(None);
+        }
+    }
+}
+
+// Returns the first offset from `start_offset` whose window matches the predicate. Only the
+// windows lying entirely within the file are checked.
+fn find_forward<R, P>(
+    readable: &mut R,
+    start_offset: u64,
+    mut predicate: P,
+) -> io::Result<Option<u64>>
+where
+    R: Read + Seek,
+    P: FnMut(&[u8; WINDOW_LEN], u64) -> bool,
+{
+    // The chunks overlap by a window so that each window is read whole
+    let read_len = CHUNK_LEN + WINDOW_LEN - 1;
+    let mut chunk = vec![0u8; read_len];
+    let mut chunk_offset = start_offset;
+
+    readable.seek(SeekFrom::Start(chunk_offset))?;
+
+    loop {
+        let mut chunk_len = 0;
+
+        while chunk_len < read_len {
+            match readable.read(&mut chunk[chunk_len..read_len])? {
+                0 => break,
+                read => chunk_len += read,
+            }
+        }
+
+        let end_reached = chunk_len < read_len;
+        let window_count = if end_reached {
+            chunk_len.saturating_sub(WINDOW_LEN - 1)
+        } else {
+            CHUNK_LEN
+        };
+        let found = (0..window_count).find(|&index| {
+            let window = chunk[index..index + WINDOW_LEN].try_into().unwrap();
+
+            predicate(window, chunk_offset + index as u64)
+        });
+
+        if let Some(index) = found {
+            return Ok(Some(chunk_offset + index as u64));
+        }
+
+        if end_reached {
+            return Ok(None);
+        }
+
+        chunk_offset += CHUNK_LEN as u64;
+        readable.seek(SeekFrom::Start(chunk_offset))?;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::writer::{EntryOptions, ZipWriter};
+    use crate::zip::Zip;
+    use std::io::{BufReader, Cursor};
+
+    #[test]
+    fn test_scan_local_headers() {
+        let mut zip_writer = ZipWriter::streaming(Vec::new());
+
+        zip_writer
+            .add_directory("docs", &EntryOptions::default())
+            .unwrap();
+        zip_writer
+            .add_file(
+                "docs/a.txt",
+                &mut "hello ".repeat(100).as_bytes(),
+                &EntryOptions::default(),
+            )
+            .unwrap();
+        zip_writer
+            .add_file("b.txt", &mut &b"world"[..], &EntryOptions::default())
+            .unwrap();
+
+        let archive = zip_writer.finish().unwrap().into_inner();
+        let central_dir_start_offset = Zip::from_readable(BufReader::new(Cursor::new(&archive)))
+            .unwrap()
+            .central_dir_start_offset();
+
+        // Partially downloaded archive missing the end of its central directory
+        let truncated_archive = &archive[..central_dir_start_offset as usize + 10];
+        let zip_files = scan_local_headers(
+            &mut Cursor::new(truncated_archive),
+            &ParseOptions::default(),
+        )
+        .unwrap();
+        let file_names: Vec<&str> = zip_files
+            .iter()
+            .map(|(zip_file, _)| zip_file.file_name().as_str())
+            .collect();
+
+        assert_eq!(file_names, vec!["docs/", "docs/a.txt", "b.txt"]);
+        assert!(zip_files[0].0.is_dir());
+        assert_eq!(zip_files[1].0.uncompressed_size().get(), 600);
+        assert_eq!(zip_files[2].0.uncompressed_size().get(), 5);
+        assert_eq!(zip_files[2].0.crc32().get(), 0x3A771143);
+        assert_eq!(zip_files[0].1, zip_files[1].0.offset());
+        assert_eq!(zip_files[1].1, zip_files[2].0.offset());
+        assert_eq!(zip_files[2].1, central_dir_start_offset);
+
+        // The last entry is left out when its data descriptor is cut off
+        let truncated_archive = &archive[..central_dir_start_offset as usize - 4];
+        let zip_files = scan_local_headers(
+            &mut Cursor::new(truncated_archive),
+            &ParseOptions::default(),
+        )
+        .unwrap();
+
+        assert_eq!(zip_files.len(), 2);
+    }
+}
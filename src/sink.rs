@@ -0,0 +1,262 @@
+use std::fs::File;
+use std::io::{Read, Write};
+use std::path::{Path, PathBuf};
+use std::time::SystemTime;
+
+use filetime::FileTime;
+
+use crate::archive::{
+    set_modified_time, set_owner, set_permissions, ExtractError, ReadableArchive,
+};
+use crate::commands::ExtractOptions;
+use crate::headers::{EntryKind, ZipFile};
+
+/// What a sink already holds at a path, as far as the extraction plan looks at it.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct SinkMetadata {
+    pub is_file: bool,
+    pub len: u64,
+    pub modified: Option<SystemTime>,
+}
+
+/// Destination the extracted entries are written to. [`FileSystemSink`] writes them to the disk,
+/// other sinks can keep them in memory or pass them on to an object store or a tar stream. The
+/// paths are the output paths resolved from the extract options.
+pub trait ExtractSink {
+    /// Returns what the sink already holds at the given path, which decides whether the entry
+    /// extracted there is skipped or overwrites it. Sinks starting out empty report every path as
+    /// absent.
+    fn metadata(&self, _path: &Path) -> Option<SinkMetadata> {
+        None
+    }
+
+    /// Opens the file the sink already holds at the given path, so that resumed extractions can
+    /// tell whether it matches the entry.
+    fn open_file(&self, _path: &Path) -> std::io::Result<Box<dyn Read + '_>> {
+        Err(std::io::ErrorKind::NotFound.into())
+    }
+
+    /// Resolves the symlinks on the given path like [`std::fs::canonicalize`]. Sinks that don't
+    /// follow symlinks return an error, so links are only checked by their path.
+    fn canonicalize(&self, _path: &Path) -> std::io::Result<PathBuf> {
+        Err(std::io::ErrorKind::Unsupported.into())
+    }
+
+    /// Creates the directory at the given path along with its missing parents.
+    fn create_dir(&mut self, path: &Path) -> std::io::Result<()>;
+
+    /// Creates the file at the given path, replacing an existing one, and returns the writer the
+    /// decoded contents of the entry are written to. Its parent directory is already created.
+    fn create_file(&mut self, path: &Path) -> std::io::Result<Box<dyn Write + '_>>;
+
+    /// Creates a symlink at the given path pointing to the target. Sinks without symlinks get a
+    /// regular file containing the target.
+    fn create_symlink(&mut self, path: &Path, target: &str) -> std::io::Result<()> {
+        self.create_file(path)?.write_all(target.as_bytes())
+    }
+
+    /// Makes the given path a hard link to the file extracted to the original path. Sinks without
+    /// hard links return an error, so the entry is extracted again instead.
+    fn hard_link(&mut self, _original_path: &Path, _path: &Path) -> std::io::Result<()> {
+        Err(std::io::ErrorKind::Unsupported.into())
+    }
+
+    /// Applies the owner, permissions and modification time of the entry to what was extracted to
+    /// the given path. Directories get theirs once all the entries are extracted. Sinks without
+    /// such metadata ignore it.
+    fn set_metadata<R>(
+        &mut self,
+        _zip_file: &ZipFile,
+        _path: &Path,
+        _readable: &mut R,
+        _extract_options: &ExtractOptions,
+    ) -> Result<(), ExtractError>
+    where
+        R: ReadableArchive,
+    {
+        Ok(())
+    }
+}
+
+/// Writes the extracted entries to the file system.
+#[derive(Debug, Default, Clone, Copy)]
+pub struct FileSystemSink;
+
+impl ExtractSink for FileSystemSink {
+    fn metadata(&self, path: &Path) -> Option<SinkMetadata> {
+        std::fs::metadata(path).ok().map(|metadata| SinkMetadata {
+            is_file: metadata.is_file(),
+            len: metadata.len(),
+            modified: metadata.modified().ok(),
+        })
+    }
+
+    fn open_file(&self, path: &Path) -> std::io::Result<Box<dyn Read + '_>> {
+        Ok(Box::new(File::open(path)?))
+    }
+
+    fn canonicalize(&self, path: &Path) -> std::io::Result<PathBuf> {
+        std::fs::canonicalize(path)
+    }
+
+    fn create_dir(&mut self, path: &Path) -> std::io::Result<()> {
+        std::fs::create_dir_all(path)
+    }
+
+    fn create_file(&mut self, path: &Path) -> std::io::Result<Box<dyn Write + '_>> {
+        Ok(Box::new(File::create(path)?))
+    }
+
+    fn create_symlink(&mut self, path: &Path, target: &str) -> std::io::Result<()> {
+        // The plan doesn't follow symlinks, so an existing link is replaced here
+        if std::fs::symlink_metadata(path).is_ok() {
+            std::fs::remove_file(path)?;
+        }
+
+        #[cfg(unix)]
+        {
+            std::os::unix::fs::symlink(target, path)
+        }
+
+        #[cfg(not(unix))]
+        {
+            std::fs::write(path, target)
+        }
+    }
+
+    fn hard_link(&mut self, original_path: &Path, path: &Path) -> std::io::Result<()> {
+        if let Some(parent_path) = path.parent() {
+            std::fs::create_dir_all(parent_path)?;
+        }
+
+        if std::fs::symlink_metadata(path).is_ok() {
+            std::fs::remove_file(path)?;
+        }
+
+        std::fs::hard_link(original_path, path)
+    }
+
+    fn set_metadata<R>(
+        &mut self,
+        zip_file: &ZipFile,
+        path: &Path,
+        readable: &mut R,
+        extract_options: &ExtractOptions,
+    ) -> Result<(), ExtractError>
+    where
+        R: ReadableArchive,
+    {
+        let is_symlink = zip_file.entry_kind() == EntryKind::Symlink;
+
+        // Changing the owner clears the setuid and setgid bits so it comes before the permissions
+        if extract_options.same_owner {
+            set_owner(zip_file, path, readable)?;
+        }
+
+        // Symlinks have no permissions of their own on most hosts
+        if !is_symlink {
+            set_permissions(zip_file, path, extract_options)?;
+        }
+
+        if extract_options.ignore_timestamps {
+            Ok(())
+        } else if is_symlink {
+            let modified = FileTime::from_system_time(zip_file.modified_time());
+
+            filetime::set_symlink_file_times(path, modified, modified)
+                .map_err(|err| ExtractError::IOError(err.to_string()))
+        } else {
+            set_modified_time(zip_file, path)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::commands::OverwritePolicy;
+    use crate::test_utils::{build_archive, TestEntry};
+    use crate::zip::Zip;
+    use std::collections::HashMap;
+    use std::io::{BufReader, Cursor};
+    use std::path::PathBuf;
+
+    #[derive(Default)]
+    struct MemorySink {
+        dirs: Vec<PathBuf>,
+        files: HashMap<PathBuf, Vec<u8>>,
+    }
+
+    impl ExtractSink for MemorySink {
+        fn metadata(&self, path: &Path) -> Option<SinkMetadata> {
+            self.files.get(path).map(|file| SinkMetadata {
+                is_file: true,
+                len: file.len() as u64,
+                modified: None,
+            })
+        }
+
+        fn create_dir(&mut self, path: &Path) -> std::io::Result<()> {
+            self.dirs.push(path.to_path_buf());
+            Ok(())
+        }
+
+        fn create_file(&mut self, path: &Path) -> std::io::Result<Box<dyn Write + '_>> {
+            let file = self.files.entry(path.to_path_buf()).or_default();
+            file.clear();
+            Ok(Box::new(file))
+        }
+    }
+
+    #[test]
+    fn test_extract_into_memory() {
+        let dir = tempfile::tempdir().unwrap();
+        let zip_path = dir.path().join("archive.zip");
+        let archive = build_archive(&[
+            TestEntry::dir("docs/"),
+            TestEntry::file("docs/a.txt", b"hello"),
+            TestEntry::file("b.txt", b"world"),
+        ]);
+        let mut zip = Zip::from_readable(BufReader::new(Cursor::new(archive))).unwrap();
+        let extract_options = ExtractOptions::new(zip_path, false, None);
+        let mut sink = MemorySink::default();
+
+        let results = zip
+            .extract_entries(&extract_options, &[], &mut sink)
+            .unwrap();
+
+        assert!(results.iter().all(|(_, result)| result.is_ok()));
+        assert!(sink.dirs.contains(&dir.path().join("docs")));
+        assert_eq!(sink.files[&dir.path().join("docs/a.txt")], b"hello");
+        assert_eq!(sink.files[&dir.path().join("b.txt")], b"world");
+        assert_eq!(std::fs::read_dir(dir.path()).unwrap().count(), 0);
+    }
+
+    #[test]
+    fn test_plan_looks_at_sink() {
+        let dir = tempfile::tempdir().unwrap();
+        let zip_path = dir.path().join("archive.zip");
+        let archive = build_archive(&[
+            TestEntry::file("a.txt", b"hello"),
+            TestEntry::file("b.txt", b"world"),
+        ]);
+        let mut zip = Zip::from_readable(BufReader::new(Cursor::new(archive))).unwrap();
+        let mut extract_options = ExtractOptions::new(zip_path, false, None);
+        extract_options.overwrite_policy = OverwritePolicy::Skip;
+        let mut sink = MemorySink::default();
+
+        // Only the file the sink holds is skipped, not the one on the disk
+        sink.files
+            .insert(dir.path().join("a.txt"), b"kept".to_vec());
+        std::fs::write(dir.path().join("b.txt"), b"on disk").unwrap();
+
+        let results = zip
+            .extract_entries(&extract_options, &[], &mut sink)
+            .unwrap();
+
+        assert_eq!(results.len(), 1);
+        assert_eq!(sink.files[&dir.path().join("a.txt")], b"kept");
+        assert_eq!(sink.files[&dir.path().join("b.txt")], b"world");
+        assert_eq!(std::fs::read(dir.path().join("b.txt")).unwrap(), b"on disk");
+    }
+}
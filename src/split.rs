@@ -0,0 +1,266 @@
+use std::ffi::OsString;
+use std::fs::File;
+use std::io::{self, Read, Seek, SeekFrom};
+use std::path::{Path, PathBuf};
+
+use crate::headers::EndOfCentralDirectory;
+
+/// Reads the segments of a split archive (`foo.z01`, `foo.z02`, ..., `foo.zip`) as one stream.
+/// The `.zip` file is the last disk of the archive and holds the central directory. Archives whose
+/// end of central directory record is on the first disk are read as a single segment.
+pub struct SplitArchive {
+    segments: Vec<File>,
+    disk_start_offsets: Vec<u64>,
+    len: u64,
+    position: u64,
+}
+
+impl SplitArchive {
+    pub fn open(path: &Path) -> io::Result<Self> {
+        let mut segment_paths = segment_paths(path);
+        let mut last_segment = File::open(&segment_paths[segment_paths.len() - 1])?;
+        let mut segments = Vec::new();
+        let mut disk_start_offsets = Vec::new();
+        let mut len = 0;
+
+        // Segments lying next to an archive that is not split are not part of it
+        let is_split = EndOfCentralDirectory::from_readable(&mut last_segment)
+            .is_ok_and(|end_of_central_dir| end_of_central_dir.disk_count() > 1);
+
+        if !is_split {
+            segment_paths.drain(..segment_paths.len() - 1);
+        }
+
+        for segment_path in segment_paths {
+            let segment = File::open(segment_path)?;
+
+            disk_start_offsets.push(len);
+            len += segment.metadata()?.len();
+            segments.push(segment);
+        }
+
+        Ok(Self {
+            segments,
+            disk_start_offsets,
+            len,
+            position: 0,
+        })
+    }
+
+    pub fn is_split(&self) -> bool {
+        self.segments.len() > 1
+    }
+
+    /// Start offsets of the disks within the stitched segments, indexed by disk number.
+    pub fn disk_start_offsets(&self) -> &[u64] {
+        &self.disk_start_offsets
+    }
+}
+
+impl Read for SplitArchive {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        if buf.is_empty() || self.position >= self.len {
+            return Ok(0);
+        }
+
+        let disk = self
+            .disk_start_offsets
+            .partition_point(|&disk_start_offset| disk_start_offset <= self.position)
+            - 1;
+        let disk_start_offset = self.disk_start_offsets[disk];
+        let disk_end_offset = self
+            .disk_start_offsets
+            .get(disk + 1)
+            .copied()
+            .unwrap_or(self.len);
+        let read_len = buf.len().min((disk_end_offset - self.position) as usize);
+        let segment = &mut self.segments[disk];
+
+        segment.seek(SeekFrom::Start(self.position - disk_start_offset))?;
+
+        let read = segment.read(&mut buf[..read_len])?;
+        self.position += read as u64;
+
+        Ok(read)
+    }
+}
+
+impl Seek for SplitArchive {
+    fn seek(&mut self, pos: SeekFrom) -> io::Result<u64> {
+        let position = match pos {
+            SeekFrom::Start(offset) => Some(offset),
+            SeekFrom::End(offset) => self.len.checked_add_signed(offset),
+            SeekFrom::Current(offset) => self.position.checked_add_signed(offset),
+        };
+
+        match position {
+            Some(position) => {
+                self.position = position;
+                Ok(position)
+            }
+            None => Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                "invalid seek to a negative position",
+            )),
+        }
+    }
+}
+
+/// Returns the paths of the segments of the archive at `path` ordered by disk number. Given one of
+/// the `.zNN` segments, the segments of the archive it belongs to are returned.
+pub fn segment_paths(path: &Path) -> Vec<PathBuf> {
+    let extension = path
+        .extension()
+        .map(|extension| extension.to_string_lossy().into_owned())
+        .unwrap_or_default();
+    let is_segment = extension
+        .strip_prefix(['z', 'Z'])
+        .is_some_and(|disk| disk.len() >= 2 && disk.bytes().all(|byte| byte.is_ascii_digit()));
+    let last_segment_path = match (is_segment, extension.starts_with('Z')) {
+        (true, true) => path.with_extension("ZIP"),
+        (true, false) => path.with_extension("zip"),
+        (false, _) => path.to_path_buf(),
+    };
+
+    let mut segment_paths: Vec<PathBuf> = (1..)
+        .map(|segment_number| segment_path(&last_segment_path, segment_number))
+        .take_while(|segment_path| segment_path.is_file())
+        .collect();
+
+    segment_paths.push(last_segment_path);
+    segment_paths
+}
+
+/// Cuts the archive at `path` into the segments of a split archive. The disks starting at the
+/// given offsets except the last one are moved to `.z01`, `.z02`, ... files next to the archive
+/// and the archive keeps the last disk. Segments left over from an earlier archive with more
+/// disks are removed.
+pub fn write_segments(path: &Path, disk_start_offsets: &[u64]) -> io::Result<()> {
+    let mut archive = File::open(path)?;
+    let archive_len = archive.metadata()?.len();
+    let last_disk_start_offset = disk_start_offsets.last().copied().unwrap_or(0);
+
+    for (index, disk_offsets) in disk_start_offsets.windows(2).enumerate() {
+        let mut segment = File::create(segment_path(path, index + 1))?;
+
+        archive.seek(SeekFrom::Start(disk_offsets[0]))?;
+        io::copy(
+            &mut (&mut archive).take(disk_offsets[1] - disk_offsets[0]),
+            &mut segment,
+        )?;
+    }
+
+    if last_disk_start_offset > 0 {
+        let mut last_segment_path = OsString::from(path);
+        last_segment_path.push(".tmp");
+
+        let mut last_segment = File::create(&last_segment_path)?;
+
+        archive.seek(SeekFrom::Start(last_disk_start_offset))?;
+        io::copy(
+            &mut (&mut archive).take(archive_len - last_disk_start_offset),
+            &mut last_segment,
+        )?;
+
+        // Windows cannot replace a file that is still open
+        drop(archive);
+        std::fs::rename(&last_segment_path, path)?;
+    }
+
+    (disk_start_offsets.len()..)
+        .map(|segment_number| segment_path(path, segment_number))
+        .take_while(|segment_path| segment_path.is_file())
+        .try_for_each(std::fs::remove_file)
+}
+
+// Path of the segment with the given number. Segments are numbered from 1 while disks start at 0.
+// The case of the extension follows the `.zip` file.
+fn segment_path(last_segment_path: &Path, segment_number: usize) -> PathBuf {
+    let uppercase = last_segment_path
+        .extension()
+        .is_some_and(|extension| extension.to_string_lossy().starts_with('Z'));
+
+    last_segment_path.with_extension(if uppercase {
+        format!("Z{:02}", segment_number)
+    } else {
+        format!("z{:02}", segment_number)
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_split_archive() {
+        let dir = tempfile::tempdir().unwrap();
+        // End of central directory record on the third disk
+        let mut last_segment = b"789".to_vec();
+        last_segment.extend_from_slice(&[
+            0x50, 0x4B, 0x05, 0x06, 0x02, 0x00, 0x02, 0x00, 0x01, 0x00, 0x01, 0x00, 0x2E, 0x00,
+            0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00,
+        ]);
+
+        std::fs::write(dir.path().join("archive.z01"), b"0123").unwrap();
+        std::fs::write(dir.path().join("archive.z02"), b"456").unwrap();
+        std::fs::write(dir.path().join("archive.zip"), &last_segment).unwrap();
+
+        assert_eq!(
+            segment_paths(&dir.path().join("archive.z02")),
+            vec![
+                dir.path().join("archive.z01"),
+                dir.path().join("archive.z02"),
+                dir.path().join("archive.zip"),
+            ]
+        );
+
+        let mut split_archive = SplitArchive::open(&dir.path().join("archive.zip")).unwrap();
+
+        assert!(split_archive.is_split());
+        assert_eq!(split_archive.disk_start_offsets(), &[0, 4, 7]);
+
+        let mut contents = Vec::new();
+        split_archive.read_to_end(&mut contents).unwrap();
+
+        assert_eq!(&contents[..10], b"0123456789");
+        assert_eq!(&contents[10..], &last_segment[3..]);
+
+        split_archive.seek(SeekFrom::Start(5)).unwrap();
+        let mut buf = [0u8; 3];
+        split_archive.read_exact(&mut buf).unwrap();
+
+        assert_eq!(&buf, b"567");
+
+        // An archive on a single disk does not include the segments lying next to it
+        last_segment[7..11].copy_from_slice(&[0x00, 0x00, 0x00, 0x00]);
+        std::fs::write(dir.path().join("archive.zip"), &last_segment).unwrap();
+
+        let split_archive = SplitArchive::open(&dir.path().join("archive.zip")).unwrap();
+
+        assert!(!split_archive.is_split());
+        assert_eq!(split_archive.disk_start_offsets(), &[0]);
+    }
+
+    #[test]
+    fn test_write_segments() {
+        let dir = tempfile::tempdir().unwrap();
+        let zip_path = dir.path().join("archive.zip");
+
+        std::fs::write(&zip_path, b"0123456789").unwrap();
+        // Left over from an earlier archive with more segments
+        std::fs::write(dir.path().join("archive.z03"), b"stale").unwrap();
+
+        write_segments(&zip_path, &[0, 4, 7]).unwrap();
+
+        assert_eq!(
+            std::fs::read(dir.path().join("archive.z01")).unwrap(),
+            b"0123"
+        );
+        assert_eq!(
+            std::fs::read(dir.path().join("archive.z02")).unwrap(),
+            b"456"
+        );
+        assert_eq!(std::fs::read(&zip_path).unwrap(), b"789");
+        assert!(!dir.path().join("archive.z03").exists());
+    }
+}
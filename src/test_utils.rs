@@ -0,0 +1,216 @@
+use std::io::Write;
+
+use byteorder::{LittleEndian, WriteBytesExt};
+use crc::{Crc, CRC_32_ISO_HDLC};
+use flate2::write::DeflateEncoder;
+use flate2::Compression;
+
+use crate::zip_crypto::{ZipCrypto, ZIP_CRYPTO_RANDOM_BYTES_LEN};
+
+const LOCAL_FILE_HEADER_SIGN: u32 = 0x04034b50;
+const CENTRAL_DIR_SIGN: u32 = 0x02014b50;
+const EOF_CENTRAL_DIR_SIGN: u32 = 0x06054b50;
+
+const UNIX_HOST: u8 = 3;
+const ZIP_VERSION: u8 = 0x14;
+
+const DEFLATE_COMPRESSION_METHOD: u16 = 0x08;
+
+/// An entry of an archive crafted by `build_archive`. Entries are stored without compression
+/// unless `deflated` is called and they are only encrypted with Zip Crypto when a password is
+/// set.
+pub struct TestEntry {
+    pub name: Vec<u8>,
+    pub data: Vec<u8>,
+    pub external_attributes: u32,
+    pub flags: u16,
+    pub compression_method: u16,
+    pub password: Option<String>,
+    pub date: u16,
+    pub time: u16,
+    // Data written as it is under the compression method, already compressed
+    pub compressed_data: Option<Vec<u8>>,
+}
+
+impl TestEntry {
+    pub fn file(name: &str, data: &[u8]) -> Self {
+        Self {
+            name: name.as_bytes().to_vec(),
+            data: data.to_vec(),
+            external_attributes: 0o100644 << 16,
+            flags: 0,
+            compression_method: 0,
+            password: None,
+            date: 0,
+            time: 0,
+            compressed_data: None,
+        }
+    }
+
+    pub fn dir(name: &str) -> Self {
+        Self {
+            name: name.as_bytes().to_vec(),
+            data: Vec::new(),
+            external_attributes: 0o040755 << 16,
+            flags: 0,
+            compression_method: 0,
+            password: None,
+            date: 0,
+            time: 0,
+            compressed_data: None,
+        }
+    }
+
+    pub fn deflated(mut self) -> Self {
+        self.compression_method = DEFLATE_COMPRESSION_METHOD;
+        self
+    }
+
+    pub fn encrypted(mut self, password: &str) -> Self {
+        self.password = Some(password.to_string());
+        self.flags |= 0x0001;
+        self
+    }
+
+    pub fn with_date_time(mut self, date: u16, time: u16) -> Self {
+        self.date = date;
+        self.time = time;
+        self
+    }
+
+    pub fn with_external_attributes(mut self, external_attributes: u32) -> Self {
+        self.external_attributes = external_attributes;
+        self
+    }
+
+    #[cfg(any(feature = "bzip2", feature = "lzma", feature = "zstd", feature = "xz"))]
+    pub fn compressed(mut self, compression_method: u16, compressed_data: Vec<u8>) -> Self {
+        self.compression_method = compression_method;
+        self.compressed_data = Some(compressed_data);
+        self
+    }
+}
+
+/// Builds an in-memory ZIP archive containing the given entries in order.
+pub fn build_archive(entries: &[TestEntry]) -> Vec<u8> {
+    let crc = Crc::<u32>::new(&CRC_32_ISO_HDLC);
+    let mut archive = Vec::new();
+    let mut central_dir = Vec::new();
+
+    for entry in entries {
+        let offset = archive.len() as u32;
+        let crc32 = crc.checksum(&entry.data);
+        let size = entry.data.len() as u32;
+        let compressed_data = if let Some(compressed_data) = &entry.compressed_data {
+            compressed_data.clone()
+        } else if entry.compression_method == DEFLATE_COMPRESSION_METHOD {
+            let mut encoder = DeflateEncoder::new(Vec::new(), Compression::default());
+            encoder.write_all(&entry.data).unwrap();
+            encoder.finish().unwrap()
+        } else {
+            entry.data.clone()
+        };
+        let compressed_data = match &entry.password {
+            Some(password) => encrypt(password, crc32, &compressed_data),
+            None => compressed_data,
+        };
+        let compressed_size = compressed_data.len() as u32;
+
+        archive
+            .write_u32::<LittleEndian>(LOCAL_FILE_HEADER_SIGN)
+            .unwrap();
+        archive
+            .write_u16::<LittleEndian>(ZIP_VERSION as u16)
+            .unwrap();
+        archive.write_u16::<LittleEndian>(entry.flags).unwrap();
+        archive
+            .write_u16::<LittleEndian>(entry.compression_method)
+            .unwrap();
+        archive.write_u16::<LittleEndian>(entry.time).unwrap();
+        archive.write_u16::<LittleEndian>(entry.date).unwrap();
+        archive.write_u32::<LittleEndian>(crc32).unwrap();
+        archive.write_u32::<LittleEndian>(compressed_size).unwrap();
+        archive.write_u32::<LittleEndian>(size).unwrap();
+        archive
+            .write_u16::<LittleEndian>(entry.name.len() as u16)
+            .unwrap();
+        archive.write_u16::<LittleEndian>(0).unwrap();
+        archive.extend_from_slice(&entry.name);
+        archive.extend_from_slice(&compressed_data);
+
+        central_dir
+            .write_u32::<LittleEndian>(CENTRAL_DIR_SIGN)
+            .unwrap();
+        central_dir.push(ZIP_VERSION);
+        central_dir.push(UNIX_HOST);
+        central_dir
+            .write_u16::<LittleEndian>(ZIP_VERSION as u16)
+            .unwrap();
+        central_dir.write_u16::<LittleEndian>(entry.flags).unwrap();
+        central_dir
+            .write_u16::<LittleEndian>(entry.compression_method)
+            .unwrap();
+        central_dir.write_u16::<LittleEndian>(entry.time).unwrap();
+        central_dir.write_u16::<LittleEndian>(entry.date).unwrap();
+        central_dir.write_u32::<LittleEndian>(crc32).unwrap();
+        central_dir
+            .write_u32::<LittleEndian>(compressed_size)
+            .unwrap();
+        central_dir.write_u32::<LittleEndian>(size).unwrap();
+        central_dir
+            .write_u16::<LittleEndian>(entry.name.len() as u16)
+            .unwrap();
+        central_dir.write_u16::<LittleEndian>(0).unwrap();
+        central_dir.write_u16::<LittleEndian>(0).unwrap();
+        central_dir.write_u16::<LittleEndian>(0).unwrap();
+        central_dir.write_u16::<LittleEndian>(0).unwrap();
+        central_dir
+            .write_u32::<LittleEndian>(entry.external_attributes)
+            .unwrap();
+        central_dir.write_u32::<LittleEndian>(offset).unwrap();
+        central_dir.extend_from_slice(&entry.name);
+    }
+
+    let central_dir_offset = archive.len() as u32;
+    archive.extend_from_slice(&central_dir);
+
+    archive
+        .write_u32::<LittleEndian>(EOF_CENTRAL_DIR_SIGN)
+        .unwrap();
+    archive.write_u16::<LittleEndian>(0).unwrap();
+    archive.write_u16::<LittleEndian>(0).unwrap();
+    archive
+        .write_u16::<LittleEndian>(entries.len() as u16)
+        .unwrap();
+    archive
+        .write_u16::<LittleEndian>(entries.len() as u16)
+        .unwrap();
+    archive
+        .write_u32::<LittleEndian>(central_dir.len() as u32)
+        .unwrap();
+    archive
+        .write_u32::<LittleEndian>(central_dir_offset)
+        .unwrap();
+    archive.write_u16::<LittleEndian>(0).unwrap();
+
+    archive
+}
+
+/// Encrypts the given data with Zip Crypto prepending the encryption header whose last byte is
+/// the high order byte of the CRC-32 checksum.
+pub fn encrypt(password: &str, crc32: u32, data: &[u8]) -> Vec<u8> {
+    let mut zip_crypto = ZipCrypto::new();
+
+    password
+        .bytes()
+        .for_each(|byte| zip_crypto.update_keys(byte));
+
+    let mut encryption_header = [0xA5u8; ZIP_CRYPTO_RANDOM_BYTES_LEN];
+    encryption_header[ZIP_CRYPTO_RANDOM_BYTES_LEN - 1] = (crc32 >> 24) as u8;
+
+    encryption_header
+        .iter()
+        .chain(data.iter())
+        .map(|byte| zip_crypto.encrypt_byte(*byte))
+        .collect()
+}
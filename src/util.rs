@@ -1,6 +1,23 @@
+use std::borrow::Cow;
 use std::error::Error;
+use std::ffi::OsStr;
 use std::fmt::Display;
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
+
+use globset::GlobBuilder;
+use ignore::WalkBuilder;
+
+// Device names Windows reserves in every directory, with or without an extension
+const RESERVED_FILE_NAMES: [&str; 22] = [
+    "CON", "PRN", "AUX", "NUL", "COM1", "COM2", "COM3", "COM4", "COM5", "COM6", "COM7", "COM8",
+    "COM9", "LPT1", "LPT2", "LPT3", "LPT4", "LPT5", "LPT6", "LPT7", "LPT8", "LPT9",
+];
+const RESERVED_CHARS: [char; 7] = ['<', '>', ':', '"', '|', '?', '*'];
+// Longest name in bytes most filesystems of Linux, macOS and Windows accept for a component
+const MAX_PORTABLE_NAME_LEN: usize = 255;
+// Longest extension kept when a portable name is shortened
+const MAX_KEPT_EXTENSION_LEN: usize = 16;
+const GLOB_CHARS: [char; 4] = ['*', '?', '[', '{'];
 
 #[derive(Debug, PartialEq, Eq)]
 pub enum PathError {
@@ -8,6 +25,7 @@ pub enum PathError {
     ParentPathGiven,
     CurrentPathGiven,
     EnvironmentError(String),
+    InvalidGlob(String),
 }
 
 impl Display for PathError {
@@ -24,6 +42,7 @@ impl Display for PathError {
                 error_msg
             ),
             PathError::EmptyPath => write!(f, "Archive file path cannot be empty"),
+            PathError::InvalidGlob(error_msg) => write!(f, "Invalid glob pattern.\n{}", error_msg),
         }
     }
 }
@@ -48,7 +67,7 @@ pub fn get_file_path(path: PathBuf) -> Result<PathBuf, PathError> {
     if path.is_relative() {
         let current_dir =
             std::env::current_dir().map_err(|err| PathError::EnvironmentError(err.to_string()))?;
-        let mut absolute_path = PathBuf::from(current_dir);
+        let mut absolute_path = current_dir;
 
         absolute_path.push(path);
 
@@ -57,3 +76,329 @@ pub fn get_file_path(path: PathBuf) -> Result<PathBuf, PathError> {
         Ok(path)
     }
 }
+
+/// Splits the arguments of `--extract` into the archives and the glob patterns of the entries to
+/// extract. When patterns are given after `--`, every argument is an archive. Otherwise the first
+/// argument and the ones following it ending in `.zip`, globs like `backups/*.zip` included, are
+/// the archives and the rest are the patterns, whether a file has their name or not.
+pub fn split_archive_args(
+    mut args: Vec<PathBuf>,
+    separated_patterns: Vec<PathBuf>,
+) -> (Vec<PathBuf>, Vec<PathBuf>) {
+    if !separated_patterns.is_empty() {
+        return (args, separated_patterns);
+    }
+
+    let archive_count = 1 + args
+        .iter()
+        .skip(1)
+        .take_while(|arg| {
+            arg.extension()
+                .is_some_and(|extension| extension.eq_ignore_ascii_case("zip"))
+        })
+        .count();
+    let patterns = args.split_off(archive_count.min(args.len()));
+
+    (args, patterns)
+}
+
+/// Expands a glob like `backups/*.zip` to the files matching it, sorted by their paths. Paths
+/// without glob characters are returned as they are, whether the file exists or not.
+pub fn expand_glob(path: PathBuf) -> Result<Vec<PathBuf>, PathError> {
+    let pattern = path.to_string_lossy();
+
+    if !pattern.contains(GLOB_CHARS) {
+        return Ok(vec![path]);
+    }
+
+    let matcher = GlobBuilder::new(&pattern)
+        .literal_separator(true)
+        .build()
+        .map_err(|err| PathError::InvalidGlob(err.to_string()))?
+        .compile_matcher();
+    // The walk starts from the components before the first one with glob characters and only
+    // goes as deep as the pattern unless it has a `**`
+    let base_path: PathBuf = path
+        .components()
+        .take_while(|component| !component.as_os_str().to_string_lossy().contains(GLOB_CHARS))
+        .collect();
+    let max_depth = (!pattern.contains("**"))
+        .then(|| path.components().count() - base_path.components().count());
+    let walk_root = if base_path.as_os_str().is_empty() {
+        Path::new(".")
+    } else {
+        &base_path
+    };
+
+    let mut paths: Vec<PathBuf> = WalkBuilder::new(walk_root)
+        .standard_filters(false)
+        .max_depth(max_depth)
+        .build()
+        .filter_map(Result::ok)
+        .filter(|entry| {
+            entry
+                .file_type()
+                .is_some_and(|file_type| file_type.is_file())
+        })
+        .map(|entry| match entry.path().strip_prefix(".") {
+            Ok(path) if base_path.as_os_str().is_empty() => path.to_path_buf(),
+            _ => entry.into_path(),
+        })
+        .filter(|path| matcher.is_match(path))
+        .collect();
+
+    paths.sort();
+
+    Ok(paths)
+}
+
+/// Returns the absolute path in its `\\?\` form on Windows so that it may be longer than 260
+/// characters. Windows doesn't normalize these paths, so `.` and `..` are resolved here and the
+/// separators become backslashes. Relative paths and other hosts get the path back unchanged.
+pub fn long_path(path: &Path) -> PathBuf {
+    #[cfg(windows)]
+    {
+        use std::ffi::OsString;
+        use std::path::{Component, Prefix};
+
+        let mut components = path.components();
+        let mut long_path = match components.next() {
+            Some(Component::Prefix(prefix)) => match prefix.kind() {
+                Prefix::Disk(_) => {
+                    let mut long_path = OsString::from(r"\\?\");
+                    long_path.push(prefix.as_os_str());
+                    PathBuf::from(long_path)
+                }
+                Prefix::UNC(server, share) => {
+                    let mut long_path = OsString::from(r"\\?\UNC\");
+                    long_path.push(server);
+                    long_path.push(r"\");
+                    long_path.push(share);
+                    PathBuf::from(long_path)
+                }
+                // Already verbatim or a device path
+                _ => return path.to_path_buf(),
+            },
+            _ => return path.to_path_buf(),
+        };
+
+        for component in components {
+            match component {
+                Component::RootDir => long_path.push(r"\"),
+                Component::ParentDir => {
+                    long_path.pop();
+                }
+                Component::Normal(name) => long_path.push(name),
+                Component::Prefix(_) | Component::CurDir => (),
+            }
+        }
+
+        long_path
+    }
+
+    #[cfg(not(windows))]
+    path.to_path_buf()
+}
+
+/// Splits the name of an entry at its `/` separators.
+pub fn split_file_name(file_name: &OsStr) -> impl Iterator<Item = &OsStr> {
+    file_name
+        .as_encoded_bytes()
+        .split(|&byte| byte == b'/')
+        // SAFETY: splitting at an ASCII character leaves valid encoded bytes on both sides
+        .map(|component| unsafe { OsStr::from_encoded_bytes_unchecked(component) })
+}
+
+/// Renames a single path component so that Windows can create it. Reserved and control characters
+/// become `_`, as do trailing dots and spaces which Windows drops, and reserved device names like
+/// `CON` or `aux.txt` get a `_` prefix. Names Windows accepts are returned as they are.
+pub fn sanitize_file_name(name: &str) -> Cow<'_, str> {
+    let stem = name
+        .split('.')
+        .next()
+        .unwrap_or_default()
+        .trim_end_matches(' ');
+    let is_reserved = RESERVED_FILE_NAMES
+        .iter()
+        .any(|reserved_name| reserved_name.eq_ignore_ascii_case(stem));
+    let trailing_len = name.len() - name.trim_end_matches(['.', ' ']).len();
+    let has_invalid_char = name
+        .chars()
+        .any(|char| char.is_ascii_control() || RESERVED_CHARS.contains(&char));
+
+    if !is_reserved && trailing_len == 0 && !has_invalid_char {
+        return Cow::Borrowed(name);
+    }
+
+    let mut sanitized_name = String::with_capacity(name.len() + 1);
+
+    if is_reserved {
+        sanitized_name.push('_');
+    }
+
+    sanitized_name.extend(name[..name.len() - trailing_len].chars().map(|char| {
+        if char.is_ascii_control() || RESERVED_CHARS.contains(&char) {
+            '_'
+        } else {
+            char
+        }
+    }));
+    sanitized_name.extend(std::iter::repeat_n('_', trailing_len));
+
+    Cow::Owned(sanitized_name)
+}
+
+/// Renames a single path component so that Linux, macOS and Windows can all create it. On top of
+/// [`sanitize_file_name`], backslashes become `_` and names longer than 255 bytes are shortened,
+/// keeping their extension.
+pub fn portable_file_name(name: &str) -> Cow<'_, str> {
+    let sanitized_name = sanitize_file_name(name);
+
+    if !sanitized_name.contains('\\') && sanitized_name.len() <= MAX_PORTABLE_NAME_LEN {
+        return sanitized_name;
+    }
+
+    let portable_name = sanitized_name.replace('\\', "_");
+
+    if portable_name.len() <= MAX_PORTABLE_NAME_LEN {
+        return Cow::Owned(portable_name);
+    }
+
+    let extension = portable_name
+        .rfind('.')
+        .filter(|&dot_index| dot_index > 0)
+        .map(|dot_index| &portable_name[dot_index..])
+        .filter(|extension| extension.len() <= MAX_KEPT_EXTENSION_LEN)
+        .unwrap_or_default();
+    let mut stem_len = MAX_PORTABLE_NAME_LEN - extension.len();
+
+    while !portable_name.is_char_boundary(stem_len) {
+        stem_len -= 1;
+    }
+
+    Cow::Owned(format!("{}{}", &portable_name[..stem_len], extension))
+}
+
+/// Returns whether the process runs with root privileges. Always false on hosts other than Unix.
+pub fn is_root() -> bool {
+    #[cfg(unix)]
+    {
+        // SAFETY: geteuid has no preconditions and cannot fail
+        unsafe { libc::geteuid() == 0 }
+    }
+
+    #[cfg(not(unix))]
+    {
+        false
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_split_archive_args() {
+        let paths = |paths: &[&str]| paths.iter().map(PathBuf::from).collect::<Vec<_>>();
+
+        // Existing files are patterns too unless they end in .zip
+        assert_eq!(
+            split_archive_args(
+                paths(&[
+                    "a.zip",
+                    "b.ZIP",
+                    "backups/*.zip",
+                    "Cargo.toml",
+                    "*.txt",
+                    "c.zip"
+                ]),
+                Vec::new()
+            ),
+            (
+                paths(&["a.zip", "b.ZIP", "backups/*.zip"]),
+                paths(&["Cargo.toml", "*.txt", "c.zip"])
+            )
+        );
+        assert_eq!(
+            split_archive_args(paths(&["archive"]), Vec::new()),
+            (paths(&["archive"]), Vec::new())
+        );
+
+        // Every argument is an archive when the patterns are given after `--`
+        assert_eq!(
+            split_archive_args(paths(&["a.zip", "backup"]), paths(&["*.zip"])),
+            (paths(&["a.zip", "backup"]), paths(&["*.zip"]))
+        );
+    }
+
+    #[test]
+    fn test_sanitize_file_name() {
+        assert_eq!(sanitize_file_name("notes.txt"), "notes.txt");
+        assert!(matches!(sanitize_file_name("CONSOLE"), Cow::Borrowed(_)));
+        assert_eq!(sanitize_file_name("CON"), "_CON");
+        assert_eq!(sanitize_file_name("aux.txt"), "_aux.txt");
+        assert_eq!(sanitize_file_name("Com1 .tar.gz"), "_Com1 .tar.gz");
+        assert_eq!(sanitize_file_name("a:b*c?.txt"), "a_b_c_.txt");
+        assert_eq!(sanitize_file_name("tab\there. "), "tab_here__");
+    }
+
+    #[test]
+    fn test_expand_glob() {
+        let dir = tempfile::tempdir().unwrap();
+
+        std::fs::create_dir(dir.path().join("old")).unwrap();
+
+        for name in ["b.zip", "a.zip", "notes.txt", "old/c.zip"] {
+            std::fs::write(dir.path().join(name), b"").unwrap();
+        }
+
+        assert_eq!(
+            expand_glob(dir.path().join("*.zip")).unwrap(),
+            vec![dir.path().join("a.zip"), dir.path().join("b.zip")]
+        );
+        assert_eq!(expand_glob(dir.path().join("**/*.zip")).unwrap().len(), 3);
+        assert_eq!(
+            expand_glob(dir.path().join("missing.zip")).unwrap(),
+            vec![dir.path().join("missing.zip")]
+        );
+        assert!(expand_glob(dir.path().join("*.{zip")).is_err());
+    }
+
+    #[test]
+    fn test_portable_file_name() {
+        assert!(matches!(portable_file_name("notes.txt"), Cow::Borrowed(_)));
+        assert_eq!(portable_file_name("a\\b:c.txt"), "a_b_c.txt");
+        assert_eq!(portable_file_name("nul"), "_nul");
+
+        let long_name = format!("a{}.tar.gz", "é".repeat(200));
+        let portable_name = portable_file_name(&long_name);
+
+        assert_eq!(portable_name.len(), 254);
+        assert!(portable_name.ends_with("éé.gz"));
+        assert_eq!(
+            portable_file_name(&"a".repeat(300)).into_owned(),
+            "a".repeat(255)
+        );
+    }
+
+    #[cfg(windows)]
+    #[test]
+    fn test_long_path() {
+        assert_eq!(
+            long_path(Path::new(r"C:\out/docs\.\..\a/b.txt")),
+            PathBuf::from(r"\\?\C:\out\a\b.txt")
+        );
+        assert_eq!(
+            long_path(Path::new(r"\\server\share\out/a.txt")),
+            PathBuf::from(r"\\?\UNC\server\share\out\a.txt")
+        );
+        assert_eq!(
+            long_path(Path::new(r"\\?\C:\out\a.txt")),
+            PathBuf::from(r"\\?\C:\out\a.txt")
+        );
+        assert_eq!(
+            long_path(Path::new("out/a.txt")),
+            PathBuf::from("out/a.txt")
+        );
+    }
+}
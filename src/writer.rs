@@ -0,0 +1,1878 @@
+use std::error::Error;
+use std::fmt::Display;
+use std::io::{Read, Seek, SeekFrom, Write};
+
+use byteorder::{ByteOrder, LittleEndian, WriteBytesExt};
+use crc::{Crc, CRC_32_ISO_HDLC};
+use flate2::write::DeflateEncoder;
+use flate2::Compression;
+
+use crate::aes::{AesError, AesExtraField, AesStrength, AesVendorVersion, AesWriter};
+use crate::date_time::ZipDateTime;
+use crate::extra_field::{
+    ExtraField, ExtraFields, AES_EXTRA_FIELD_ID, ALIGNMENT_EXTRA_FIELD_ID, EXTRA_FIELD_HEADER_SIZE,
+    UNICODE_PATH_EXTRA_FIELD_ID, UNICODE_PATH_EXTRA_FIELD_VERSION, ZIP64_EXTRA_FIELD_ID,
+};
+use crate::headers::{
+    CompressionMethod, DeflateCompressionMode, ZipFile, CENTRAL_DIR_SIGN, EOF_CENTRAL_DIR_SIGN,
+    LOCAL_FILE_HEADER_SIGN, MAX_COMMENT_LEN, ZIP64_EOF_CENTRAL_DIR_LOCATOR_SIGN,
+    ZIP64_EOF_CENTRAL_DIR_SIGN,
+};
+use crate::zip_crypto::{ZipCryptoError, ZipCryptoWriter};
+use crate::Crc32;
+
+// ZIP 2.0 is enough for stored and deflated entries
+const ZIP_VERSION: u16 = 0x14;
+// ZIP 4.5 introduced the ZIP64 extensions
+const ZIP64_VERSION: u16 = 0x2D;
+// ZIP 4.6 introduced bzip2 compression
+const BZIP2_VERSION: u16 = 0x2E;
+// WinZip AES encrypted entries always need ZIP 5.1
+const AES_VERSION: u16 = 0x33;
+// ZIP 6.3 introduced LZMA, Zstandard and XZ compression
+const LZMA_VERSION: u16 = 0x3F;
+const ZSTD_VERSION: u16 = 0x3F;
+const XZ_VERSION: u16 = 0x3F;
+const STORED_COMPRESSION_METHOD: u16 = 0x00;
+const DEFLATE_COMPRESSION_METHOD: u16 = 0x08;
+const BZIP2_COMPRESSION_METHOD: u16 = 0x0C;
+const LZMA_COMPRESSION_METHOD: u16 = 0x0E;
+const ZSTD_COMPRESSION_METHOD: u16 = 0x5D;
+const XZ_COMPRESSION_METHOD: u16 = 0x5F;
+// The real compression method of AES encrypted entries is kept in their AES extra field
+const AES_COMPRESSION_METHOD: u16 = 0x63;
+// Fields that do not fit are set to these values and the real values are kept in ZIP64 records
+const ZIP64_SIZE_MARKER: u32 = 0xFFFFFFFF;
+const ZIP64_ENTRY_COUNT_MARKER: u16 = 0xFFFF;
+// The local ZIP64 extra field holds the uncompressed and compressed sizes
+const LOCAL_ZIP64_EXTRA_FIELD_DATA_SIZE: usize = 16;
+const ZIP64_EOF_CENTRAL_DIR_SIZE: u64 = 56;
+// Bit 3 of the general purpose bit flag means the CRC-32 and sizes follow the data
+const DATA_DESCRIPTOR_FLAG: u16 = 1 << 3;
+const DATA_DESCRIPTOR_SIGN: u32 = 0x08074b50;
+// Bit 0 of the general purpose bit flag marks encrypted entries
+const ENCRYPTED_FLAG: u16 = 1;
+const MS_DOS_DIRECTORY_ATTRIBUTE: u32 = 0x10;
+const MS_DOS_HOST: u8 = 0;
+const UNIX_HOST: u8 = 3;
+// Bit 11 of the general purpose bit flag marks names encoded in UTF-8
+const UTF8_FILE_NAME_FLAG: u16 = 1 << 11;
+const MAX_FILE_NAME_LEN: usize = 0xFFFF;
+// Offset of the CRC-32 field in the local file header. It is followed by the compressed and
+// uncompressed sizes which are patched together after the data is written.
+const LOCAL_FILE_HEADER_CRC32_OFFSET: u64 = 14;
+const MIN_LOCAL_FILE_HEADER_SIZE: usize = 30;
+const MIN_CENTRAL_DIR_SIZE: usize = 46;
+const MIN_EOF_CENTRAL_DIR_SIZE: u64 = 22;
+const ZIP64_EOF_CENTRAL_DIR_LOCATOR_SIZE: u64 = 20;
+// The first segment of a split archive starts with this signature. Archives that fit in a single
+// segment get the temporary spanning marker instead.
+const SPLIT_SIGN: u32 = 0x08074b50;
+const SINGLE_SEGMENT_SPLIT_SIGN: u32 = 0x30304b50;
+/// Smallest segment size of split archives, the same as the minimum of Info-ZIP.
+pub const MIN_SPLIT_SIZE: u64 = 64 * 1024;
+// Disk numbers are 16 bits wide in the end of central directory record and 0xFFFF marks ZIP64
+const MAX_DISK_COUNT: usize = 0xFFFF;
+const FILE_READ_WRITE_BUFFER_SIZE: usize = 4096;
+
+#[derive(Debug, PartialEq, Eq)]
+pub enum WriteError {
+    IOError(String),
+    FileNameTooLong(usize),
+    FileTooLarge(String),
+    ArchiveTooLarge,
+    CommentTooLong(usize),
+    AesError(AesError),
+    ZipCryptoError(ZipCryptoError),
+    SplitSizeTooSmall(u64),
+    TooManyDisks,
+    UnsupportedCompression(CompressionMethod),
+    EntryNotFound(usize),
+}
+
+impl Display for WriteError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            WriteError::IOError(error_msg) => write!(
+                f,
+                "An I/O error occurred while writing the zip file. Message: {}",
+                error_msg
+            ),
+            WriteError::FileNameTooLong(len) => write!(
+                f,
+                "File name cannot be longer than {} bytes. Given file name length: {}",
+                MAX_FILE_NAME_LEN, len
+            ),
+            WriteError::FileTooLarge(file_name) => write!(
+                f,
+                "\"{}\" is larger than 4 GB but it was not added as a large file",
+                file_name
+            ),
+            WriteError::ArchiveTooLarge => write!(
+                f,
+                "The archive is larger than 4 GB which requires ZIP64 extensions"
+            ),
+            WriteError::CommentTooLong(len) => write!(
+                f,
+                "Archive comment cannot be longer than {} bytes. Given comment length: {}",
+                MAX_COMMENT_LEN, len
+            ),
+            WriteError::AesError(err) => write!(f, "{}", err),
+            WriteError::ZipCryptoError(err) => write!(f, "{}", err),
+            WriteError::SplitSizeTooSmall(split_size) => write!(
+                f,
+                "Split archive segments must be at least {} bytes. Given segment size: {}",
+                MIN_SPLIT_SIZE, split_size
+            ),
+            WriteError::TooManyDisks => write!(
+                f,
+                "The archive cannot be split into more than {} segments",
+                MAX_DISK_COUNT - 1
+            ),
+            WriteError::UnsupportedCompression(compression_method) => write!(
+                f,
+                "Entries can only be read with {} compression, not written",
+                compression_method
+            ),
+            WriteError::EntryNotFound(index) => {
+                write!(f, "The archive has no entry at index {}", index)
+            }
+        }
+    }
+}
+
+impl Error for WriteError {}
+
+/// Options applied to a single entry written by `ZipWriter`.
+#[derive(Debug, Clone, Copy)]
+pub struct EntryOptions {
+    pub compression_method: CompressionMethod,
+    pub last_modified: ZipDateTime,
+    /// Unix mode of the source file including the file type bits. When it is set the entry is
+    /// marked as made on Unix and the mode is kept in the upper 16 bits of the external
+    /// attributes, so the permissions can be restored on extraction.
+    pub unix_mode: Option<u32>,
+    /// Also write non-ASCII names to the Info-ZIP Unicode Path extra field for the tools that
+    /// ignore the UTF-8 flag of the general purpose bit flag.
+    pub unicode_path_extra_field: bool,
+    /// Reserve a ZIP64 extra field in the local file header so the entry can be 4 GB or larger.
+    /// The sizes are only known after the data is written, so entries that might not fit must be
+    /// marked up front.
+    pub large_file: bool,
+    /// Deflate level from 1 to 9 used instead of the level implied by the deflate mode of the
+    /// compression method. Zstandard uses it as its own level.
+    pub compression_level: Option<u32>,
+    /// Pad the local file header of stored entries so their data starts at a multiple of the
+    /// given number of bytes, like `zipalign`. The data can then be mapped into memory directly
+    /// from the archive.
+    pub alignment: Option<u16>,
+}
+
+impl Default for EntryOptions {
+    fn default() -> Self {
+        Self {
+            compression_method: CompressionMethod::Deflate(DeflateCompressionMode::Normal),
+            last_modified: ZipDateTime::default(),
+            unix_mode: None,
+            unicode_path_extra_field: false,
+            large_file: false,
+            compression_level: None,
+            alignment: None,
+        }
+    }
+}
+
+/// Encryption applied to the file entries written by `ZipWriter`. Directories are never
+/// encrypted.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Encryption {
+    /// WinZip AES with a 256-bit key and the given password. Entries are written as AE-2 so
+    /// their CRC-32 is not stored and the authentication code is used instead.
+    Aes256(String),
+    /// Traditional PKWARE encryption with the given password. It is weak but every unzip tool
+    /// can decrypt it.
+    ZipCrypto(String),
+}
+
+// Fields of a written entry that are repeated in its central directory record
+struct CentralDirEntry {
+    file_name: Vec<u8>,
+    extra_field: Vec<u8>,
+    general_purpose_bit_flag: u16,
+    compression_method: u16,
+    last_modified: ZipDateTime,
+    host: u8,
+    large_file: bool,
+    crc32: Crc32,
+    compressed_size: u64,
+    uncompressed_size: u64,
+    external_attributes: u32,
+    // Disk the local file header is on and its offset from the start of the disk
+    disk_number: u16,
+    offset: u64,
+}
+
+// Fields of the end of central directory records. Offsets are relative to the start of their disk.
+struct CentralDirLocation {
+    disk_number: u32,
+    central_dir_start_disk: u32,
+    disk_entry_count: u64,
+    entry_count: u64,
+    central_dir_size: u64,
+    central_dir_start_offset: u64,
+}
+
+/// Wraps an output that cannot seek like a pipe or a socket. It keeps count of the written bytes
+/// so the position can be queried with `SeekFrom::Current(0)`, any other seek fails.
+pub struct StreamingWriter<W: Write> {
+    writer: W,
+    position: u64,
+}
+
+impl<W: Write> StreamingWriter<W> {
+    pub fn new(writer: W) -> Self {
+        Self {
+            writer,
+            position: 0,
+        }
+    }
+
+    pub fn into_inner(self) -> W {
+        self.writer
+    }
+}
+
+impl<W: Write> Write for StreamingWriter<W> {
+    fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+        let written = self.writer.write(buf)?;
+        self.position += written as u64;
+
+        Ok(written)
+    }
+
+    fn flush(&mut self) -> std::io::Result<()> {
+        self.writer.flush()
+    }
+}
+
+impl<W: Write> Seek for StreamingWriter<W> {
+    fn seek(&mut self, pos: SeekFrom) -> std::io::Result<u64> {
+        match pos {
+            SeekFrom::Current(0) => Ok(self.position),
+            _ => Err(std::io::Error::new(
+                std::io::ErrorKind::Unsupported,
+                "The output does not support seeking",
+            )),
+        }
+    }
+}
+
+// Destination of the data of an entry which encrypts the data first when the archive is encrypted
+enum EntryDataWriter<'a, W: Write> {
+    Plain(&'a mut W),
+    Aes(Box<AesWriter<&'a mut W>>),
+    ZipCrypto(ZipCryptoWriter<&'a mut W>),
+}
+
+impl<'a, W: Write> EntryDataWriter<'a, W> {
+    fn finish(self) -> std::io::Result<()> {
+        match self {
+            EntryDataWriter::Plain(_) | EntryDataWriter::ZipCrypto(_) => Ok(()),
+            EntryDataWriter::Aes(aes_writer) => aes_writer.finish().map(|_| ()),
+        }
+    }
+}
+
+impl<'a, W: Write> Write for EntryDataWriter<'a, W> {
+    fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+        match self {
+            EntryDataWriter::Plain(writer) => writer.write(buf),
+            EntryDataWriter::Aes(aes_writer) => aes_writer.write(buf),
+            EntryDataWriter::ZipCrypto(zip_crypto_writer) => zip_crypto_writer.write(buf),
+        }
+    }
+
+    fn flush(&mut self) -> std::io::Result<()> {
+        match self {
+            EntryDataWriter::Plain(writer) => writer.flush(),
+            EntryDataWriter::Aes(aes_writer) => aes_writer.flush(),
+            EntryDataWriter::ZipCrypto(zip_crypto_writer) => zip_crypto_writer.flush(),
+        }
+    }
+}
+
+/// Writes a zip file entry by entry. The local file header of each entry is written before its
+/// data and patched with the CRC-32 and sizes afterwards, so the output must be seekable unless
+/// the writer is created with `streaming`. The central directory is written by `finish`.
+pub struct ZipWriter<W: Write + Seek> {
+    writer: W,
+    // Central directory records of the written entries, in the order they were written
+    central_dir_records: Vec<Vec<u8>>,
+    comment: Vec<u8>,
+    // Write the CRC-32 and sizes to a data descriptor after the data instead of patching the
+    // local file header
+    use_data_descriptors: bool,
+    encryption: Option<Encryption>,
+    // Maximum size of the disks when the archive is split
+    split_size: Option<u64>,
+    // Offsets the disks start at in the output. Headers and end records are not split, so a disk
+    // ends early when the next one would not fit.
+    disk_start_offsets: Vec<u64>,
+}
+
+impl<W: Write> ZipWriter<StreamingWriter<W>> {
+    /// Creates a writer for outputs that cannot seek. The CRC-32 and sizes of each file are
+    /// written to a data descriptor after its data instead of the local file header.
+    pub fn streaming(writer: W) -> Self {
+        Self {
+            writer: StreamingWriter::new(writer),
+            central_dir_records: Vec::new(),
+            comment: Vec::new(),
+            use_data_descriptors: true,
+            encryption: None,
+            split_size: None,
+            disk_start_offsets: vec![0],
+        }
+    }
+}
+
+impl<W: Write + Seek> ZipWriter<W> {
+    pub fn new(writer: W) -> Self {
+        Self {
+            writer,
+            central_dir_records: Vec::new(),
+            comment: Vec::new(),
+            use_data_descriptors: false,
+            encryption: None,
+            split_size: None,
+            disk_start_offsets: vec![0],
+        }
+    }
+
+    /// Creates a writer appending entries to an existing zip file. The writer starts writing at
+    /// the offset of the old central directory, which is written again by `finish` with the
+    /// given records of the existing entries followed by the new ones.
+    pub fn append(
+        mut writer: W,
+        central_dir_start_offset: u64,
+        central_dir_records: Vec<Vec<u8>>,
+    ) -> Result<Self, WriteError> {
+        writer
+            .seek(SeekFrom::Start(central_dir_start_offset))
+            .map_err(|err| WriteError::IOError(err.to_string()))?;
+
+        Ok(Self {
+            writer,
+            central_dir_records,
+            comment: Vec::new(),
+            use_data_descriptors: false,
+            encryption: None,
+            split_size: None,
+            disk_start_offsets: vec![0],
+        })
+    }
+
+    /// Sets the archive comment written to the end of central directory record.
+    pub fn set_comment(&mut self, comment: &[u8]) -> Result<(), WriteError> {
+        if comment.len() as u64 > MAX_COMMENT_LEN {
+            return Err(WriteError::CommentTooLong(comment.len()));
+        }
+
+        self.comment = comment.to_vec();
+
+        Ok(())
+    }
+
+    /// Encrypts the file entries added after this call.
+    pub fn set_encryption(&mut self, encryption: Encryption) {
+        self.encryption = Some(encryption);
+    }
+
+    /// Splits the archive into disks of at most `split_size` bytes, recording the disk of every
+    /// record along with its offset from the start of the disk. The output is then cut into
+    /// segments at the offsets returned by `finish_split`. Must be called before any entry is
+    /// added.
+    pub fn set_split_size(&mut self, split_size: u64) -> Result<(), WriteError> {
+        if split_size < MIN_SPLIT_SIZE {
+            return Err(WriteError::SplitSizeTooSmall(split_size));
+        }
+
+        self.split_size = Some(split_size);
+
+        self.writer
+            .write_u32::<LittleEndian>(SPLIT_SIGN)
+            .map_err(|err| WriteError::IOError(err.to_string()))
+    }
+
+    /// Adds a file entry with the bytes read from the given reader.
+    pub fn add_file<R>(
+        &mut self,
+        file_name: &str,
+        reader: &mut R,
+        entry_options: &EntryOptions,
+    ) -> Result<(), WriteError>
+    where
+        R: Read,
+    {
+        let (compression_method, general_purpose_bit_flag) = match entry_options.compression_method
+        {
+            CompressionMethod::NoCompression => (STORED_COMPRESSION_METHOD, 0x0000),
+            CompressionMethod::Deflate(deflate_mode) => (
+                DEFLATE_COMPRESSION_METHOD,
+                deflate_mode_bits(&deflate_mode) << 1,
+            ),
+            #[cfg(feature = "zstd")]
+            CompressionMethod::Zstd => (ZSTD_COMPRESSION_METHOD, 0x0000),
+            // Entries compressed with these are only decoded
+            #[cfg(any(feature = "bzip2", feature = "lzma", feature = "xz"))]
+            compression_method => {
+                return Err(WriteError::UnsupportedCompression(compression_method))
+            }
+        };
+        let general_purpose_bit_flag =
+            general_purpose_bit_flag | file_name_flag(file_name) | self.data_descriptor_flag();
+        let mut extra_field = file_name_extra_field(file_name, entry_options);
+
+        let (compression_method, general_purpose_bit_flag) = match &self.encryption {
+            Some(Encryption::Aes256(_)) => {
+                extra_field
+                    .extend_from_slice(&aes_extra_field(AesStrength::Aes256, compression_method));
+
+                (
+                    AES_COMPRESSION_METHOD,
+                    general_purpose_bit_flag | ENCRYPTED_FLAG,
+                )
+            }
+            // The encryption header is written before the CRC-32 is known, so the password is
+            // verified with the DOS time and the CRC-32 is written to a data descriptor
+            Some(Encryption::ZipCrypto(_)) => (
+                compression_method,
+                general_purpose_bit_flag | ENCRYPTED_FLAG | DATA_DESCRIPTOR_FLAG,
+            ),
+            None => (compression_method, general_purpose_bit_flag),
+        };
+
+        let mut local_extra_field = Vec::new();
+
+        // The ZIP64 extra field comes first so its sizes can be patched at a known offset
+        if entry_options.large_file {
+            local_extra_field.extend_from_slice(
+                &ExtraField {
+                    header_id: ZIP64_EXTRA_FIELD_ID,
+                    data: &[0u8; LOCAL_ZIP64_EXTRA_FIELD_DATA_SIZE],
+                }
+                .to_bytes(),
+            );
+        }
+
+        local_extra_field.extend_from_slice(&extra_field);
+
+        // Compressed or encrypted data cannot be used in place so only stored entries are aligned
+        if let (CompressionMethod::NoCompression, Some(alignment), None) = (
+            entry_options.compression_method,
+            entry_options.alignment,
+            &self.encryption,
+        ) {
+            let data_offset = self.stream_position()?
+                + MIN_LOCAL_FILE_HEADER_SIZE as u64
+                + file_name.len() as u64
+                + local_extra_field.len() as u64;
+
+            local_extra_field.extend_from_slice(&alignment_extra_field(data_offset, alignment));
+        }
+
+        let offset = self.write_local_file_header(
+            file_name.as_bytes(),
+            &local_extra_field,
+            general_purpose_bit_flag,
+            compression_method,
+            version_needed(compression_method, entry_options.large_file),
+            &entry_options.last_modified,
+        )?;
+        let (disk_number, disk_offset) = self.disk_position(offset);
+        let data_start = self.stream_position()?;
+        let mut data_writer = match &self.encryption {
+            Some(Encryption::Aes256(password)) => EntryDataWriter::Aes(Box::new(
+                AesWriter::new(password, AesStrength::Aes256, &mut self.writer)
+                    .map_err(WriteError::AesError)?,
+            )),
+            Some(Encryption::ZipCrypto(password)) => {
+                let (_, time) = entry_options.last_modified.to_bytes();
+
+                EntryDataWriter::ZipCrypto(
+                    ZipCryptoWriter::new(password, (time >> 8) as u8, &mut self.writer)
+                        .map_err(WriteError::ZipCryptoError)?,
+                )
+            }
+            None => EntryDataWriter::Plain(&mut self.writer),
+        };
+
+        let (crc32, uncompressed_size) = match entry_options.compression_method {
+            CompressionMethod::NoCompression => copy_and_calculate_crc32(reader, &mut data_writer)?,
+            CompressionMethod::Deflate(deflate_mode) => {
+                let compression_level = entry_options
+                    .compression_level
+                    .map(Compression::new)
+                    .unwrap_or_else(|| compression_level(&deflate_mode));
+                let mut encoder = DeflateEncoder::new(&mut data_writer, compression_level);
+                let copy_result = copy_and_calculate_crc32(reader, &mut encoder)?;
+
+                encoder
+                    .finish()
+                    .map_err(|err| WriteError::IOError(err.to_string()))?;
+
+                copy_result
+            }
+            #[cfg(feature = "zstd")]
+            CompressionMethod::Zstd => {
+                let zstd_level = entry_options
+                    .compression_level
+                    .map(|level| level as i32)
+                    .unwrap_or(zstd::DEFAULT_COMPRESSION_LEVEL);
+                let mut encoder = zstd::Encoder::new(&mut data_writer, zstd_level)
+                    .map_err(|err| WriteError::IOError(err.to_string()))?;
+                let copy_result = copy_and_calculate_crc32(reader, &mut encoder)?;
+
+                encoder
+                    .finish()
+                    .map_err(|err| WriteError::IOError(err.to_string()))?;
+
+                copy_result
+            }
+            // Rejected along with the compression method of the header above
+            #[cfg(any(feature = "bzip2", feature = "lzma", feature = "xz"))]
+            _ => unreachable!(),
+        };
+
+        data_writer
+            .finish()
+            .map_err(|err| WriteError::IOError(err.to_string()))?;
+
+        // AE-2 entries rely on the authentication code instead of the CRC-32
+        let crc32 = match self.encryption {
+            Some(Encryption::Aes256(_)) => 0,
+            _ => crc32,
+        };
+        let data_end = self.stream_position()?;
+        let compressed_size = data_end - data_start;
+        let fits_in_local_file_header = compressed_size < ZIP64_SIZE_MARKER as u64
+            && uncompressed_size < ZIP64_SIZE_MARKER as u64;
+
+        if !entry_options.large_file && !fits_in_local_file_header {
+            return Err(WriteError::FileTooLarge(file_name.to_string()));
+        }
+
+        if general_purpose_bit_flag & DATA_DESCRIPTOR_FLAG != 0 {
+            self.write_data_descriptor(
+                crc32,
+                compressed_size,
+                uncompressed_size,
+                entry_options.large_file,
+            )?;
+        } else {
+            self.patch_local_file_header(
+                offset,
+                file_name,
+                crc32,
+                compressed_size,
+                uncompressed_size,
+                entry_options.large_file,
+            )?;
+        }
+
+        self.central_dir_records
+            .push(central_dir_record(&CentralDirEntry {
+                file_name: file_name.as_bytes().to_vec(),
+                extra_field,
+                general_purpose_bit_flag,
+                compression_method,
+                last_modified: entry_options.last_modified,
+                host: host(entry_options),
+                large_file: entry_options.large_file,
+                crc32,
+                compressed_size,
+                uncompressed_size,
+                external_attributes: external_attributes(entry_options, 0),
+                disk_number,
+                offset: disk_offset,
+            }));
+
+        Ok(())
+    }
+
+    /// Adds a directory entry. A trailing slash is appended to the name if it is missing and the
+    /// compression method of the options is ignored.
+    pub fn add_directory(
+        &mut self,
+        dir_name: &str,
+        entry_options: &EntryOptions,
+    ) -> Result<(), WriteError> {
+        let mut file_name = dir_name.to_string();
+
+        if !dir_name.ends_with('/') {
+            file_name.push('/');
+        }
+
+        let general_purpose_bit_flag = file_name_flag(&file_name);
+        let extra_field = file_name_extra_field(&file_name, entry_options);
+        let offset = self.write_local_file_header(
+            file_name.as_bytes(),
+            &extra_field,
+            general_purpose_bit_flag,
+            STORED_COMPRESSION_METHOD,
+            ZIP_VERSION,
+            &entry_options.last_modified,
+        )?;
+        let (disk_number, offset) = self.disk_position(offset);
+
+        self.central_dir_records
+            .push(central_dir_record(&CentralDirEntry {
+                file_name: file_name.into_bytes(),
+                extra_field,
+                general_purpose_bit_flag,
+                compression_method: STORED_COMPRESSION_METHOD,
+                last_modified: entry_options.last_modified,
+                host: host(entry_options),
+                large_file: false,
+                crc32: 0,
+                compressed_size: 0,
+                uncompressed_size: 0,
+                external_attributes: external_attributes(entry_options, MS_DOS_DIRECTORY_ATTRIBUTE),
+                disk_number,
+                offset,
+            }));
+
+        Ok(())
+    }
+
+    /// Copies an entry of another zip file without decompressing it, storing it under the given
+    /// name. `entry_len` is the length of the local file header, the file data and the data
+    /// descriptor of the entry in the source.
+    pub fn copy_entry<R>(
+        &mut self,
+        readable: &mut R,
+        zip_file: &ZipFile,
+        entry_len: u64,
+        file_name: &str,
+    ) -> Result<(), WriteError>
+    where
+        R: Read + Seek,
+    {
+        if file_name.len() > MAX_FILE_NAME_LEN {
+            return Err(WriteError::FileNameTooLong(file_name.len()));
+        }
+
+        let (central_header_start, central_header_len) = zip_file.central_header_range();
+        let mut central_dir_bytes = vec![0u8; central_header_len as usize];
+        let mut local_file_header_bytes = vec![0u8; MIN_LOCAL_FILE_HEADER_SIZE];
+
+        readable
+            .seek(SeekFrom::Start(central_header_start))
+            .and_then(|_| readable.read_exact(&mut central_dir_bytes))
+            .and_then(|_| readable.seek(SeekFrom::Start(zip_file.offset())))
+            .and_then(|_| readable.read_exact(&mut local_file_header_bytes))
+            .map_err(|err| WriteError::IOError(err.to_string()))?;
+
+        let local_file_name_len = LittleEndian::read_u16(&local_file_header_bytes[26..28]) as u64;
+        let local_extra_field_len = LittleEndian::read_u16(&local_file_header_bytes[28..30]);
+        let mut local_extra_field = vec![0u8; local_extra_field_len as usize];
+
+        readable
+            .seek(SeekFrom::Current(local_file_name_len as i64))
+            .and_then(|_| readable.read_exact(&mut local_extra_field))
+            .map_err(|err| WriteError::IOError(err.to_string()))?;
+
+        self.start_record(
+            MIN_LOCAL_FILE_HEADER_SIZE as u64
+                + file_name.len() as u64
+                + local_extra_field_len as u64,
+        )?;
+
+        let offset = self.stream_position()?;
+        let (disk_number, offset) = self.disk_position(offset);
+        let offset = u32::try_from(offset).map_err(|_| WriteError::ArchiveTooLarge)?;
+        let data_len = entry_len.saturating_sub(
+            MIN_LOCAL_FILE_HEADER_SIZE as u64 + local_file_name_len + local_extra_field_len as u64,
+        );
+
+        // A non-ASCII name needs the UTF-8 flag even if the original name did not
+        let local_flag = LittleEndian::read_u16(&local_file_header_bytes[6..8]);
+        LittleEndian::write_u16(
+            &mut local_file_header_bytes[6..8],
+            local_flag | file_name_flag(file_name),
+        );
+
+        // Only the name changes so the rest of the local file header and the data is copied as is
+        let write_result = self
+            .writer
+            .write_all(&local_file_header_bytes[..26])
+            .and_then(|_| {
+                self.writer
+                    .write_u16::<LittleEndian>(file_name.len() as u16)
+            })
+            .and_then(|_| self.writer.write_u16::<LittleEndian>(local_extra_field_len))
+            .and_then(|_| self.writer.write_all(file_name.as_bytes()))
+            .and_then(|_| self.writer.write_all(&local_extra_field))
+            .and_then(|_| std::io::copy(&mut readable.take(data_len), &mut self.writer));
+
+        match write_result {
+            Ok(copied_len) if copied_len == data_len => (),
+            Ok(_) => {
+                return Err(WriteError::IOError(format!(
+                    "Unexpected end of file while copying \"{}\"",
+                    zip_file.file_name()
+                )))
+            }
+            Err(err) => return Err(WriteError::IOError(err.to_string())),
+        }
+
+        // Entries recovered from their local file headers have no central directory record to
+        // copy, so one is built from the local file header
+        if central_dir_bytes.is_empty() {
+            let extra_field: Vec<u8> = ExtraFields::new(&local_extra_field)
+                .filter(|extra_field| extra_field.header_id != ZIP64_EXTRA_FIELD_ID)
+                .flat_map(|extra_field| extra_field.to_bytes())
+                .collect();
+            let external_attributes = if zip_file.is_dir() {
+                MS_DOS_DIRECTORY_ATTRIBUTE
+            } else {
+                0
+            };
+
+            self.central_dir_records
+                .push(central_dir_record(&CentralDirEntry {
+                    file_name: file_name.as_bytes().to_vec(),
+                    extra_field,
+                    general_purpose_bit_flag: local_flag | file_name_flag(file_name),
+                    compression_method: LittleEndian::read_u16(&local_file_header_bytes[8..10]),
+                    last_modified: *zip_file.date_time(),
+                    host: MS_DOS_HOST,
+                    large_file: false,
+                    crc32: zip_file.crc32().get(),
+                    compressed_size: zip_file.compressed_size().get(),
+                    uncompressed_size: zip_file.uncompressed_size().get(),
+                    external_attributes,
+                    disk_number,
+                    offset: offset as u64,
+                }));
+
+            return Ok(());
+        }
+
+        let central_file_name_len = LittleEndian::read_u16(&central_dir_bytes[28..30]) as usize;
+        let mut central_dir_record = Vec::with_capacity(central_dir_bytes.len());
+
+        let central_flag = LittleEndian::read_u16(&central_dir_bytes[8..10]);
+        LittleEndian::write_u16(
+            &mut central_dir_bytes[8..10],
+            central_flag | file_name_flag(file_name),
+        );
+
+        central_dir_record.extend_from_slice(&central_dir_bytes[..28]);
+        central_dir_record.extend_from_slice(&(file_name.len() as u16).to_le_bytes());
+        central_dir_record.extend_from_slice(&central_dir_bytes[30..34]);
+        central_dir_record.extend_from_slice(&disk_number.to_le_bytes());
+        central_dir_record.extend_from_slice(&central_dir_bytes[36..42]);
+        central_dir_record.extend_from_slice(&offset.to_le_bytes());
+        central_dir_record.extend_from_slice(file_name.as_bytes());
+        central_dir_record
+            .extend_from_slice(&central_dir_bytes[MIN_CENTRAL_DIR_SIZE + central_file_name_len..]);
+
+        self.central_dir_records.push(central_dir_record);
+
+        Ok(())
+    }
+
+    /// Writes the central directory and the end of central directory record, returning the
+    /// underlying writer.
+    pub fn finish(self) -> Result<W, WriteError> {
+        self.finish_split().map(|(writer, _)| writer)
+    }
+
+    /// Finishes the archive like `finish` and also returns the offsets the disks of the archive
+    /// start at. It is a single disk starting at 0 unless the archive is split.
+    pub fn finish_split(mut self) -> Result<(W, Vec<u64>), WriteError> {
+        let central_dir_records = std::mem::take(&mut self.central_dir_records);
+        let entry_count = central_dir_records.len() as u64;
+
+        self.start_record(central_dir_records.first().map_or(0, Vec::len) as u64)?;
+
+        let central_dir_start_offset = self.stream_position()?;
+        let mut record_offsets = Vec::with_capacity(central_dir_records.len());
+
+        for central_dir_record in central_dir_records.iter() {
+            self.start_record(central_dir_record.len() as u64)?;
+            record_offsets.push(self.stream_position()?);
+            self.writer
+                .write_all(central_dir_record)
+                .map_err(|err| WriteError::IOError(err.to_string()))?;
+        }
+
+        let central_dir_end_offset = self.stream_position()?;
+        let central_dir_size = central_dir_end_offset - central_dir_start_offset;
+        let (central_dir_start_disk, central_dir_start_offset) =
+            self.disk_position(central_dir_start_offset);
+
+        // The end of central directory record is still written with the fields that do not fit
+        // set to their markers, readers then look for the ZIP64 record through the locator
+        let needs_zip64 = entry_count >= ZIP64_ENTRY_COUNT_MARKER as u64
+            || central_dir_size >= ZIP64_SIZE_MARKER as u64
+            || central_dir_start_offset >= ZIP64_SIZE_MARKER as u64;
+        let end_records_len = if needs_zip64 {
+            ZIP64_EOF_CENTRAL_DIR_SIZE + ZIP64_EOF_CENTRAL_DIR_LOCATOR_SIZE
+        } else {
+            0
+        } + MIN_EOF_CENTRAL_DIR_SIZE
+            + self.comment.len() as u64;
+
+        // Readers look for the end records in the last segment so they are kept together
+        self.start_record(end_records_len)?;
+
+        let end_records_offset = self.stream_position()?;
+        let (disk_number, end_records_offset) = self.disk_position(end_records_offset);
+        let central_dir_location = CentralDirLocation {
+            disk_number: disk_number as u32,
+            central_dir_start_disk: central_dir_start_disk as u32,
+            disk_entry_count: record_offsets
+                .iter()
+                .filter(|&&record_offset| self.disk_position(record_offset).0 == disk_number)
+                .count() as u64,
+            entry_count,
+            central_dir_size,
+            central_dir_start_offset,
+        };
+
+        if needs_zip64 {
+            write_zip64_end_of_central_dir(
+                &mut self.writer,
+                &central_dir_location,
+                end_records_offset,
+                self.disk_start_offsets.len() as u32,
+            )
+            .map_err(|err| WriteError::IOError(err.to_string()))?;
+        }
+
+        write_end_of_central_dir(&mut self.writer, &central_dir_location, &self.comment)
+            .map_err(|err| WriteError::IOError(err.to_string()))?;
+
+        // Split archives that ended up in a single segment are marked as such
+        if self.split_size.is_some() && self.disk_start_offsets.len() == 1 {
+            let archive_end = self.stream_position()?;
+
+            self.writer
+                .seek(SeekFrom::Start(0))
+                .and_then(|_| {
+                    self.writer
+                        .write_u32::<LittleEndian>(SINGLE_SEGMENT_SPLIT_SIGN)
+                })
+                .and_then(|_| self.writer.seek(SeekFrom::Start(archive_end)))
+                .map_err(|err| WriteError::IOError(err.to_string()))?;
+        }
+
+        self.writer
+            .flush()
+            .map_err(|err| WriteError::IOError(err.to_string()))?;
+
+        Ok((self.writer, self.disk_start_offsets))
+    }
+
+    // Starts the disks the data written since the last record spilled over into. When the record
+    // of the given length would not fit in the rest of the current disk, a new disk is started
+    // at the current position since headers and end records must not span disks.
+    fn start_record(&mut self, record_len: u64) -> Result<(), WriteError> {
+        let Some(split_size) = self.split_size else {
+            return Ok(());
+        };
+
+        let position = self.stream_position()?;
+        let mut disk_start_offset = self.disk_start_offsets[self.disk_start_offsets.len() - 1];
+
+        while position >= disk_start_offset + split_size {
+            disk_start_offset += split_size;
+            self.disk_start_offsets.push(disk_start_offset);
+        }
+
+        if position > disk_start_offset && position + record_len > disk_start_offset + split_size {
+            self.disk_start_offsets.push(position);
+        }
+
+        if self.disk_start_offsets.len() >= MAX_DISK_COUNT {
+            return Err(WriteError::TooManyDisks);
+        }
+
+        Ok(())
+    }
+
+    // Returns the disk the given offset is on and the offset from the start of that disk
+    fn disk_position(&self, offset: u64) -> (u16, u64) {
+        let disk = self
+            .disk_start_offsets
+            .partition_point(|&disk_start_offset| disk_start_offset <= offset)
+            - 1;
+
+        (disk as u16, offset - self.disk_start_offsets[disk])
+    }
+
+    // Goes back to the local file header to fill in the fields only known after the data is
+    // written. Large files keep their sizes in the ZIP64 extra field right after the name.
+    fn patch_local_file_header(
+        &mut self,
+        offset: u64,
+        file_name: &str,
+        crc32: Crc32,
+        compressed_size: u64,
+        uncompressed_size: u64,
+        large_file: bool,
+    ) -> Result<(), WriteError> {
+        let data_end = self.stream_position()?;
+
+        let patch_result = self
+            .writer
+            .seek(SeekFrom::Start(offset + LOCAL_FILE_HEADER_CRC32_OFFSET))
+            .and_then(|_| self.writer.write_u32::<LittleEndian>(crc32))
+            .and_then(|_| {
+                if large_file {
+                    self.writer
+                        .write_u32::<LittleEndian>(ZIP64_SIZE_MARKER)
+                        .and_then(|_| self.writer.write_u32::<LittleEndian>(ZIP64_SIZE_MARKER))
+                        .and_then(|_| {
+                            self.writer.seek(SeekFrom::Start(
+                                offset
+                                    + MIN_LOCAL_FILE_HEADER_SIZE as u64
+                                    + file_name.len() as u64
+                                    + 4,
+                            ))
+                        })
+                        .and_then(|_| self.writer.write_u64::<LittleEndian>(uncompressed_size))
+                        .and_then(|_| self.writer.write_u64::<LittleEndian>(compressed_size))
+                } else {
+                    self.writer
+                        .write_u32::<LittleEndian>(compressed_size as u32)
+                        .and_then(|_| {
+                            self.writer
+                                .write_u32::<LittleEndian>(uncompressed_size as u32)
+                        })
+                }
+            })
+            .and_then(|_| self.writer.seek(SeekFrom::Start(data_end)));
+
+        patch_result
+            .map(|_| ())
+            .map_err(|err| WriteError::IOError(err.to_string()))
+    }
+
+    // Writes the CRC-32 and sizes after the data of an entry. Large files use 8 byte sizes like
+    // their ZIP64 extra field.
+    fn write_data_descriptor(
+        &mut self,
+        crc32: Crc32,
+        compressed_size: u64,
+        uncompressed_size: u64,
+        large_file: bool,
+    ) -> Result<(), WriteError> {
+        self.start_record(if large_file { 24 } else { 16 })?;
+
+        let write_result = self
+            .writer
+            .write_u32::<LittleEndian>(DATA_DESCRIPTOR_SIGN)
+            .and_then(|_| self.writer.write_u32::<LittleEndian>(crc32))
+            .and_then(|_| {
+                if large_file {
+                    self.writer
+                        .write_u64::<LittleEndian>(compressed_size)
+                        .and_then(|_| self.writer.write_u64::<LittleEndian>(uncompressed_size))
+                } else {
+                    self.writer
+                        .write_u32::<LittleEndian>(compressed_size as u32)
+                        .and_then(|_| {
+                            self.writer
+                                .write_u32::<LittleEndian>(uncompressed_size as u32)
+                        })
+                }
+            });
+
+        write_result.map_err(|err| WriteError::IOError(err.to_string()))
+    }
+
+    fn data_descriptor_flag(&self) -> u16 {
+        if self.use_data_descriptors {
+            DATA_DESCRIPTOR_FLAG
+        } else {
+            0x0000
+        }
+    }
+
+    // Writes a local file header with zeroed CRC-32 and sizes and returns its offset
+    fn write_local_file_header(
+        &mut self,
+        file_name: &[u8],
+        extra_field: &[u8],
+        general_purpose_bit_flag: u16,
+        compression_method: u16,
+        version_needed: u16,
+        last_modified: &ZipDateTime,
+    ) -> Result<u64, WriteError> {
+        if file_name.len() > MAX_FILE_NAME_LEN {
+            return Err(WriteError::FileNameTooLong(file_name.len()));
+        }
+
+        self.start_record(
+            (MIN_LOCAL_FILE_HEADER_SIZE + file_name.len() + extra_field.len()) as u64,
+        )?;
+
+        let offset = self.stream_position()?;
+        let (date, time) = last_modified.to_bytes();
+
+        let write_result = self
+            .writer
+            .write_u32::<LittleEndian>(LOCAL_FILE_HEADER_SIGN)
+            .and_then(|_| self.writer.write_u16::<LittleEndian>(version_needed))
+            .and_then(|_| {
+                self.writer
+                    .write_u16::<LittleEndian>(general_purpose_bit_flag)
+            })
+            .and_then(|_| self.writer.write_u16::<LittleEndian>(compression_method))
+            .and_then(|_| self.writer.write_u16::<LittleEndian>(time))
+            .and_then(|_| self.writer.write_u16::<LittleEndian>(date))
+            .and_then(|_| self.writer.write_u32::<LittleEndian>(0))
+            .and_then(|_| self.writer.write_u32::<LittleEndian>(0))
+            .and_then(|_| self.writer.write_u32::<LittleEndian>(0))
+            .and_then(|_| {
+                self.writer
+                    .write_u16::<LittleEndian>(file_name.len() as u16)
+            })
+            .and_then(|_| {
+                self.writer
+                    .write_u16::<LittleEndian>(extra_field.len() as u16)
+            })
+            .and_then(|_| self.writer.write_all(file_name))
+            .and_then(|_| self.writer.write_all(extra_field));
+
+        write_result.map_err(|err| WriteError::IOError(err.to_string()))?;
+
+        Ok(offset)
+    }
+
+    fn stream_position(&mut self) -> Result<u64, WriteError> {
+        self.writer
+            .stream_position()
+            .map_err(|err| WriteError::IOError(err.to_string()))
+    }
+}
+
+// The padding follows the alignment in the data of the extra field, so the data of the entry
+// starts after the whole extra field
+fn alignment_extra_field(data_offset: u64, alignment: u16) -> Vec<u8> {
+    if alignment <= 1 {
+        return Vec::new();
+    }
+
+    let unpadded_data_offset =
+        data_offset + (EXTRA_FIELD_HEADER_SIZE + std::mem::size_of::<u16>()) as u64;
+    let padding = (alignment as u64 - unpadded_data_offset % alignment as u64) % alignment as u64;
+    let mut data = alignment.to_le_bytes().to_vec();
+
+    data.resize(data.len() + padding as usize, 0);
+
+    ExtraField {
+        header_id: ALIGNMENT_EXTRA_FIELD_ID,
+        data: &data,
+    }
+    .to_bytes()
+}
+
+fn aes_extra_field(strength: AesStrength, compression_method: u16) -> Vec<u8> {
+    let data = AesExtraField {
+        vendor_version: AesVendorVersion::Ae2,
+        strength,
+        compression_method,
+    }
+    .to_bytes();
+
+    ExtraField {
+        header_id: AES_EXTRA_FIELD_ID,
+        data: &data,
+    }
+    .to_bytes()
+}
+
+fn deflate_mode_bits(deflate_mode: &DeflateCompressionMode) -> u16 {
+    match deflate_mode {
+        DeflateCompressionMode::Normal => 0b00,
+        DeflateCompressionMode::Maximum => 0b01,
+        DeflateCompressionMode::Fast => 0b10,
+        DeflateCompressionMode::SuperFast => 0b11,
+    }
+}
+
+// ASCII names are valid in every encoding so the UTF-8 flag is only needed for the others
+fn file_name_flag(file_name: &str) -> u16 {
+    if file_name.is_ascii() {
+        0x0000
+    } else {
+        UTF8_FILE_NAME_FLAG
+    }
+}
+
+// The Unicode Path extra field holds the CRC-32 of the name in the header so readers can tell
+// whether the name was changed by a tool unaware of the extra field
+fn file_name_extra_field(file_name: &str, entry_options: &EntryOptions) -> Vec<u8> {
+    if !entry_options.unicode_path_extra_field || file_name.is_ascii() {
+        return Vec::new();
+    }
+
+    let name_crc32 = Crc::<u32>::new(&CRC_32_ISO_HDLC).checksum(file_name.as_bytes());
+    let mut data = Vec::with_capacity(5 + file_name.len());
+
+    data.push(UNICODE_PATH_EXTRA_FIELD_VERSION);
+    data.extend_from_slice(&name_crc32.to_le_bytes());
+    data.extend_from_slice(file_name.as_bytes());
+
+    ExtraField {
+        header_id: UNICODE_PATH_EXTRA_FIELD_ID,
+        data: &data,
+    }
+    .to_bytes()
+}
+
+fn version_needed(compression_method: u16, zip64: bool) -> u16 {
+    if compression_method == AES_COMPRESSION_METHOD {
+        AES_VERSION
+    } else if compression_method == ZSTD_COMPRESSION_METHOD {
+        ZSTD_VERSION
+    } else if compression_method == XZ_COMPRESSION_METHOD {
+        XZ_VERSION
+    } else if compression_method == LZMA_COMPRESSION_METHOD {
+        LZMA_VERSION
+    } else if compression_method == BZIP2_COMPRESSION_METHOD {
+        BZIP2_VERSION
+    } else if zip64 {
+        ZIP64_VERSION
+    } else {
+        ZIP_VERSION
+    }
+}
+
+// Returns the value written to a 32-bit field, moving it to the ZIP64 extra field data if it does
+// not fit
+fn zip64_field(value: u64, zip64_data: &mut Vec<u8>) -> u32 {
+    if value >= ZIP64_SIZE_MARKER as u64 {
+        zip64_data.extend_from_slice(&value.to_le_bytes());
+        ZIP64_SIZE_MARKER
+    } else {
+        value as u32
+    }
+}
+
+fn host(entry_options: &EntryOptions) -> u8 {
+    match entry_options.unix_mode {
+        Some(_) => UNIX_HOST,
+        None => MS_DOS_HOST,
+    }
+}
+
+// MS-DOS attributes are kept in the lower byte and the Unix mode in the upper 16 bits
+fn external_attributes(entry_options: &EntryOptions, ms_dos_attributes: u32) -> u32 {
+    match entry_options.unix_mode {
+        Some(unix_mode) => ((unix_mode & 0xFFFF) << 16) | ms_dos_attributes,
+        None => ms_dos_attributes,
+    }
+}
+
+fn compression_level(deflate_mode: &DeflateCompressionMode) -> Compression {
+    match deflate_mode {
+        DeflateCompressionMode::Normal => Compression::default(),
+        DeflateCompressionMode::Maximum => Compression::best(),
+        DeflateCompressionMode::Fast => Compression::new(2),
+        DeflateCompressionMode::SuperFast => Compression::fast(),
+    }
+}
+
+// Copies the reader into the writer returning CRC-32 and length of the copied bytes
+fn copy_and_calculate_crc32<R, W>(
+    reader: &mut R,
+    writer: &mut W,
+) -> Result<(Crc32, u64), WriteError>
+where
+    R: Read,
+    W: Write,
+{
+    let mut buf = vec![0u8; FILE_READ_WRITE_BUFFER_SIZE];
+    let crc = Crc::<u32>::new(&CRC_32_ISO_HDLC);
+    let mut digest = crc.digest();
+    let mut len = 0;
+
+    loop {
+        let read_bytes = reader
+            .read(&mut buf)
+            .map_err(|err| WriteError::IOError(err.to_string()))?;
+
+        if read_bytes == 0 {
+            break;
+        }
+
+        let read_bytes_buf = &buf[..read_bytes];
+
+        writer
+            .write_all(read_bytes_buf)
+            .map_err(|err| WriteError::IOError(err.to_string()))?;
+        digest.update(read_bytes_buf);
+        len += read_bytes as u64;
+    }
+
+    Ok((digest.finalize(), len))
+}
+
+fn central_dir_record(entry: &CentralDirEntry) -> Vec<u8> {
+    // The ZIP64 extra field only holds the values that do not fit, in the order the spec defines
+    let mut zip64_data = Vec::new();
+    let uncompressed_size = zip64_field(entry.uncompressed_size, &mut zip64_data);
+    let compressed_size = zip64_field(entry.compressed_size, &mut zip64_data);
+    let offset = zip64_field(entry.offset, &mut zip64_data);
+    let mut extra_field = Vec::new();
+
+    if !zip64_data.is_empty() {
+        extra_field.extend_from_slice(
+            &ExtraField {
+                header_id: ZIP64_EXTRA_FIELD_ID,
+                data: &zip64_data,
+            }
+            .to_bytes(),
+        );
+    }
+
+    extra_field.extend_from_slice(&entry.extra_field);
+
+    let mut record =
+        Vec::with_capacity(MIN_CENTRAL_DIR_SIZE + entry.file_name.len() + extra_field.len());
+    let (date, time) = entry.last_modified.to_bytes();
+
+    record.extend_from_slice(&CENTRAL_DIR_SIGN.to_le_bytes());
+    // Version made by, the upper byte is the host the entry was made on
+    record.push(ZIP_VERSION as u8);
+    record.push(entry.host);
+    record.extend_from_slice(
+        &version_needed(
+            entry.compression_method,
+            entry.large_file || !zip64_data.is_empty(),
+        )
+        .to_le_bytes(),
+    );
+    record.extend_from_slice(&entry.general_purpose_bit_flag.to_le_bytes());
+    record.extend_from_slice(&entry.compression_method.to_le_bytes());
+    record.extend_from_slice(&time.to_le_bytes());
+    record.extend_from_slice(&date.to_le_bytes());
+    record.extend_from_slice(&entry.crc32.to_le_bytes());
+    record.extend_from_slice(&compressed_size.to_le_bytes());
+    record.extend_from_slice(&uncompressed_size.to_le_bytes());
+    record.extend_from_slice(&(entry.file_name.len() as u16).to_le_bytes());
+    record.extend_from_slice(&(extra_field.len() as u16).to_le_bytes());
+    // File comment length, disk number start and internal attributes
+    record.extend_from_slice(&[0u8; 2]);
+    record.extend_from_slice(&entry.disk_number.to_le_bytes());
+    record.extend_from_slice(&[0u8; 2]);
+    record.extend_from_slice(&entry.external_attributes.to_le_bytes());
+    record.extend_from_slice(&offset.to_le_bytes());
+    record.extend_from_slice(&entry.file_name);
+    record.extend_from_slice(&extra_field);
+
+    record
+}
+
+// Writes the ZIP64 end of central directory record followed by its locator
+fn write_zip64_end_of_central_dir<W>(
+    writer: &mut W,
+    central_dir_location: &CentralDirLocation,
+    record_offset: u64,
+    disk_count: u32,
+) -> std::io::Result<()>
+where
+    W: Write,
+{
+    writer.write_u32::<LittleEndian>(ZIP64_EOF_CENTRAL_DIR_SIGN)?;
+    // Size of the record without the signature and this field
+    writer.write_u64::<LittleEndian>(ZIP64_EOF_CENTRAL_DIR_SIZE - 12)?;
+    writer.write_u16::<LittleEndian>(ZIP_VERSION)?;
+    writer.write_u16::<LittleEndian>(ZIP64_VERSION)?;
+    // Disk number and the disk central directory starts on
+    writer.write_u32::<LittleEndian>(central_dir_location.disk_number)?;
+    writer.write_u32::<LittleEndian>(central_dir_location.central_dir_start_disk)?;
+    writer.write_u64::<LittleEndian>(central_dir_location.disk_entry_count)?;
+    writer.write_u64::<LittleEndian>(central_dir_location.entry_count)?;
+    writer.write_u64::<LittleEndian>(central_dir_location.central_dir_size)?;
+    writer.write_u64::<LittleEndian>(central_dir_location.central_dir_start_offset)?;
+
+    writer.write_u32::<LittleEndian>(ZIP64_EOF_CENTRAL_DIR_LOCATOR_SIGN)?;
+    // Disk of the ZIP64 record, its offset and the total number of disks
+    writer.write_u32::<LittleEndian>(central_dir_location.disk_number)?;
+    writer.write_u64::<LittleEndian>(record_offset)?;
+    writer.write_u32::<LittleEndian>(disk_count)
+}
+
+fn write_end_of_central_dir<W>(
+    writer: &mut W,
+    central_dir_location: &CentralDirLocation,
+    comment: &[u8],
+) -> std::io::Result<()>
+where
+    W: Write,
+{
+    let entry_count_field =
+        |entry_count: u64| entry_count.min(ZIP64_ENTRY_COUNT_MARKER as u64) as u16;
+    let offset_field = |offset: u64| offset.min(ZIP64_SIZE_MARKER as u64) as u32;
+
+    writer.write_u32::<LittleEndian>(EOF_CENTRAL_DIR_SIGN)?;
+    // Disk number and the disk central directory starts on
+    writer.write_u16::<LittleEndian>(central_dir_location.disk_number as u16)?;
+    writer.write_u16::<LittleEndian>(central_dir_location.central_dir_start_disk as u16)?;
+    writer.write_u16::<LittleEndian>(entry_count_field(central_dir_location.disk_entry_count))?;
+    writer.write_u16::<LittleEndian>(entry_count_field(central_dir_location.entry_count))?;
+    writer.write_u32::<LittleEndian>(offset_field(central_dir_location.central_dir_size))?;
+    writer
+        .write_u32::<LittleEndian>(offset_field(central_dir_location.central_dir_start_offset))?;
+    writer.write_u16::<LittleEndian>(comment.len() as u16)?;
+    writer.write_all(comment)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::archive::MemoryBudget;
+    use crate::headers::{EntryKind, FileEnvironment, ParseOptions};
+    use crate::zip::Zip;
+    use std::io::{BufReader, Cursor};
+
+    #[test]
+    fn test_zip_writer() {
+        let mut zip_writer = ZipWriter::new(Cursor::new(Vec::new()));
+        let stored = EntryOptions {
+            compression_method: CompressionMethod::NoCompression,
+            ..Default::default()
+        };
+
+        zip_writer
+            .add_directory("docs", &EntryOptions::default())
+            .unwrap();
+        zip_writer
+            .add_file(
+                "docs/a.txt",
+                &mut "hello ".repeat(100).as_bytes(),
+                &EntryOptions::default(),
+            )
+            .unwrap();
+        zip_writer
+            .add_file("b.txt", &mut &b"world"[..], &stored)
+            .unwrap();
+
+        let archive = zip_writer.finish().unwrap().into_inner();
+        let mut zip = Zip::from_readable(BufReader::new(Cursor::new(archive))).unwrap();
+
+        assert_eq!(zip.dir_count(), 1);
+        assert_eq!(zip.file_count(), 2);
+        assert_eq!(zip.zip_files()[0].file_name(), "docs/");
+        assert_eq!(
+            zip.zip_files()[1].compression_method(),
+            &CompressionMethod::Deflate(DeflateCompressionMode::Normal)
+        );
+        assert!(zip.zip_files()[1].compressed_size().get() < 600);
+        assert_eq!(
+            zip.zip_files()[2].compression_method(),
+            &CompressionMethod::NoCompression
+        );
+
+        let budget = MemoryBudget::new(usize::MAX);
+        let entries = zip
+            .extract_all_to_memory(&None, &budget, &CRC_32_ISO_HDLC)
+            .unwrap();
+
+        assert_eq!(entries[0].1, "hello ".repeat(100).as_bytes());
+        assert_eq!(entries[1].1, b"world");
+    }
+
+    #[test]
+    fn test_zip_writer_unicode_names() {
+        let mut zip_writer = ZipWriter::new(Cursor::new(Vec::new()));
+        let unicode_path = EntryOptions {
+            unicode_path_extra_field: true,
+            ..Default::default()
+        };
+
+        zip_writer
+            .add_file("ascii.txt", &mut &b"a"[..], &unicode_path)
+            .unwrap();
+        zip_writer
+            .add_file("résumé.txt", &mut &b"b"[..], &EntryOptions::default())
+            .unwrap();
+        zip_writer.add_directory("日本語", &unicode_path).unwrap();
+
+        let archive = zip_writer.finish().unwrap().into_inner();
+        let mut zip = Zip::from_readable(BufReader::new(Cursor::new(archive))).unwrap();
+        let central_dir_records = zip.central_dir_records().unwrap();
+        let flag = |index: usize| LittleEndian::read_u16(&central_dir_records[index][8..10]);
+
+        assert_eq!(flag(0) & UTF8_FILE_NAME_FLAG, 0);
+        assert_eq!(flag(1) & UTF8_FILE_NAME_FLAG, UTF8_FILE_NAME_FLAG);
+        assert_eq!(flag(2) & UTF8_FILE_NAME_FLAG, UTF8_FILE_NAME_FLAG);
+        assert_eq!(zip.zip_files()[1].file_name(), "résumé.txt");
+        assert_eq!(zip.zip_files()[2].file_name(), "日本語/");
+        assert_eq!(zip.zip_files()[0].extra_fields().count(), 0);
+        assert_eq!(zip.zip_files()[1].extra_fields().count(), 0);
+
+        let extra_fields = zip.zip_files()[2].extra_fields().collect::<Vec<_>>();
+
+        assert_eq!(extra_fields.len(), 1);
+        assert_eq!(extra_fields[0].header_id, UNICODE_PATH_EXTRA_FIELD_ID);
+        assert_eq!(&extra_fields[0].data[5..], "日本語/".as_bytes());
+    }
+
+    #[test]
+    fn test_zip_writer_compression_level() {
+        let mut zip_writer = ZipWriter::new(Cursor::new(Vec::new()));
+        let data = "compressible ".repeat(1000);
+
+        for level in [1, 5, 9] {
+            let entry_options = EntryOptions {
+                compression_method: CompressionMethod::Deflate(DeflateCompressionMode::from_level(
+                    level,
+                )),
+                compression_level: Some(level),
+                ..Default::default()
+            };
+
+            zip_writer
+                .add_file(&level.to_string(), &mut data.as_bytes(), &entry_options)
+                .unwrap();
+        }
+
+        let archive = zip_writer.finish().unwrap().into_inner();
+        let zip = Zip::from_readable(BufReader::new(Cursor::new(archive))).unwrap();
+        let compression_methods = zip
+            .zip_files()
+            .iter()
+            .map(|zip_file| *zip_file.compression_method())
+            .collect::<Vec<_>>();
+
+        assert_eq!(
+            compression_methods,
+            vec![
+                CompressionMethod::Deflate(DeflateCompressionMode::SuperFast),
+                CompressionMethod::Deflate(DeflateCompressionMode::Normal),
+                CompressionMethod::Deflate(DeflateCompressionMode::Maximum)
+            ]
+        );
+        assert!(
+            zip.zip_files()[2].compressed_size().get()
+                <= zip.zip_files()[0].compressed_size().get()
+        );
+    }
+
+    #[cfg(feature = "bzip2")]
+    #[test]
+    fn test_zip_writer_rejects_decode_only_compression() {
+        let mut zip_writer = ZipWriter::new(Cursor::new(Vec::new()));
+        let bzip2 = EntryOptions {
+            compression_method: CompressionMethod::Bzip2,
+            ..Default::default()
+        };
+
+        assert!(matches!(
+            zip_writer.add_file("a.txt", &mut &b"a"[..], &bzip2),
+            Err(WriteError::UnsupportedCompression(CompressionMethod::Bzip2))
+        ));
+        assert_eq!(zip_writer.finish().unwrap().into_inner().len(), 22);
+    }
+
+    #[cfg(feature = "zstd")]
+    #[test]
+    fn test_zip_writer_zstd() {
+        let mut zip_writer = ZipWriter::new(Cursor::new(Vec::new()));
+        let zstd = EntryOptions {
+            compression_method: CompressionMethod::Zstd,
+            ..Default::default()
+        };
+        let data = "zstandard ".repeat(100);
+
+        zip_writer
+            .add_file("a.txt", &mut data.as_bytes(), &zstd)
+            .unwrap();
+
+        let archive = zip_writer.finish().unwrap().into_inner();
+        let compressed_size = LittleEndian::read_u32(&archive[18..22]) as usize;
+        let data_start = MIN_LOCAL_FILE_HEADER_SIZE + "a.txt".len();
+
+        assert_eq!(LittleEndian::read_u16(&archive[4..6]), ZSTD_VERSION);
+        assert_eq!(
+            LittleEndian::read_u16(&archive[8..10]),
+            ZSTD_COMPRESSION_METHOD
+        );
+        assert_eq!(
+            zstd::decode_all(&archive[data_start..data_start + compressed_size]).unwrap(),
+            data.as_bytes()
+        );
+    }
+
+    #[test]
+    fn test_streaming_zip_writer() {
+        let mut zip_writer = ZipWriter::streaming(Vec::new());
+        let stored = EntryOptions {
+            compression_method: CompressionMethod::NoCompression,
+            ..Default::default()
+        };
+
+        zip_writer
+            .add_directory("docs", &EntryOptions::default())
+            .unwrap();
+        zip_writer
+            .add_file(
+                "docs/a.txt",
+                &mut "hello ".repeat(100).as_bytes(),
+                &EntryOptions::default(),
+            )
+            .unwrap();
+        zip_writer
+            .add_file("b.txt", &mut &b"world"[..], &stored)
+            .unwrap();
+
+        let archive = zip_writer.finish().unwrap().into_inner();
+
+        // The local file header of the first file keeps zeroed CRC-32 and sizes
+        let first_file_offset = MIN_LOCAL_FILE_HEADER_SIZE + "docs/".len();
+        let local_file_header = &archive[first_file_offset..first_file_offset + 30];
+
+        assert_eq!(
+            LittleEndian::read_u16(&local_file_header[6..8]) & DATA_DESCRIPTOR_FLAG,
+            DATA_DESCRIPTOR_FLAG
+        );
+        assert_eq!(&local_file_header[14..26], &[0u8; 12]);
+
+        let mut zip = Zip::from_readable(BufReader::new(Cursor::new(archive))).unwrap();
+
+        assert!(zip.zip_files()[1].data_descriptor_used());
+        assert_eq!(zip.zip_files()[2].uncompressed_size().get(), 5);
+
+        let budget = MemoryBudget::new(usize::MAX);
+        let entries = zip
+            .extract_all_to_memory(&None, &budget, &CRC_32_ISO_HDLC)
+            .unwrap();
+
+        assert_eq!(entries[0].1, "hello ".repeat(100).as_bytes());
+        assert_eq!(entries[1].1, b"world");
+    }
+
+    #[test]
+    fn test_zip_writer_large_file() {
+        let mut zip_writer = ZipWriter::new(Cursor::new(Vec::new()));
+        let large_file = EntryOptions {
+            large_file: true,
+            ..Default::default()
+        };
+
+        zip_writer
+            .add_file("large.bin", &mut &b"not that large"[..], &large_file)
+            .unwrap();
+
+        let archive = zip_writer.finish().unwrap().into_inner();
+
+        // Version needed, the size markers and the ZIP64 extra field after the name
+        assert_eq!(LittleEndian::read_u16(&archive[4..6]), ZIP64_VERSION);
+        assert_eq!(LittleEndian::read_u32(&archive[18..22]), ZIP64_SIZE_MARKER);
+        assert_eq!(LittleEndian::read_u32(&archive[22..26]), ZIP64_SIZE_MARKER);
+        assert_eq!(
+            LittleEndian::read_u16(&archive[39..41]),
+            ZIP64_EXTRA_FIELD_ID
+        );
+        assert_eq!(LittleEndian::read_u64(&archive[43..51]), 14);
+
+        let mut zip = Zip::from_readable(BufReader::new(Cursor::new(archive))).unwrap();
+        let budget = MemoryBudget::new(usize::MAX);
+        let entries = zip
+            .extract_all_to_memory(&None, &budget, &CRC_32_ISO_HDLC)
+            .unwrap();
+
+        assert_eq!(entries[0].1, b"not that large");
+    }
+
+    #[test]
+    fn test_central_dir_record_zip64_offset() {
+        let record = central_dir_record(&CentralDirEntry {
+            file_name: b"a.txt".to_vec(),
+            extra_field: Vec::new(),
+            general_purpose_bit_flag: 0,
+            compression_method: 0,
+            last_modified: ZipDateTime::default(),
+            host: MS_DOS_HOST,
+            large_file: false,
+            crc32: 0,
+            compressed_size: 1,
+            uncompressed_size: 1,
+            external_attributes: 0,
+            disk_number: 0,
+            offset: 0x1_0000_0000,
+        });
+
+        assert_eq!(LittleEndian::read_u16(&record[6..8]), ZIP64_VERSION);
+        assert_eq!(LittleEndian::read_u32(&record[20..24]), 1);
+        assert_eq!(LittleEndian::read_u32(&record[42..46]), ZIP64_SIZE_MARKER);
+        assert_eq!(
+            &record[51..],
+            &[0x01, 0x00, 0x08, 0x00, 0x00, 0x00, 0x00, 0x00, 0x01, 0x00, 0x00, 0x00]
+        );
+    }
+
+    #[test]
+    fn test_zip_writer_zip64_entry_count() {
+        let mut zip_writer = ZipWriter::new(Cursor::new(Vec::new()));
+        let entry_count = ZIP64_ENTRY_COUNT_MARKER as u64 + 1;
+
+        for index in 0..entry_count {
+            zip_writer
+                .add_directory(&index.to_string(), &EntryOptions::default())
+                .unwrap();
+        }
+
+        let archive = zip_writer.finish().unwrap().into_inner();
+        let eocd = &archive[archive.len() - 22..];
+        let locator = &archive[archive.len() - 42..archive.len() - 22];
+        let zip64_eocd_offset = LittleEndian::read_u64(&locator[8..16]) as usize;
+        let zip64_eocd = &archive[zip64_eocd_offset..zip64_eocd_offset + 56];
+
+        assert_eq!(
+            LittleEndian::read_u16(&eocd[10..12]),
+            ZIP64_ENTRY_COUNT_MARKER
+        );
+        assert_eq!(
+            LittleEndian::read_u32(&locator[0..4]),
+            ZIP64_EOF_CENTRAL_DIR_LOCATOR_SIGN
+        );
+        assert_eq!(
+            LittleEndian::read_u32(&zip64_eocd[0..4]),
+            ZIP64_EOF_CENTRAL_DIR_SIGN
+        );
+        assert_eq!(LittleEndian::read_u64(&zip64_eocd[32..40]), entry_count);
+        assert_eq!(
+            LittleEndian::read_u64(&zip64_eocd[48..56]) as u32,
+            LittleEndian::read_u32(&eocd[16..20])
+        );
+    }
+
+    #[test]
+    fn test_zip_writer_unix_mode() {
+        let mut zip_writer = ZipWriter::new(Cursor::new(Vec::new()));
+        let executable = EntryOptions {
+            unix_mode: Some(0o100755),
+            ..Default::default()
+        };
+        let dir = EntryOptions {
+            unix_mode: Some(0o040700),
+            ..Default::default()
+        };
+
+        zip_writer.add_directory("bin", &dir).unwrap();
+        zip_writer
+            .add_file("bin/run.sh", &mut &b"#!/bin/sh"[..], &executable)
+            .unwrap();
+        zip_writer
+            .add_file("README", &mut &b"readme"[..], &EntryOptions::default())
+            .unwrap();
+
+        let archive = zip_writer.finish().unwrap().into_inner();
+        let zip = Zip::from_readable(BufReader::new(Cursor::new(archive))).unwrap();
+        let zip_files = zip.zip_files();
+
+        assert_eq!(zip_files[0].environment(), &FileEnvironment::Unix);
+        assert_eq!(zip_files[0].entry_kind(), EntryKind::Directory);
+        assert_eq!(zip_files[0].external_attributes(), (0o040700 << 16) | 0x10);
+        assert_eq!(zip_files[1].environment(), &FileEnvironment::Unix);
+        assert_eq!(zip_files[1].external_attributes() >> 16, 0o100755);
+        assert_eq!(zip_files[2].environment(), &FileEnvironment::MsDos);
+        assert_eq!(zip_files[2].external_attributes(), 0);
+    }
+
+    #[test]
+    fn test_zip_writer_alignment() {
+        let mut zip_writer = ZipWriter::new(Cursor::new(Vec::new()));
+        let stored = EntryOptions {
+            compression_method: CompressionMethod::NoCompression,
+            alignment: Some(4096),
+            ..Default::default()
+        };
+        let deflated = EntryOptions {
+            alignment: Some(4096),
+            ..Default::default()
+        };
+
+        zip_writer
+            .add_file("a.txt", &mut &b"first stored"[..], &stored)
+            .unwrap();
+        zip_writer
+            .add_file("deflated.txt", &mut &b"deflated"[..], &deflated)
+            .unwrap();
+        zip_writer
+            .add_file("dir/b.txt", &mut &b"second stored"[..], &stored)
+            .unwrap();
+
+        let archive = zip_writer.finish().unwrap().into_inner();
+        let data_offset = |data: &[u8]| {
+            archive
+                .windows(data.len())
+                .position(|window| window == data)
+                .unwrap()
+        };
+
+        assert_eq!(data_offset(b"first stored") % 4096, 0);
+        assert_eq!(data_offset(b"second stored") % 4096, 0);
+        assert_eq!(
+            &archive[30 + 5..30 + 5 + 6],
+            &[0x35, 0xD9, 0xD9, 0x0F, 0x00, 0x10]
+        );
+
+        let zip = Zip::from_readable(BufReader::new(Cursor::new(archive))).unwrap();
+
+        assert_eq!(zip.zip_files().len(), 3);
+    }
+
+    #[test]
+    fn test_zip_writer_split() {
+        let mut zip_writer = ZipWriter::new(Cursor::new(Vec::new()));
+        let stored = EntryOptions {
+            compression_method: CompressionMethod::NoCompression,
+            ..Default::default()
+        };
+        let data: Vec<u8> = (0..100_000u32).map(|value| (value % 251) as u8).collect();
+
+        assert_eq!(
+            zip_writer.set_split_size(MIN_SPLIT_SIZE - 1),
+            Err(WriteError::SplitSizeTooSmall(MIN_SPLIT_SIZE - 1))
+        );
+
+        zip_writer.set_split_size(MIN_SPLIT_SIZE).unwrap();
+        zip_writer
+            .add_file("a.bin", &mut data.as_slice(), &stored)
+            .unwrap();
+        // Leaves less room on the second disk than the next local file header needs
+        zip_writer
+            .add_file("b.bin", &mut &data[..30_988], &stored)
+            .unwrap();
+        zip_writer
+            .add_file("c.txt", &mut "hello".as_bytes(), &stored)
+            .unwrap();
+
+        let (cursor, disk_start_offsets) = zip_writer.finish_split().unwrap();
+        let archive = cursor.into_inner();
+
+        assert_eq!(LittleEndian::read_u32(&archive[..4]), SPLIT_SIGN);
+        assert_eq!(
+            disk_start_offsets,
+            vec![0, MIN_SPLIT_SIZE, 2 * MIN_SPLIT_SIZE - 10]
+        );
+
+        let eocd_offset = archive.len() - MIN_EOF_CENTRAL_DIR_SIZE as usize;
+
+        // Disk number, the disk the central directory starts on and the entries on this disk
+        assert_eq!(LittleEndian::read_u16(&archive[eocd_offset + 4..]), 2);
+        assert_eq!(LittleEndian::read_u16(&archive[eocd_offset + 6..]), 2);
+        assert_eq!(LittleEndian::read_u16(&archive[eocd_offset + 8..]), 3);
+
+        let parse_options = ParseOptions {
+            disk_start_offsets: disk_start_offsets.clone(),
+            ..Default::default()
+        };
+        let mut zip =
+            Zip::from_readable_with_options(BufReader::new(Cursor::new(archive)), &parse_options)
+                .unwrap();
+
+        assert_eq!(zip.zip_files()[2].offset(), disk_start_offsets[2]);
+        assert_eq!(
+            zip.read_entry(
+                "a.bin",
+                &None,
+                &MemoryBudget::new(usize::MAX),
+                &CRC_32_ISO_HDLC
+            )
+            .unwrap(),
+            data
+        );
+        assert_eq!(
+            zip.read_entry(
+                "c.txt",
+                &None,
+                &MemoryBudget::new(usize::MAX),
+                &CRC_32_ISO_HDLC
+            )
+            .unwrap(),
+            b"hello"
+        );
+    }
+
+    #[test]
+    fn test_zip_writer_aes_encryption() {
+        use crate::archive::ExtractError;
+        use crate::headers::EncryptionMethod;
+
+        let mut zip_writer = ZipWriter::new(Cursor::new(Vec::new()));
+        let stored = EntryOptions {
+            compression_method: CompressionMethod::NoCompression,
+            ..Default::default()
+        };
+        let data = "encrypted ".repeat(100);
+
+        zip_writer.set_encryption(Encryption::Aes256("password".to_string()));
+        zip_writer
+            .add_file("a.txt", &mut data.as_bytes(), &EntryOptions::default())
+            .unwrap();
+        zip_writer
+            .add_file("b.txt", &mut &b"stored"[..], &stored)
+            .unwrap();
+        zip_writer
+            .add_directory("dir", &EntryOptions::default())
+            .unwrap();
+
+        let archive = zip_writer.finish().unwrap().into_inner();
+
+        // Neither the data nor its CRC-32 are visible in the archive
+        assert!(!archive
+            .windows(b"stored".len())
+            .any(|window| window == b"stored"));
+        assert_eq!(
+            LittleEndian::read_u16(&archive[8..10]),
+            AES_COMPRESSION_METHOD
+        );
+        assert_eq!(LittleEndian::read_u32(&archive[14..18]), 0);
+
+        let mut zip = Zip::from_readable(BufReader::new(Cursor::new(archive))).unwrap();
+        let budget = MemoryBudget::new(4096);
+        let password = Some("password".to_string());
+
+        assert_eq!(
+            zip.zip_files()[0].encryption_method(),
+            &EncryptionMethod::Aes
+        );
+        assert_eq!(
+            zip.zip_files()[1].compression_method(),
+            &CompressionMethod::NoCompression
+        );
+        assert_eq!(
+            zip.zip_files()[2].encryption_method(),
+            &EncryptionMethod::NoEncryption
+        );
+        assert_eq!(
+            zip.read_entry("a.txt", &password, &budget, &CRC_32_ISO_HDLC)
+                .unwrap(),
+            data.as_bytes()
+        );
+        assert_eq!(
+            zip.read_entry("b.txt", &password, &budget, &CRC_32_ISO_HDLC)
+                .unwrap(),
+            b"stored"
+        );
+        assert_eq!(
+            zip.read_entry(
+                "b.txt",
+                &Some("wrong".to_string()),
+                &budget,
+                &CRC_32_ISO_HDLC
+            ),
+            Err(ExtractError::AesError(AesError::IncorrectPassword))
+        );
+    }
+}
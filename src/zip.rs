@@ -1,18 +1,43 @@
+use std::collections::{HashMap, HashSet};
 use std::error::Error;
 use std::fmt::Display;
-use std::io::SeekFrom;
-use std::path::PathBuf;
+use std::io::{Read, Seek, SeekFrom, Write};
+use std::path::{Path, PathBuf};
 
-use crate::archive::{Archive, Extract, ExtractError, ReadableArchive};
-use crate::commands::ExtractOptions;
+use byteorder::{ByteOrder, LittleEndian};
+use crc::Algorithm;
+
+use crate::archive::{
+    check_entry_password, entry_reader, is_already_extracted, is_outdated, read_to_memory,
+    sniff_file_type, verify_entry, with_entry_password, Archive, EntryResult, Extract,
+    ExtractError, MemoryBudget, MemoryBuffer, ReadableArchive,
+};
+use crate::commands::{
+    CaseCollisionPolicy, CollisionPolicy, DuplicatePolicy, ExtractOptions, OverwritePolicy,
+};
 use crate::headers::{
-    EncryptionMethod, EndOfCentralDirectory, EndOfCentralDirectoryError, ZipFile, ZipFileError,
+    EncryptionMethod, EndOfCentralDirectory, EndOfCentralDirectoryError, EntryKind, ParseOptions,
+    ZipFile, ZipFileError, ARCHIVE_EXTRA_DATA_SIGN, CENTRAL_DIR_SIGN, LOCAL_FILE_HEADER_SIGN,
+    MIN_CENTRAL_DIR_SIZE,
 };
+use crate::plan::{PlanAction, PlannedEntry};
+use crate::pretty_printer::format_failures;
+use crate::recovery::{find_data_descriptor, scan_local_headers};
+use crate::sink::{ExtractSink, FileSystemSink};
+use crate::writer::{WriteError, ZipWriter};
+
+// Reason of the entries whose paths equal an earlier one's except for case
+const CASE_COLLISION: &str = "same name as an earlier entry except for case";
+const FILE_TYPE_NOT_SELECTED: &str = "file type not selected";
 
 #[derive(Debug)]
 pub enum ZipError {
     EndOfCentralDirectoryError(EndOfCentralDirectoryError),
     ZipFileError(ZipFileError),
+    InvalidCentralDirectoryOffset { offset: u64, file_size: u64 },
+    CentralDirectoryEncrypted,
+    MissingDisks { disk_count: u32, found: usize },
+    NoEntriesRecovered,
     IOError(String),
 }
 
@@ -25,6 +50,24 @@ impl Display for ZipError {
             Self::ZipFileError(err) => {
                 write!(f, "An error occurred while reading ZIP file.\n{}", err)
             }
+            Self::InvalidCentralDirectoryOffset { offset, file_size } => write!(
+                f,
+                "Invalid ZIP file. Central directory offset points beyond the end of the file. Central directory offset: {}, File size: {}",
+                offset, file_size
+            ),
+            Self::CentralDirectoryEncrypted => write!(
+                f,
+                "The central directory of the ZIP file is encrypted with PKWARE Strong Encryption, so its entries cannot be listed or extracted. Decrypt the archive with PKZIP or SecureZIP first"
+            ),
+            Self::MissingDisks { disk_count, found } => write!(
+                f,
+                "The split archive has {} disks but only {} of them were found. Put all the .z01, .z02, ... segments next to the .zip file",
+                disk_count, found
+            ),
+            Self::NoEntriesRecovered => write!(
+                f,
+                "No complete entries could be recovered from the local file headers of the ZIP file"
+            ),
             Self::IOError(error_msg) => write!(
                 f,
                 "An I/O error occured while parsing ZIP file. Message: {}",
@@ -36,32 +79,176 @@ impl Display for ZipError {
 
 impl Error for ZipError {}
 
+impl ZipError {
+    /// Whether the error comes from a missing or damaged central directory, in which case the
+    /// entries may still be recovered from their local file headers.
+    pub fn central_dir_damaged(&self) -> bool {
+        matches!(
+            self,
+            Self::EndOfCentralDirectoryError(
+                EndOfCentralDirectoryError::InvalidSignature(_)
+                    | EndOfCentralDirectoryError::InvalidZipFile(_)
+                    | EndOfCentralDirectoryError::EmptyZipFile
+            ) | Self::ZipFileError(ZipFileError::InvalidSignature(_) | ZipFileError::IOError(_))
+                | Self::InvalidCentralDirectoryOffset { .. }
+        )
+    }
+}
+
 pub struct Zip<R: ReadableArchive> {
     readable: R,
     zip_file_count: usize,
     file_count: usize,
     dir_count: usize,
     files_encrypted: bool,
+    is_zip64: bool,
+    central_dir_start_offset: u64,
+    prepended_data_len: u64,
+    trailing_data_len: u64,
+    recovered: bool,
+    comment: Vec<u8>,
     zip_files: Vec<ZipFile>,
+    // Where the data of each entry ends, only known for entries recovered from their local file
+    // headers
+    entry_ends: Vec<u64>,
+}
+
+/// Metadata of an entry yielded by `EntriesReader`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct EntryInfo {
+    pub file_name: String,
+    pub entry_kind: EntryKind,
+    pub crc32: u32,
+    pub compressed_size: u64,
+    pub uncompressed_size: u64,
+}
+
+impl From<&ZipFile> for EntryInfo {
+    fn from(zip_file: &ZipFile) -> Self {
+        Self {
+            file_name: zip_file.file_name().clone(),
+            entry_kind: zip_file.entry_kind(),
+            crc32: zip_file.crc32().get(),
+            compressed_size: zip_file.compressed_size().get(),
+            uncompressed_size: zip_file.uncompressed_size().get(),
+        }
+    }
+}
+
+/// Streams the decompressed contents of the entries one at a time in central directory order.
+///
+/// Every stream borrows the underlying archive, so entries must be consumed in order and the
+/// stream of an entry has to be dropped before the next one is requested. The streams don't
+/// verify CRC-32 checksums.
+pub struct EntriesReader<'a, R: ReadableArchive> {
+    zip_files: std::slice::Iter<'a, ZipFile>,
+    readable: &'a mut R,
+    password: Option<String>,
+}
+
+impl<'a, R: ReadableArchive> EntriesReader<'a, R> {
+    #[allow(clippy::should_implement_trait)]
+    pub fn next(&mut self) -> Option<Result<(EntryInfo, impl Read + '_), ExtractError>> {
+        let zip_file = self.zip_files.next()?;
+
+        Some(
+            entry_reader(zip_file, self.readable, &self.password)
+                .map(|reader| (EntryInfo::from(zip_file), reader)),
+        )
+    }
 }
 
 impl<R: ReadableArchive> Zip<R> {
-    pub fn from_readable(mut readable: R) -> Result<Self, ZipError> {
+    pub fn from_readable(readable: R) -> Result<Self, ZipError> {
+        Self::from_readable_with_options(readable, &ParseOptions::default())
+    }
+
+    pub fn from_readable_with_options(
+        mut readable: R,
+        parse_options: &ParseOptions,
+    ) -> Result<Self, ZipError> {
         let end_of_central_dir = EndOfCentralDirectory::from_readable(&mut readable)
-            .map_err(|err| ZipError::EndOfCentralDirectoryError(err))?;
+            .map_err(ZipError::EndOfCentralDirectoryError)?;
+
+        let file_size = readable
+            .seek(SeekFrom::End(0))
+            .map_err(|err| ZipError::IOError(err.to_string()))?;
+        let disk_count = end_of_central_dir.disk_count();
+        let missing_disks = ZipError::MissingDisks {
+            disk_count,
+            found: parse_options.disk_start_offsets.len(),
+        };
+
+        // The offsets of a split archive are relative to the disks, so they would point at the
+        // wrong data without the other segments
+        if parse_options.disk_start_offsets.is_empty() && disk_count > 1 {
+            return Err(ZipError::EndOfCentralDirectoryError(
+                EndOfCentralDirectoryError::SpannedArchive {
+                    disk_count,
+                    central_dir_start_disk: end_of_central_dir.central_dir_start_disk(),
+                },
+            ));
+        } else if !parse_options.disk_start_offsets.is_empty()
+            && parse_options.disk_start_offsets.len() != disk_count as usize
+        {
+            return Err(missing_disks);
+        }
+
+        // Self-extracting archives start with an executable stub and some tools prepend data to
+        // archives without updating their offsets, which then start at the end of the data
+        let prepended_data_len = if parse_options.disk_start_offsets.is_empty() {
+            prepended_data_len(&mut readable, &end_of_central_dir)
+        } else {
+            0
+        };
+        let parse_options = match prepended_data_len {
+            0 => parse_options.clone(),
+            prepended_data_len => ParseOptions {
+                disk_start_offsets: vec![prepended_data_len],
+                ..parse_options.clone()
+            },
+        };
+
+        // The central directory offset is relative to the disk it starts on
+        let central_dir_start_offset = parse_options
+            .disk_start_offset(end_of_central_dir.central_dir_start_disk())
+            .map(|disk_start_offset| {
+                disk_start_offset + end_of_central_dir.central_dir_start_offset()
+            })
+            .ok_or(missing_disks)?;
+
+        if central_dir_start_offset >= file_size {
+            return Err(ZipError::InvalidCentralDirectoryOffset {
+                offset: central_dir_start_offset,
+                file_size,
+            });
+        }
 
         readable
-            .seek(SeekFrom::Start(
-                end_of_central_dir.central_dir_start_offset() as u64,
-            ))
+            .seek(SeekFrom::Start(central_dir_start_offset))
             .map_err(|err| ZipError::IOError(err.to_string()))?;
 
-        let mut zip_files: Vec<ZipFile> =
-            Vec::with_capacity(end_of_central_dir.central_dir_size() as usize);
+        // The ZIP64 entry count is 64 bits wide, so the count is only trusted as far as the file
+        // can hold the records
+        let mut zip_files: Vec<ZipFile> = Vec::with_capacity(
+            end_of_central_dir
+                .central_dir_size()
+                .min(file_size / MIN_CENTRAL_DIR_SIZE) as usize,
+        );
 
-        for _ in 0..end_of_central_dir.central_dir_size() {
-            match ZipFile::from_readable(&mut readable) {
+        for index in 0..end_of_central_dir.central_dir_size() {
+            match ZipFile::from_readable_with_options(&mut readable, &parse_options) {
                 Ok(zip_file) => zip_files.push(zip_file),
+                Err(ZipFileError::InvalidSignature(_))
+                    if index == 0
+                        && central_dir_encrypted(
+                            &mut readable,
+                            prepended_data_len,
+                            central_dir_start_offset,
+                        ) =>
+                {
+                    return Err(ZipError::CentralDirectoryEncrypted)
+                }
                 Err(err) => return Err(ZipError::ZipFileError(err)),
             }
         }
@@ -71,49 +258,93 @@ impl<R: ReadableArchive> Zip<R> {
             .filter(|zip_file| zip_file.is_dir())
             .count();
 
-        let file_count = ((end_of_central_dir.central_dir_size()) as usize) - dir_count;
+        // Directories are detected from the entry kind rather than a trailing slash since some
+        // archivers only mark directories through the external attributes.
+        let file_count = zip_files.len() - dir_count;
 
         // Update CRC-32, Uncompressed size as well as compressed size in case ZIP file is
-        // configured with Data descriptor
-        let zip_file_offsets: Vec<u32> =
-            zip_files.iter().map(|zip_file| zip_file.offset()).collect();
-
-        zip_files = zip_files
-            .into_iter()
-            .enumerate()
-            .map(|(index, zip_file)| {
-                if zip_file.data_descriptor_used() {
-                    if index == (zip_file_offsets.len() - 1) {
-                        zip_file.update_with_data_descriptor(
-                            &mut readable,
-                            end_of_central_dir.central_dir_start_offset(),
-                        );
-                    } else {
-                        zip_file.update_with_data_descriptor(
-                            &mut readable,
-                            zip_file_offsets[index + 1],
-                        );
-                    }
-                }
-
-                zip_file
-            })
-            .collect();
+        // configured with Data descriptor. The descriptor is the one following the data of the
+        // entry, since entries aren't always stored one after another, and the central directory
+        // values are kept when it can't be read.
+        for zip_file in zip_files
+            .iter()
+            .filter(|zip_file| zip_file.data_descriptor_used())
+        {
+            let _ = update_with_data_descriptor(&mut readable, zip_file, &parse_options);
+        }
 
         let files_encrypted = zip_files
             .iter()
             .any(|zip_file| zip_file.encryption_method() != &EncryptionMethod::NoEncryption);
 
+        let is_zip64 = end_of_central_dir.zip64_locator_found()
+            || zip_files
+                .iter()
+                .any(|zip_file| zip_file.uses_zip64_extra_field());
+
         Ok(Self {
             readable,
+            is_zip64,
             zip_file_count: end_of_central_dir.central_dir_size() as usize,
+            central_dir_start_offset,
+            prepended_data_len,
+            trailing_data_len: end_of_central_dir.trailing_data_len(),
+            recovered: false,
+            comment: end_of_central_dir.comment().to_vec(),
             zip_files,
+            entry_ends: Vec::new(),
             dir_count,
             files_encrypted,
             file_count,
         })
     }
 
+    /// Builds the entries from the local file headers instead of the central directory, for
+    /// archives whose central directory is missing or truncated like partially downloaded ones.
+    /// Only the entries whose data is complete are kept.
+    pub fn from_local_headers(
+        mut readable: R,
+        parse_options: &ParseOptions,
+    ) -> Result<Self, ZipError> {
+        let (zip_files, entry_ends): (Vec<ZipFile>, Vec<u64>) =
+            scan_local_headers(&mut readable, parse_options)
+                .map_err(|err| ZipError::IOError(err.to_string()))?
+                .into_iter()
+                .unzip();
+
+        if zip_files.is_empty() {
+            return Err(ZipError::NoEntriesRecovered);
+        }
+
+        let dir_count = zip_files
+            .iter()
+            .filter(|zip_file| zip_file.is_dir())
+            .count();
+        let files_encrypted = zip_files
+            .iter()
+            .any(|zip_file| zip_file.encryption_method() != &EncryptionMethod::NoEncryption);
+        let is_zip64 = zip_files
+            .iter()
+            .any(|zip_file| zip_file.uses_zip64_extra_field());
+
+        Ok(Self {
+            readable,
+            is_zip64,
+            zip_file_count: zip_files.len(),
+            // A rebuilt central directory starts right after the last recovered entry
+            central_dir_start_offset: entry_ends[entry_ends.len() - 1],
+            prepended_data_len: 0,
+            trailing_data_len: 0,
+            recovered: true,
+            comment: Vec::new(),
+            file_count: zip_files.len() - dir_count,
+            zip_files,
+            entry_ends,
+            dir_count,
+            files_encrypted,
+        })
+    }
+
     pub fn zip_file_couunt(&self) -> usize {
         self.zip_file_count
     }
@@ -122,6 +353,20 @@ impl<R: ReadableArchive> Zip<R> {
         &self.zip_files
     }
 
+    /// Keeps only the entries matching the predicate and updates the file and directory counts.
+    pub fn retain_zip_files<F>(&mut self, predicate: F)
+    where
+        F: FnMut(&ZipFile) -> bool,
+    {
+        self.zip_files.retain(predicate);
+        self.dir_count = self
+            .zip_files
+            .iter()
+            .filter(|zip_file| zip_file.is_dir())
+            .count();
+        self.file_count = self.zip_files.len() - self.dir_count;
+    }
+
     pub fn dir_count(&self) -> usize {
         self.dir_count
     }
@@ -133,32 +378,3039 @@ impl<R: ReadableArchive> Zip<R> {
     pub fn files_encrypted(&self) -> bool {
         self.files_encrypted
     }
-}
 
-impl<R: ReadableArchive> Archive for Zip<R> {
-    fn extract_items(
+    pub fn comment(&self) -> &[u8] {
+        &self.comment
+    }
+
+    pub fn central_dir_start_offset(&self) -> u64 {
+        self.central_dir_start_offset
+    }
+
+    /// Length of the data before the archive like the stub of a self-extracting archive. The
+    /// offsets of the entries are corrected by it.
+    pub fn prepended_data_len(&self) -> u64 {
+        self.prepended_data_len
+    }
+
+    /// Whether the entries were recovered from the local file headers.
+    pub fn recovered(&self) -> bool {
+        self.recovered
+    }
+
+    /// Length of the data after the end of central directory record and its comment.
+    pub fn trailing_data_len(&self) -> u64 {
+        self.trailing_data_len
+    }
+
+    /// Reads the raw central directory records of the entries in central directory order. The
+    /// offsets of archives with prepended data are corrected to the actual offsets of the entries.
+    pub fn central_dir_records(&mut self) -> Result<Vec<Vec<u8>>, ZipError> {
+        self.zip_files
+            .iter()
+            .map(|zip_file| {
+                let (central_header_start, central_header_len) = zip_file.central_header_range();
+                let mut central_dir_record = vec![0u8; central_header_len as usize];
+
+                self.readable
+                    .seek(SeekFrom::Start(central_header_start))
+                    .and_then(|_| self.readable.read_exact(&mut central_dir_record))
+                    .map_err(|err| ZipError::IOError(err.to_string()))?;
+
+                // Offsets kept in the ZIP64 extra field are left as they are
+                let recorded_offset = LittleEndian::read_u32(&central_dir_record[42..46]);
+
+                if self.prepended_data_len > 0 && recorded_offset != u32::MAX {
+                    if let Ok(offset) = u32::try_from(zip_file.offset()) {
+                        LittleEndian::write_u32(&mut central_dir_record[42..46], offset);
+                    }
+                }
+
+                Ok(central_dir_record)
+            })
+            .collect()
+    }
+
+    /// Copies the entry at the given index into the writer without decompressing it, storing it
+    /// under the given name. Fails with `WriteError::EntryNotFound` when there is no such entry.
+    pub fn copy_entry<W>(
         &mut self,
-        extract_options: ExtractOptions,
-        password: Option<String>,
-    ) -> Result<usize, ExtractError> {
-        let parent = extract_options
-            .path
-            .parent()
-            .map(|parent_path| PathBuf::from(parent_path))
-            .unwrap();
+        index: usize,
+        zip_writer: &mut ZipWriter<W>,
+        file_name: &str,
+    ) -> Result<(), WriteError>
+    where
+        W: Write + Seek,
+    {
+        let zip_file = self
+            .zip_files
+            .get(index)
+            .ok_or(WriteError::EntryNotFound(index))?;
+        let entry_start = zip_file.offset();
+
+        // The length of the data descriptor isn't stored anywhere, so an entry spans until the
+        // next local file header or the central directory. Recovered entries know their end
+        // already, as the next one may come after an incomplete entry which was left out.
+        let entry_end = match self.entry_ends.get(index) {
+            Some(&entry_end) => entry_end,
+            None => self
+                .zip_files
+                .iter()
+                .map(|zip_file| zip_file.offset())
+                .filter(|offset| *offset > entry_start)
+                .min()
+                .unwrap_or(self.central_dir_start_offset),
+        };
+
+        zip_writer.copy_entry(
+            &mut self.readable,
+            zip_file,
+            entry_end - entry_start,
+            file_name,
+        )
+    }
+
+    /// Returns a reader streaming the entries of the archive one at a time.
+    pub fn entries_reader(&mut self, password: Option<String>) -> EntriesReader<'_, R> {
+        EntriesReader {
+            zip_files: self.zip_files.iter(),
+            readable: &mut self.readable,
+            password,
+        }
+    }
+
+    /// Checks the password against the entry with the given name without decoding its data. Zip
+    /// Crypto checks a single byte of the password, so a wrong one may still pass.
+    pub fn check_password(
+        &mut self,
+        file_name: &str,
+        password: &Option<String>,
+    ) -> Result<(), ExtractError> {
+        let zip_file = self
+            .zip_files
+            .iter()
+            .find(|zip_file| zip_file.file_name() == file_name)
+            .ok_or_else(|| ExtractError::EntryNotFound(file_name.to_string()))?;
+
+        entry_reader(zip_file, &mut self.readable, password).map(|_| ())
+    }
+
+    /// Decodes the entry with the given name into memory, charging the decoded bytes to the
+    /// budget until the returned buffer is dropped.
+    pub fn read_entry<'a>(
+        &mut self,
+        file_name: &str,
+        password: &Option<String>,
+        budget: &'a MemoryBudget,
+        crc_algorithm: &'static Algorithm<u32>,
+    ) -> Result<MemoryBuffer<'a>, ExtractError> {
+        let zip_file = self
+            .zip_files
+            .iter()
+            .find(|zip_file| zip_file.file_name() == file_name)
+            .ok_or_else(|| ExtractError::EntryNotFound(file_name.to_string()))?;
+
+        read_to_memory(
+            zip_file,
+            &mut self.readable,
+            password,
+            budget,
+            crc_algorithm,
+        )
+    }
 
+    /// Decodes every file entry into memory in central directory order. All of the buffers are
+    /// charged to the same budget so the total decoded size is capped, not just the size of each
+    /// entry. If an entry fails, the buffers decoded so far are dropped and released.
+    pub fn extract_all_to_memory<'a>(
+        &mut self,
+        password: &Option<String>,
+        budget: &'a MemoryBudget,
+        crc_algorithm: &'static Algorithm<u32>,
+    ) -> Result<Vec<(EntryInfo, MemoryBuffer<'a>)>, ExtractError> {
         self.zip_files
             .iter()
-            .map(|zip_item| {
-                zip_item.extract(
-                    &parent,
+            .filter(|zip_file| !zip_file.is_dir())
+            .map(|zip_file| {
+                read_to_memory(
+                    zip_file,
                     &mut self.readable,
-                    &password,
-                    extract_options.verbose,
+                    password,
+                    budget,
+                    crc_algorithm,
+                )
+                .map(|data| (EntryInfo::from(zip_file), data))
+            })
+            .collect()
+    }
+
+    /// Decodes every entry in central directory order without writing anything and verifies its
+    /// CRC-32 and uncompressed size. The result of each entry is returned with its name. Each of
+    /// the passwords is tried on the encrypted entries.
+    pub fn verify_entries(&mut self, passwords: &[String]) -> Vec<EntryResult> {
+        let mut password_for = |_: &str, attempt: usize| passwords.get(attempt).cloned();
+
+        self.zip_files
+            .iter()
+            .map(|zip_file| {
+                (
+                    zip_file.file_name().clone(),
+                    with_entry_password(
+                        zip_file,
+                        &mut self.readable,
+                        &mut password_for,
+                        |readable, password| verify_entry(zip_file, readable, password),
+                    ),
+                )
+            })
+            .collect()
+    }
+
+    /// Checks the passwords against every encrypted entry in central directory order without
+    /// writing anything, as described in [`check_entry_password`]. The result of each encrypted
+    /// entry is returned with its name.
+    pub fn check_passwords(&mut self, passwords: &[String]) -> Vec<EntryResult> {
+        let mut password_for = |_: &str, attempt: usize| passwords.get(attempt).cloned();
+
+        self.zip_files
+            .iter()
+            .filter(|zip_file| {
+                matches!(
+                    zip_file.encryption_method(),
+                    EncryptionMethod::ZipCrypto | EncryptionMethod::Aes
+                )
+            })
+            .map(|zip_file| {
+                (
+                    zip_file.file_name().clone(),
+                    with_entry_password(
+                        zip_file,
+                        &mut self.readable,
+                        &mut password_for,
+                        |readable, password| check_entry_password(zip_file, readable, password),
+                    ),
                 )
             })
-            .try_fold(0, |count, zip_extract_result| {
-                zip_extract_result.map(|_| count + 1)
+            .collect()
+    }
+
+    /// Writes one tab separated line per entry in central directory order containing the index,
+    /// name, CRC-32, compressed size, uncompressed size and compression method of the entry.
+    pub fn write_manifest<W: Write>(&self, out: &mut W) -> std::io::Result<()> {
+        for (index, zip_file) in self.zip_files.iter().enumerate() {
+            writeln!(
+                out,
+                "{}\t{}\t{:08X}\t{}\t{}\t{}",
+                index,
+                zip_file.file_name(),
+                zip_file.crc32().get(),
+                zip_file.compressed_size().get(),
+                zip_file.uncompressed_size().get(),
+                zip_file.compression_method()
+            )?;
+        }
+
+        Ok(())
+    }
+
+    /// Decides for every entry, in central directory order, whether it is extracted, skipped or
+    /// overwrites an existing file without writing anything.
+    pub fn plan_extraction<S: ExtractSink>(
+        &self,
+        extract_options: &ExtractOptions,
+        sink: &S,
+    ) -> Vec<PlannedEntry> {
+        let extract_root = extract_options.extract_root();
+        let mut planned_file_count = 0;
+        let mut planned_paths = HashSet::new();
+        let last_copies: HashMap<&String, usize> = self
+            .zip_files
+            .iter()
+            .enumerate()
+            .map(|(index, zip_file)| (zip_file.file_name(), index))
+            .collect();
+        let mut seen_names = HashSet::new();
+        let mut folded_paths = HashSet::new();
+        // Renamed entries must not take the path of a later entry either
+        let entry_paths: HashSet<String> = if extract_options.case_insensitive
+            && extract_options.case_collision_policy == CaseCollisionPolicy::Rename
+        {
+            self.zip_files
+                .iter()
+                .map(|zip_file| fold_case(&extract_options.output_path(&extract_root, zip_file)))
+                .collect()
+        } else {
+            HashSet::new()
+        };
+
+        self.zip_files
+            .iter()
+            .enumerate()
+            .map(|(index, zip_file)| {
+                let mut output_path = extract_options.output_path(&extract_root, zip_file);
+                // Archives may hold the same name more than once. Directories are left alone since
+                // creating them again changes nothing.
+                let later_copy = !zip_file.is_dir() && !seen_names.insert(zip_file.file_name());
+                let earlier_copy = !zip_file.is_dir() && last_copies[zip_file.file_name()] != index;
+
+                if later_copy && extract_options.duplicate_policy == DuplicatePolicy::KeepAllRenamed
+                {
+                    output_path = renamed_path(&output_path, |path| planned_paths.contains(path));
+                }
+
+                // Files from different directories end up with the same name once the paths are
+                // junked.
+                let collides = extract_options.junk_paths
+                    && !zip_file.is_dir()
+                    && planned_paths.contains(&output_path);
+
+                if collides && extract_options.collision_policy == CollisionPolicy::Rename {
+                    output_path = renamed_path(&output_path, |path| planned_paths.contains(path));
+                }
+
+                // Paths equal to an earlier one are handled above, the ones equal except for case
+                // only collide on a case-insensitive filesystem.
+                let case_collides = extract_options.case_insensitive
+                    && !zip_file.is_dir()
+                    && !planned_paths.contains(&output_path)
+                    && folded_paths.contains(&fold_case(&output_path));
+
+                if case_collides
+                    && extract_options.case_collision_policy == CaseCollisionPolicy::Rename
+                {
+                    output_path = renamed_path(&output_path, |path| {
+                        let folded_path = fold_case(path);
+                        folded_paths.contains(&folded_path) || entry_paths.contains(&folded_path)
+                    });
+                }
+
+                // Directories don't count towards the limit so that the structure of the
+                // extracted files is still created.
+                let (action, reason) = if extract_options
+                    .limit
+                    .is_some_and(|limit| planned_file_count >= limit)
+                {
+                    (PlanAction::Skip, "limit reached")
+                } else if !extract_options.is_selected(zip_file) {
+                    (PlanAction::Skip, "not selected by the entry filters")
+                } else if extract_options.skip_macos_metadata && zip_file.is_macos_metadata() {
+                    (PlanAction::Skip, "macOS metadata")
+                } else if extract_options.filters_file_types() && zip_file.is_dir() {
+                    (PlanAction::Skip, "directory with file type filters")
+                } else if extract_options.is_selected_file_type(zip_file) == Some(false) {
+                    (PlanAction::Skip, FILE_TYPE_NOT_SELECTED)
+                } else if later_copy && extract_options.duplicate_policy == DuplicatePolicy::First {
+                    (PlanAction::Skip, "same name as an earlier entry")
+                } else if earlier_copy && extract_options.duplicate_policy == DuplicatePolicy::Last
+                {
+                    (PlanAction::Skip, "same name as a later entry")
+                } else if extract_options.junk_paths && zip_file.is_dir() {
+                    (PlanAction::Skip, "directory with junked paths")
+                } else if extract_options.escapes_root(zip_file) {
+                    (PlanAction::Skip, "path outside the extraction root")
+                } else if extract_options.entry_path(zip_file).is_none() {
+                    (PlanAction::Skip, "no path left after stripping components")
+                } else if collides && extract_options.collision_policy == CollisionPolicy::Skip {
+                    (PlanAction::Skip, "same name as an earlier entry")
+                } else if collides && extract_options.collision_policy == CollisionPolicy::Overwrite
+                {
+                    (PlanAction::Overwrite, "same name as an earlier entry")
+                } else if case_collides {
+                    match extract_options.case_collision_policy {
+                        CaseCollisionPolicy::Warn => (PlanAction::Overwrite, CASE_COLLISION),
+                        CaseCollisionPolicy::Rename => (PlanAction::Extract, CASE_COLLISION),
+                    }
+                } else if extract_options.freshen && sink.metadata(&output_path).is_none() {
+                    (PlanAction::Skip, "target missing")
+                } else if extract_options.freshen && !zip_file.is_dir() {
+                    if is_outdated(zip_file, &output_path, sink) {
+                        (PlanAction::Overwrite, "target older than the entry")
+                    } else {
+                        (PlanAction::Skip, "target up to date")
+                    }
+                } else if extract_options.resume
+                    && is_already_extracted(
+                        zip_file,
+                        &output_path,
+                        extract_options.crc_algorithm,
+                        sink,
+                    )
+                {
+                    (PlanAction::Skip, "already extracted")
+                } else if !zip_file.is_dir() && sink.metadata(&output_path).is_some() {
+                    match extract_options.overwrite_policy {
+                        OverwritePolicy::Always => (PlanAction::Overwrite, "target exists"),
+                        OverwritePolicy::Skip => (PlanAction::Skip, "target exists"),
+                    }
+                } else if zip_file.is_dir() {
+                    (PlanAction::Extract, "directory")
+                } else {
+                    (PlanAction::Extract, "new file")
+                };
+
+                if action != PlanAction::Skip && !zip_file.is_dir() {
+                    planned_file_count += 1;
+                    planned_paths.insert(output_path.clone());
+                    folded_paths.insert(fold_case(&output_path));
+                }
+
+                PlannedEntry {
+                    name: zip_file.file_name().clone(),
+                    output_path,
+                    action,
+                    reason,
+                }
             })
+            .collect()
+    }
+
+    /// Returns whether the archive uses ZIP64 extensions, either through the ZIP64 end of central
+    /// directory locator or a ZIP64 extra field in any of the entries.
+    pub fn is_zip64(&self) -> bool {
+        self.is_zip64
+    }
+
+    /// Extracts the entries as planned by [`Zip::plan_extraction`] into the sink and returns the
+    /// result of each extracted entry with its name. The first failure stops the extraction unless
+    /// the options salvage, in which case the failed entries are returned along with the others.
+    /// Each of the passwords is tried on the encrypted entries, which may use different ones.
+    pub fn extract_entries<S: ExtractSink>(
+        &mut self,
+        extract_options: &ExtractOptions,
+        passwords: &[String],
+        sink: &mut S,
+    ) -> Result<Vec<EntryResult>, ExtractError> {
+        self.extract_entries_with(
+            extract_options,
+            |_, attempt| passwords.get(attempt).cloned(),
+            sink,
+        )
+    }
+
+    /// Extracts the entries like [`Zip::extract_entries`], asking the callback for the passwords
+    /// of the encrypted entries as described in [`with_entry_password`].
+    pub fn extract_entries_with<S, F>(
+        &mut self,
+        extract_options: &ExtractOptions,
+        mut password_for: F,
+        sink: &mut S,
+    ) -> Result<Vec<EntryResult>, ExtractError>
+    where
+        S: ExtractSink,
+        F: FnMut(&str, usize) -> Option<String>,
+    {
+        // The entries of the archives extracted before with the same totals count as well
+        let entry_count = extract_options.totals.entry_count.get() + self.zip_files.len();
+
+        if let Some(max_entry_count) = extract_options.max_entry_count {
+            if entry_count > max_entry_count {
+                return Err(ExtractError::EntryCountExceeded(max_entry_count));
+            }
+        }
+
+        extract_options.totals.entry_count.set(entry_count);
+
+        if extract_options.duplicate_policy == DuplicatePolicy::Error {
+            let mut file_names = HashSet::new();
+            let duplicate = self
+                .zip_files
+                .iter()
+                .filter(|zip_file| !zip_file.is_dir())
+                .find(|zip_file| !file_names.insert(zip_file.file_name()));
+
+            if let Some(duplicate) = duplicate {
+                return Err(ExtractError::DuplicateEntry(duplicate.file_name().clone()));
+            }
+        }
+
+        let plan = self.plan_extraction(extract_options, sink);
+        // A path written more than once is not linked so that writing it again cannot change the
+        // files linked to it
+        let mut planned_paths = HashSet::new();
+        let rewritten_paths: HashSet<PathBuf> = plan
+            .iter()
+            .filter(|planned_entry| planned_entry.action != PlanAction::Skip)
+            .filter(|planned_entry| !planned_paths.insert(&planned_entry.output_path))
+            .map(|planned_entry| planned_entry.output_path.clone())
+            .collect();
+        let mut results = Vec::new();
+        let mut extracted_dirs = Vec::new();
+        let mut extracted_files: HashMap<_, PathBuf> = HashMap::new();
+
+        for (zip_item, planned_entry) in self.zip_files.iter().zip(plan) {
+            if planned_entry.action == PlanAction::Skip {
+                if extract_options.verbose {
+                    println!(
+                        "Skipping {} ({})",
+                        planned_entry.output_path.display(),
+                        planned_entry.reason
+                    );
+                }
+
+                continue;
+            }
+
+            // Files whose extension tells no type are only recognized once they are decoded
+            if extract_options.is_selected_file_type(zip_item).is_none() {
+                let sniffed_type = with_entry_password(
+                    zip_item,
+                    &mut self.readable,
+                    &mut password_for,
+                    |readable, password| sniff_file_type(zip_item, readable, password),
+                );
+
+                match sniffed_type {
+                    Ok(Some(file_type)) if extract_options.only_types.contains(&file_type) => (),
+                    Ok(_) => {
+                        if extract_options.verbose {
+                            println!(
+                                "Skipping {} ({})",
+                                planned_entry.output_path.display(),
+                                FILE_TYPE_NOT_SELECTED
+                            );
+                        }
+
+                        continue;
+                    }
+                    Err(err) if extract_options.salvage => {
+                        results.push((zip_item.file_name().clone(), Err(err)));
+                        continue;
+                    }
+                    Err(err) => return Err(err),
+                }
+            }
+
+            if planned_entry.reason == CASE_COLLISION {
+                match extract_options.case_collision_policy {
+                    CaseCollisionPolicy::Warn => eprintln!(
+                        "Warning: {} differs from an earlier entry only by case and overwrites it",
+                        planned_entry.name
+                    ),
+                    CaseCollisionPolicy::Rename => println!(
+                        "Renamed {} to {}",
+                        planned_entry.name,
+                        planned_entry.output_path.display()
+                    ),
+                }
+            } else if extract_options.is_sanitized(zip_item) {
+                println!(
+                    "Renamed {} to {}",
+                    planned_entry.name,
+                    planned_entry.output_path.display()
+                );
+            }
+
+            // Files with the same CRC-32, size and permissions are taken as copies of each other.
+            // Empty files are left alone since linking them saves nothing.
+            let duplicate_key = (extract_options.link_duplicates
+                && zip_item.entry_kind() == EntryKind::File
+                && zip_item.stores_crc32()
+                && zip_item.uncompressed_size().get() != 0
+                && !rewritten_paths.contains(&planned_entry.output_path))
+            .then(|| {
+                (
+                    zip_item.crc32().get(),
+                    zip_item.uncompressed_size().get(),
+                    zip_item.unix_permissions(),
+                )
+            });
+            // A symlink extracted before could point anywhere once the entries after it are
+            // written through it
+            let through_symlink =
+                extract_options.passes_through_symlink(zip_item, &planned_entry.output_path);
+            let linked = duplicate_key
+                .filter(|_| !through_symlink)
+                .and_then(|duplicate_key| extracted_files.get(&duplicate_key))
+                .is_some_and(|original_path| {
+                    link_duplicate(
+                        sink,
+                        original_path,
+                        &planned_entry.output_path,
+                        extract_options.verbose,
+                    )
+                });
+
+            let extract_result = if through_symlink {
+                Err(ExtractError::EntryThroughSymlink(
+                    zip_item.file_name().clone(),
+                ))
+            } else if linked {
+                Ok(())
+            } else {
+                with_entry_password(
+                    zip_item,
+                    &mut self.readable,
+                    &mut password_for,
+                    |readable, password| {
+                        zip_item.extract(
+                            &planned_entry.output_path,
+                            readable,
+                            password,
+                            extract_options,
+                            sink,
+                        )
+                    },
+                )
+            };
+
+            match extract_result {
+                Ok(()) if zip_item.is_dir() => {
+                    extracted_dirs.push((results.len(), zip_item, planned_entry.output_path));
+                    results.push((zip_item.file_name().clone(), Ok(())));
+                }
+                Ok(()) => {
+                    results.push((zip_item.file_name().clone(), Ok(())));
+
+                    if let Some(duplicate_key) = duplicate_key {
+                        extracted_files
+                            .entry(duplicate_key)
+                            .or_insert(planned_entry.output_path);
+                    }
+                }
+                // Salvaging keeps extracting the other entries and reports the failures at the end
+                Err(err) if extract_options.salvage => {
+                    results.push((zip_item.file_name().clone(), Err(err)))
+                }
+                Err(err) => return Err(err),
+            }
+        }
+
+        // Extracting the files inside a directory changes its modification time and needs it to
+        // be writable, so directories get their owner, permissions and modification time once
+        // everything is extracted, nested directories first.
+        for (index, zip_item, output_path) in extracted_dirs.iter().rev() {
+            let dir_result =
+                sink.set_metadata(zip_item, output_path, &mut self.readable, extract_options);
+
+            match dir_result {
+                Ok(()) => (),
+                Err(err) if extract_options.salvage => results[*index].1 = Err(err),
+                Err(err) => return Err(err),
+            }
+        }
+
+        Ok(results)
+    }
+}
+
+impl<R: ReadableArchive> Archive for Zip<R> {
+    fn extract_items(
+        &mut self,
+        extract_options: ExtractOptions,
+        passwords: Vec<String>,
+    ) -> Result<usize, ExtractError> {
+        // The destination is created even when the archive has no entries
+        std::fs::create_dir_all(extract_options.extract_root())
+            .map_err(|err| ExtractError::IOError(err.to_string()))?;
+
+        let results = self.extract_entries(&extract_options, &passwords, &mut FileSystemSink)?;
+        let failures: Vec<_> = results
+            .iter()
+            .filter_map(|(file_name, result)| result.as_ref().err().map(|err| (file_name, err)))
+            .collect();
+
+        if !failures.is_empty() {
+            eprintln!(
+                "{} of {} entries could not be extracted:\n",
+                failures.len(),
+                results.len()
+            );
+            eprint!("{}", format_failures(&failures));
+
+            return Err(ExtractError::EntriesFailed(failures.len()));
+        }
+
+        Ok(results.len())
+    }
+}
+
+// Reads the data descriptor of the entry from where its data ends, found by walking from its local
+// file header. Only the ZIP64 extra field of the local file header tells whether the descriptor
+// stores 8 byte sizes, so the descriptor is read through the local entry.
+fn update_with_data_descriptor<R>(
+    readable: &mut R,
+    zip_file: &ZipFile,
+    parse_options: &ParseOptions,
+) -> std::io::Result<()>
+where
+    R: Read + Seek,
+{
+    readable.seek(SeekFrom::Start(zip_file.offset()))?;
+
+    let local_zip_file = ZipFile::from_local_header_with_options(readable, parse_options)
+        .map_err(|err| std::io::Error::new(std::io::ErrorKind::InvalidData, err.to_string()))?;
+    let data_start_offset = readable.stream_position()?;
+
+    if let Some(data_descriptor_offset) =
+        find_data_descriptor(readable, zip_file, data_start_offset)?
+    {
+        local_zip_file.update_with_data_descriptor(
+            readable,
+            data_descriptor_offset,
+            data_descriptor_offset - data_start_offset,
+        )?;
+
+        zip_file.crc32().set(local_zip_file.crc32().get());
+        zip_file
+            .compressed_size()
+            .set(local_zip_file.compressed_size().get());
+        zip_file
+            .uncompressed_size()
+            .set(local_zip_file.uncompressed_size().get());
+    }
+
+    Ok(())
+}
+
+// The central directory ends where the end records start, so when no central directory is found
+// at the recorded offset, its distance to the actual start is the length of the prepended data
+fn prepended_data_len<R>(readable: &mut R, end_of_central_dir: &EndOfCentralDirectory) -> u64
+where
+    R: Read + Seek,
+{
+    let recorded_offset = end_of_central_dir.central_dir_start_offset();
+    let actual_offset = end_of_central_dir
+        .zip64_record_offset()
+        .unwrap_or(end_of_central_dir.offset())
+        .checked_sub(end_of_central_dir.central_dir_size_in_bytes());
+
+    match actual_offset {
+        Some(actual_offset)
+            if actual_offset > recorded_offset
+                && !has_signature(readable, recorded_offset, CENTRAL_DIR_SIGN)
+                && has_signature(readable, actual_offset, CENTRAL_DIR_SIGN) =>
+        {
+            actual_offset - recorded_offset
+        }
+        _ => 0,
+    }
+}
+
+fn has_signature<R>(readable: &mut R, offset: u64, signature: u32) -> bool
+where
+    R: Read + Seek,
+{
+    let mut signature_bytes = [0u8; 4];
+
+    readable
+        .seek(SeekFrom::Start(offset))
+        .and_then(|_| readable.read_exact(&mut signature_bytes))
+        .is_ok_and(|_| LittleEndian::read_u32(&signature_bytes) == signature)
+}
+
+// PKWARE Strong Encryption can encrypt the central directory. The encrypted central directory is
+// preceded by an archive extra data record and the local file headers are masked, which is marked
+// by bit 13 of their general purpose bit flag.
+fn central_dir_encrypted<R>(
+    readable: &mut R,
+    archive_start_offset: u64,
+    central_dir_start_offset: u64,
+) -> bool
+where
+    R: Read + Seek,
+{
+    let mut signature = [0u8; 4];
+    let mut local_file_header = [0u8; 8];
+
+    let archive_extra_data_found = readable
+        .seek(SeekFrom::Start(central_dir_start_offset))
+        .and_then(|_| readable.read_exact(&mut signature))
+        .is_ok_and(|_| LittleEndian::read_u32(&signature) == ARCHIVE_EXTRA_DATA_SIGN);
+    let local_file_header_masked = readable
+        .seek(SeekFrom::Start(archive_start_offset))
+        .and_then(|_| readable.read_exact(&mut local_file_header))
+        .is_ok_and(|_| {
+            LittleEndian::read_u32(&local_file_header[..4]) == LOCAL_FILE_HEADER_SIGN
+                && LittleEndian::read_u16(&local_file_header[6..8]) & (1 << 13) != 0
+        });
+
+    archive_extra_data_found || local_file_header_masked
+}
+
+// Hard links the output path to the identical file extracted before. Returns false when the file
+// cannot be linked, like on file systems without hard links, so that the entry is extracted instead.
+fn link_duplicate<S: ExtractSink>(
+    sink: &mut S,
+    original_path: &Path,
+    output_path: &Path,
+    verbose: bool,
+) -> bool {
+    if original_path == output_path {
+        return false;
+    }
+
+    let linked = sink.hard_link(original_path, output_path).is_ok();
+
+    if linked && verbose {
+        println!(
+            "Linking {} to {}",
+            output_path.display(),
+            original_path.display()
+        );
+    }
+
+    linked
+}
+
+// Adds the first `-1`, `-2`, ... suffix to the file stem that gives a path not planned yet.
+fn renamed_path(path: &Path, is_taken: impl Fn(&Path) -> bool) -> PathBuf {
+    let file_stem = path.file_stem().unwrap_or_default().to_string_lossy();
+    let extension = path
+        .extension()
+        .map(|extension| extension.to_string_lossy());
+
+    (1..)
+        .map(|suffix| {
+            path.with_file_name(match &extension {
+                Some(extension) => format!("{}-{}.{}", file_stem, suffix, extension),
+                None => format!("{}-{}", file_stem, suffix),
+            })
+        })
+        .find(|renamed_path| !is_taken(renamed_path))
+        .unwrap()
+}
+
+// Returns the key paths differing only by case share on case-insensitive filesystems.
+fn fold_case(path: &Path) -> String {
+    path.to_string_lossy().to_lowercase()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::date_time::ZipDateTime;
+    use crate::file_type::FileType;
+    use crate::headers::FileNamePolicy;
+    use crate::split::SplitArchive;
+    use crate::test_utils::{build_archive, TestEntry};
+    use crate::writer::EntryOptions;
+    use crate::zip_crypto::{ZipCryptoError, ZipCryptoReader};
+    use byteorder::{ByteOrder, LittleEndian};
+    use crc::CRC_32_ISO_HDLC;
+    use globset::{Glob, GlobSetBuilder};
+    use regex::Regex;
+    use std::fs::File;
+    use std::io::{BufReader, Cursor};
+    use std::time::{Duration, UNIX_EPOCH};
+
+    #[test]
+    fn test_dir_count_with_mode_based_directory() {
+        let archive = build_archive(&[
+            TestEntry::dir("docs/"),
+            TestEntry::file("assets", b"").with_external_attributes(0o040755 << 16),
+            TestEntry::file("docs/readme.txt", b"hello"),
+        ]);
+        let zip = Zip::from_readable(BufReader::new(Cursor::new(archive))).unwrap();
+
+        assert_eq!(zip.dir_count(), 2);
+        assert_eq!(zip.file_count(), 1);
+    }
+
+    #[test]
+    fn test_extract_with_limit() {
+        let dir = tempfile::tempdir().unwrap();
+        let zip_path = dir.path().join("archive.zip");
+
+        std::fs::write(
+            &zip_path,
+            build_archive(&[
+                TestEntry::dir("docs/"),
+                TestEntry::file("docs/1.txt", b"1"),
+                TestEntry::file("docs/2.txt", b"2"),
+                TestEntry::file("docs/3.txt", b"3"),
+                TestEntry::file("docs/4.txt", b"4"),
+            ]),
+        )
+        .unwrap();
+
+        let mut zip = Zip::from_readable(BufReader::new(File::open(&zip_path).unwrap())).unwrap();
+        let mut extract_options = ExtractOptions::new(zip_path, false, None);
+        extract_options.limit = Some(2);
+
+        assert_eq!(zip.extract_items(extract_options, Vec::new()).unwrap(), 3);
+        assert!(dir.path().join("docs/1.txt").exists());
+        assert!(dir.path().join("docs/2.txt").exists());
+        assert!(!dir.path().join("docs/3.txt").exists());
+        assert!(!dir.path().join("docs/4.txt").exists());
+    }
+
+    #[test]
+    fn test_extract_with_salvage() {
+        let dir = tempfile::tempdir().unwrap();
+        let zip_path = dir.path().join("archive.zip");
+        let mut archive = build_archive(&[
+            TestEntry::file("a.txt", b"hello"),
+            TestEntry::file("b.txt", b"world"),
+        ]);
+
+        // Corrupt the data of the first entry so its CRC-32 doesn't match
+        archive[30 + "a.txt".len()] = b'j';
+        std::fs::write(&zip_path, &archive).unwrap();
+
+        let mut zip = Zip::from_readable(BufReader::new(File::open(&zip_path).unwrap())).unwrap();
+        let extract_options = ExtractOptions::new(zip_path.clone(), false, None);
+
+        assert!(matches!(
+            zip.extract_items(extract_options, Vec::new()),
+            Err(ExtractError::InvalidExtractedFile(_, _))
+        ));
+        assert!(!dir.path().join("b.txt").exists());
+
+        let mut extract_options = ExtractOptions::new(zip_path, false, None);
+        extract_options.salvage = true;
+
+        let results = zip
+            .extract_entries(&extract_options, &[], &mut FileSystemSink)
+            .unwrap();
+
+        assert_eq!(results.len(), 2);
+        assert!(matches!(
+            results[0],
+            (ref file_name, Err(ExtractError::InvalidExtractedFile(_, _))) if file_name == "a.txt"
+        ));
+        assert_eq!(results[1], ("b.txt".to_string(), Ok(())));
+        assert_eq!(
+            zip.extract_items(extract_options, Vec::new()),
+            Err(ExtractError::EntriesFailed(1))
+        );
+        assert_eq!(std::fs::read(dir.path().join("b.txt")).unwrap(), b"world");
+    }
+
+    #[test]
+    fn test_extract_with_regex() {
+        let dir = tempfile::tempdir().unwrap();
+        let zip_path = dir.path().join("archive.zip");
+
+        std::fs::write(
+            &zip_path,
+            build_archive(&[
+                TestEntry::file("src/main.rs", b"fn main() {}"),
+                TestEntry::file("src/lib.rs", b""),
+                TestEntry::file("src/notes.txt", b"notes"),
+                TestEntry::file("tests/test.rs", b""),
+                TestEntry::file("Cargo.toml", b""),
+            ]),
+        )
+        .unwrap();
+
+        let mut zip = Zip::from_readable(BufReader::new(File::open(&zip_path).unwrap())).unwrap();
+        let mut extract_options = ExtractOptions::new(zip_path, false, None);
+        extract_options.regex = Some(Regex::new(r"^src/.*\.rs$").unwrap());
+
+        assert_eq!(zip.extract_items(extract_options, Vec::new()).unwrap(), 2);
+        assert!(dir.path().join("src/main.rs").exists());
+        assert!(dir.path().join("src/lib.rs").exists());
+        assert!(!dir.path().join("src/notes.txt").exists());
+        assert!(!dir.path().join("tests").exists());
+        assert!(!dir.path().join("Cargo.toml").exists());
+    }
+
+    #[test]
+    fn test_extract_skipping_macos_metadata() {
+        let dir = tempfile::tempdir().unwrap();
+        let zip_path = dir.path().join("archive.zip");
+
+        std::fs::write(
+            &zip_path,
+            build_archive(&[
+                TestEntry::dir("photos/"),
+                TestEntry::file("photos/cat.jpg", b"cat"),
+                TestEntry::file("photos/.DS_Store", b""),
+                TestEntry::dir("__MACOSX/"),
+                TestEntry::dir("__MACOSX/photos/"),
+                TestEntry::file("__MACOSX/photos/._cat.jpg", b""),
+            ]),
+        )
+        .unwrap();
+
+        let mut zip = Zip::from_readable(BufReader::new(File::open(&zip_path).unwrap())).unwrap();
+        let mut extract_options = ExtractOptions::new(zip_path, false, None);
+        extract_options.skip_macos_metadata = true;
+
+        assert_eq!(zip.extract_items(extract_options, Vec::new()).unwrap(), 2);
+        assert!(dir.path().join("photos/cat.jpg").exists());
+        assert!(!dir.path().join("photos/.DS_Store").exists());
+        assert!(!dir.path().join("__MACOSX").exists());
+
+        zip.retain_zip_files(|zip_file| !zip_file.is_macos_metadata());
+
+        assert_eq!(zip.zip_files().len(), 2);
+        assert_eq!((zip.file_count(), zip.dir_count()), (1, 1));
+    }
+
+    #[test]
+    fn test_extract_only_file_types() {
+        let dir = tempfile::tempdir().unwrap();
+        let zip_path = dir.path().join("archive.zip");
+
+        std::fs::write(
+            &zip_path,
+            build_archive(&[
+                TestEntry::dir("photos/"),
+                TestEntry::file("photos/cat.jpg", b"cat"),
+                TestEntry::file("photos/scan", b"\x89PNG\r\n\x1a\n\0\0\0\rIHDR").deflated(),
+                TestEntry::file("docs/report.PDF", b"%PDF-1.7"),
+                TestEntry::file("docs/notes.txt", b"notes"),
+                TestEntry::file("data/blob", b"hello"),
+            ]),
+        )
+        .unwrap();
+
+        let mut zip = Zip::from_readable(BufReader::new(File::open(&zip_path).unwrap())).unwrap();
+        let mut extract_options = ExtractOptions::new(zip_path, false, None);
+        extract_options.only_types = vec![FileType::Images];
+        extract_options.only_extensions = vec![String::from("pdf")];
+
+        let plan = zip.plan_extraction(&extract_options, &FileSystemSink);
+
+        assert_eq!(plan[0].reason, "directory with file type filters");
+        assert_eq!(plan[4].reason, FILE_TYPE_NOT_SELECTED);
+        // The type of the files without an extension is only known once they are decoded
+        assert_eq!(plan[2].action, PlanAction::Extract);
+        assert_eq!(plan[5].action, PlanAction::Extract);
+
+        assert_eq!(zip.extract_items(extract_options, Vec::new()).unwrap(), 3);
+        assert!(dir.path().join("photos/cat.jpg").exists());
+        assert!(dir.path().join("photos/scan").exists());
+        assert!(dir.path().join("docs/report.PDF").exists());
+        assert!(!dir.path().join("docs/notes.txt").exists());
+        assert!(!dir.path().join("data").exists());
+    }
+
+    #[test]
+    fn test_extract_with_duplicate_policy() {
+        let dir = tempfile::tempdir().unwrap();
+        let zip_path = dir.path().join("archive.zip");
+
+        std::fs::write(
+            &zip_path,
+            build_archive(&[
+                TestEntry::file("notes.txt", b"first"),
+                TestEntry::file("todo.txt", b"todo"),
+                TestEntry::file("notes.txt", b"second"),
+            ]),
+        )
+        .unwrap();
+
+        let mut zip = Zip::from_readable(BufReader::new(File::open(&zip_path).unwrap())).unwrap();
+        let extract = |zip: &mut Zip<_>, into: &str, duplicate_policy| {
+            let mut extract_options = ExtractOptions::new(zip_path.clone(), false, None);
+            extract_options.into = Some(PathBuf::from(into));
+            extract_options.duplicate_policy = duplicate_policy;
+            zip.extract_items(extract_options, Vec::new())
+        };
+        let read = |path: &str| std::fs::read_to_string(dir.path().join(path)).unwrap();
+
+        assert_eq!(
+            extract(&mut zip, "first", DuplicatePolicy::First).unwrap(),
+            2
+        );
+        assert_eq!(read("first/notes.txt"), "first");
+        assert_eq!(extract(&mut zip, "last", DuplicatePolicy::Last).unwrap(), 2);
+        assert_eq!(read("last/notes.txt"), "second");
+        assert_eq!(
+            extract(&mut zip, "renamed", DuplicatePolicy::KeepAllRenamed).unwrap(),
+            3
+        );
+        assert_eq!(read("renamed/notes.txt"), "first");
+        assert_eq!(read("renamed/notes-1.txt"), "second");
+        assert!(matches!(
+            extract(&mut zip, "error", DuplicatePolicy::Error),
+            Err(ExtractError::DuplicateEntry(file_name)) if file_name == "notes.txt"
+        ));
+        assert!(!dir.path().join("error").exists());
+    }
+
+    #[test]
+    fn test_extract_with_case_collisions() {
+        let dir = tempfile::tempdir().unwrap();
+        let zip_path = dir.path().join("archive.zip");
+
+        std::fs::write(
+            &zip_path,
+            build_archive(&[
+                TestEntry::file("README", b"upper"),
+                TestEntry::file("readme", b"lower"),
+                TestEntry::file("readme-1", b"taken"),
+                TestEntry::file("Readme", b"title"),
+            ]),
+        )
+        .unwrap();
+
+        let mut zip = Zip::from_readable(BufReader::new(File::open(&zip_path).unwrap())).unwrap();
+        let mut extract_options = ExtractOptions::new(zip_path, false, None);
+        extract_options.into = Some(PathBuf::from("out"));
+        extract_options.case_insensitive = true;
+
+        let actions: Vec<_> = zip
+            .plan_extraction(&extract_options, &FileSystemSink)
+            .into_iter()
+            .map(|planned_entry| planned_entry.action)
+            .collect();
+
+        assert_eq!(
+            actions,
+            [
+                PlanAction::Extract,
+                PlanAction::Overwrite,
+                PlanAction::Extract,
+                PlanAction::Overwrite
+            ]
+        );
+
+        extract_options.case_collision_policy = CaseCollisionPolicy::Rename;
+
+        assert_eq!(zip.extract_items(extract_options, Vec::new()).unwrap(), 4);
+
+        let read = |path: &str| std::fs::read_to_string(dir.path().join("out").join(path)).unwrap();
+
+        assert_eq!(read("README"), "upper");
+        assert_eq!(read("readme-2"), "lower");
+        assert_eq!(read("readme-1"), "taken");
+        assert_eq!(read("Readme-3"), "title");
+    }
+
+    #[test]
+    fn test_extract_with_patterns() {
+        let dir = tempfile::tempdir().unwrap();
+        let zip_path = dir.path().join("archive.zip");
+
+        std::fs::write(
+            &zip_path,
+            build_archive(&[
+                TestEntry::file("docs/index.md", b"# Index"),
+                TestEntry::file("docs/guide/setup.md", b"# Setup"),
+                TestEntry::file("docs/guide/logo.png", b""),
+                TestEntry::file("README.md", b""),
+                TestEntry::file("Cargo.toml", b""),
+            ]),
+        )
+        .unwrap();
+
+        let mut glob_set_builder = GlobSetBuilder::new();
+        glob_set_builder.add(Glob::new("docs/**/*.md").unwrap());
+        glob_set_builder.add(Glob::new("Cargo.toml").unwrap());
+
+        let mut zip = Zip::from_readable(BufReader::new(File::open(&zip_path).unwrap())).unwrap();
+        let mut extract_options = ExtractOptions::new(zip_path, false, None);
+        extract_options.patterns = Some(glob_set_builder.build().unwrap());
+
+        assert_eq!(zip.extract_items(extract_options, Vec::new()).unwrap(), 3);
+        assert!(dir.path().join("docs/index.md").exists());
+        assert!(dir.path().join("docs/guide/setup.md").exists());
+        assert!(dir.path().join("Cargo.toml").exists());
+        assert!(!dir.path().join("docs/guide/logo.png").exists());
+        assert!(!dir.path().join("README.md").exists());
+    }
+
+    #[test]
+    fn test_extract_with_strip_components() {
+        let dir = tempfile::tempdir().unwrap();
+        let zip_path = dir.path().join("archive.zip");
+
+        std::fs::write(
+            &zip_path,
+            build_archive(&[
+                TestEntry::dir("project-1.2.3/"),
+                TestEntry::file("project-1.2.3/README.md", b"# Project"),
+                TestEntry::dir("project-1.2.3/src/"),
+                TestEntry::file("project-1.2.3/src/main.rs", b"fn main() {}"),
+                TestEntry::file("LICENSE", b""),
+            ]),
+        )
+        .unwrap();
+
+        let mut zip = Zip::from_readable(BufReader::new(File::open(&zip_path).unwrap())).unwrap();
+        let mut extract_options = ExtractOptions::new(zip_path, false, None);
+        extract_options.strip_components = 1;
+
+        let plan = zip.plan_extraction(&extract_options, &FileSystemSink);
+        assert_eq!(plan[0].action, PlanAction::Skip);
+        assert_eq!(plan[4].action, PlanAction::Skip);
+
+        assert_eq!(zip.extract_items(extract_options, Vec::new()).unwrap(), 3);
+        assert_eq!(
+            std::fs::read(dir.path().join("src/main.rs")).unwrap(),
+            b"fn main() {}"
+        );
+        assert!(dir.path().join("README.md").exists());
+        assert!(!dir.path().join("project-1.2.3").exists());
+        assert!(!dir.path().join("LICENSE").exists());
+    }
+
+    #[test]
+    fn test_extract_with_sanitized_names() {
+        let dir = tempfile::tempdir().unwrap();
+        let zip_path = dir.path().join("archive.zip");
+
+        std::fs::write(
+            &zip_path,
+            build_archive(&[
+                TestEntry::dir("aux/"),
+                TestEntry::file("aux/CON.txt", b"con"),
+                TestEntry::file("a:b.txt", b"a:b"),
+                TestEntry::file("notes.txt", b"notes"),
+            ]),
+        )
+        .unwrap();
+
+        let mut zip = Zip::from_readable(BufReader::new(File::open(&zip_path).unwrap())).unwrap();
+        let mut extract_options = ExtractOptions::new(zip_path.clone(), false, None);
+        extract_options.sanitize_names = true;
+
+        let output_paths: Vec<_> = zip
+            .plan_extraction(&extract_options, &FileSystemSink)
+            .into_iter()
+            .map(|planned_entry| planned_entry.output_path)
+            .collect();
+
+        assert_eq!(
+            output_paths,
+            vec![
+                dir.path().join("_aux"),
+                dir.path().join("_aux/_CON.txt"),
+                dir.path().join("a_b.txt"),
+                dir.path().join("notes.txt"),
+            ]
+        );
+        assert!(!extract_options.is_sanitized(&zip.zip_files()[3]));
+        assert_eq!(zip.extract_items(extract_options, Vec::new()).unwrap(), 4);
+        assert_eq!(
+            std::fs::read(dir.path().join("_aux/_CON.txt")).unwrap(),
+            b"con"
+        );
+    }
+
+    #[test]
+    fn test_extract_with_portable_names() {
+        let dir = tempfile::tempdir().unwrap();
+        let zip_path = dir.path().join("archive.zip");
+        let long_name = format!("{}.txt", "x".repeat(300));
+
+        std::fs::write(
+            &zip_path,
+            build_archive(&[
+                TestEntry::file("docs\\a?.txt", b"a"),
+                TestEntry::file(&format!("long/{}", long_name), b"long"),
+            ]),
+        )
+        .unwrap();
+
+        let mut zip = Zip::from_readable(BufReader::new(File::open(&zip_path).unwrap())).unwrap();
+        let mut extract_options = ExtractOptions::new(zip_path, false, None);
+        extract_options.portable_names = true;
+
+        assert!(zip
+            .zip_files()
+            .iter()
+            .all(|zip_file| extract_options.is_sanitized(zip_file)));
+        assert_eq!(zip.extract_items(extract_options, Vec::new()).unwrap(), 2);
+        assert!(dir.path().join("docs_a_.txt").exists());
+        assert!(dir
+            .path()
+            .join("long")
+            .join(format!("{}.txt", "x".repeat(251)))
+            .exists());
+    }
+
+    #[test]
+    fn test_extract_with_junk_paths() {
+        let dir = tempfile::tempdir().unwrap();
+        let zip_path = dir.path().join("archive.zip");
+
+        std::fs::write(
+            &zip_path,
+            build_archive(&[
+                TestEntry::dir("docs/"),
+                TestEntry::file("docs/notes.txt", b"docs"),
+                TestEntry::file("src/notes.txt", b"src"),
+                TestEntry::file("tests/notes.txt", b"tests"),
+                TestEntry::file("README", b"readme"),
+            ]),
+        )
+        .unwrap();
+
+        let mut zip = Zip::from_readable(BufReader::new(File::open(&zip_path).unwrap())).unwrap();
+        let mut extract_options = ExtractOptions::new(zip_path.clone(), false, None);
+        extract_options.junk_paths = true;
+        extract_options.collision_policy = CollisionPolicy::Rename;
+
+        let output_paths: Vec<_> = zip
+            .plan_extraction(&extract_options, &FileSystemSink)
+            .into_iter()
+            .filter(|planned_entry| planned_entry.action != PlanAction::Skip)
+            .map(|planned_entry| planned_entry.output_path)
+            .collect();
+
+        assert_eq!(
+            output_paths,
+            vec![
+                dir.path().join("notes.txt"),
+                dir.path().join("notes-1.txt"),
+                dir.path().join("notes-2.txt"),
+                dir.path().join("README"),
+            ]
+        );
+
+        extract_options.collision_policy = CollisionPolicy::Skip;
+        let plan = zip.plan_extraction(&extract_options, &FileSystemSink);
+
+        assert_eq!(plan[0].action, PlanAction::Skip);
+        assert_eq!(plan[2].action, PlanAction::Skip);
+        assert_eq!(plan[3].action, PlanAction::Skip);
+
+        extract_options.collision_policy = CollisionPolicy::Overwrite;
+
+        assert_eq!(zip.extract_items(extract_options, Vec::new()).unwrap(), 4);
+        assert_eq!(
+            std::fs::read(dir.path().join("notes.txt")).unwrap(),
+            b"tests"
+        );
+        assert!(dir.path().join("README").exists());
+        assert!(!dir.path().join("docs").exists());
+    }
+
+    #[test]
+    fn test_extract_with_freshen() {
+        let dir = tempfile::tempdir().unwrap();
+        let zip_path = dir.path().join("archive.zip");
+        // 2099-01-01 is later than the modification time of any file written by the test
+        let later_date = ((2099 - 1980) << 9) | (1 << 5) | 1;
+
+        std::fs::write(
+            &zip_path,
+            build_archive(&[
+                TestEntry::file("newer.txt", b"new").with_date_time(later_date, 0),
+                TestEntry::file("older.txt", b"new"),
+                TestEntry::file("missing.txt", b"new").with_date_time(later_date, 0),
+            ]),
+        )
+        .unwrap();
+        std::fs::write(dir.path().join("newer.txt"), b"old").unwrap();
+        std::fs::write(dir.path().join("older.txt"), b"old").unwrap();
+
+        let mut zip = Zip::from_readable(BufReader::new(File::open(&zip_path).unwrap())).unwrap();
+        let mut extract_options = ExtractOptions::new(zip_path, false, None);
+        extract_options.freshen = true;
+
+        let reasons: Vec<_> = zip
+            .plan_extraction(&extract_options, &FileSystemSink)
+            .into_iter()
+            .map(|planned_entry| planned_entry.reason)
+            .collect();
+
+        assert_eq!(
+            reasons,
+            vec![
+                "target older than the entry",
+                "target up to date",
+                "target missing"
+            ]
+        );
+        assert_eq!(zip.extract_items(extract_options, Vec::new()).unwrap(), 1);
+        assert_eq!(std::fs::read(dir.path().join("newer.txt")).unwrap(), b"new");
+        assert_eq!(std::fs::read(dir.path().join("older.txt")).unwrap(), b"old");
+        assert!(!dir.path().join("missing.txt").exists());
+    }
+
+    #[test]
+    fn test_verify_entries() {
+        let mut archive = build_archive(&[
+            TestEntry::file("a.txt", b"hello"),
+            TestEntry::dir("docs/"),
+            TestEntry::file("docs/b.txt", &[b'b'; 1000]).deflated(),
+            TestEntry::file("c.txt", &[b'c'; 1000]).deflated(),
+        ]);
+
+        // Corrupt the data of the first entry so its CRC-32 doesn't match
+        archive[30 + "a.txt".len()] = b'j';
+
+        // Store a wrong uncompressed size in the central directory record of the last entry
+        let last_record_offset = archive
+            .windows(4)
+            .rposition(|window| window == b"PK\x01\x02")
+            .unwrap();
+        archive[last_record_offset + 24..last_record_offset + 28]
+            .copy_from_slice(&1001u32.to_le_bytes());
+
+        let mut zip = Zip::from_readable(BufReader::new(Cursor::new(archive))).unwrap();
+        let results = zip.verify_entries(&[]);
+
+        assert_eq!(results.len(), 4);
+        assert!(matches!(
+            results[0],
+            (_, Err(ExtractError::InvalidExtractedFile(_, _)))
+        ));
+        assert_eq!(results[1], (String::from("docs/"), Ok(())));
+        assert_eq!(results[2], (String::from("docs/b.txt"), Ok(())));
+        assert_eq!(
+            results[3],
+            (
+                String::from("c.txt"),
+                Err(ExtractError::InvalidExtractedSize(1001, 1000))
+            )
+        );
+    }
+
+    #[test]
+    fn test_extract_preserves_modification_times() {
+        let dir = tempfile::tempdir().unwrap();
+        let zip_path = dir.path().join("archive.zip");
+
+        // 2023-09-25 20:59:30 and 2001-02-03 04:05:06 in local time
+        std::fs::write(
+            &zip_path,
+            build_archive(&[
+                TestEntry::dir("docs/").with_date_time(0x5739, 0xA76F),
+                TestEntry::file("docs/a.txt", b"a").with_date_time(0x2A43, 0x20A3),
+            ]),
+        )
+        .unwrap();
+
+        let modified_time = |path: &str| {
+            std::fs::metadata(dir.path().join(path))
+                .unwrap()
+                .modified()
+                .unwrap()
+        };
+
+        let mut zip = Zip::from_readable(BufReader::new(File::open(&zip_path).unwrap())).unwrap();
+        let extract_options = ExtractOptions::new(zip_path.clone(), false, None);
+
+        assert_eq!(zip.extract_items(extract_options, Vec::new()).unwrap(), 2);
+        assert_eq!(
+            modified_time("docs"),
+            ZipDateTime::from_bytes(0x5739, 0xA76F).to_system_time()
+        );
+        assert_eq!(
+            modified_time("docs/a.txt"),
+            ZipDateTime::from_bytes(0x2A43, 0x20A3).to_system_time()
+        );
+
+        let mut extract_options = ExtractOptions::new(zip_path, false, None);
+        extract_options.ignore_timestamps = true;
+
+        assert_eq!(zip.extract_items(extract_options, Vec::new()).unwrap(), 2);
+        assert!(modified_time("docs/a.txt") > UNIX_EPOCH + Duration::from_secs(1695675570));
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_extract_restores_unix_permissions() {
+        use std::os::unix::fs::PermissionsExt;
+
+        let dir = tempfile::tempdir().unwrap();
+        let zip_path = dir.path().join("archive.zip");
+
+        std::fs::write(
+            &zip_path,
+            build_archive(&[
+                TestEntry::dir("bin/").with_external_attributes(0o040550 << 16),
+                TestEntry::file("bin/run.sh", b"#!/bin/sh")
+                    .with_external_attributes(0o100754 << 16),
+                TestEntry::file("README", b"").with_external_attributes(0),
+                TestEntry::file("bin/setuid", b"").with_external_attributes(0o104755 << 16),
+            ]),
+        )
+        .unwrap();
+
+        let mode = |path: &str| {
+            std::fs::metadata(dir.path().join(path))
+                .unwrap()
+                .permissions()
+                .mode()
+                & 0o7777
+        };
+
+        let mut zip = Zip::from_readable(BufReader::new(File::open(&zip_path).unwrap())).unwrap();
+        let extract_options = ExtractOptions::new(zip_path.clone(), false, None);
+
+        assert_eq!(zip.zip_files()[2].unix_permissions(), None);
+        assert_eq!(zip.extract_items(extract_options, Vec::new()).unwrap(), 4);
+        assert_eq!(mode("bin"), 0o550);
+        assert_eq!(mode("bin/run.sh"), 0o754);
+        // The setuid bit is cleared unless it is preserved
+        assert_eq!(mode("bin/setuid"), 0o755);
+
+        // The options override the permissions of the entries and mask them
+        let mut zip = Zip::from_readable(BufReader::new(File::open(&zip_path).unwrap())).unwrap();
+        let mut extract_options = ExtractOptions::new(zip_path, false, None);
+        extract_options.into = Some(PathBuf::from("forced"));
+        extract_options.dir_mode = Some(0o775);
+        extract_options.mode_mask = Some(0o027);
+        extract_options.preserve_special_permissions = true;
+
+        assert_eq!(zip.extract_items(extract_options, Vec::new()).unwrap(), 4);
+        assert_eq!(mode("forced/bin"), 0o750);
+        assert_eq!(mode("forced/bin/run.sh"), 0o750);
+        assert_eq!(mode("forced/README") & 0o027, 0);
+        assert_eq!(mode("forced/bin/setuid"), 0o4750);
+
+        // Let the temporary directory be removed
+        std::fs::set_permissions(
+            dir.path().join("bin"),
+            std::fs::Permissions::from_mode(0o755),
+        )
+        .unwrap();
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_extract_undecodable_file_name() {
+        use std::ffi::OsStr;
+        use std::os::unix::ffi::OsStrExt;
+
+        let dir = tempfile::tempdir().unwrap();
+        let zip_path = dir.path().join("archive.zip");
+        let mut archive = build_archive(&[TestEntry::file("caf?.txt", b"coffee")]);
+        let central_dir_offset =
+            LittleEndian::read_u32(&archive[archive.len() - 6..archive.len() - 2]) as usize;
+        let name_offsets: Vec<usize> = (0..archive.len() - 3)
+            .filter(|&index| &archive[index..index + 4] == b"caf?")
+            .collect();
+
+        // Names flagged as UTF-8 which are not valid UTF-8
+        for name_offset in name_offsets {
+            archive[name_offset + 3] = 0xE9;
+        }
+
+        archive[7] |= 0x08;
+        archive[central_dir_offset + 9] |= 0x08;
+        std::fs::write(&zip_path, archive).unwrap();
+
+        assert!(Zip::from_readable(BufReader::new(File::open(&zip_path).unwrap())).is_err());
+
+        let parse_options = ParseOptions {
+            file_name_policy: FileNamePolicy::Lossy,
+            ..Default::default()
+        };
+        let mut zip = Zip::from_readable_with_options(
+            BufReader::new(File::open(&zip_path).unwrap()),
+            &parse_options,
+        )
+        .unwrap();
+
+        assert_eq!(zip.zip_files()[0].file_name(), "caf\u{FFFD}.txt");
+        assert_eq!(
+            zip.extract_items(ExtractOptions::new(zip_path, false, None), Vec::new())
+                .unwrap(),
+            1
+        );
+        assert_eq!(
+            std::fs::read(dir.path().join(OsStr::from_bytes(b"caf\xE9.txt"))).unwrap(),
+            b"coffee"
+        );
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_extract_symlinks() {
+        let dir = tempfile::tempdir().unwrap();
+        let zip_path = dir.path().join("archive.zip");
+        let symlink = |name: &str, target: &str| {
+            TestEntry::file(name, target.as_bytes()).with_external_attributes(0o120777 << 16)
+        };
+
+        std::fs::write(
+            &zip_path,
+            build_archive(&[
+                TestEntry::file("b.txt", b"b"),
+                TestEntry::dir("a/"),
+                symlink("a/link", "../b.txt"),
+                symlink("escape", "a/../../outside"),
+                symlink("absolute", "/etc/passwd"),
+            ]),
+        )
+        .unwrap();
+
+        let mut zip = Zip::from_readable(BufReader::new(File::open(&zip_path).unwrap())).unwrap();
+        let extract_options = ExtractOptions::new(zip_path.clone(), false, None);
+
+        assert_eq!(zip.extract_items(extract_options, Vec::new()).unwrap(), 5);
+        assert_eq!(
+            std::fs::read_link(dir.path().join("a/link")).unwrap(),
+            PathBuf::from("../b.txt")
+        );
+        assert_eq!(std::fs::read(dir.path().join("a/link")).unwrap(), b"b");
+        assert_eq!(
+            std::fs::read_link(dir.path().join("absolute")).unwrap(),
+            PathBuf::from("/etc/passwd")
+        );
+
+        std::fs::remove_file(dir.path().join("escape")).unwrap();
+        std::fs::remove_file(dir.path().join("absolute")).unwrap();
+
+        let mut extract_options = ExtractOptions::new(zip_path, false, None);
+        extract_options.refuse_external_symlinks = true;
+        extract_options.salvage = true;
+
+        // The existing link is replaced while the external ones are refused
+        assert_eq!(
+            zip.extract_items(extract_options, Vec::new()),
+            Err(ExtractError::EntriesFailed(2))
+        );
+        assert!(dir.path().join("a/link").exists());
+        assert!(std::fs::symlink_metadata(dir.path().join("escape")).is_err());
+        assert!(std::fs::symlink_metadata(dir.path().join("absolute")).is_err());
+
+        // Each link stays inside on its own, but the second one goes through the first
+        let extract_dir = dir.path().join("chained");
+        let zip_path = extract_dir.join("archive.zip");
+
+        std::fs::create_dir(&extract_dir).unwrap();
+        std::fs::write(
+            &zip_path,
+            build_archive(&[
+                symlink("a", "."),
+                symlink("a/b", ".."),
+                TestEntry::file("a/b/pwned.txt", b"pwned"),
+            ]),
+        )
+        .unwrap();
+
+        for refuse_external_symlinks in [false, true] {
+            let mut zip =
+                Zip::from_readable(BufReader::new(File::open(&zip_path).unwrap())).unwrap();
+            let mut extract_options = ExtractOptions::new(zip_path.clone(), false, None);
+            extract_options.refuse_external_symlinks = refuse_external_symlinks;
+            extract_options.salvage = true;
+
+            assert_eq!(
+                zip.extract_items(extract_options, Vec::new()),
+                Err(ExtractError::EntriesFailed(2))
+            );
+            assert!(!dir.path().join("pwned.txt").exists());
+            assert!(std::fs::symlink_metadata(extract_dir.join("b")).is_err());
+        }
+
+        // A link already on the disk is resolved before the target is checked
+        std::fs::remove_file(extract_dir.join("a")).unwrap();
+        std::os::unix::fs::symlink(".", extract_dir.join("a")).unwrap();
+        std::fs::write(&zip_path, build_archive(&[symlink("a/b", "..")])).unwrap();
+
+        let mut zip = Zip::from_readable(BufReader::new(File::open(&zip_path).unwrap())).unwrap();
+        let mut extract_options = ExtractOptions::new(zip_path, false, None);
+        extract_options.refuse_external_symlinks = true;
+
+        assert_eq!(
+            zip.extract_items(extract_options, Vec::new()),
+            Err(ExtractError::ExternalSymlink(
+                "a/b".to_string(),
+                "..".to_string()
+            ))
+        );
+        assert!(std::fs::symlink_metadata(extract_dir.join("b")).is_err());
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_extract_with_link_duplicates() {
+        use std::os::unix::fs::MetadataExt;
+
+        let dir = tempfile::tempdir().unwrap();
+        let zip_path = dir.path().join("archive.zip");
+
+        std::fs::write(
+            &zip_path,
+            build_archive(&[
+                TestEntry::file("a/LICENSE", b"MIT License"),
+                TestEntry::file("b/LICENSE", b"MIT License").deflated(),
+                TestEntry::file("c/LICENSE", b"MIT License")
+                    .with_external_attributes(0o100755 << 16),
+                TestEntry::file("d/LICENSE", b"BSD License"),
+            ]),
+        )
+        .unwrap();
+
+        let mut zip = Zip::from_readable(BufReader::new(File::open(&zip_path).unwrap())).unwrap();
+        let mut extract_options = ExtractOptions::new(zip_path, false, None);
+        extract_options.link_duplicates = true;
+
+        assert_eq!(zip.extract_items(extract_options, Vec::new()).unwrap(), 4);
+
+        let inode = |path: &str| std::fs::metadata(dir.path().join(path)).unwrap().ino();
+
+        assert_eq!(inode("a/LICENSE"), inode("b/LICENSE"));
+        assert_ne!(inode("a/LICENSE"), inode("c/LICENSE"));
+        assert_ne!(inode("a/LICENSE"), inode("d/LICENSE"));
+        assert_eq!(
+            std::fs::read(dir.path().join("b/LICENSE")).unwrap(),
+            b"MIT License"
+        );
+    }
+
+    #[test]
+    fn test_extract_zip_bomb() {
+        let dir = tempfile::tempdir().unwrap();
+        let zip_path = dir.path().join("archive.zip");
+
+        std::fs::write(
+            &zip_path,
+            build_archive(&[TestEntry::file("zeros.bin", &vec![0; 2 * 1024 * 1024]).deflated()]),
+        )
+        .unwrap();
+
+        let mut zip = Zip::from_readable(BufReader::new(File::open(&zip_path).unwrap())).unwrap();
+        let extract_options = ExtractOptions::new(zip_path.clone(), false, None);
+
+        assert!(matches!(
+            zip.extract_items(extract_options, Vec::new()),
+            Err(ExtractError::CompressionRatioExceeded(_, 100))
+        ));
+        assert!(!dir.path().join("zeros.bin").exists());
+
+        let mut extract_options = ExtractOptions::new(zip_path, false, None);
+        extract_options.max_compression_ratio = None;
+
+        assert_eq!(zip.extract_items(extract_options, Vec::new()).unwrap(), 1);
+        assert!(dir.path().join("zeros.bin").exists());
+    }
+
+    #[test]
+    fn test_extract_with_resource_limits() {
+        let dir = tempfile::tempdir().unwrap();
+        let zip_path = dir.path().join("archive.zip");
+        let mut archive = build_archive(&[
+            TestEntry::file("a.txt", &[b'a'; 100]),
+            TestEntry::file("b.txt", &[b'b'; 100]).deflated(),
+            TestEntry::file("c.txt", &[b'c'; 100]),
+        ]);
+
+        std::fs::write(&zip_path, &archive).unwrap();
+
+        let mut zip = Zip::from_readable(BufReader::new(File::open(&zip_path).unwrap())).unwrap();
+        let mut extract_options = ExtractOptions::new(zip_path.clone(), false, None);
+        extract_options.max_entry_count = Some(2);
+
+        assert_eq!(
+            zip.extract_items(extract_options, Vec::new()),
+            Err(ExtractError::EntryCountExceeded(2))
+        );
+
+        let mut extract_options = ExtractOptions::new(zip_path.clone(), false, None);
+        extract_options.max_entry_size = Some(50);
+
+        assert_eq!(
+            zip.extract_items(extract_options, Vec::new()),
+            Err(ExtractError::EntrySizeExceeded(50))
+        );
+        assert!(!dir.path().join("a.txt").exists());
+
+        let mut extract_options = ExtractOptions::new(zip_path.clone(), false, None);
+        extract_options.max_total_size = Some(250);
+        extract_options.salvage = true;
+
+        assert_eq!(
+            zip.extract_items(extract_options, Vec::new()),
+            Err(ExtractError::EntriesFailed(1))
+        );
+        assert!(dir.path().join("b.txt").exists());
+        assert!(!dir.path().join("c.txt").exists());
+
+        // The size of an entry missing from its ZIP64 extra field is checked while decoding
+        let record_offset = archive
+            .windows(4)
+            .position(|window| window == b"PK\x01\x02")
+            .unwrap();
+        archive[record_offset + 24..record_offset + 28].copy_from_slice(&u32::MAX.to_le_bytes());
+        std::fs::write(&zip_path, &archive).unwrap();
+
+        let mut zip = Zip::from_readable(BufReader::new(File::open(&zip_path).unwrap())).unwrap();
+        let mut extract_options = ExtractOptions::new(zip_path, false, None);
+        extract_options.max_entry_size = Some(50);
+
+        assert_eq!(
+            zip.extract_items(extract_options, Vec::new()),
+            Err(ExtractError::EntrySizeExceeded(50))
+        );
+    }
+
+    #[test]
+    fn test_read_entry_exceeding_declared_size() {
+        let mut archive = build_archive(&[TestEntry::file("a.txt", &[b'a'; 1000]).deflated()]);
+        let record_offset = archive
+            .windows(4)
+            .rposition(|window| window == b"PK\x01\x02")
+            .unwrap();
+
+        // The central directory declares fewer bytes than the entry decodes to
+        archive[record_offset + 24..record_offset + 28].copy_from_slice(&500u32.to_le_bytes());
+
+        let mut zip = Zip::from_readable(BufReader::new(Cursor::new(archive))).unwrap();
+
+        assert_eq!(
+            zip.read_entry("a.txt", &None, &MemoryBudget::new(10000), &CRC_32_ISO_HDLC),
+            Err(ExtractError::DeclaredSizeExceeded(500))
+        );
+        assert_eq!(
+            zip.verify_entries(&[])[0].1,
+            Err(ExtractError::DeclaredSizeExceeded(500))
+        );
+    }
+
+    #[test]
+    fn test_extract_all_to_memory_budget() {
+        let archive = build_archive(&[
+            TestEntry::file("a.txt", &[b'a'; 6000]).deflated(),
+            TestEntry::dir("docs/"),
+            TestEntry::file("docs/b.txt", &[b'b'; 6000]),
+        ]);
+        let mut zip = Zip::from_readable(BufReader::new(Cursor::new(archive.clone()))).unwrap();
+
+        let budget = MemoryBudget::new(10000);
+        assert_eq!(
+            zip.extract_all_to_memory(&None, &budget, &CRC_32_ISO_HDLC),
+            Err(ExtractError::MemoryBudgetExceeded(10000))
+        );
+        assert_eq!(budget.used(), 0);
+
+        let budget = MemoryBudget::new(12000);
+        let entries = zip
+            .extract_all_to_memory(&None, &budget, &CRC_32_ISO_HDLC)
+            .unwrap();
+        assert_eq!(
+            entries
+                .iter()
+                .map(|(entry_info, data)| (entry_info.file_name.as_str(), data.len()))
+                .collect::<Vec<_>>(),
+            vec![("a.txt", 6000), ("docs/b.txt", 6000)]
+        );
+        assert!(entries.iter().all(|(_, data)| data.charged() == 6000));
+        assert_eq!(budget.used(), 12000);
+
+        // Dropping the buffers gives their bytes back
+        drop(entries);
+        assert_eq!(budget.used(), 0);
+
+        // A budget can be shared by threads decoding at the same time. Each buffer may be charged
+        // for twice the capacity it needs while it grows.
+        let budget = MemoryBudget::new(16384);
+        std::thread::scope(|scope| {
+            let budget = &budget;
+            let handles = ["a.txt", "docs/b.txt"].map(|file_name| {
+                let archive = archive.clone();
+
+                scope.spawn(move || {
+                    let mut zip = Zip::from_readable(BufReader::new(Cursor::new(archive))).unwrap();
+                    zip.read_entry(file_name, &None, budget, &CRC_32_ISO_HDLC)
+                        .map(|data| data.len())
+                })
+            });
+
+            for handle in handles {
+                assert_eq!(handle.join().unwrap(), Ok(6000));
+            }
+        });
+        assert_eq!(budget.used(), 0);
+
+        assert_eq!(
+            zip.read_entry(
+                "docs/b.txt",
+                &None,
+                &MemoryBudget::new(5999),
+                &CRC_32_ISO_HDLC
+            ),
+            Err(ExtractError::MemoryBudgetExceeded(5999))
+        );
+        assert_eq!(
+            zip.read_entry("c.txt", &None, &MemoryBudget::new(5999), &CRC_32_ISO_HDLC),
+            Err(ExtractError::EntryNotFound("c.txt".to_string()))
+        );
+    }
+
+    #[test]
+    fn test_extract_into() {
+        let dir = tempfile::tempdir().unwrap();
+        let zip_path = dir.path().join("archive.zip");
+
+        std::fs::write(
+            &zip_path,
+            build_archive(&[
+                TestEntry::file("a.txt", b"a"),
+                TestEntry::dir("docs/"),
+                TestEntry::file("docs/b.txt", b"b"),
+            ]),
+        )
+        .unwrap();
+
+        let mut zip = Zip::from_readable(BufReader::new(File::open(&zip_path).unwrap())).unwrap();
+        let mut extract_options = ExtractOptions::new(zip_path, false, None);
+
+        for into in ["../pkg", "/pkg", "pkg/../..", "./pkg"] {
+            assert_eq!(
+                extract_options.set_into(PathBuf::from(into)),
+                Err(ExtractError::InvalidInto(PathBuf::from(into)))
+            );
+        }
+
+        extract_options.set_into(PathBuf::from("pkg")).unwrap();
+
+        assert_eq!(zip.extract_items(extract_options, Vec::new()).unwrap(), 3);
+        assert_eq!(std::fs::read(dir.path().join("pkg/a.txt")).unwrap(), b"a");
+        assert_eq!(
+            std::fs::read(dir.path().join("pkg/docs/b.txt")).unwrap(),
+            b"b"
+        );
+        assert!(!dir.path().join("a.txt").exists());
+        assert!(!dir.path().join("docs").exists());
+    }
+
+    #[test]
+    fn test_is_zip64() {
+        let archive = build_archive(&[TestEntry::file("a.txt", b"hello")]);
+        let zip = Zip::from_readable(BufReader::new(Cursor::new(archive.clone()))).unwrap();
+
+        assert!(!zip.is_zip64());
+
+        // Insert a ZIP64 end of central directory record and locator before the end of central
+        // directory record
+        let eocd_offset = archive.len() - 22;
+        let mut zip64_archive = archive[..eocd_offset].to_vec();
+        let zip64_eocd_offset = zip64_archive.len() as u64;
+
+        zip64_archive.extend_from_slice(&[0x50, 0x4B, 0x06, 0x06]);
+        zip64_archive.extend_from_slice(&44u64.to_le_bytes());
+        zip64_archive.extend_from_slice(&[0x2D, 0x03, 0x2D, 0x00]);
+        zip64_archive.extend_from_slice(&[0u8; 8]);
+        zip64_archive.extend_from_slice(&1u64.to_le_bytes());
+        zip64_archive.extend_from_slice(&1u64.to_le_bytes());
+        zip64_archive.extend_from_slice(&archive[eocd_offset + 12..eocd_offset + 16]);
+        zip64_archive.extend_from_slice(&[0u8; 4]);
+        zip64_archive.extend_from_slice(&archive[eocd_offset + 16..eocd_offset + 20]);
+        zip64_archive.extend_from_slice(&[0u8; 4]);
+
+        zip64_archive.extend_from_slice(&[0x50, 0x4B, 0x06, 0x07]);
+        zip64_archive.extend_from_slice(&[0u8; 4]);
+        zip64_archive.extend_from_slice(&zip64_eocd_offset.to_le_bytes());
+        zip64_archive.extend_from_slice(&1u32.to_le_bytes());
+
+        zip64_archive.extend_from_slice(&archive[eocd_offset..]);
+
+        let zip = Zip::from_readable(BufReader::new(Cursor::new(zip64_archive))).unwrap();
+
+        assert!(zip.is_zip64());
+    }
+
+    #[test]
+    fn test_zip64_extra_field_round_trip() {
+        // The entry starts past 4 GB in a sparse file, so its offset only fits in the ZIP64 extra
+        // field of its central directory record
+        let entry_offset = 0x1_0000_0000;
+        let mut archive_file = tempfile::tempfile().unwrap();
+        let large_file = EntryOptions {
+            large_file: true,
+            ..Default::default()
+        };
+        let mut zip_writer = ZipWriter::append(
+            std::io::BufWriter::new(archive_file.try_clone().unwrap()),
+            entry_offset,
+            Vec::new(),
+        )
+        .unwrap();
+
+        zip_writer
+            .add_file("a.txt", &mut &b"hello"[..], &large_file)
+            .unwrap();
+        zip_writer.finish().unwrap();
+
+        let mut zip =
+            Zip::from_readable(BufReader::new(archive_file.try_clone().unwrap())).unwrap();
+
+        assert_eq!(zip.zip_files()[0].offset(), entry_offset);
+
+        let budget = MemoryBudget::new(usize::MAX);
+        let entries = zip
+            .extract_all_to_memory(&None, &budget, &CRC_32_ISO_HDLC)
+            .unwrap();
+
+        assert_eq!(entries[0].1, b"hello");
+
+        // The local file header keeps both sizes in its ZIP64 extra field
+        archive_file.seek(SeekFrom::Start(entry_offset)).unwrap();
+        let local_file =
+            ZipFile::from_local_header_with_options(&mut archive_file, &ParseOptions::default())
+                .unwrap();
+
+        assert_eq!(
+            local_file.compressed_size().get(),
+            zip.zip_files()[0].compressed_size().get()
+        );
+        assert_eq!(local_file.uncompressed_size().get(), 5);
+    }
+
+    #[test]
+    fn test_zip64_end_of_central_dir() {
+        let archive = build_archive(&[
+            TestEntry::file("a.txt", b"hello"),
+            TestEntry::file("b.txt", b"world"),
+        ]);
+
+        // Write the real values to a ZIP64 end of central directory record and set the fields of
+        // the end of central directory record to the ZIP64 markers
+        let eocd_offset = archive.len() - 22;
+        let mut zip64_archive = archive[..eocd_offset].to_vec();
+        let zip64_eocd_offset = zip64_archive.len() as u64;
+
+        zip64_archive.extend_from_slice(&[0x50, 0x4B, 0x06, 0x06]);
+        zip64_archive.extend_from_slice(&44u64.to_le_bytes());
+        zip64_archive.extend_from_slice(&[0x2D, 0x03, 0x2D, 0x00]);
+        zip64_archive.extend_from_slice(&[0u8; 8]);
+        zip64_archive.extend_from_slice(&2u64.to_le_bytes());
+        zip64_archive.extend_from_slice(&2u64.to_le_bytes());
+        zip64_archive.extend_from_slice(&archive[eocd_offset + 12..eocd_offset + 16]);
+        zip64_archive.extend_from_slice(&[0u8; 4]);
+        zip64_archive.extend_from_slice(&archive[eocd_offset + 16..eocd_offset + 20]);
+        zip64_archive.extend_from_slice(&[0u8; 4]);
+
+        zip64_archive.extend_from_slice(&[0x50, 0x4B, 0x06, 0x07]);
+        zip64_archive.extend_from_slice(&[0u8; 4]);
+        zip64_archive.extend_from_slice(&zip64_eocd_offset.to_le_bytes());
+        zip64_archive.extend_from_slice(&1u32.to_le_bytes());
+
+        zip64_archive.extend_from_slice(&archive[eocd_offset..eocd_offset + 8]);
+        zip64_archive.extend_from_slice(&[0xFF; 12]);
+        zip64_archive.extend_from_slice(&[0u8; 2]);
+
+        let mut zip =
+            Zip::from_readable(BufReader::new(Cursor::new(zip64_archive.clone()))).unwrap();
+
+        assert_eq!(zip.zip_file_couunt(), 2);
+        assert_eq!(
+            zip.central_dir_start_offset(),
+            LittleEndian::read_u32(&archive[eocd_offset + 16..eocd_offset + 20]) as u64
+        );
+        assert_eq!(
+            zip.read_entry("b.txt", &None, &MemoryBudget::new(1024), &CRC_32_ISO_HDLC)
+                .unwrap(),
+            b"world"
+        );
+
+        // Prepended data also shifts the offset of the ZIP64 end of central directory record
+        let mut sfx_archive = vec![0x4Du8; 100];
+        sfx_archive.extend_from_slice(&zip64_archive);
+
+        let mut zip = Zip::from_readable(BufReader::new(Cursor::new(sfx_archive))).unwrap();
+
+        assert_eq!(zip.prepended_data_len(), 100);
+        assert_eq!(
+            zip.read_entry("b.txt", &None, &MemoryBudget::new(1024), &CRC_32_ISO_HDLC)
+                .unwrap(),
+            b"world"
+        );
+
+        // The locator must point at a ZIP64 end of central directory record
+        let locator_offset = zip64_eocd_offset as usize + 56;
+
+        zip64_archive[locator_offset + 8..locator_offset + 16]
+            .copy_from_slice(&(zip64_eocd_offset + 1).to_le_bytes());
+
+        assert!(matches!(
+            Zip::from_readable(BufReader::new(Cursor::new(zip64_archive))),
+            Err(ZipError::EndOfCentralDirectoryError(
+                EndOfCentralDirectoryError::InvalidSignature(_)
+            ))
+        ));
+    }
+
+    #[cfg(any(feature = "bzip2", feature = "lzma", feature = "zstd", feature = "xz"))]
+    #[test]
+    fn test_read_compressed_entries() {
+        use crate::headers::CompressionMethod;
+
+        let data = "compressed ".repeat(50);
+        // Compression method, its ID, the general purpose bit flag, the compressed data and the
+        // offset of a byte breaking the stream when zeroed
+        let mut cases: Vec<(CompressionMethod, u16, u16, Vec<u8>, usize)> = Vec::new();
+
+        #[cfg(feature = "bzip2")]
+        {
+            use std::io::Write;
+
+            let mut encoder =
+                bzip2::write::BzEncoder::new(Vec::new(), bzip2::Compression::default());
+            encoder.write_all(data.as_bytes()).unwrap();
+
+            cases.push((
+                CompressionMethod::Bzip2,
+                0x0C,
+                0,
+                encoder.finish().unwrap(),
+                0,
+            ));
+        }
+
+        #[cfg(feature = "lzma")]
+        {
+            use std::io::Write;
+
+            // liblzma writes the `.lzma` header, which entries replace with the version of the
+            // LZMA SDK and the size of the properties
+            let stream = xz2::stream::Stream::new_lzma_encoder(
+                &xz2::stream::LzmaOptions::new_preset(6).unwrap(),
+            )
+            .unwrap();
+            let mut encoder = xz2::write::XzEncoder::new_stream(Vec::new(), stream);
+            encoder.write_all(data.as_bytes()).unwrap();
+            let alone_data = encoder.finish().unwrap();
+            let lzma_data = [&[9, 20, 5, 0], &alone_data[..5], &alone_data[13..]].concat();
+
+            // The stream ends with an end marker, the size is enough to end it too
+            cases.push((
+                CompressionMethod::Lzma { end_marker: true },
+                0x0E,
+                0x02,
+                lzma_data.clone(),
+                2,
+            ));
+            cases.push((
+                CompressionMethod::Lzma { end_marker: false },
+                0x0E,
+                0,
+                lzma_data,
+                2,
+            ));
+        }
+
+        #[cfg(feature = "zstd")]
+        cases.push((
+            CompressionMethod::Zstd,
+            0x5D,
+            0,
+            zstd::encode_all(data.as_bytes(), 0).unwrap(),
+            0,
+        ));
+
+        #[cfg(feature = "xz")]
+        {
+            use std::io::Write;
+
+            let mut encoder = xz2::write::XzEncoder::new(Vec::new(), 6);
+            encoder.write_all(data.as_bytes()).unwrap();
+
+            cases.push((CompressionMethod::Xz, 0x5F, 0, encoder.finish().unwrap(), 0));
+        }
+
+        for (compression_method, method_id, flags, compressed_data, corrupt_offset) in cases {
+            let entry = TestEntry {
+                flags,
+                ..TestEntry::file("a.txt", data.as_bytes()).compressed(method_id, compressed_data)
+            };
+            let mut archive = build_archive(&[entry]);
+            let mut zip = Zip::from_readable(BufReader::new(Cursor::new(archive.clone()))).unwrap();
+
+            assert_eq!(zip.zip_files()[0].compression_method(), &compression_method);
+            assert_eq!(
+                zip.read_entry("a.txt", &None, &MemoryBudget::new(1024), &CRC_32_ISO_HDLC)
+                    .unwrap(),
+                data.as_bytes()
+            );
+
+            // The data starts right after the local file header
+            archive[30 + "a.txt".len() + corrupt_offset] = 0;
+
+            let mut zip = Zip::from_readable(BufReader::new(Cursor::new(archive))).unwrap();
+
+            assert!(matches!(
+                zip.read_entry("a.txt", &None, &MemoryBudget::new(1024), &CRC_32_ISO_HDLC),
+                Err(ExtractError::DecodingError(method, _)) if method == compression_method
+            ));
+        }
+    }
+
+    #[test]
+    fn test_read_aes_entries() {
+        use crate::aes::{AesError, AesStrength, AesVendorVersion};
+        use crate::headers::CompressionMethod;
+
+        // An AE-1 stored entry encrypted with AES-128 and an AE-2 deflated entry encrypted with
+        // AES-256, both with the password "pass"
+        let mut archive = vec![
+            0x50, 0x4B, 0x03, 0x04, 0x33, 0x00, 0x01, 0x00, 0x63, 0x00, 0x00, 0x00, 0x21, 0x58,
+            0x71, 0xB3, 0x3E, 0xED, 0x1E, 0x00, 0x00, 0x00, 0x0A, 0x00, 0x00, 0x00, 0x05, 0x00,
+            0x0B, 0x00, 0x61, 0x2E, 0x74, 0x78, 0x74, 0x01, 0x99, 0x07, 0x00, 0x01, 0x00, 0x41,
+            0x45, 0x01, 0x00, 0x00, 0x01, 0x02, 0x03, 0x04, 0x05, 0x06, 0x07, 0x08, 0xAA, 0xAA,
+            0x57, 0x71, 0x27, 0xE9, 0x50, 0x8E, 0x03, 0x86, 0x16, 0x38, 0xAF, 0x12, 0xFD, 0xF7,
+            0xB2, 0x4E, 0x74, 0x5D, 0x37, 0xB2, 0x50, 0x4B, 0x03, 0x04, 0x33, 0x00, 0x01, 0x00,
+            0x63, 0x00, 0x00, 0x00, 0x21, 0x58, 0x00, 0x00, 0x00, 0x00, 0x2E, 0x00, 0x00, 0x00,
+            0x34, 0x00, 0x00, 0x00, 0x05, 0x00, 0x0B, 0x00, 0x62, 0x2E, 0x74, 0x78, 0x74, 0x01,
+            0x99, 0x07, 0x00, 0x02, 0x00, 0x41, 0x45, 0x03, 0x08, 0x00, 0x01, 0x02, 0x03, 0x04,
+            0x05, 0x06, 0x07, 0x08, 0x09, 0x0A, 0x0B, 0x0C, 0x0D, 0x0E, 0x0F, 0x10, 0x28, 0xC8,
+            0xD6, 0xD7, 0xA7, 0x21, 0x62, 0x5F, 0xDB, 0x22, 0xA3, 0xA0, 0xA8, 0xD0, 0x0E, 0x0F,
+            0x16, 0x0C, 0x1B, 0xC5, 0xF8, 0x0D, 0x57, 0x0F, 0x5B, 0xB5, 0xE4, 0x04, 0x79, 0x76,
+            0x50, 0x4B, 0x01, 0x02, 0x3F, 0x03, 0x33, 0x00, 0x01, 0x00, 0x63, 0x00, 0x00, 0x00,
+            0x21, 0x58, 0x71, 0xB3, 0x3E, 0xED, 0x1E, 0x00, 0x00, 0x00, 0x0A, 0x00, 0x00, 0x00,
+            0x05, 0x00, 0x0B, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0xA4, 0x81,
+            0x00, 0x00, 0x00, 0x00, 0x61, 0x2E, 0x74, 0x78, 0x74, 0x01, 0x99, 0x07, 0x00, 0x01,
+            0x00, 0x41, 0x45, 0x01, 0x00, 0x00, 0x50, 0x4B, 0x01, 0x02, 0x3F, 0x03, 0x33, 0x00,
+            0x01, 0x00, 0x63, 0x00, 0x00, 0x00, 0x21, 0x58, 0x00, 0x00, 0x00, 0x00, 0x2E, 0x00,
+            0x00, 0x00, 0x34, 0x00, 0x00, 0x00, 0x05, 0x00, 0x0B, 0x00, 0x00, 0x00, 0x00, 0x00,
+            0x00, 0x00, 0x00, 0x00, 0xA4, 0x81, 0x4C, 0x00, 0x00, 0x00, 0x62, 0x2E, 0x74, 0x78,
+            0x74, 0x01, 0x99, 0x07, 0x00, 0x02, 0x00, 0x41, 0x45, 0x03, 0x08, 0x00, 0x50, 0x4B,
+            0x05, 0x06, 0x00, 0x00, 0x00, 0x00, 0x02, 0x00, 0x02, 0x00, 0x7C, 0x00, 0x00, 0x00,
+            0xA8, 0x00, 0x00, 0x00, 0x00, 0x00,
+        ];
+        let mut zip = Zip::from_readable(BufReader::new(Cursor::new(archive.clone()))).unwrap();
+        let password = Some("pass".to_string());
+
+        assert!(zip.files_encrypted());
+        assert_eq!(
+            zip.zip_files()[0].encryption_method(),
+            &EncryptionMethod::Aes
+        );
+
+        let aes_extra_field = zip.zip_files()[1].aes_extra_field().unwrap();
+
+        assert_eq!(aes_extra_field.vendor_version, AesVendorVersion::Ae2);
+        assert_eq!(aes_extra_field.strength, AesStrength::Aes256);
+        assert!(matches!(
+            zip.zip_files()[1].compression_method(),
+            CompressionMethod::Deflate(_)
+        ));
+
+        assert_eq!(
+            zip.read_entry(
+                "a.txt",
+                &password,
+                &MemoryBudget::new(1024),
+                &CRC_32_ISO_HDLC
+            )
+            .unwrap(),
+            b"hello aes\n"
+        );
+        assert_eq!(
+            zip.read_entry(
+                "b.txt",
+                &password,
+                &MemoryBudget::new(1024),
+                &CRC_32_ISO_HDLC
+            )
+            .unwrap(),
+            "deflated aes ".repeat(4).as_bytes()
+        );
+        assert_eq!(
+            zip.read_entry(
+                "a.txt",
+                &Some("wrong".to_string()),
+                &MemoryBudget::new(1024),
+                &CRC_32_ISO_HDLC
+            ),
+            Err(ExtractError::AesError(AesError::IncorrectPassword))
+        );
+        assert_eq!(
+            zip.read_entry("a.txt", &None, &MemoryBudget::new(1024), &CRC_32_ISO_HDLC),
+            Err(ExtractError::AesError(AesError::EmptyPassword))
+        );
+
+        // Flip a bit of the encrypted data of b.txt right after its salt and password verifier
+        archive[140] ^= 1;
+
+        let mut zip = Zip::from_readable(BufReader::new(Cursor::new(archive))).unwrap();
+
+        assert_eq!(
+            zip.read_entry(
+                "b.txt",
+                &password,
+                &MemoryBudget::new(1024),
+                &CRC_32_ISO_HDLC
+            ),
+            Err(ExtractError::AesError(AesError::AuthenticationFailed))
+        );
+    }
+
+    #[test]
+    fn test_write_manifest() {
+        let archive = build_archive(&[
+            TestEntry::dir("docs/"),
+            TestEntry::file("docs/readme.txt", b"hello"),
+            TestEntry::file("a.txt", b""),
+        ]);
+        let zip = Zip::from_readable(BufReader::new(Cursor::new(archive))).unwrap();
+        let mut manifest = Vec::new();
+
+        zip.write_manifest(&mut manifest).unwrap();
+
+        let manifest = String::from_utf8(manifest).unwrap();
+        let lines: Vec<&str> = manifest.lines().collect();
+
+        assert_eq!(
+            lines,
+            vec![
+                "0\tdocs/\t00000000\t0\t0\tNo Compression",
+                "1\tdocs/readme.txt\t3610A686\t5\t5\tNo Compression",
+                "2\ta.txt\t00000000\t0\t0\tNo Compression",
+            ]
+        );
+
+        for (line, zip_file) in lines.iter().zip(zip.zip_files()) {
+            assert_eq!(line.split('\t').nth(1).unwrap(), zip_file.file_name());
+        }
+    }
+
+    #[test]
+    fn test_extract_with_resume() {
+        let dir = tempfile::tempdir().unwrap();
+        let zip_path = dir.path().join("archive.zip");
+
+        std::fs::write(
+            &zip_path,
+            build_archive(&[
+                TestEntry::file("a.txt", b"a"),
+                TestEntry::file("b.txt", b"b"),
+                TestEntry::file("c.txt", b"c"),
+            ]),
+        )
+        .unwrap();
+
+        std::fs::write(dir.path().join("a.txt"), b"a").unwrap();
+        std::fs::write(dir.path().join("b.txt"), b"partially written").unwrap();
+        std::fs::write(dir.path().join("c.txt"), b"x").unwrap();
+
+        let mut zip = Zip::from_readable(BufReader::new(File::open(&zip_path).unwrap())).unwrap();
+        let mut extract_options = ExtractOptions::new(zip_path, false, None);
+        extract_options.resume = true;
+
+        assert_eq!(zip.extract_items(extract_options, Vec::new()).unwrap(), 2);
+        assert_eq!(std::fs::read(dir.path().join("a.txt")).unwrap(), b"a");
+        assert_eq!(std::fs::read(dir.path().join("b.txt")).unwrap(), b"b");
+        assert_eq!(std::fs::read(dir.path().join("c.txt")).unwrap(), b"c");
+    }
+
+    #[test]
+    fn test_central_header_range() {
+        let archive = build_archive(&[
+            TestEntry::file("a.txt", b"hello"),
+            TestEntry::file("docs/b.txt", b"world"),
+        ]);
+        let central_dir_offset = u32::from_le_bytes(
+            archive[archive.len() - 6..archive.len() - 2]
+                .try_into()
+                .unwrap(),
+        );
+        let zip = Zip::from_readable(BufReader::new(Cursor::new(archive.clone()))).unwrap();
+
+        let (start, len) = zip.zip_files()[0].central_header_range();
+        let central_header = &archive[start as usize..(start + len) as usize];
+
+        assert_eq!(start, central_dir_offset as u64);
+        assert_eq!(len, 46 + "a.txt".len() as u64);
+        assert_eq!(&central_header[..4], &[0x50, 0x4B, 0x01, 0x02]);
+        assert!(central_header.ends_with(b"a.txt"));
+
+        let (next_start, _) = zip.zip_files()[1].central_header_range();
+
+        assert_eq!(next_start, start + len);
+    }
+
+    #[test]
+    fn test_extract_with_crc_algorithm() {
+        let archive = build_archive(&[
+            TestEntry::file("a.txt", b"hello"),
+            TestEntry::file("b.txt", b"world"),
+        ]);
+        let default_dir = tempfile::tempdir().unwrap();
+        let explicit_dir = tempfile::tempdir().unwrap();
+
+        for (dir, crc_algorithm) in [
+            (&default_dir, None),
+            (&explicit_dir, Some(&crc::CRC_32_ISO_HDLC)),
+        ] {
+            let zip_path = dir.path().join("archive.zip");
+            std::fs::write(&zip_path, &archive).unwrap();
+
+            let mut zip =
+                Zip::from_readable(BufReader::new(File::open(&zip_path).unwrap())).unwrap();
+            let mut extract_options = ExtractOptions::new(zip_path, false, None);
+
+            if let Some(crc_algorithm) = crc_algorithm {
+                extract_options.crc_algorithm = crc_algorithm;
+            }
+
+            assert_eq!(zip.extract_items(extract_options, Vec::new()).unwrap(), 2);
+        }
+
+        for file_name in ["a.txt", "b.txt"] {
+            assert_eq!(
+                std::fs::read(default_dir.path().join(file_name)).unwrap(),
+                std::fs::read(explicit_dir.path().join(file_name)).unwrap()
+            );
+        }
+
+        let zip_path = default_dir.path().join("archive.zip");
+        let mut zip = Zip::from_readable(BufReader::new(File::open(&zip_path).unwrap())).unwrap();
+        let mut extract_options = ExtractOptions::new(zip_path, false, None);
+        extract_options.crc_algorithm = &crc::CRC_32_BZIP2;
+
+        assert!(matches!(
+            zip.extract_items(extract_options, Vec::new()),
+            Err(ExtractError::InvalidExtractedFile(_, _))
+        ));
+    }
+
+    #[test]
+    fn test_plan_extraction_json() {
+        let dir = tempfile::tempdir().unwrap();
+        let zip_path = dir.path().join("archive.zip");
+
+        std::fs::write(
+            &zip_path,
+            build_archive(&[
+                TestEntry::dir("docs/"),
+                TestEntry::file("docs/a.txt", b"a"),
+                TestEntry::file("b.txt", b"b"),
+            ]),
+        )
+        .unwrap();
+        std::fs::write(dir.path().join("b.txt"), b"existing").unwrap();
+
+        let zip = Zip::from_readable(BufReader::new(File::open(&zip_path).unwrap())).unwrap();
+        let mut extract_options = ExtractOptions::new(zip_path, false, None);
+        extract_options.overwrite_policy = OverwritePolicy::Skip;
+
+        let plan =
+            serde_json::to_value(zip.plan_extraction(&extract_options, &FileSystemSink)).unwrap();
+        let expected = [
+            ("docs/", dir.path().join("docs/"), "extract"),
+            ("docs/a.txt", dir.path().join("docs/a.txt"), "extract"),
+            ("b.txt", dir.path().join("b.txt"), "skip"),
+        ];
+
+        assert_eq!(plan.as_array().unwrap().len(), expected.len());
+
+        for (planned_entry, (name, output_path, action)) in
+            plan.as_array().unwrap().iter().zip(expected)
+        {
+            assert_eq!(planned_entry["name"], name);
+            assert_eq!(planned_entry["output_path"], output_path.to_str().unwrap());
+            assert_eq!(planned_entry["action"], action);
+        }
+
+        assert_eq!(plan[2]["reason"], "target exists");
+        assert_eq!(
+            std::fs::read(dir.path().join("b.txt")).unwrap(),
+            b"existing"
+        );
+        assert!(!dir.path().join("docs").exists());
+    }
+
+    #[test]
+    fn test_central_dir_offset_beyond_eof() {
+        let mut archive = build_archive(&[TestEntry::file("a.txt", b"hello")]);
+        let archive_len = archive.len();
+
+        archive[archive_len - 6..archive_len - 2].copy_from_slice(&0x00100000u32.to_le_bytes());
+
+        let zip_result = Zip::from_readable(BufReader::new(Cursor::new(archive)));
+
+        assert!(matches!(
+            zip_result,
+            Err(ZipError::InvalidCentralDirectoryOffset {
+                offset: 0x00100000,
+                file_size,
+            }) if file_size == archive_len as u64
+        ));
+    }
+
+    #[test]
+    fn test_read_archive_with_prepended_data() {
+        let archive = build_archive(&[
+            TestEntry::file("a.txt", b"hello").deflated(),
+            TestEntry::file("b.txt", b"world"),
+        ]);
+        // Executable stub of a self-extracting archive whose offsets were not updated
+        let mut sfx_archive = vec![0x4Du8; 100];
+        sfx_archive.extend_from_slice(&archive);
+
+        let mut zip = Zip::from_readable(BufReader::new(Cursor::new(sfx_archive))).unwrap();
+
+        assert_eq!(zip.prepended_data_len(), 100);
+        assert_eq!(zip.zip_files()[0].offset(), 100);
+
+        let central_dir_records = zip.central_dir_records().unwrap();
+        assert_eq!(
+            LittleEndian::read_u32(&central_dir_records[1][42..46]) as u64,
+            zip.zip_files()[1].offset()
+        );
+
+        let mut entries_reader = zip.entries_reader(None);
+        let mut contents = Vec::new();
+
+        while let Some(entry) = entries_reader.next() {
+            let (_, mut reader) = entry.unwrap();
+            let mut entry_contents = String::new();
+
+            reader.read_to_string(&mut entry_contents).unwrap();
+            contents.push(entry_contents);
+        }
+
+        assert_eq!(contents, vec!["hello", "world"]);
+
+        let zip = Zip::from_readable(BufReader::new(Cursor::new(archive))).unwrap();
+
+        assert_eq!(zip.prepended_data_len(), 0);
+    }
+
+    #[test]
+    fn test_recover_from_local_headers() {
+        // The stored archive holds local file header signatures of its own
+        let nested_archive = build_archive(&[TestEntry::file("nested.txt", b"nested")]);
+        let archive = build_archive(&[
+            TestEntry::file("nested.zip", &nested_archive),
+            TestEntry::file("a.txt", b"hello").deflated(),
+        ]);
+        let central_dir_start_offset = Zip::from_readable(BufReader::new(Cursor::new(&archive)))
+            .unwrap()
+            .central_dir_start_offset() as usize;
+        let truncated_archive = archive[..central_dir_start_offset + 20].to_vec();
+
+        let zip_result = Zip::from_readable(BufReader::new(Cursor::new(&truncated_archive)));
+
+        assert!(zip_result.is_err_and(|err| err.central_dir_damaged()));
+
+        let mut zip = Zip::from_local_headers(
+            BufReader::new(Cursor::new(truncated_archive)),
+            &ParseOptions::default(),
+        )
+        .unwrap();
+
+        assert!(zip.recovered());
+        assert_eq!(zip.zip_file_couunt(), 2);
+        assert_eq!(
+            zip.central_dir_start_offset(),
+            central_dir_start_offset as u64
+        );
+        assert_eq!(
+            zip.read_entry(
+                "nested.zip",
+                &None,
+                &MemoryBudget::new(1024),
+                &CRC_32_ISO_HDLC
+            )
+            .unwrap(),
+            nested_archive
+        );
+        assert_eq!(
+            zip.read_entry("a.txt", &None, &MemoryBudget::new(1024), &CRC_32_ISO_HDLC)
+                .unwrap(),
+            b"hello"
+        );
+
+        assert!(matches!(
+            Zip::from_local_headers(
+                BufReader::new(Cursor::new(vec![0u8; 64])),
+                &ParseOptions::default()
+            ),
+            Err(ZipError::NoEntriesRecovered)
+        ));
+    }
+
+    #[test]
+    fn test_data_descriptors_of_reordered_entries() {
+        let mut zip_writer = ZipWriter::streaming(Vec::new());
+
+        zip_writer
+            .add_file(
+                "a.txt",
+                &mut "hello ".repeat(100).as_bytes(),
+                &EntryOptions::default(),
+            )
+            .unwrap();
+        zip_writer
+            .add_file("b.txt", &mut &b"world"[..], &EntryOptions::default())
+            .unwrap();
+
+        let mut archive = zip_writer.finish().unwrap().into_inner();
+        let zip = Zip::from_readable(BufReader::new(Cursor::new(&archive))).unwrap();
+        let (first_start, first_len) = zip.zip_files()[0].central_header_range();
+        let (second_start, second_len) = zip.zip_files()[1].central_header_range();
+
+        // The central directory lists the entries in the reverse order of their data
+        let first_record =
+            archive[first_start as usize..(first_start + first_len) as usize].to_vec();
+        let second_record =
+            archive[second_start as usize..(second_start + second_len) as usize].to_vec();
+        let reordered_records = [second_record, first_record].concat();
+
+        archive.splice(
+            first_start as usize..(second_start + second_len) as usize,
+            reordered_records,
+        );
+
+        let mut zip = Zip::from_readable(BufReader::new(Cursor::new(archive))).unwrap();
+        let file_names: Vec<&str> = zip
+            .zip_files()
+            .iter()
+            .map(|zip_file| zip_file.file_name().as_str())
+            .collect();
+
+        assert_eq!(file_names, vec!["b.txt", "a.txt"]);
+        assert_eq!(zip.zip_files()[0].uncompressed_size().get(), 5);
+        assert_eq!(zip.zip_files()[0].crc32().get(), 0x3A771143);
+        assert_eq!(zip.zip_files()[1].uncompressed_size().get(), 600);
+        assert_eq!(
+            zip.read_entry("a.txt", &None, &MemoryBudget::new(1024), &CRC_32_ISO_HDLC)
+                .unwrap(),
+            "hello ".repeat(100).as_bytes()
+        );
+        assert_eq!(
+            zip.read_entry("b.txt", &None, &MemoryBudget::new(1024), &CRC_32_ISO_HDLC)
+                .unwrap(),
+            b"world"
+        );
+    }
+
+    #[test]
+    fn test_copy_entry_out_of_range() {
+        let archive = build_archive(&[TestEntry::file("a.txt", b"hello")]);
+        let mut zip = Zip::from_readable(BufReader::new(Cursor::new(archive))).unwrap();
+        let mut zip_writer = ZipWriter::new(Cursor::new(Vec::new()));
+
+        assert!(matches!(
+            zip.copy_entry(1, &mut zip_writer, "b.txt"),
+            Err(WriteError::EntryNotFound(1))
+        ));
+
+        zip.copy_entry(0, &mut zip_writer, "b.txt").unwrap();
+    }
+
+    #[test]
+    fn test_check_password() {
+        let archive = build_archive(&[
+            TestEntry::file("a.txt", b"hello"),
+            TestEntry::file("b.txt", b"secret").encrypted("password"),
+        ]);
+        let mut zip = Zip::from_readable(BufReader::new(Cursor::new(archive))).unwrap();
+
+        assert!(zip
+            .check_password("b.txt", &Some("password".to_string()))
+            .is_ok());
+        assert!(matches!(
+            zip.check_password("b.txt", &Some("wrong".to_string())),
+            Err(ExtractError::ZipCryptoError(
+                ZipCryptoError::IncorrectPassword
+            ))
+        ));
+        assert!(zip.check_password("a.txt", &None).is_ok());
+        assert!(matches!(
+            zip.check_password("c.txt", &None),
+            Err(ExtractError::EntryNotFound(_))
+        ));
+    }
+
+    #[test]
+    fn test_check_passwords() {
+        use crate::headers::CompressionMethod;
+        use crate::writer::Encryption;
+
+        let archive = build_archive(&[
+            TestEntry::file("a.txt", b"hello"),
+            TestEntry::file("b.txt", b"secret").encrypted("password"),
+            TestEntry::file("c.txt", b"other").encrypted("other"),
+        ]);
+        let mut zip = Zip::from_readable(BufReader::new(Cursor::new(archive))).unwrap();
+        let results = zip.check_passwords(&["password".to_string()]);
+
+        assert_eq!(results.len(), 2);
+        assert_eq!(results[0], (String::from("b.txt"), Ok(())));
+        assert!(matches!(
+            results[1],
+            (
+                _,
+                Err(ExtractError::ZipCryptoError(
+                    ZipCryptoError::IncorrectPassword
+                ))
+            )
+        ));
+
+        let results = zip.check_passwords(&["password".to_string(), "other".to_string()]);
+
+        assert_eq!(
+            results,
+            vec![
+                (String::from("b.txt"), Ok(())),
+                (String::from("c.txt"), Ok(()))
+            ]
+        );
+
+        // An AES entry whose compressed stream is corrupt still has the right password
+        let mut zip_writer = ZipWriter::new(Cursor::new(Vec::new()));
+        zip_writer.set_encryption(Encryption::Aes256("password".to_string()));
+        zip_writer
+            .add_file(
+                "d.txt",
+                &mut &b"not a deflate stream"[..],
+                &EntryOptions {
+                    compression_method: CompressionMethod::NoCompression,
+                    ..Default::default()
+                },
+            )
+            .unwrap();
+
+        let mut archive = zip_writer.finish().unwrap().into_inner();
+        let aes_extra_field_offsets: Vec<usize> = archive
+            .windows(4)
+            .enumerate()
+            .filter(|(_, window)| window == &[0x01, 0x99, 0x07, 0x00])
+            .map(|(offset, _)| offset)
+            .collect();
+
+        // The actual compression method is kept at the end of the AES extra field
+        for offset in aes_extra_field_offsets {
+            archive[offset + 9..offset + 11].copy_from_slice(&8u16.to_le_bytes());
+        }
+
+        let mut zip = Zip::from_readable(BufReader::new(Cursor::new(archive))).unwrap();
+
+        assert_eq!(
+            zip.check_passwords(&["password".to_string()]),
+            vec![(String::from("d.txt"), Ok(()))]
+        );
+        assert!(matches!(
+            &zip.verify_entries(&["password".to_string()])[0].1,
+            Err(err) if !err.is_password_failure()
+        ));
+        assert!(matches!(
+            &zip.check_passwords(&["wrong".to_string()])[0].1,
+            Err(err) if err.is_password_failure()
+        ));
+    }
+
+    #[test]
+    fn test_entries_reader() {
+        let archive = build_archive(&[
+            TestEntry::file("a.txt", b"hello").deflated(),
+            TestEntry::file("b.txt", b"world"),
+        ]);
+        let mut zip = Zip::from_readable(BufReader::new(Cursor::new(archive))).unwrap();
+        let mut entries_reader = zip.entries_reader(None);
+        let mut entries = Vec::new();
+
+        while let Some(entry) = entries_reader.next() {
+            let (entry_info, mut reader) = entry.unwrap();
+            let mut contents = String::new();
+
+            reader.read_to_string(&mut contents).unwrap();
+            entries.push((entry_info.file_name, contents));
+        }
+
+        assert_eq!(
+            entries,
+            vec![
+                ("a.txt".to_string(), "hello".to_string()),
+                ("b.txt".to_string(), "world".to_string())
+            ]
+        );
+    }
+
+    #[test]
+    fn test_extract_strong_encryption() {
+        let dir = tempfile::tempdir().unwrap();
+        let zip_path = dir.path().join("archive.zip");
+        let mut entry = TestEntry::file("a.txt", b"encrypted");
+        entry.flags = 0x0041;
+
+        std::fs::write(&zip_path, build_archive(&[entry])).unwrap();
+
+        let mut zip = Zip::from_readable(BufReader::new(File::open(&zip_path).unwrap())).unwrap();
+
+        assert_eq!(
+            zip.zip_files()[0].encryption_method(),
+            &EncryptionMethod::StrongEncryption
+        );
+        assert_eq!(
+            zip.extract_items(ExtractOptions::new(zip_path, false, None), Vec::new()),
+            Err(ExtractError::UnsupportedEncryption(
+                EncryptionMethod::StrongEncryption
+            ))
+        );
+    }
+
+    #[test]
+    fn test_read_split_archive() {
+        let archive = build_archive(&[
+            TestEntry::file("a.txt", b"hello"),
+            TestEntry::file("b.txt", b"world"),
+        ]);
+        let eocd_offset = archive.len() - 22;
+        let central_dir_offset =
+            LittleEndian::read_u32(&archive[eocd_offset + 16..eocd_offset + 20]) as usize;
+        let second_entry_offset = 30 + "a.txt".len() + "hello".len();
+        let second_central_dir_offset = central_dir_offset + 46 + "a.txt".len();
+
+        // Split the data of the first entry across the first two disks and move the central
+        // directory to the last disk
+        let mut last_disk = archive[central_dir_offset..].to_vec();
+        let first_disk_len = 10;
+        let second_record = second_central_dir_offset - central_dir_offset;
+        let last_eocd_offset = eocd_offset - central_dir_offset;
+
+        last_disk[second_record + 34..second_record + 36].copy_from_slice(&1u16.to_le_bytes());
+        last_disk[second_record + 42..second_record + 46]
+            .copy_from_slice(&((second_entry_offset - first_disk_len) as u32).to_le_bytes());
+        last_disk[last_eocd_offset + 4..last_eocd_offset + 8].copy_from_slice(&[2, 0, 2, 0]);
+        last_disk[last_eocd_offset + 16..last_eocd_offset + 20].copy_from_slice(&[0u8; 4]);
+
+        let dir = tempfile::tempdir().unwrap();
+        let zip_path = dir.path().join("archive.zip");
+
+        std::fs::write(dir.path().join("archive.z01"), &archive[..first_disk_len]).unwrap();
+        std::fs::write(
+            dir.path().join("archive.z02"),
+            &archive[first_disk_len..central_dir_offset],
+        )
+        .unwrap();
+        std::fs::write(&zip_path, &last_disk).unwrap();
+
+        let split_archive = SplitArchive::open(&zip_path).unwrap();
+        let parse_options = ParseOptions {
+            disk_start_offsets: split_archive.disk_start_offsets().to_vec(),
+            ..Default::default()
+        };
+        let mut zip =
+            Zip::from_readable_with_options(BufReader::new(split_archive), &parse_options).unwrap();
+
+        assert_eq!(zip.central_dir_start_offset(), central_dir_offset as u64);
+        assert_eq!(zip.zip_files()[1].offset(), second_entry_offset as u64);
+        assert_eq!(
+            zip.read_entry("a.txt", &None, &MemoryBudget::new(1024), &CRC_32_ISO_HDLC)
+                .unwrap(),
+            b"hello"
+        );
+        assert_eq!(
+            zip.read_entry("b.txt", &None, &MemoryBudget::new(1024), &CRC_32_ISO_HDLC)
+                .unwrap(),
+            b"world"
+        );
+
+        // The central directory is on the third disk so the archive cannot be read without the
+        // first two segments
+        std::fs::remove_file(dir.path().join("archive.z01")).unwrap();
+
+        let split_archive = SplitArchive::open(&zip_path).unwrap();
+        let parse_options = ParseOptions {
+            disk_start_offsets: split_archive.disk_start_offsets().to_vec(),
+            ..Default::default()
+        };
+
+        assert!(matches!(
+            Zip::from_readable_with_options(BufReader::new(split_archive), &parse_options),
+            Err(ZipError::MissingDisks {
+                disk_count: 3,
+                found: 1
+            })
+        ));
+    }
+
+    #[test]
+    fn test_read_segment_of_split_archive() {
+        let mut archive = build_archive(&[TestEntry::file("a.txt", b"hello")]);
+        let eocd_offset = archive.len() - 22;
+
+        // The last segment of an archive split into two disks
+        archive[eocd_offset + 4..eocd_offset + 8].copy_from_slice(&[1, 0, 1, 0]);
+
+        assert!(matches!(
+            Zip::from_readable(BufReader::new(Cursor::new(archive))),
+            Err(ZipError::EndOfCentralDirectoryError(
+                EndOfCentralDirectoryError::SpannedArchive {
+                    disk_count: 2,
+                    central_dir_start_disk: 1
+                }
+            ))
+        ));
+    }
+
+    #[test]
+    fn test_encrypted_central_directory() {
+        let mut entry = TestEntry::file("a.txt", b"encrypted");
+        entry.flags = 0x2041;
+
+        let mut archive_bytes = build_archive(&[entry]);
+        let end_of_central_dir_offset = archive_bytes.len() - 22;
+        let central_dir_offset = LittleEndian::read_u32(
+            &archive_bytes[end_of_central_dir_offset + 16..end_of_central_dir_offset + 20],
+        ) as usize;
+
+        // The encrypted central directory is unreadable without the decryption key
+        archive_bytes[central_dir_offset..end_of_central_dir_offset].fill(0xA5);
+
+        let zip_result = Zip::from_readable(BufReader::new(Cursor::new(archive_bytes)));
+
+        assert!(matches!(
+            zip_result,
+            Err(ZipError::CentralDirectoryEncrypted)
+        ));
+    }
+
+    #[test]
+    fn test_extract_with_wrong_password_passing_verification() {
+        let dir = tempfile::tempdir().unwrap();
+        let zip_path = dir.path().join("archive.zip");
+        let data = b"some secret contents";
+
+        std::fs::write(
+            &zip_path,
+            build_archive(&[TestEntry::file("a.txt", data).encrypted("correct")]),
+        )
+        .unwrap();
+
+        // Find a wrong password whose verification byte matches by chance
+        let crc32 = crc::Crc::<u32>::new(&crc::CRC_32_ISO_HDLC).checksum(data);
+        let encrypted = crate::test_utils::encrypt("correct", crc32, data);
+        let wrong_password = (0..)
+            .map(|index| format!("wrong{}", index))
+            .find(|password| {
+                ZipCryptoReader::new(password.clone(), crc32, Cursor::new(&encrypted)).is_ok()
+            })
+            .unwrap();
+
+        let mut zip = Zip::from_readable(BufReader::new(File::open(&zip_path).unwrap())).unwrap();
+
+        // A single password passing the check byte can't be told apart from a corrupt entry
+        assert!(matches!(
+            zip.extract_items(
+                ExtractOptions::new(zip_path.clone(), false, None),
+                vec![wrong_password.clone()]
+            ),
+            Err(ExtractError::InvalidExtractedFile(..))
+        ));
+
+        // The next password is tried once the data shows the first one is wrong
+        assert_eq!(
+            zip.extract_items(
+                ExtractOptions::new(zip_path, false, None),
+                vec![wrong_password, "correct".to_string()]
+            ),
+            Ok(1)
+        );
+        assert_eq!(std::fs::read(dir.path().join("a.txt")).unwrap(), data);
+    }
+
+    #[test]
+    fn test_extract_corrupt_zip_crypto_entry() {
+        let dir = tempfile::tempdir().unwrap();
+        let zip_path = dir.path().join("archive.zip");
+        let mut archive =
+            build_archive(&[TestEntry::file("a.txt", b"hello world").encrypted("pw")]);
+
+        // The last byte of the encrypted data, past the encryption header
+        archive[30 + 5 + 12 + 10] ^= 0xFF;
+        std::fs::write(&zip_path, &archive).unwrap();
+
+        let mut zip = Zip::from_readable(BufReader::new(Cursor::new(archive))).unwrap();
+
+        // The corruption is reported rather than a wrong password, even after trying the others
+        for passwords in [vec!["pw"], vec!["pw", "other"], vec!["other", "pw"]] {
+            let passwords: Vec<String> = passwords.into_iter().map(String::from).collect();
+
+            assert!(matches!(
+                zip.extract_items(
+                    ExtractOptions::new(zip_path.clone(), false, None),
+                    passwords.clone()
+                ),
+                Err(ExtractError::InvalidExtractedFile(..))
+            ));
+            assert!(matches!(
+                &zip.verify_entries(&passwords)[0].1,
+                Err(err) if !err.is_password_failure()
+            ));
+        }
+
+        assert!(matches!(
+            &zip.verify_entries(&["other".to_string()])[0].1,
+            Err(err) if err.is_wrong_password()
+        ));
+    }
+
+    #[test]
+    fn test_extract_with_several_passwords() {
+        let dir = tempfile::tempdir().unwrap();
+        let zip_path = dir.path().join("archive.zip");
+        let archive = build_archive(&[
+            TestEntry::file("a.txt", b"first").encrypted("one"),
+            TestEntry::file("b.txt", b"second").encrypted("two"),
+            TestEntry::file("c.txt", b"plain"),
+        ]);
+        let mut zip = Zip::from_readable(BufReader::new(Cursor::new(archive))).unwrap();
+        let passwords = vec!["two".to_string(), "one".to_string()];
+
+        assert_eq!(
+            zip.extract_items(
+                ExtractOptions::new(zip_path.clone(), false, None),
+                passwords
+            ),
+            Ok(3)
+        );
+        assert_eq!(std::fs::read(dir.path().join("a.txt")).unwrap(), b"first");
+        assert_eq!(std::fs::read(dir.path().join("b.txt")).unwrap(), b"second");
+
+        // The callback is asked for the password of each encrypted entry until one decrypts it
+        let mut extract_options = ExtractOptions::new(zip_path, false, None);
+        extract_options.salvage = true;
+        let mut asked = Vec::new();
+        let results = zip
+            .extract_entries_with(
+                &extract_options,
+                |file_name, attempt| {
+                    asked.push((file_name.to_string(), attempt));
+                    ["one", "wrong"]
+                        .get(attempt)
+                        .map(|password| password.to_string())
+                },
+                &mut FileSystemSink,
+            )
+            .unwrap();
+
+        assert!(results[0].1.is_ok());
+        assert!(results[1]
+            .1
+            .as_ref()
+            .is_err_and(|err| err.is_wrong_password()));
+        assert!(results[2].1.is_ok());
+        assert_eq!(
+            asked,
+            vec![
+                ("a.txt".to_string(), 0),
+                ("b.txt".to_string(), 0),
+                ("b.txt".to_string(), 1),
+                ("b.txt".to_string(), 2),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_read_zip_crypto_entry_with_data_descriptor() {
+        use crate::date_time::ZipDateTime;
+        use crate::writer::{Encryption, EntryOptions, ZipWriter};
+
+        let data = b"some secret contents";
+        let mut zip_writer = ZipWriter::new(Cursor::new(Vec::new()));
+        let entry_options = EntryOptions {
+            last_modified: ZipDateTime::from_bytes(0x5739, 0xA76F),
+            ..Default::default()
+        };
+
+        zip_writer.set_encryption(Encryption::ZipCrypto("password".to_string()));
+        zip_writer
+            .add_file("a.txt", &mut &data[..], &entry_options)
+            .unwrap();
+
+        let archive = zip_writer.finish().unwrap().into_inner();
+        let mut zip = Zip::from_readable(BufReader::new(Cursor::new(archive))).unwrap();
+        let zip_file = &zip.zip_files()[0];
+
+        // The password is verified with the high order byte of the DOS time instead of the CRC-32
+        assert!(zip_file.data_descriptor_used());
+        assert_eq!(zip_file.zip_crypto_check_byte(), 0xA7);
+        assert_ne!((zip_file.crc32().get() >> 24) as u8, 0xA7);
+        assert_eq!(
+            zip.read_entry(
+                "a.txt",
+                &Some("password".to_string()),
+                &MemoryBudget::new(1024),
+                &CRC_32_ISO_HDLC
+            )
+            .unwrap(),
+            data
+        );
     }
 }
@@ -1,6 +1,6 @@
 use std::error::Error;
 use std::fmt::Display;
-use std::io::{BufRead, Read};
+use std::io::{BufRead, Read, Write};
 
 use crate::Crc32;
 
@@ -10,7 +10,7 @@ const PKZIP_KEY2_DEFAULT_VALUE: u32 = 0x34567890;
 
 pub const ZIP_CRYPTO_RANDOM_BYTES_LEN: usize = 12;
 
-#[derive(Debug, PartialEq, Eq)]
+#[derive(Debug, PartialEq, Eq, Clone)]
 pub enum ZipCryptoError {
     IncorrectPassword,
     IOError(String),
@@ -40,7 +40,7 @@ pub struct ZipCryptoReader<R: BufRead> {
 }
 
 #[derive(Debug)]
-struct ZipCrypto {
+pub(crate) struct ZipCrypto {
     key0: u32,
     key1: u32,
     key2: u32,
@@ -79,10 +79,28 @@ impl ZipCrypto {
 
         cipher_byte
     }
+
+    pub fn encrypt_byte(&mut self, byte: u8) -> u8 {
+        let cipher_byte = self.stream_byte() ^ byte;
+        self.update_keys(byte);
+
+        cipher_byte
+    }
 }
 
 impl<R: BufRead> ZipCryptoReader<R> {
-    pub fn new(password: String, file_crc32: Crc32, mut reader: R) -> Result<Self, ZipCryptoError> {
+    pub fn new(password: String, file_crc32: Crc32, reader: R) -> Result<Self, ZipCryptoError> {
+        Self::with_check_byte(password, (file_crc32 >> 24) as u8, reader)
+    }
+
+    /// Creates a reader verifying the password with the given byte instead of the high order
+    /// byte of the CRC-32. Entries using a data descriptor are verified with the high order byte
+    /// of their DOS time since their CRC-32 isn't known when the encryption header is written.
+    pub fn with_check_byte(
+        password: String,
+        check_byte: u8,
+        mut reader: R,
+    ) -> Result<Self, ZipCryptoError> {
         let mut zip_crypto = ZipCrypto::new();
 
         password.bytes().for_each(|byte| {
@@ -98,11 +116,9 @@ impl<R: BufRead> ZipCryptoReader<R> {
             .iter_mut()
             .for_each(|byte| *byte = zip_crypto.process_byte(*byte));
 
-        let crc32_high_order_byte = (file_crc32 >> 24) as u8;
-
-        // The last byte of the first random 12 bytes should be the same as the high order byte of
-        // file CRC-32. If they don't match then the entered password is incorrect!
-        if crc32_high_order_byte != random_bytes[11] {
+        // The last byte of the first random 12 bytes should be the same as the check byte. If they
+        // don't match then the entered password is incorrect!
+        if check_byte != random_bytes[11] {
             return Err(ZipCryptoError::IncorrectPassword);
         }
 
@@ -132,41 +148,64 @@ impl<R: BufRead> BufRead for ZipCryptoReader<R> {
     }
 }
 
-#[cfg(test)]
-mod tests {
-    use super::*;
-    use std::io::Cursor;
+/// Encrypts the data of an entry with Zip Crypto. The 12 byte encryption header is written as
+/// soon as the writer is created. Its last byte lets readers verify the password, it is the high
+/// order byte of the CRC-32 of the file or of its DOS time when the CRC-32 is only known after
+/// the data is written.
+pub struct ZipCryptoWriter<W: Write> {
+    writer: W,
+    zip_crypto: ZipCrypto,
+    buf: Vec<u8>,
+}
 
-    #[test]
-    fn test_correct_password() {
-        let password = "test".to_string();
-        let file_crc32 = 0x5579202F;
-        let encryption_bytes = vec![
-            0xCA, 0x2D, 0x1D, 0x27, 0x19, 0x19, 0x63, 0x43, 0x77, 0x9A, 0x71, 0x76,
-        ];
-        let cursor = Cursor::new(encryption_bytes);
+impl<W: Write> ZipCryptoWriter<W> {
+    pub fn new(password: &str, check_byte: u8, mut writer: W) -> Result<Self, ZipCryptoError> {
+        if password.is_empty() {
+            return Err(ZipCryptoError::EmptyPassword);
+        }
 
-        let zip_crypto_reader_result = ZipCryptoReader::new(password, file_crc32, cursor);
+        let mut zip_crypto = ZipCrypto::new();
 
-        assert!(zip_crypto_reader_result.is_ok());
+        password.bytes().for_each(|byte| {
+            zip_crypto.update_keys(byte);
+        });
+
+        let mut encryption_header = vec![0u8; ZIP_CRYPTO_RANDOM_BYTES_LEN];
+
+        getrandom::fill(&mut encryption_header[..ZIP_CRYPTO_RANDOM_BYTES_LEN - 1])
+            .map_err(|err| ZipCryptoError::IOError(err.to_string()))?;
+        encryption_header[ZIP_CRYPTO_RANDOM_BYTES_LEN - 1] = check_byte;
+
+        encryption_header
+            .iter_mut()
+            .for_each(|byte| *byte = zip_crypto.encrypt_byte(*byte));
+
+        writer
+            .write_all(&encryption_header)
+            .map_err(|err| ZipCryptoError::IOError(err.to_string()))?;
+
+        Ok(Self {
+            writer,
+            zip_crypto,
+            buf: Vec::new(),
+        })
     }
+}
 
-    #[test]
-    fn test_incorrect_password() {
-        let password = "wrong_password".to_string();
-        let file_crc32 = 0x5579202F;
-        let encryption_bytes = vec![
-            0xCA, 0x2D, 0x1D, 0x27, 0x19, 0x19, 0x63, 0x43, 0x77, 0x9A, 0x71, 0x76,
-        ];
-        let cursor = Cursor::new(encryption_bytes);
+impl<W: Write> Write for ZipCryptoWriter<W> {
+    fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+        // The keys move forward as the bytes are encrypted, so the whole buffer is written before
+        // returning
+        self.buf.clear();
+        self.buf
+            .extend(buf.iter().map(|byte| self.zip_crypto.encrypt_byte(*byte)));
+        self.writer.write_all(&self.buf)?;
 
-        let zip_crypto_reader_err_result =
-            ZipCryptoReader::new(password, file_crc32, cursor).unwrap_err();
+        Ok(buf.len())
+    }
 
-        assert_eq!(
-            zip_crypto_reader_err_result,
-            ZipCryptoError::IncorrectPassword
-        );
+    fn flush(&mut self) -> std::io::Result<()> {
+        self.writer.flush()
     }
 }
 
@@ -204,3 +243,69 @@ const PRE_CALCULATED_CRC_TABLE: [Crc32; 256] = [
     0xBDBDF21C, 0xCABAC28A, 0x53B39330, 0x24B4A3A6, 0xBAD03605, 0xCDD70693, 0x54DE5729, 0x23D967BF,
     0xB3667A2E, 0xC4614AB8, 0x5D681B02, 0x2A6F2B94, 0xB40BBE37, 0xC30C8EA1, 0x5A05DF1B, 0x2D02EF8D,
 ];
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Cursor;
+
+    #[test]
+    fn test_correct_password() {
+        let password = "test".to_string();
+        let file_crc32 = 0x5579202F;
+        let encryption_bytes = vec![
+            0xCA, 0x2D, 0x1D, 0x27, 0x19, 0x19, 0x63, 0x43, 0x77, 0x9A, 0x71, 0x76,
+        ];
+        let cursor = Cursor::new(encryption_bytes);
+
+        let zip_crypto_reader_result = ZipCryptoReader::new(password, file_crc32, cursor);
+
+        assert!(zip_crypto_reader_result.is_ok());
+    }
+
+    #[test]
+    fn test_incorrect_password() {
+        let password = "wrong_password".to_string();
+        let file_crc32 = 0x5579202F;
+        let encryption_bytes = vec![
+            0xCA, 0x2D, 0x1D, 0x27, 0x19, 0x19, 0x63, 0x43, 0x77, 0x9A, 0x71, 0x76,
+        ];
+        let cursor = Cursor::new(encryption_bytes);
+
+        let zip_crypto_reader_err_result =
+            ZipCryptoReader::new(password, file_crc32, cursor).unwrap_err();
+
+        assert_eq!(
+            zip_crypto_reader_err_result,
+            ZipCryptoError::IncorrectPassword
+        );
+    }
+
+    #[test]
+    fn test_zip_crypto_writer() {
+        let data = b"some secret contents";
+        let file_crc32 = 0x5579202F;
+        let mut zip_crypto_writer =
+            ZipCryptoWriter::new("test", (file_crc32 >> 24) as u8, Vec::new()).unwrap();
+
+        zip_crypto_writer.write_all(data).unwrap();
+
+        let encrypted = zip_crypto_writer.writer;
+
+        assert_eq!(encrypted.len(), ZIP_CRYPTO_RANDOM_BYTES_LEN + data.len());
+        assert_ne!(&encrypted[ZIP_CRYPTO_RANDOM_BYTES_LEN..], data);
+
+        let mut decrypted = Vec::new();
+
+        ZipCryptoReader::new("test".to_string(), file_crc32, Cursor::new(encrypted))
+            .unwrap()
+            .read_to_end(&mut decrypted)
+            .unwrap();
+
+        assert_eq!(decrypted, data);
+        assert!(matches!(
+            ZipCryptoWriter::new("", 0, Vec::new()),
+            Err(ZipCryptoError::EmptyPassword)
+        ));
+    }
+}